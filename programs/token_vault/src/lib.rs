@@ -1,10 +1,95 @@
+// 'initialize_vault' takes one argument per configurable field, and '#[program]' mirrors it in generated code
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
-use anchor_lang::solana_program::clock::Clock; 
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        confidential_transfer::ConfidentialTransferMint, interest_bearing_mint::InterestBearingConfig,
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+    },
+};
+use anchor_spl::token_2022::{amount_to_ui_amount, AmountToUiAmount};
+use anchor_spl::token_interface::{
+    self, Approve, Burn, CloseAccount, FreezeAccount, Mint, MintTo, Revoke, SyncNative,
+    ThawAccount, TokenAccount, TokenInterface, TransferChecked,
+};
+use anchor_lang::solana_program::clock::Clock;
+use solana_sha256_hasher::hash;
+use anchor_lang::solana_program::instruction::{
+    get_stack_height, AccountMeta, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT,
+};
+use anchor_lang::solana_program::program::{get_return_data, invoke, invoke_signed};
+use solana_keccak_hasher as keccak;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked,
+}; 
 
 // 1. Program ID: You MUST update this in Anchor.toml after running 'anchor keys list'
 declare_id!("8WijvK9GJ5q1KSP1o1xuH4J1qw9VHie47riZecc9zmBS"); 
 
+pub use utils::{
+    derive_depositor_record_pda, derive_indexed_vault_pda, derive_lp_mint_pda, derive_vault_authority_pda,
+    derive_vault_pda, derive_whitelist_entry_pda, GenericYieldProtocol, YieldProtocol,
+};
+
+// --- Constants ---
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const MAX_FEE_BPS: u16 = 1_000; // 10%
+pub const SECONDS_PER_DAY: i64 = 86_400;
+// Legacy Pyth price account layout (pyth-client 'PriceAccount')
+pub const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+pub const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+pub const PYTH_AGG_PRICE_OFFSET: usize = 208; // 'agg.price', an i64
+pub const ORACLE_UNLOCK_ABOVE: u8 = 0;
+pub const ORACLE_UNLOCK_BELOW: u8 = 1;
+pub const REFERRAL_FEE_SHARE_BPS: u64 = 2_000; // Referrers earn 20% of the deposit fee
+pub const RECOVERY_DELAY_SECS: i64 = SECONDS_PER_DAY; // Window for the owner to react to a recovery
+pub const SCHEDULED_WITHDRAWAL_EXPIRY_SECS: i64 = 30 * SECONDS_PER_DAY; // Unclaimed schedules become sweepable
+pub const MAX_SWEEP_BATCH: usize = 10;
+pub const MAX_BATCH_DEPOSITS: usize = 5; // Keeps a batch within the compute budget
+pub const BATCH_DEPOSIT_GROUP_LEN: usize = 4; // (vault, user token account, vault token account, depositor access)
+pub const MAX_BATCH_WITHDRAWALS: usize = 8;
+pub const MIN_HARVEST_INTERVAL_SECS: i64 = SECONDS_PER_DAY;
+pub const MAX_LOCKED_MESSAGE_LEN: usize = 64; // UTF-8 bytes, fixed-size so the layout stays zero-copy friendly
+pub const TREASURY_TRANSFER_TIMELOCK_SECS: i64 = 2 * SECONDS_PER_DAY; // Fees keep flowing to the old treasury meanwhile
+pub const MAX_MEMO_LEN: usize = 64; // Deposit reference numbers, in bytes
+pub const MAX_BULK_UPDATE: usize = 20; // Accounts per bulk 'remaining_accounts' update
+pub const MAX_BATCH_LOCK: usize = 5; // Vaults per 'batch_lock_vaults'
+pub const AUTHORITY_CHANGE_DELAY_SECS: i64 = 2 * SECONDS_PER_DAY; // Default 'authority_change_delay_secs' for a new Program Config
+pub const MAX_RISK_SCORE: u8 = 100;
+pub const HIGH_RISK_SCORE: u8 = 80; // At or above, the withdrawal cooldown is doubled
+pub const RISK_RAPID_WITHDRAWAL_SECS: i64 = 3_600; // Withdrawals closer together than this count as rapid
+pub const DEPOSIT_RATE_LIMIT_WINDOW_SECS: i64 = 3_600; // 'max_deposits_per_hour' counts deposits in this window
+pub const DEFAULT_REVEAL_WINDOW_SECS: i64 = 600; // Used by 'commit_deposit' while 'reveal_window_secs' is 0
+pub const MAX_ALLOWLISTED_PROGRAMS: usize = 8; // Per Program Config allowlist
+// Token-2022 confidential transfer extension, see 'confidential_transfer_from_vault'
+pub const ZK_ELGAMAL_PROOF_PROGRAM_ID: Pubkey = pubkey!("ZkE1Gama1Proof11111111111111111111111111111");
+pub const SPL_MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+// Switchboard v2 'AggregatorAccountData' (packed, after the 8-byte discriminator), see 'read_switchboard_price'
+pub const SWITCHBOARD_V2_PROGRAM_ID: Pubkey = pubkey!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+pub const SWITCHBOARD_AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+pub const SWITCHBOARD_ROUND_OPEN_TIMESTAMP_OFFSET: usize = 358; // 'latest_confirmed_round.round_open_timestamp', an i64
+pub const SWITCHBOARD_RESULT_MANTISSA_OFFSET: usize = 366; // 'latest_confirmed_round.result.mantissa', an i128
+pub const CONFIDENTIAL_TRANSFER_EXTENSION: u8 = 27; // 'TokenInstruction::ConfidentialTransferExtension'
+pub const CONFIDENTIAL_DEPOSIT: u8 = 5; // 'ConfidentialTransferInstruction::Deposit'
+pub const CONFIDENTIAL_WITHDRAW: u8 = 6; // 'ConfidentialTransferInstruction::Withdraw'
+// Program version reported by 'vault_version', see 'VaultVersion'
+pub const VAULT_VERSION: u8 = VaultVersion::V1 as u8;
+// Logged by 'vault_version' so clients can match a program version to a Vault layout
+pub const CHANGELOG: &str = "v1: Vault.schema_version = 1 for new vaults; \
+    0 = created before schema_version existed, each 'realloc_vault' adds 1";
+
+// Program versions; renaming an instruction changes its discriminator, so it needs a new version
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VaultVersion {
+    V1 = 1,
+}
+
 #[program]
 pub mod token_vault {
     use super::*;
@@ -14,71 +99,681 @@ pub mod token_vault {
         ctx: Context<InitializeVault>,
         _vault_bump: u8,         // Passed from client, stored for future PDA checks
         _authority_bump: u8,     // Passed from client, stored for future PDA checks
+        vault_index: u8,         // Lets one payer own several vaults, 0 = the original seeds
+        guardian: Option<Pubkey>,            // Optional recovery key for 'emergency_withdraw'
+        emergency_recipient: Option<Pubkey>, // Token account that emergency funds may go to
+        beneficiary: Option<Pubkey>,         // Who receives funds, defaults to the authority
+        deposit_fee_bps: u16,
+        withdraw_fee_bps: u16,
+        fee_treasury: Option<Pubkey>,        // Token account collecting protocol fees
+        max_capacity: u64,                   // 0 = unlimited
+        min_deposit_amount: u64,             // 0 = no minimum
+        is_sol_vault: bool,                  // Holds wSOL, funded with native SOL
+        min_lock_duration_secs: i64,         // Shortest lock 'lock_vault' accepts, 0 = any
+        minimum_lock_balance: u64,           // 'lock_vault' refuses below this balance, 0 = no minimum
     ) -> Result<()> {
         let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: Fees are capped ---
+        require!(
+            deposit_fee_bps <= MAX_FEE_BPS && withdraw_fee_bps <= MAX_FEE_BPS,
+            VaultError::FeeTooHigh
+        );
         
         // Initialize the Vault account's fields
         vault.authority = ctx.accounts.payer.key();
+        vault.original_authority = ctx.accounts.payer.key(); // Baked into the vault PDA seeds
         vault.token_account = ctx.accounts.token_account.key();
+        vault.token_program_id = ctx.accounts.token_program.key(); // Token or Token-2022, fixed for life
+        vault.schema_version = 1;
         vault.bump = _vault_bump;
         vault.authority_bump = _authority_bump;
+        vault.vault_index = vault_index;
         vault.is_locked = false; // Starts unlocked
         vault.unlock_timestamp = 0; // Starts with no time lock
+        vault.guardian = guardian.unwrap_or_default(); // Default = no guardian
+        vault.emergency_recipient = emergency_recipient.unwrap_or_default();
+
+        // Optional: hand the vault over to a multisig PDA instead of the payer
+        if let Some(multisig_config) = ctx.accounts.multisig_config.as_mut() {
+            require!(
+                multisig_config.vault == Pubkey::default(),
+                VaultError::MultisigAlreadyBound
+            );
+            multisig_config.vault = vault.key();
+            vault.authority = multisig_config.key();
+        }
+
+        // Optional: record the new vault in the payer's registry (already grown by one entry)
+        if let Some(vault_registry) = ctx.accounts.vault_registry.as_mut() {
+            require!(
+                vault_registry.vaults.len() < VaultRegistry::MAX_VAULTS,
+                VaultError::RegistryFull
+            );
+            require!(
+                (vault_index as usize) < VaultRegistry::MAX_VAULTS,
+                VaultError::VaultIndexOutOfRange
+            );
+            vault_registry.vaults.push(RegistryEntry {
+                vault: vault.key(),
+                vault_index,
+            });
+            vault_registry.vault_count = vault_registry
+                .vault_count
+                .checked_add(1)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+        }
+
+        // Depositor and recipient are the same person unless told otherwise
+        vault.beneficiary = beneficiary.unwrap_or(vault.authority);
+
+        vault.deposit_fee_bps = deposit_fee_bps;
+        vault.withdraw_fee_bps = withdraw_fee_bps;
+        vault.fee_treasury = fee_treasury.unwrap_or_default(); // Default = no treasury
+        vault.max_capacity = max_capacity;
+        vault.min_deposit_amount = min_deposit_amount;
+
+        // SOL vaults wrap lamports into a wSOL token account, so the mint must be the native mint
+        if is_sol_vault {
+            require_keys_eq!(ctx.accounts.mint.key(), native_mint::ID, VaultError::NotSolVault);
+        }
+        vault.is_sol_vault = is_sol_vault;
+
+        // --- Security Check: A negative minimum would make every lock pass ---
+        require!(min_lock_duration_secs >= 0, VaultError::LockDurationTooShort);
+        vault.min_lock_duration_secs = min_lock_duration_secs;
+        vault.minimum_lock_balance = minimum_lock_balance;
+
+        vault.last_activity_timestamp = Clock::get()?.unix_timestamp;
+
+        // Global dashboard (opt-in): the Global Stats PDA may be passed in 'remaining_accounts'
+        if let (Some(global_stats), _) = split_global_stats(ctx.program_id, ctx.remaining_accounts) {
+            update_global_stats(ctx.program_id, global_stats, |stats| {
+                stats.total_vaults = stats
+                    .total_vaults
+                    .checked_add(1)
+                    .ok_or(VaultError::ArithmeticOverflow)?;
+                Ok(())
+            })?;
+        }
 
         msg!("Vault Initialized!");
         msg!("Vault Authority (Owner): {}", vault.authority);
         msg!("Vault Token Account: {}", vault.token_account);
 
+        emit!(VaultInitialized {
+            vault: vault.key(),
+            authority: vault.authority,
+            token_account: vault.token_account,
+            mint: ctx.accounts.mint.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
     // Instruction 2: Deposit Tokens
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        let cpi_accounts = Transfer {
-            // Note: .to_account_info() works fine even with Box<Account<...>>
-            from: ctx.accounts.user_token_account.to_account_info(), 
-            to: ctx.accounts.vault_token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
-        };
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        referrer: Option<Pubkey>,
+        merkle_proof: Option<Vec<[u8; 32]>>, // Required while a merkle whitelist root is set
+        memo_data: Option<Vec<u8>>,          // Required above 'memo_required_above', e.g. an AML reference
+    ) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let clock = Clock::get()?;
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        // --- Security Check: The checks every deposit shares, see 'check_deposit_gates' ---
+        let memo_data = memo_data.filter(|memo| !memo.is_empty());
+        let fee = check_deposit_gates(
+            vault,
+            DepositGate {
+                program_config: &ctx.accounts.program_config,
+                depositor: ctx.accounts.authority.key(),
+                amount,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                role_config: ctx.accounts.role_config.as_deref(),
+                whitelist_entry: ctx.accounts.whitelist_entry.as_deref(),
+                merkle_proof: merkle_proof.as_deref().unwrap_or_default(),
+                memo_attached: memo_data.is_some(),
+                depositor_record: ctx.accounts.depositor_record.as_deref(),
+                depositor_rate_limit: ctx.accounts.depositor_rate_limit.as_deref_mut(),
+            },
+            clock.unix_timestamp,
+        )?;
+        let net_amount = amount
+            .checked_sub(fee)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        // --- Security Check: Memos fit in one instruction ---
+        if let Some(memo) = memo_data.as_ref() {
+            require!(memo.len() <= MAX_MEMO_LEN, VaultError::MemoTooLong);
+        }
+
+        // --- Security Check: No self-referral ---
+        if let Some(referrer) = referrer {
+            require_keys_neq!(referrer, ctx.accounts.authority.key(), VaultError::ReferrerIsDepositor);
+        }
 
         // Perform the CPI to transfer tokens
-        token::transfer(cpi_ctx, amount)?;
+        // Note: .to_account_info() works fine even with Box<Account<...>>
+        transfer_from_user(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.authority.to_account_info(),
+            net_amount,
+        )?;
+
+        if fee > 0 {
+            let fee_treasury_token_account = ctx
+                .accounts
+                .fee_treasury_token_account
+                .as_ref()
+                .ok_or(VaultError::FeeTreasuryRequired)?;
+            transfer_from_user(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.user_token_account.to_account_info(),
+                fee_treasury_token_account.to_account_info(),
+                &ctx.accounts.mint,
+                ctx.accounts.authority.to_account_info(),
+                fee,
+            )?;
+        }
+
+        msg!("Deposited {} tokens into the vault (fee: {}).", net_amount, fee);
+
+        // LP vaults mint transferable shares, priced off the balance before this deposit
+        if vault.lp_mint.is_some() {
+            let lp_mint = ctx.accounts.lp_mint.as_ref().ok_or(VaultError::LpMintNotConfigured)?;
+            let user_lp_token_account = ctx
+                .accounts
+                .user_lp_token_account
+                .as_ref()
+                .ok_or(VaultError::LpMintNotConfigured)?;
+            let vault_authority = ctx
+                .accounts
+                .vault_authority
+                .as_ref()
+                .ok_or(VaultError::LpMintNotConfigured)?;
+            require_keys_eq!(user_lp_token_account.mint, lp_mint.key(), VaultError::LpMintNotConfigured);
+
+            let shares = lp_shares(
+                net_amount,
+                lp_mint.supply,
+                ctx.accounts.vault_token_account.amount,
+                false,
+            )?;
+            mint_lp_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                lp_mint.to_account_info(),
+                user_lp_token_account.to_account_info(),
+                vault_authority.to_account_info(),
+                vault,
+                shares,
+            )?;
+            msg!("Minted {} LP shares.", shares);
+        }
+
+        // Per-depositor accounting (opt-in); a fresh record means a first-time depositor
+        let mut new_depositor = None;
+        if let Some(depositor_record) = ctx.accounts.depositor_record.as_mut() {
+            new_depositor = Some(depositor_record.depositor == Pubkey::default());
+            depositor_record.record_deposit(
+                ctx.accounts.authority.key(),
+                net_amount,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        if let Some(vault_stats) = ctx.accounts.vault_stats.as_mut() {
+            // Without a depositor record, fall back to counting the first deposit only
+            let new_depositor = new_depositor.unwrap_or(vault_stats.total_deposits == 0);
+            vault_stats.record_deposit(net_amount, new_depositor, clock.unix_timestamp)?;
+        }
+
+        // Referral attribution: volume plus a share of the fee, paid out by 'claim_referral_fees'
+        if let Some(referrer) = referrer {
+            let referral_record = ctx
+                .accounts
+                .referral_record
+                .as_mut()
+                .ok_or(VaultError::ReferralRecordRequired)?;
+            referral_record.record_referral(referrer, amount, fee)?;
+        }
 
-        msg!("Deposited {} tokens into the vault.", amount);
+        emit!(TokensDeposited {
+            vault: vault.key(),
+            depositor: ctx.accounts.authority.key(),
+            amount: net_amount,
+            fee,
+            timestamp: clock.unix_timestamp,
+        });
+
+        // Analytics: re-read the balance after the CPI to track the peak TVL
+        ctx.accounts.vault_token_account.reload()?;
+        let balance = ctx.accounts.vault_token_account.amount;
+        let vault = &mut ctx.accounts.vault;
+        let vault_key = vault.key();
+        vault.record_deposit(vault_key, ctx.accounts.authority.key(), net_amount, balance, clock.unix_timestamp)?;
+        // A clawback has to take the deposit back out of the same counters
+        if vault.clawback_window_secs > 0 {
+            vault.clawback_in_stats = ctx.accounts.vault_stats.is_some();
+            vault.clawback_in_record = ctx.accounts.depositor_record.is_some();
+        }
+
+        // Confidential vaults shield the deposit into the vault's pending confidential balance
+        if vault.confidential_transfers_enabled {
+            let vault_authority = ctx
+                .accounts
+                .vault_authority
+                .as_ref()
+                .ok_or(VaultError::UnauthorizedAccess)?;
+            confidential_transfer_from_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_token_account,
+                &ctx.accounts.mint,
+                vault_authority,
+                vault,
+                net_amount,
+                None,
+            )?;
+        }
+
+        // The memo is appended to the transaction through the SPL Memo program, signed by the depositor
+        if let Some(memo) = memo_data {
+            let memo_program = ctx
+                .accounts
+                .memo_program
+                .as_ref()
+                .ok_or(VaultError::MemoRequired)?;
+            invoke(
+                &Instruction {
+                    program_id: SPL_MEMO_PROGRAM_ID,
+                    accounts: vec![AccountMeta::new_readonly(ctx.accounts.authority.key(), true)],
+                    data: memo.clone(),
+                },
+                &[ctx.accounts.authority.to_account_info(), memo_program.to_account_info()],
+            )?;
+            vault.last_memo_hash = keccak::hash(&memo).to_bytes();
+        }
+
+        // Global dashboard (opt-in): the Global Stats PDA may be passed in 'remaining_accounts'
+        if let (Some(global_stats), _) = split_global_stats(ctx.program_id, ctx.remaining_accounts) {
+            update_global_stats(ctx.program_id, global_stats, |stats| {
+                stats.total_value_locked_approx = stats
+                    .total_value_locked_approx
+                    .checked_add(net_amount as u128)
+                    .ok_or(VaultError::ArithmeticOverflow)?;
+                stats.total_deposit_events = stats
+                    .total_deposit_events
+                    .checked_add(1)
+                    .ok_or(VaultError::ArithmeticOverflow)?;
+                Ok(())
+            })?;
+        }
         Ok(())
     }
 
     // Instruction 3: Withdraw Tokens (Conditional)
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-        let vault = &ctx.accounts.vault;
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+        amount: u64,
+        new_decryptable_available_balance: Option<[u8; 36]>, // Confidential vaults: the vault's balance after unshielding
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
         let clock = Clock::get()?;
 
-        // --- Security Check 1: Time Lock ---
-        // Ensure vault is NOT locked OR that the lock time has expired
+        // Confidential vaults first move `amount` from the confidential back into the public balance,
+        // so every check below sees it. 'remaining_accounts' ordering:
+        //   [equality proof context, range proof context] (only when unshielding),
+        //   then the oracle feed (if configured), then the Switchboard price floor aggregator (if active),
+        //   then split recipient token accounts (if configured),
+        //   then the Global Stats PDA (opt-in, always last).
+        // Both proofs are pre-verified context state accounts owned by the ZK ElGamal proof program.
+        let (global_stats, mut remaining_accounts) = split_global_stats(ctx.program_id, ctx.remaining_accounts);
+        if let Some(new_decryptable_available_balance) = new_decryptable_available_balance {
+            require!(vault.confidential_transfers_enabled, VaultError::ConfidentialTransfersNotEnabled);
+            let [equality_proof_context, range_proof_context, rest @ ..] = remaining_accounts else {
+                return err!(VaultError::InvalidConfidentialProofAccount);
+            };
+            remaining_accounts = rest;
+
+            confidential_transfer_from_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_token_account,
+                &ctx.accounts.mint,
+                &ctx.accounts.vault_authority,
+                vault,
+                amount,
+                Some((new_decryptable_available_balance, equality_proof_context, range_proof_context)),
+            )?;
+            ctx.accounts.vault_token_account.reload()?;
+        }
+
+        // --- Security Check 1: The checks every payout shares, see 'check_withdraw_gates' ---
+        // Consumes the oracle feed and the price floor aggregator from the front of 'remaining_accounts'
+        let previous_withdrawal_timestamp = vault.last_withdrawal_timestamp;
+        let remaining_accounts = check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // Token-2022 interest-bearing mints: report what the balance is worth with interest (informational)
+        if let Some(value) = interest_bearing_value(
+            &ctx.accounts.token_program,
+            &ctx.accounts.mint,
+            ctx.accounts.vault_token_account.amount,
+        )? {
+            msg!(
+                "Withdrawable incl. interest: {} (interest earned: {})",
+                value,
+                value.saturating_sub(vault.deposit_amount_original)
+            );
+        }
+
+        // LP vaults burn the shares backing the withdrawn amount, rounded up in the vault's favour
+        if vault.lp_mint.is_some() {
+            let lp_mint = ctx.accounts.lp_mint.as_ref().ok_or(VaultError::LpMintNotConfigured)?;
+            let user_lp_token_account = ctx
+                .accounts
+                .user_lp_token_account
+                .as_ref()
+                .ok_or(VaultError::LpMintNotConfigured)?;
+
+            let shares = lp_shares(
+                amount,
+                lp_mint.supply,
+                ctx.accounts.vault_token_account.amount,
+                true,
+            )?;
+            require!(user_lp_token_account.amount >= shares, VaultError::InsufficientLpTokens);
+
+            token_interface::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Burn {
+                        mint: lp_mint.to_account_info(),
+                        from: user_lp_token_account.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                shares,
+            )?;
+            msg!("Burned {} LP shares.", shares);
+        }
+
+        // Protocol fee and early-unlock penalty are taken out of the withdrawn amount
+        let (fee, penalty) = withdrawal_fees(vault, amount, clock.unix_timestamp)?;
+        let treasury_amount = fee
+            .checked_add(penalty)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let net_amount = amount
+            .checked_sub(treasury_amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        // Perform the PDA-signed transfer out of the vault
+        if vault.beneficiary_split_enabled {
+            // Split vaults pay each recipient its share, token accounts follow any oracle feed
+            let beneficiary_split = ctx
+                .accounts
+                .beneficiary_split
+                .as_ref()
+                .ok_or(VaultError::BeneficiarySplitRequired)?;
+            require!(
+                remaining_accounts.len() == beneficiary_split.recipient_token_accounts.len(),
+                VaultError::SplitRecipientMismatch
+            );
+
+            let shares = beneficiary_split.shares(net_amount)?;
+            for ((recipient_token_account, expected), share) in remaining_accounts
+                .iter()
+                .zip(beneficiary_split.recipient_token_accounts.iter())
+                .zip(shares)
+            {
+                require_keys_eq!(
+                    recipient_token_account.key(),
+                    *expected,
+                    VaultError::SplitRecipientMismatch
+                );
+                transfer_from_vault(
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.vault_token_account.to_account_info(),
+                    recipient_token_account.clone(),
+                    &ctx.accounts.mint,
+                    ctx.accounts.vault_authority.to_account_info(),
+                    vault,
+                    share,
+                )?;
+            }
+        } else {
+            // Token-2022 transfer fees are withheld in the destination account, so the transfer
+            // is grossed up and the user still nets exactly `net_amount`. The vault pays the fee.
+            let transfer_fee = get_transfer_fee_for_amount(&ctx.accounts.mint.to_account_info(), net_amount)?;
+            let total_debit = amount
+                .checked_add(transfer_fee)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+            require!(
+                ctx.accounts.vault_token_account.amount >= total_debit,
+                VaultError::InsufficientFunds
+            );
+            if transfer_fee > 0 {
+                msg!("Token-2022 transfer fee withheld: {}", transfer_fee);
+            }
+
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.user_token_account.to_account_info(),
+                &ctx.accounts.mint,
+                ctx.accounts.vault_authority.to_account_info(),
+                vault,
+                net_amount
+                    .checked_add(transfer_fee)
+                    .ok_or(VaultError::ArithmeticOverflow)?,
+            )?;
+        }
+
+        if treasury_amount > 0 {
+            let fee_treasury_token_account = ctx
+                .accounts
+                .fee_treasury_token_account
+                .as_ref()
+                .ok_or(VaultError::FeeTreasuryRequired)?;
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                fee_treasury_token_account.to_account_info(),
+                &ctx.accounts.mint,
+                ctx.accounts.vault_authority.to_account_info(),
+                vault,
+                treasury_amount,
+            )?;
+        }
+
+        msg!(
+            "Withdrew {} tokens from the vault (fee: {}, early-unlock penalty: {}).",
+            net_amount,
+            fee,
+            penalty
+        );
+
+        if let Some(depositor_record) = ctx.accounts.depositor_record.as_mut() {
+            depositor_record.record_withdrawal(amount)?;
+        }
+
+        if let Some(vault_stats) = ctx.accounts.vault_stats.as_mut() {
+            vault_stats.record_withdrawal(amount, clock.unix_timestamp)?;
+        }
+        vault.all_time_withdrawal_count = vault
+            .all_time_withdrawal_count
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        vault.deposit_amount_original = vault.deposit_amount_original.saturating_sub(amount);
+        vault.record_withdrawal_risk(
+            amount,
+            ctx.accounts.vault_token_account.amount,
+            previous_withdrawal_timestamp,
+            clock.unix_timestamp,
+        );
+
+        // Raw amounts across every mint, so TVL is approximate and never goes below zero
+        if let Some(global_stats) = global_stats {
+            update_global_stats(ctx.program_id, global_stats, |stats| {
+                stats.total_value_locked_approx = stats.total_value_locked_approx.saturating_sub(amount as u128);
+                Ok(())
+            })?;
+        }
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.user_token_account.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+
+    // Instruction 4: Lock the Vault with a Timestamp
+    pub fn lock_vault(
+        ctx: Context<LockVault>,
+        unlock_timestamp: i64,
+        amount_to_lock: u64, // 0 = the whole balance
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The checks every lock shares, see 'check_lock_gates' ---
+        check_lock_gates(
+            vault,
+            LockGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                unlock_timestamp,
+                locked_amount: amount_to_lock,
+            },
+            clock.unix_timestamp,
+        )?;
+
+        vault.is_locked = true;
+        vault.unlock_timestamp = unlock_timestamp;
+        vault.locked_amount = amount_to_lock;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+        
+        msg!("Vault locked until timestamp: {} (amount: {}, 0 = all)", unlock_timestamp, amount_to_lock);
+
+        emit!(VaultLocked {
+            vault: vault.key(),
+            unlock_timestamp,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 5: Unlock the Vault (Time-Based), see 'crank_unlock_vault' for the permissionless path
+    pub fn authority_unlock_vault(ctx: Context<AuthorityUnlockVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?; // Get the current on-chain time
+
+        // --- Security Check 0: The program is not paused ---
+        require!(!ctx.accounts.program_config.is_paused, VaultError::ProgramPaused);
+
+        // --- Security Check 1: Has enough time passed, or have enough counterparties voted? ---
+        let vote_passed = ctx
+            .accounts
+            .unlock_vote
+            .as_ref()
+            .is_some_and(|unlock_vote| unlock_vote.has_passed());
+        // Guardian override: the vault's Recovery Config, passed as the first remaining account, carries a
+        // fully approved recovery that names the current authority, i.e. consent to unlock rather than a takeover
+        let guardian_override = match ctx.remaining_accounts.first() {
+            Some(recovery_info) => {
+                require_keys_eq!(*recovery_info.owner, crate::ID, VaultError::UnauthorizedAccess);
+                let mut data = recovery_info.try_borrow_mut_data()?;
+                let mut recovery_config = RecoveryConfig::try_deserialize(&mut &data[..])?;
+                require_keys_eq!(recovery_config.vault, vault.key(), VaultError::UnauthorizedAccess);
+                require!(
+                    recovery_config.recovery_initiated_at != 0
+                        && recovery_config.new_authority_proposed == vault.authority
+                        && recovery_config.approvals.len() >= recovery_config.threshold as usize,
+                    VaultError::GuardianOverrideInsufficientApprovals
+                );
+
+                // Approvals are single-use, like the unlock vote below
+                let guardians_who_approved = recovery_config.approvals.clone();
+                recovery_config.clear();
+                recovery_config.try_serialize(&mut &mut data[..])?;
+                Some(guardians_who_approved)
+            }
+            None => None,
+        };
         require!(
-            !vault.is_locked || clock.unix_timestamp >= vault.unlock_timestamp,
+            vote_passed || guardian_override.is_some() || clock.unix_timestamp >= vault.unlock_timestamp,
             VaultError::VaultStillLocked
         );
 
-        // --- Security Check 2: Insufficient Funds (Best practice) ---
-        // Note: .amount is accessed via the Boxed Account
+        if let Some(guardians_who_approved) = guardian_override {
+            msg!("Time-lock overridden by {} guardians", guardians_who_approved.len());
+            emit!(GuardianOverrideUsed {
+                vault: vault.key(),
+                guardians_who_approved,
+            });
+        }
+
+        // Votes are single-use, the next lock needs a fresh round
+        if let Some(unlock_vote) = ctx.accounts.unlock_vote.as_mut() {
+            unlock_vote.approvals_bitmask = 0;
+        }
+
+        vault.is_locked = false;
+        vault.unlock_timestamp = 0; // Reset timestamp
+        vault.locked_amount = 0;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+        
+        msg!("Vault unlocked successfully at timestamp: {}", clock.unix_timestamp);
+
+        emit!(VaultUnlocked {
+            vault: vault.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 6: Close the Vault and reclaim rent
+    pub fn close_vault(ctx: Context<CloseVault>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Vault must be empty ---
+        // Closing an SPL account with a balance fails anyway, but this gives a clear error
         require!(
-            ctx.accounts.vault_token_account.amount >= amount, 
-            VaultError::InsufficientFunds
+            ctx.accounts.vault_token_account.amount == 0,
+            VaultError::VaultNotEmpty
         );
 
-        // 1. Setup the CPI accounts 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.vault_token_account.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
-            authority: ctx.accounts.vault_authority.to_account_info(), // PDA is the authority
+        // 1. Setup the CPI accounts (rent goes back to the authority)
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.vault_token_account.to_account_info(),
+            destination: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.vault_authority.to_account_info(), // PDA is the owner
         };
-        
+
         // 2. Setup the PDA signer seeds
-        let vault_key = vault.key(); 
+        let vault_key = vault.key();
         let authority_seed = &[
             b"authority",
             vault_key.as_ref(),
@@ -86,200 +781,10702 @@ pub mod token_vault {
         ];
         let signer = &[&authority_seed[..]];
 
-        // 3. Create the CPI context
+        // 3. Close the token account
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token_interface::close_account(cpi_ctx)?;
 
-        // 4. Perform the transfer
-        token::transfer(cpi_ctx, amount)?;
-
-        msg!("Withdrew {} tokens from the vault.", amount);
+        // 4. The Vault PDA itself is closed by the `close = authority` constraint
+        msg!("Vault closed. Rent returned to: {}", ctx.accounts.authority.key());
         Ok(())
     }
 
-
-    // Instruction 4: Lock the Vault with a Timestamp
-    pub fn lock_vault(ctx: Context<LockVault>, unlock_timestamp: i64) -> Result<()> {
+    // Instruction 7: Transfer Vault Ownership to a new Authority
+    pub fn transfer_vault_authority(
+        ctx: Context<TransferVaultAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
         let vault = &mut ctx.accounts.vault;
-        
-        // --- Security Check 1: Ensure time is in the future ---
-        let clock = Clock::get()?;
+
+        // --- Security Check 1: New authority must actually differ ---
         require!(
-            unlock_timestamp > clock.unix_timestamp,
-            VaultError::InvalidUnlockTime
+            new_authority != vault.authority,
+            VaultError::SameAuthority
         );
 
-        vault.is_locked = true;
-        vault.unlock_timestamp = unlock_timestamp;
-        
-        msg!("Vault locked until timestamp: {}", unlock_timestamp);
+        // --- Security Check 2: Immediate transfers only where the deployment has no timelock ---
+        // Otherwise a stolen key could take the vault at once, see 'request_authority_change'
+        require!(
+            ctx.accounts.program_config.authority_change_delay_secs == 0,
+            VaultError::AuthorityChangeTimelockActive
+        );
+
+        let old_authority = vault.authority;
+        vault.authority = new_authority;
+        vault.pending_authority = Pubkey::default();
+        vault.authority_change_unlocks_at = 0;
+
+        msg!("Vault authority transferred from {} to {}", old_authority, new_authority);
+
+        emit!(AuthorityTransferred {
+            vault: vault.key(),
+            old_authority,
+            new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    // Instruction 5: Unlock the Vault (Time-Based)
-    pub fn unlock_vault(ctx: Context<UnlockVault>) -> Result<()> {
+    // Instruction 8: Emergency Withdraw by the Guardian (bypasses the time lock)
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>, amount: u64, nonce: u64) -> Result<()> {
         let vault = &mut ctx.accounts.vault;
-        let clock = Clock::get()?; // Get the current on-chain time
 
-        // --- Security Check 1: Has enough time passed? ---
+        // --- Security Check 0: A signed emergency withdrawal can only be submitted once ---
+        mark_nonce_used(&mut ctx.accounts.instruction_nonce, vault.key(), nonce)?;
+
+        // Guardian and recipient are validated by the accounts struct; the lock is ignored on purpose
         require!(
-            clock.unix_timestamp >= vault.unlock_timestamp,
-            VaultError::VaultStillLocked
+            ctx.accounts.vault_token_account.amount >= amount,
+            VaultError::InsufficientFunds
         );
 
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.emergency_recipient_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        // Keep lock state consistent with the funds having been released
         vault.is_locked = false;
-        vault.unlock_timestamp = 0; // Reset timestamp
-        
-        msg!("Vault unlocked successfully at timestamp: {}", clock.unix_timestamp);
+        vault.unlock_timestamp = 0;
+        vault.locked_amount = 0;
+
+        msg!(
+            "!!! EMERGENCY WITHDRAW !!! Guardian {} moved {} tokens to {}",
+            ctx.accounts.guardian.key(),
+            amount,
+            vault.emergency_recipient
+        );
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: vault.emergency_recipient,
+            amount,
+            fee: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
-}
 
-// --- Account Validation Structs ---
+    // Instruction 9: Create a Multisig Config (M-of-N owners)
+    pub fn create_multisig_config(
+        ctx: Context<CreateMultisigConfig>,
+        owners: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        // --- Security Check 1: Owner set must be valid ---
+        require!(
+            !owners.is_empty() && owners.len() <= MultisigConfig::MAX_OWNERS,
+            VaultError::InvalidMultisigOwners
+        );
+        for (i, owner) in owners.iter().enumerate() {
+            require!(
+                !owners[..i].contains(owner),
+                VaultError::InvalidMultisigOwners
+            );
+        }
 
-// Accounts for 'initialize_vault'
-#[derive(Accounts)]
-#[instruction(vault_bump: u8, authority_bump: u8)]
-pub struct InitializeVault<'info> {
-    // Vault PDA: Creates and funds the vault account
-    #[account(
-        init,
-        payer = payer,
-        seeds = [b"vault", payer.key().as_ref()], // Seeds: ["vault", payer_pubkey]
-        bump,
-        space = 8 + Vault::INIT_SPACE
-    )]
-    pub vault: Account<'info, Vault>,
+        // --- Security Check 2: Threshold must be reachable ---
+        require!(
+            threshold > 0 && threshold as usize <= owners.len(),
+            VaultError::InvalidMultisigThreshold
+        );
 
-    // Vault Authority PDA: Owner of the vault's token account
-    /// CHECK: This is safe because we derive it with PDA and only use it as an authority
-    #[account(
-        seeds = [b"authority", vault.key().as_ref()], // Seeds: ["authority", vault_pubkey]
-        bump = authority_bump
-    )]
-    pub vault_authority: UncheckedAccount<'info>,
+        let multisig_config = &mut ctx.accounts.multisig_config;
+        multisig_config.vault = Pubkey::default(); // Bound later in 'initialize_vault'
+        multisig_config.owners = owners;
+        multisig_config.threshold = threshold;
+        multisig_config.pending_action = None;
+        multisig_config.bump = ctx.bumps.multisig_config;
 
-    // Token Account: Holds the actual tokens, owned by the Vault Authority PDA
-    #[account(
-        init,
-        payer = payer,
-        token::mint = mint,
-        token::authority = vault_authority, // Owned by the PDA
-    )]
-    // FIX: Box<Account<...>> used for large non-Anchor SPL account
-    pub token_account: Box<Account<'info, TokenAccount>>,
-    
-    // Other necessary accounts
-    // FIX: Box<Account<...>> used for large non-Anchor SPL account
-    pub mint: Box<Account<'info, Mint>>, 
-    #[account(mut)]
-    pub payer: Signer<'info>, // The wallet paying for the accounts and initializing the vault
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+        msg!("Multisig created: {}-of-{}", threshold, multisig_config.owners.len());
+        Ok(())
+    }
 
-// Accounts for 'deposit'
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    // Vault PDA check: only the correct authority can deposit
-    #[account(
-        mut,
-        seeds = [b"vault", authority.key().as_ref()],
-        bump = vault.bump,
-        has_one = authority // Ensures the vault is owned by the signer
-    )]
-    pub vault: Account<'info, Vault>,
+    // Instruction 10: Propose a Multisig Action
+    pub fn propose_action(
+        ctx: Context<ProposeAction>,
+        action: MultisigAction,
+        amount: u64,
+        recipient: Pubkey,
+        unlock_timestamp: i64,
+    ) -> Result<()> {
+        let multisig_config = &mut ctx.accounts.multisig_config;
 
-    // User's token account (from)
-    // FIX: Box<Account<...>> used for large non-Anchor SPL account
-    #[account(mut, token::authority = authority)]
-    pub user_token_account: Box<Account<'info, TokenAccount>>,
-    
-    // Vault's token account (to)
-    // FIX: Box<Account<...>> used for large non-Anchor SPL account
-    #[account(mut, address = vault.token_account)]
-    pub vault_token_account: Box<Account<'info, TokenAccount>>,
-    
-    pub authority: Signer<'info>, // The user depositing
-    pub token_program: Program<'info, Token>,
-}
+        // --- Security Check 1: Only an owner can propose ---
+        let owner_index = multisig_config.owner_index(&ctx.accounts.owner.key())?;
 
-// Accounts for 'withdraw'
-#[derive(Accounts)]
-pub struct Withdraw<'info> {
-    // Vault PDA check: only the correct authority can withdraw
-    #[account(
-        mut,
-        seeds = [b"vault", authority.key().as_ref()],
-        bump = vault.bump,
-        has_one = authority
-    )]
-    pub vault: Account<'info, Vault>,
+        // --- Security Check 2: One action at a time ---
+        require!(
+            multisig_config.pending_action.is_none(),
+            VaultError::ActionAlreadyPending
+        );
 
-    // Vault Authority PDA: The signer for the transfer out of the vault
-    /// CHECK: This is safe because it is a verified PDA
-    #[account(
-        seeds = [b"authority", vault.key().as_ref()],
-        bump = vault.authority_bump
-    )]
-    pub vault_authority: UncheckedAccount<'info>,
-    
-    // User's token account (to)
-    // FIX: Box<Account<...>> used for large non-Anchor SPL account
-    #[account(mut, token::authority = authority)]
-    pub user_token_account: Box<Account<'info, TokenAccount>>,
-    
-    // Vault's token account (from)
-    // FIX: Box<Account<...>> used for large non-Anchor SPL account
-    #[account(mut, address = vault.token_account)]
-    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+        // The proposer's approval is recorded straight away
+        multisig_config.pending_action = Some(PendingAction {
+            action,
+            amount,
+            recipient,
+            unlock_timestamp,
+            approvals: 1 << owner_index,
+        });
 
-    pub authority: Signer<'info>, // The user withdrawing
-    pub token_program: Program<'info, Token>,
-}
+        msg!("Multisig action proposed by: {}", ctx.accounts.owner.key());
+        Ok(())
+    }
 
-// Accounts for 'lock_vault'
-#[derive(Accounts)]
-pub struct LockVault<'info> {
-    // Vault PDA check: Only the vault authority can lock it
-    #[account(
-        mut,
-        seeds = [b"vault", authority.key().as_ref()],
-        bump = vault.bump,
-        has_one = authority
-    )]
-    pub vault: Account<'info, Vault>,
-    
-    pub authority: Signer<'info>, // The user locking
-}
+    // Instruction 11: Approve the pending Multisig Action
+    pub fn approve_action(ctx: Context<ApproveAction>) -> Result<()> {
+        let multisig_config = &mut ctx.accounts.multisig_config;
 
-// Accounts for 'unlock_vault'
-#[derive(Accounts)]
-pub struct UnlockVault<'info> {
-    // Vault PDA check: Only the vault authority can unlock it
-    #[account(
-        mut,
-        seeds = [b"vault", authority.key().as_ref()],
-        bump = vault.bump,
-        has_one = authority
-    )]
-    pub vault: Account<'info, Vault>,
-    
-    pub authority: Signer<'info>, // The user unlocking
-}
+        // --- Security Check 1: Only an owner can approve ---
+        let owner_index = multisig_config.owner_index(&ctx.accounts.owner.key())?;
 
+        let pending_action = multisig_config
+            .pending_action
+            .as_mut()
+            .ok_or(VaultError::NoPendingAction)?;
 
-// --- Account Data Structure ---
+        // --- Security Check 2: Each owner approves once ---
+        require!(
+            pending_action.approvals & (1 << owner_index) == 0,
+            VaultError::AlreadyApproved
+        );
 
-#[account]
-#[derive(InitSpace)]
-pub struct Vault {
-    pub authority: Pubkey,
-    pub token_account: Pubkey,
+        pending_action.approvals |= 1 << owner_index;
+
+        msg!(
+            "Multisig action approved by: {} ({} approvals)",
+            ctx.accounts.owner.key(),
+            pending_action.approvals.count_ones()
+        );
+        Ok(())
+    }
+
+    // Instruction 12: Execute the pending Multisig Action once the threshold is met
+    pub fn execute_action(ctx: Context<ExecuteAction>) -> Result<()> {
+        let multisig_config = &mut ctx.accounts.multisig_config;
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Only an owner can execute ---
+        multisig_config.owner_index(&ctx.accounts.owner.key())?;
+
+        let pending_action = multisig_config
+            .pending_action
+            .clone()
+            .ok_or(VaultError::NoPendingAction)?;
+
+        // --- Security Check 2: Enough approvals collected ---
+        require!(
+            pending_action.approvals.count_ones() >= multisig_config.threshold as u32,
+            VaultError::NotEnoughApprovals
+        );
+
+        match pending_action.action {
+            MultisigAction::Withdraw => {
+                let recipient_token_account = ctx
+                    .accounts
+                    .recipient_token_account
+                    .as_ref()
+                    .ok_or(VaultError::InvalidRecipient)?;
+                require_keys_eq!(
+                    recipient_token_account.key(),
+                    pending_action.recipient,
+                    VaultError::InvalidRecipient
+                );
+
+                // The recipient is only known once the action is loaded, so its entry is derived here
+                if let Some(destination_entry) = ctx.accounts.destination_entry.as_ref() {
+                    let (expected_entry, _) = Pubkey::find_program_address(
+                        &[b"wd", vault.key().as_ref(), pending_action.recipient.as_ref()],
+                        ctx.program_id,
+                    );
+                    require_keys_eq!(destination_entry.key(), expected_entry, VaultError::DestinationNotApproved);
+                }
+
+                // The checks every payout shares, see 'check_withdraw_gates'
+                check_withdraw_gates(
+                    vault,
+                    WithdrawGate {
+                        program_config: &ctx.accounts.program_config,
+                        vault_balance: ctx.accounts.vault_token_account.amount,
+                        amount: pending_action.amount,
+                        destination_entry: ctx.accounts.destination_entry.as_deref(),
+                        queued: false,
+                    },
+                    &clock,
+                    ctx.remaining_accounts,
+                )?;
+                vault.last_activity_timestamp = clock.unix_timestamp;
+
+                let (net_amount, treasury_amount) = pay_out_with_fees(
+                    Payout {
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                        from: ctx.accounts.vault_token_account.to_account_info(),
+                        to: recipient_token_account.to_account_info(),
+                        fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                        mint: &ctx.accounts.mint,
+                        vault_authority: ctx.accounts.vault_authority.to_account_info(),
+                    },
+                    vault,
+                    pending_action.amount,
+                    clock.unix_timestamp,
+                )?;
+                msg!("Multisig withdrew {} tokens from the vault (fees: {}).", net_amount, treasury_amount);
+                emit!(TokensWithdrawn {
+                    vault: vault.key(),
+                    destination: pending_action.recipient,
+                    amount: net_amount,
+                    fee: treasury_amount,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+            MultisigAction::Lock => {
+                // The checks every lock shares, see 'check_lock_gates'
+                check_lock_gates(
+                    vault,
+                    LockGate {
+                        program_config: &ctx.accounts.program_config,
+                        vault_balance: ctx.accounts.vault_token_account.amount,
+                        unlock_timestamp: pending_action.unlock_timestamp,
+                        locked_amount: 0,
+                    },
+                    clock.unix_timestamp,
+                )?;
+                vault.is_locked = true;
+                vault.unlock_timestamp = pending_action.unlock_timestamp;
+                vault.locked_amount = 0; // Locks the whole balance
+                msg!("Multisig locked vault until timestamp: {}", pending_action.unlock_timestamp);
+                emit!(VaultLocked {
+                    vault: vault.key(),
+                    unlock_timestamp: pending_action.unlock_timestamp,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+            MultisigAction::Unlock => {
+                require!(
+                    clock.unix_timestamp >= vault.unlock_timestamp,
+                    VaultError::VaultStillLocked
+                );
+                vault.is_locked = false;
+                vault.unlock_timestamp = 0;
+                vault.locked_amount = 0;
+                msg!("Multisig unlocked vault at timestamp: {}", clock.unix_timestamp);
+                emit!(VaultUnlocked {
+                    vault: vault.key(),
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+        }
+
+        // Clear the action so a fresh proposal can be made
+        multisig_config.pending_action = None;
+        Ok(())
+    }
+
+    // Instruction 13: Configure a Linear Vesting Schedule (locks the vault)
+    pub fn setup_vesting(
+        ctx: Context<SetupVesting>,
+        vesting_start: i64,
+        vesting_end: i64,
+        total_vested_amount: u64,
+        cliff_timestamp: i64, // 0 = no cliff
+        cliff_amount: u64,    // Released in one go at the cliff
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: Schedule must be well-formed ---
+        require!(
+            vesting_end > vesting_start && total_vested_amount > 0,
+            VaultError::InvalidVestingSchedule
+        );
+
+        // --- Security Check 2: Cliff must sit inside the schedule ---
+        if cliff_timestamp == 0 {
+            require!(cliff_amount == 0, VaultError::InvalidVestingSchedule);
+        } else {
+            require!(
+                cliff_timestamp >= vesting_start && cliff_timestamp < vesting_end,
+                VaultError::InvalidVestingSchedule
+            );
+            require!(
+                cliff_amount <= total_vested_amount,
+                VaultError::InvalidVestingSchedule
+            );
+        }
+
+        // --- Security Check 3: The vesting lock never weakens a lock that is still running ---
+        check_lock_not_weakened(vault, vesting_end, 0, Clock::get()?.unix_timestamp)?;
+
+        vault.vesting_start = vesting_start;
+        vault.vesting_end = vesting_end;
+        vault.total_vested_amount = total_vested_amount;
+        vault.already_claimed = 0;
+        vault.cliff_timestamp = cliff_timestamp;
+        vault.cliff_amount = cliff_amount;
+        vault.cliff_claimed = false;
+
+        // Regular withdrawals stay blocked until the schedule has fully vested
+        vault.is_locked = true;
+        vault.unlock_timestamp = vesting_end;
+        vault.locked_amount = 0; // Locks the whole balance
+
+        msg!(
+            "Vesting configured: {} tokens from {} to {}",
+            total_vested_amount,
+            vesting_start,
+            vesting_end
+        );
+        Ok(())
+    }
+
+    // Instruction 14: Claim the currently vested portion
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Vesting must be configured ---
+        require!(
+            vault.total_vested_amount > 0,
+            VaultError::VestingNotConfigured
+        );
+
+        // --- Security Check 3: Cliff-based schedules release nothing before the cliff ---
+        require!(
+            vault.cliff_timestamp == 0 || clock.unix_timestamp >= vault.cliff_timestamp,
+            VaultError::CliffNotReached
+        );
+
+        // --- Security Check 4: Something must have vested since the last claim ---
+        let claimable = vault
+            .vested_amount(clock.unix_timestamp)?
+            .checked_sub(vault.already_claimed)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(claimable > 0, VaultError::NothingToClaim);
+
+        // --- Security Check 5: The checks every claim shares, see 'check_claim_gate' ---
+        check_claim_gate(
+            vault,
+            &ctx.accounts.program_config,
+            ctx.accounts.vault_token_account.amount,
+            claimable,
+            clock.unix_timestamp,
+            true,
+        )?;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.beneficiary_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            claimable,
+        )?;
+
+        vault.already_claimed = vault
+            .already_claimed
+            .checked_add(claimable)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        if vault.cliff_timestamp != 0 {
+            vault.cliff_claimed = true; // The first post-cliff claim always includes the cliff amount
+        }
+
+        msg!(
+            "Claimed {} vested tokens ({} of {} total)",
+            claimable,
+            vault.already_claimed,
+            vault.total_vested_amount
+        );
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.beneficiary_token_account.key(),
+            amount: claimable,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 15: Set the Vault Beneficiary
+    pub fn set_beneficiary(ctx: Context<SetBeneficiary>, new_beneficiary: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.beneficiary = new_beneficiary;
+
+        msg!("Vault beneficiary set to: {}", new_beneficiary);
+
+        emit!(BeneficiarySet {
+            vault: vault.key(),
+            beneficiary: new_beneficiary,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 16: Withdraw Tokens as the Beneficiary (Conditional)
+    pub fn beneficiary_withdraw(ctx: Context<BeneficiaryWithdraw>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The checks every payout shares, see 'check_withdraw_gates' ---
+        check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                to: ctx.accounts.beneficiary_token_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.mint,
+                vault_authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault,
+            amount,
+            clock.unix_timestamp,
+        )?;
+
+        msg!("Beneficiary withdrew {} tokens from the vault (fees: {}).", net_amount, treasury_amount);
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.beneficiary_token_account.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 17: Update the Deposit/Withdraw Fee Configuration
+    pub fn update_fee_config(
+        ctx: Context<UpdateFeeConfig>,
+        deposit_fee_bps: u16,
+        withdraw_fee_bps: u16,
+        fee_treasury: Pubkey,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: Fees are capped ---
+        require!(
+            deposit_fee_bps <= MAX_FEE_BPS && withdraw_fee_bps <= MAX_FEE_BPS,
+            VaultError::FeeTooHigh
+        );
+
+        // --- Security Check 2: Rotating an existing treasury goes through 'transfer_fee_treasury' ---
+        require!(
+            vault.fee_treasury == Pubkey::default() || fee_treasury == vault.fee_treasury,
+            VaultError::TreasuryTransferTimelockActive
+        );
+
+        vault.deposit_fee_bps = deposit_fee_bps;
+        vault.withdraw_fee_bps = withdraw_fee_bps;
+        vault.fee_treasury = fee_treasury;
+
+        msg!(
+            "Fee config updated: deposit {} bps, withdraw {} bps, treasury {}",
+            deposit_fee_bps,
+            withdraw_fee_bps,
+            fee_treasury
+        );
+        Ok(())
+    }
+
+    // Instruction 18: Update the Daily Withdrawal Limit
+    pub fn update_limits(ctx: Context<UpdateLimits>, daily_withdrawal_limit: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.daily_withdrawal_limit = daily_withdrawal_limit;
+
+        msg!("Daily withdrawal limit set to: {} (0 = unlimited)", daily_withdrawal_limit);
+        Ok(())
+    }
+
+    // Instruction 19: Set the Cooldown between Withdrawals
+    pub fn set_withdrawal_cooldown(
+        ctx: Context<SetWithdrawalCooldown>,
+        withdrawal_cooldown_secs: i64,
+    ) -> Result<()> {
+        require!(withdrawal_cooldown_secs >= 0, VaultError::InvalidCooldown);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.withdrawal_cooldown_secs = withdrawal_cooldown_secs;
+
+        msg!("Withdrawal cooldown set to: {} seconds (0 = disabled)", withdrawal_cooldown_secs);
+        Ok(())
+    }
+
+    // Instruction 20: Create the Vault Statistics account
+    pub fn initialize_vault_stats(ctx: Context<InitializeVaultStats>) -> Result<()> {
+        let vault_stats = &mut ctx.accounts.vault_stats;
+        vault_stats.vault = ctx.accounts.vault.key();
+        vault_stats.last_updated = Clock::get()?.unix_timestamp;
+
+        msg!("Vault stats initialized for vault: {}", vault_stats.vault);
+        Ok(())
+    }
+
+    // Instruction 21: Log the Vault Statistics (read-only)
+    pub fn get_stats(ctx: Context<GetStats>) -> Result<()> {
+        let vault_stats = &ctx.accounts.vault_stats;
+
+        msg!("Vault: {}", vault_stats.vault);
+        msg!("Total deposits: {}", vault_stats.total_deposits);
+        msg!("Total withdrawals: {}", vault_stats.total_withdrawals);
+        msg!("Cumulative deposit volume: {}", vault_stats.cumulative_deposit_volume);
+        msg!("Cumulative withdrawal volume: {}", vault_stats.cumulative_withdrawal_volume);
+        msg!("Unique depositors: {}", vault_stats.unique_depositor_count);
+        msg!("Last updated: {}", vault_stats.last_updated);
+        Ok(())
+    }
+
+    // Instruction 22: Log a Depositor Record (read-only)
+    pub fn get_depositor_record(ctx: Context<GetDepositorRecord>) -> Result<()> {
+        let depositor_record = &ctx.accounts.depositor_record;
+
+        msg!("Depositor: {}", depositor_record.depositor);
+        msg!("Total deposited: {}", depositor_record.total_deposited);
+        msg!("Total withdrawn: {}", depositor_record.total_withdrawn);
+        msg!("Last deposit: {}", depositor_record.last_deposit_timestamp);
+        Ok(())
+    }
+
+    // Instruction 23: Set the Maximum Vault Capacity
+    pub fn set_max_capacity(ctx: Context<SetMaxCapacity>, max_capacity: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.max_capacity = max_capacity;
+
+        msg!("Max capacity set to: {} (0 = unlimited)", max_capacity);
+        Ok(())
+    }
+
+    // Instruction 24: Update the Minimum Deposit Amount
+    pub fn update_min_deposit(ctx: Context<UpdateMinDeposit>, min_deposit_amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.min_deposit_amount = min_deposit_amount;
+
+        msg!("Minimum deposit set to: {} (0 = no minimum)", min_deposit_amount);
+        Ok(())
+    }
+
+    // Instruction 25: Set the Auto-Lock Duration applied on every Deposit
+    pub fn set_auto_lock_duration(
+        ctx: Context<SetAutoLockDuration>,
+        auto_lock_duration_secs: i64,
+    ) -> Result<()> {
+        require!(auto_lock_duration_secs >= 0, VaultError::InvalidUnlockTime);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.auto_lock_duration_secs = auto_lock_duration_secs;
+
+        msg!("Auto-lock duration set to: {} seconds (0 = disabled)", auto_lock_duration_secs);
+        Ok(())
+    }
+
+    // Instruction 26: Lock the Vault for a Duration (relative to the on-chain clock)
+    pub fn lock_vault_for_duration(ctx: Context<LockVaultForDuration>, duration_secs: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: Duration must be positive and bounded ---
+        require!(duration_secs > 0, VaultError::InvalidUnlockTime);
+        require!(duration_secs <= u32::MAX as u64, VaultError::LockDurationTooLong);
+        let duration_secs = i64::try_from(duration_secs).map_err(|_| VaultError::ArithmeticOverflow)?;
+
+        let clock = Clock::get()?;
+        let unlock_timestamp = clock
+            .unix_timestamp
+            .checked_add(duration_secs)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        // --- Security Check 2: The checks every lock shares, see 'check_lock_gates' ---
+        check_lock_gates(
+            vault,
+            LockGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                unlock_timestamp,
+                locked_amount: 0,
+            },
+            clock.unix_timestamp,
+        )?;
+
+        vault.is_locked = true;
+        vault.unlock_timestamp = unlock_timestamp;
+        vault.locked_amount = 0; // Locks the whole balance
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        msg!("Vault locked for {} seconds, until timestamp: {}", duration_secs, unlock_timestamp);
+
+        emit!(VaultLocked {
+            vault: vault.key(),
+            unlock_timestamp,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 27: Extend the Lock on a currently-locked Vault
+    pub fn extend_lock(ctx: Context<ExtendLock>, new_unlock_timestamp: i64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Only an active lock can be extended ---
+        require!(vault.is_locked, VaultError::VaultNotLocked);
+
+        // --- Security Check 2: Locks can only be pushed out, never shortened ---
+        require!(
+            new_unlock_timestamp > vault.unlock_timestamp,
+            VaultError::NewTimestampNotLater
+        );
+        require!(
+            new_unlock_timestamp > clock.unix_timestamp,
+            VaultError::InvalidUnlockTime
+        );
+
+        vault.unlock_timestamp = new_unlock_timestamp;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        msg!("Vault lock extended until timestamp: {}", new_unlock_timestamp);
+
+        emit!(VaultLocked {
+            vault: vault.key(),
+            unlock_timestamp: new_unlock_timestamp,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 28: Add a Depositor to the Whitelist
+    pub fn add_to_whitelist(ctx: Context<AddToWhitelist>) -> Result<()> {
+        let whitelist_entry = &mut ctx.accounts.whitelist_entry;
+        whitelist_entry.is_active = true;
+        whitelist_entry.added_by = ctx.accounts.authority.key();
+
+        msg!("Whitelisted depositor: {}", ctx.accounts.candidate.key());
+        Ok(())
+    }
+
+    // Instruction 29: Remove a Depositor from the Whitelist
+    pub fn remove_from_whitelist(ctx: Context<RemoveFromWhitelist>) -> Result<()> {
+        ctx.accounts.whitelist_entry.is_active = false;
+
+        msg!("Removed depositor from whitelist: {}", ctx.accounts.candidate.key());
+        Ok(())
+    }
+
+    // Instruction 30: Enable the Deposit Whitelist
+    pub fn enable_whitelist(ctx: Context<EnableWhitelist>) -> Result<()> {
+        ctx.accounts.vault.whitelist_enabled = true;
+
+        msg!("Deposit whitelist enabled.");
+        Ok(())
+    }
+
+    // Instruction 31: Disable the Deposit Whitelist
+    pub fn disable_whitelist(ctx: Context<DisableWhitelist>) -> Result<()> {
+        ctx.accounts.vault.whitelist_enabled = false;
+
+        msg!("Deposit whitelist disabled.");
+        Ok(())
+    }
+
+    // Instruction 32: Approve a Withdrawal Destination Token Account
+    pub fn approve_destination(ctx: Context<ApproveDestination>) -> Result<()> {
+        ctx.accounts.destination_entry.is_approved = true;
+
+        msg!("Approved withdrawal destination: {}", ctx.accounts.destination_token_account.key());
+        Ok(())
+    }
+
+    // Instruction 33: Revoke a Withdrawal Destination Token Account
+    pub fn revoke_destination(ctx: Context<RevokeDestination>) -> Result<()> {
+        ctx.accounts.destination_entry.is_approved = false;
+
+        msg!("Revoked withdrawal destination: {}", ctx.accounts.destination_token_account.key());
+        Ok(())
+    }
+
+    // Instruction 34: Enable the Withdrawal Destination Whitelist
+    pub fn enable_destination_whitelist(ctx: Context<EnableDestinationWhitelist>) -> Result<()> {
+        ctx.accounts.vault.withdrawal_destination_whitelist_enabled = true;
+
+        msg!("Withdrawal destination whitelist enabled.");
+        Ok(())
+    }
+
+    // Instruction 35: Disable the Withdrawal Destination Whitelist
+    pub fn disable_destination_whitelist(ctx: Context<DisableDestinationWhitelist>) -> Result<()> {
+        ctx.accounts.vault.withdrawal_destination_whitelist_enabled = false;
+
+        msg!("Withdrawal destination whitelist disabled.");
+        Ok(())
+    }
+
+    // Instruction 36: Grant a Delegate a capped Withdrawal Allowance
+    pub fn grant_delegate_allowance(
+        ctx: Context<GrantDelegateAllowance>,
+        allowance: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        // --- Security Check 1: The grant must not already be expired ---
+        let clock = Clock::get()?;
+        require!(expires_at > clock.unix_timestamp, VaultError::DelegateExpired);
+
+        // A new grant replaces the previous one and resets its usage
+        let delegate_config = &mut ctx.accounts.delegate_config;
+        delegate_config.delegate = ctx.accounts.delegate.key();
+        delegate_config.allowance = allowance;
+        delegate_config.used = 0;
+        delegate_config.expires_at = expires_at;
+
+        msg!(
+            "Delegate {} may withdraw up to {} tokens until {}",
+            delegate_config.delegate,
+            allowance,
+            expires_at
+        );
+        Ok(())
+    }
+
+    // Instruction 37: Revoke a Delegate (closes the Delegate Config)
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        msg!("Delegate revoked: {}", ctx.accounts.delegate.key());
+        Ok(())
+    }
+
+    // Instruction 38: Withdraw as a Delegate, within the granted Allowance
+    pub fn delegate_withdraw(ctx: Context<DelegateWithdraw>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let delegate_config = &mut ctx.accounts.delegate_config;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The checks every payout shares, see 'check_withdraw_gates' ---
+        check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // --- Security Check 2: The grant is still valid ---
+        require!(clock.unix_timestamp < delegate_config.expires_at, VaultError::DelegateExpired);
+
+        // --- Security Check 3: The allowance covers the amount ---
+        let used = delegate_config
+            .used
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(used <= delegate_config.allowance, VaultError::DelegateAllowanceExceeded);
+
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                to: ctx.accounts.delegate_token_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.mint,
+                vault_authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault,
+            amount,
+            clock.unix_timestamp,
+        )?;
+        delegate_config.used = used;
+
+        msg!(
+            "Delegate withdrew {} tokens ({} of {} used, fees: {}).",
+            net_amount,
+            used,
+            delegate_config.allowance,
+            treasury_amount
+        );
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.delegate_token_account.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 39: Set the HTLC Hashlock (SHA-256 of the secret preimage)
+    pub fn set_hashlock(ctx: Context<SetHashlock>, hashlock: [u8; 32]) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.hashlock = Some(hashlock);
+
+        msg!("Hashlock set on vault: {}", vault.key());
+        Ok(())
+    }
+
+    // Instruction 40: Withdraw by revealing the Hashlock Preimage
+    // HTLC: the hash AND the normal time lock must both be satisfied
+    pub fn hashlock_withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, HashlockWithdraw<'info>>,
+        amount: u64,
+        preimage: [u8; 32],
+        nonce: u64, // Replay protection, see 'InstructionNonce'
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.withdraw.vault;
+
+        // --- Security Check 0: A revealed preimage transaction can only be submitted once ---
+        mark_nonce_used(&mut ctx.accounts.instruction_nonce, vault.key(), nonce)?;
+
+        // --- Security Check 1: The preimage hashes to the stored hashlock ---
+        let hashlock = vault.hashlock.ok_or(VaultError::NoHashlockConfigured)?;
+        require!(hash(&preimage).to_bytes() == hashlock, VaultError::InvalidPreimage);
+
+        // The secret is now public, so the hashlock is spent
+        vault.hashlock = None;
+        msg!("Hashlock preimage accepted.");
+
+        // Everything else follows the standard withdrawal path
+        let withdraw_ctx = Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.withdraw,
+            ctx.remaining_accounts,
+            ctx.bumps.withdraw,
+        );
+        withdraw(withdraw_ctx, amount, None)
+    }
+
+    // Instruction 41: Create the global Program Config (the caller becomes the admin)
+    pub fn initialize_program_config(ctx: Context<InitializeProgramConfig>) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.admin = ctx.accounts.admin.key();
+        program_config.protocol_admin = ctx.accounts.admin.key();
+        program_config.authority_change_delay_secs = AUTHORITY_CHANGE_DELAY_SECS;
+        program_config.risk_admin = ctx.accounts.admin.key();
+        program_config.is_paused = false;
+        program_config.bump = ctx.bumps.program_config;
+
+        msg!("Program config initialized, admin: {}", program_config.admin);
+        Ok(())
+    }
+
+    // Instruction 42: Pause the whole Program (incident response)
+    pub fn pause_program(ctx: Context<PauseProgram>) -> Result<()> {
+        ctx.accounts.program_config.is_paused = true;
+
+        msg!("Program paused by admin.");
+        Ok(())
+    }
+
+    // Instruction 43: Unpause the whole Program
+    pub fn unpause_program(ctx: Context<UnpauseProgram>) -> Result<()> {
+        ctx.accounts.program_config.is_paused = false;
+
+        msg!("Program unpaused by admin.");
+        Ok(())
+    }
+
+    // Instruction 44: Add a Token Slot so the Vault can hold another Mint
+    pub fn add_token_slot(ctx: Context<AddTokenSlot>) -> Result<()> {
+        let slot = &mut ctx.accounts.slot;
+
+        // --- Security Check 1: One slot per mint ---
+        require!(slot.mint == Pubkey::default(), VaultError::SlotAlreadyExists);
+
+        slot.vault = ctx.accounts.vault.key();
+        slot.mint = ctx.accounts.mint.key();
+        slot.token_account = ctx.accounts.slot_token_account.key();
+        slot.slot_bump = ctx.bumps.slot;
+
+        msg!("Token slot added for mint: {}", slot.mint);
+        Ok(())
+    }
+
+    // Instruction 45: Deposit Tokens into a Token Slot
+    pub fn deposit_to_slot(ctx: Context<DepositToSlot>, amount: u64) -> Result<()> {
+        // --- Security Check 1: The checks every deposit shares, see 'check_deposit_gates' ---
+        // No fee is charged, the fee treasury holds the vault's primary mint
+        check_deposit_gates(
+            &ctx.accounts.vault,
+            DepositGate {
+                program_config: &ctx.accounts.program_config,
+                depositor: ctx.accounts.authority.key(),
+                amount,
+                vault_balance: ctx.accounts.slot_token_account.amount,
+                role_config: None,
+                whitelist_entry: None,
+                merkle_proof: &[],
+                memo_attached: false,
+                depositor_record: None,
+                depositor_rate_limit: None,
+            },
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        transfer_from_user(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.slot_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.authority.to_account_info(),
+            amount,
+        )?;
+
+        msg!("Deposited {} tokens into the {} slot.", amount, ctx.accounts.mint.key());
+        Ok(())
+    }
+
+    // Instruction 46: Withdraw Tokens from a Token Slot
+    pub fn withdraw_from_slot(ctx: Context<WithdrawFromSlot>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Time Lock ---
+        // A partial lock is counted in the primary mint, so any running lock holds every slot
+        require!(
+            !vault.is_locked || clock.unix_timestamp >= vault.unlock_timestamp,
+            VaultError::VaultStillLocked
+        );
+
+        // --- Security Check 2: The checks every payout shares, see 'check_withdraw_gates' ---
+        // No fee is charged, the fee treasury holds the vault's primary mint
+        check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.slot_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.slot_token_account.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        msg!("Withdrew {} tokens from the {} slot.", amount, ctx.accounts.mint.key());
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.user_token_account.key(),
+            amount,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 47: Deposit native SOL, wrapped into the vault's wSOL account
+    pub fn deposit_sol(ctx: Context<DepositSol>, amount: u64) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The program is not paused ---
+        require!(!ctx.accounts.program_config.is_paused, VaultError::ProgramPaused);
+
+        // --- Security Check 2: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 3: Only SOL vaults accept lamports ---
+        require!(vault.is_sol_vault, VaultError::NotSolVault);
+
+        // --- Security Check 4: A vault being drained takes no new funds ---
+        require!(!vault.shutdown_mode, VaultError::VaultInShutdown);
+
+        // --- Security Check 5: Reject dust deposits ---
+        require!(amount >= vault.min_deposit_amount, VaultError::DepositTooSmall);
+
+        // Move the lamports into the wSOL account, then sync its token balance
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.vault_token_account.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        token_interface::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.vault_token_account.to_account_info(),
+            },
+        ))?;
+
+        msg!("Deposited {} lamports into the SOL vault.", amount);
+
+        emit!(TokensDeposited {
+            vault: vault.key(),
+            depositor: ctx.accounts.authority.key(),
+            amount,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 48: Withdraw native SOL, unwrapped through the user's wSOL account
+    pub fn withdraw_sol(ctx: Context<WithdrawSol>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Only SOL vaults pay out lamports ---
+        require!(vault.is_sol_vault, VaultError::NotSolVault);
+
+        // --- Security Check 2: The checks every payout shares, see 'check_withdraw_gates' ---
+        check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // Move the wSOL, less fees, to the user's wSOL account...
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                to: ctx.accounts.user_wsol_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.native_mint,
+                vault_authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault,
+            amount,
+            clock.unix_timestamp,
+        )?;
+
+        // ...then close it, which unwraps every lamport it holds back to the user
+        token_interface::close_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.user_wsol_account.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            },
+        ))?;
+
+        msg!("Withdrew {} lamports from the SOL vault (fees: {}).", net_amount, treasury_amount);
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.authority.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 49: Move Tokens between two Vaults owned by the same Authority
+    pub fn vault_to_vault_transfer(ctx: Context<VaultToVaultTransfer>, amount: u64) -> Result<()> {
+        let source_vault = &mut ctx.accounts.source_vault;
+        let destination_vault = &mut ctx.accounts.destination_vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The checks every payout from the source shares, see 'check_withdraw_gates' ---
+        check_withdraw_gates(
+            source_vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.source_vault_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        source_vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // --- Security Check 2: The checks every deposit into the destination shares, see 'check_deposit_gates' ---
+        // The source's withdrawal fees stand in for the destination's deposit fee. No depositor record or
+        // rate limit is passed, a destination that caps its depositors is funded through 'deposit'.
+        check_deposit_gates(
+            destination_vault,
+            DepositGate {
+                program_config: &ctx.accounts.program_config,
+                depositor: ctx.accounts.authority.key(),
+                amount,
+                vault_balance: ctx.accounts.destination_vault_token_account.amount,
+                role_config: None,
+                whitelist_entry: None,
+                merkle_proof: &[],
+                memo_attached: false,
+                depositor_record: None,
+                depositor_rate_limit: None,
+            },
+            clock.unix_timestamp,
+        )?;
+
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.source_vault_token_account.to_account_info(),
+                to: ctx.accounts.destination_vault_token_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.mint,
+                vault_authority: ctx.accounts.source_vault_authority.to_account_info(),
+            },
+            source_vault,
+            amount,
+            clock.unix_timestamp,
+        )?;
+
+        // The destination books the transfer like any other deposit
+        ctx.accounts.destination_vault_token_account.reload()?;
+        let destination_key = destination_vault.key();
+        destination_vault.record_deposit(
+            destination_key,
+            ctx.accounts.authority.key(),
+            net_amount,
+            ctx.accounts.destination_vault_token_account.amount,
+            clock.unix_timestamp,
+        )?;
+
+        msg!(
+            "Moved {} tokens from vault {} to vault {} (fees: {}).",
+            net_amount,
+            source_vault.key(),
+            destination_vault.key(),
+            treasury_amount
+        );
+
+        emit!(TokensWithdrawn {
+            vault: source_vault.key(),
+            destination: ctx.accounts.destination_vault_token_account.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        emit!(TokensDeposited {
+            vault: destination_vault.key(),
+            depositor: ctx.accounts.authority.key(),
+            amount: net_amount,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 50: Split part of a Vault off into a brand new, independent Vault
+    pub fn split_vault(
+        ctx: Context<SplitVault>,
+        split_amount: u64,
+        new_authority_bump: u8, // Passed from client, stored for future PDA checks
+        _split_index: u8,       // Lets one source vault be split more than once
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let source_vault = &mut ctx.accounts.source_vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The split must be covered by the source balance ---
+        require!(
+            ctx.accounts.source_vault_token_account.amount >= split_amount,
+            VaultError::SplitAmountExceedsBalance
+        );
+
+        // --- Security Check 2: The checks every payout from the source shares, see 'check_withdraw_gates' ---
+        check_withdraw_gates(
+            source_vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.source_vault_token_account.amount,
+                amount: split_amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        source_vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // The new vault is keyed by the split seed, so every other instruction can re-derive it
+        let new_vault = &mut ctx.accounts.new_vault;
+        new_vault.authority = new_authority;
+        new_vault.original_authority = ctx.accounts.split_seed.key();
+        new_vault.token_account = ctx.accounts.new_token_account.key();
+        new_vault.token_program_id = source_vault.token_program_id;
+        new_vault.schema_version = 1;
+        new_vault.bump = ctx.bumps.new_vault;
+        new_vault.authority_bump = new_authority_bump;
+        new_vault.beneficiary = new_authority;
+        new_vault.is_sol_vault = source_vault.is_sol_vault;
+
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.source_vault_token_account.to_account_info(),
+                to: ctx.accounts.new_token_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.mint,
+                vault_authority: ctx.accounts.source_vault_authority.to_account_info(),
+            },
+            source_vault,
+            split_amount,
+            clock.unix_timestamp,
+        )?;
+
+        msg!("Split {} tokens into new vault: {} (fees: {})", net_amount, new_vault.key(), treasury_amount);
+
+        emit!(VaultInitialized {
+            vault: new_vault.key(),
+            authority: new_vault.authority,
+            token_account: new_vault.token_account,
+            mint: ctx.accounts.mint.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        emit!(TokensWithdrawn {
+            vault: source_vault.key(),
+            destination: new_vault.token_account,
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 51: Create the LP share Mint for the Vault
+    pub fn initialize_lp_mint(ctx: Context<InitializeLpMint>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Shares start 1:1, so the vault must not hold unbacked tokens ---
+        require!(
+            ctx.accounts.vault_token_account.amount == 0,
+            VaultError::VaultNotEmpty
+        );
+
+        vault.lp_mint = Some(ctx.accounts.lp_mint.key());
+
+        msg!("LP mint initialized: {}", ctx.accounts.lp_mint.key());
+        Ok(())
+    }
+
+    // Instruction 52: Set the Flash Loan Fee
+    pub fn set_flash_loan_fee(ctx: Context<SetFlashLoanFee>, flash_loan_fee_bps: u16) -> Result<()> {
+        // --- Security Check 1: Fees are capped ---
+        require!(flash_loan_fee_bps <= MAX_FEE_BPS, VaultError::FeeTooHigh);
+
+        ctx.accounts.vault.flash_loan_fee_bps = flash_loan_fee_bps;
+
+        msg!("Flash loan fee set to: {} bps", flash_loan_fee_bps);
+        Ok(())
+    }
+
+    // Instruction 53: Borrow Vault Tokens for the rest of this Transaction
+    pub fn flash_loan(ctx: Context<FlashLoan>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: One loan at a time ---
+        require!(!vault.flash_loan_active, VaultError::FlashLoanActive);
+
+        // --- Security Check 2: Insufficient Funds ---
+        require!(
+            ctx.accounts.vault_token_account.amount >= amount,
+            VaultError::InsufficientFunds
+        );
+
+        // --- Security Check 3: A repay for this vault must follow in the same transaction ---
+        let instructions = ctx.accounts.instructions.to_account_info();
+        let current_index = load_current_index_checked(&instructions)? as usize;
+        let mut index = current_index + 1;
+        let mut repay_found = false;
+        while let Ok(ix) = load_instruction_at_checked(index, &instructions) {
+            if ix.program_id == crate::ID
+                && ix.data.starts_with(crate::instruction::FlashLoanRepay::DISCRIMINATOR)
+                && ix.accounts.first().map(|meta| meta.pubkey) == Some(vault.key())
+            {
+                repay_found = true;
+                break;
+            }
+            index += 1;
+        }
+        require!(repay_found, VaultError::FlashLoanRepaymentMissing);
+
+        vault.flash_loan_active = true;
+        vault.flash_loan_amount = amount;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.borrower_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        msg!("Flash loan of {} tokens issued.", amount);
+        Ok(())
+    }
+
+    // Instruction 54: Repay the active Flash Loan plus its Fee
+    pub fn flash_loan_repay(ctx: Context<FlashLoanRepay>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: There must be a loan to repay ---
+        require!(vault.flash_loan_active, VaultError::FlashLoanNotActive);
+
+        let fee = fee_amount(vault.flash_loan_amount, vault.flash_loan_fee_bps)?;
+        let repayment = vault
+            .flash_loan_amount
+            .checked_add(fee)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        // --- Security Check 2: The borrower can cover principal and fee ---
+        require!(
+            ctx.accounts.borrower_token_account.amount >= repayment,
+            VaultError::FlashLoanRepaymentInsufficient
+        );
+
+        transfer_from_user(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.borrower_token_account.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.borrower.to_account_info(),
+            repayment,
+        )?;
+
+        vault.flash_loan_active = false;
+        vault.flash_loan_amount = 0;
+
+        msg!("Flash loan repaid: {} tokens (fee: {}).", repayment, fee);
+        Ok(())
+    }
+
+    // Instruction 55: Create a Payment Stream that drips Tokens to a Recipient
+    pub fn create_stream(
+        ctx: Context<CreateStream>,
+        rate_per_second: u64,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<()> {
+        // --- Security Check 1: The schedule must be a real interval with a positive rate ---
+        require!(
+            end_time > start_time && rate_per_second > 0,
+            VaultError::InvalidVestingSchedule
+        );
+
+        let stream = &mut ctx.accounts.stream;
+        stream.recipient = ctx.accounts.recipient.key();
+        stream.rate_per_second = rate_per_second;
+        stream.start_time = start_time;
+        stream.end_time = end_time;
+        stream.last_claimed_at = start_time;
+        stream.total_claimed = 0;
+
+        msg!(
+            "Stream created for {}: {} tokens/s from {} to {}",
+            stream.recipient,
+            rate_per_second,
+            start_time,
+            end_time
+        );
+        Ok(())
+    }
+
+    // Instruction 56: Claim everything accrued on a Stream since the last Claim
+    pub fn claim_stream(ctx: Context<ClaimStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(&ctx.accounts.vault)?;
+
+        // --- Security Check 2: The stream is running ---
+        require!(clock.unix_timestamp >= stream.start_time, VaultError::StreamNotStarted);
+        require!(stream.last_claimed_at < stream.end_time, VaultError::StreamEnded);
+
+        // --- Security Check 3: Something has accrued since the last claim ---
+        let claimable = stream.claimable(clock.unix_timestamp)?;
+        require!(claimable > 0, VaultError::NothingToClaim);
+
+        // --- Security Check 4: The checks every claim shares, see 'check_claim_gate' ---
+        check_claim_gate(
+            &ctx.accounts.vault,
+            &ctx.accounts.program_config,
+            ctx.accounts.vault_token_account.amount,
+            claimable,
+            clock.unix_timestamp,
+            false,
+        )?;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.recipient_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            &ctx.accounts.vault,
+            claimable,
+        )?;
+
+        stream.last_claimed_at = clock.unix_timestamp.min(stream.end_time);
+        stream.total_claimed = stream
+            .total_claimed
+            .checked_add(claimable)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!("Claimed {} streamed tokens ({} total)", claimable, stream.total_claimed);
+
+        emit!(TokensWithdrawn {
+            vault: ctx.accounts.vault.key(),
+            destination: ctx.accounts.recipient_token_account.key(),
+            amount: claimable,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 57: Cancel a Stream, whatever accrued so far stays claimable
+    pub fn cancel_stream(ctx: Context<CancelStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let clock = Clock::get()?;
+
+        stream.end_time = stream
+            .end_time
+            .min(clock.unix_timestamp.max(stream.last_claimed_at));
+
+        msg!("Stream for {} cancelled, accrual ends at {}", stream.recipient, stream.end_time);
+        Ok(())
+    }
+
+    // Instruction 58: Schedule a Withdrawal to be executed after a given Time
+    pub fn create_scheduled_withdrawal(
+        ctx: Context<CreateScheduledWithdrawal>,
+        nonce: u64, // Lets one vault hold several scheduled withdrawals
+        amount: u64,
+        execute_after: i64,
+        recipient_token_account: Pubkey,
+    ) -> Result<()> {
+        let scheduled = &mut ctx.accounts.scheduled_withdrawal;
+        scheduled.amount = amount;
+        scheduled.execute_after = execute_after;
+        scheduled.recipient_token_account = recipient_token_account;
+        scheduled.cancelled = false;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.pending_scheduled_withdrawal_count = vault
+            .pending_scheduled_withdrawal_count
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!(
+            "Scheduled withdrawal #{} of {} tokens after {}",
+            nonce,
+            amount,
+            execute_after
+        );
+        Ok(())
+    }
+
+    // Instruction 59: Execute a due Scheduled Withdrawal (permissionless crank)
+    pub fn execute_scheduled_withdrawal(
+        ctx: Context<ExecuteScheduledWithdrawal>,
+        _nonce: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let scheduled = &ctx.accounts.scheduled_withdrawal;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Not cancelled and due ---
+        require!(!scheduled.cancelled, VaultError::WithdrawalCancelled);
+        require!(
+            clock.unix_timestamp >= scheduled.execute_after,
+            VaultError::WithdrawalNotReady
+        );
+
+        // --- Security Check 2: The checks every payout shares, see 'check_withdraw_gates' ---
+        check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount: scheduled.amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.mint,
+                vault_authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault,
+            scheduled.amount,
+            clock.unix_timestamp,
+        )?;
+
+        msg!("Executed scheduled withdrawal of {} tokens (fees: {}).", net_amount, treasury_amount);
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.recipient_token_account.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        // The Scheduled Withdrawal PDA is closed by the `close = rent_recipient` constraint.
+        // Schedules created before the counter existed were never counted, hence saturating.
+        vault.pending_scheduled_withdrawal_count = vault.pending_scheduled_withdrawal_count.saturating_sub(1);
+        Ok(())
+    }
+
+    // Instruction 60: Cancel a pending Scheduled Withdrawal
+    pub fn cancel_scheduled_withdrawal(
+        ctx: Context<CancelScheduledWithdrawal>,
+        nonce: u64,
+    ) -> Result<()> {
+        ctx.accounts.scheduled_withdrawal.cancelled = true;
+
+        msg!("Scheduled withdrawal #{} cancelled.", nonce);
+        Ok(())
+    }
+
+    // Instruction 61: Configure the Guardians allowed to recover the Vault
+    pub fn setup_recovery_config(
+        ctx: Context<SetupRecoveryConfig>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        // --- Security Check 1: Guardian set must be valid ---
+        require!(
+            !guardians.is_empty() && guardians.len() <= RecoveryConfig::MAX_GUARDIANS,
+            VaultError::InvalidRecoveryConfig
+        );
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(
+                !guardians[..i].contains(guardian),
+                VaultError::InvalidRecoveryConfig
+            );
+        }
+
+        // --- Security Check 2: Threshold must be reachable ---
+        require!(
+            threshold > 0 && threshold as usize <= guardians.len(),
+            VaultError::InvalidRecoveryConfig
+        );
+
+        let recovery_config = &mut ctx.accounts.recovery_config;
+        recovery_config.vault = ctx.accounts.vault.key();
+        recovery_config.guardians = guardians;
+        recovery_config.threshold = threshold;
+        recovery_config.clear();
+
+        msg!(
+            "Recovery configured: {}-of-{} guardians",
+            threshold,
+            recovery_config.guardians.len()
+        );
+        Ok(())
+    }
+
+    // Instruction 62: Start a Recovery to a new Authority (any guardian)
+    pub fn initiate_recovery(ctx: Context<InitiateRecovery>, new_authority: Pubkey) -> Result<()> {
+        let recovery_config = &mut ctx.accounts.recovery_config;
+        let guardian = ctx.accounts.guardian.key();
+
+        // --- Security Check 1: Only a guardian can start a recovery ---
+        require!(
+            recovery_config.guardians.contains(&guardian),
+            VaultError::NotRecoveryGuardian
+        );
+
+        // --- Security Check 2: One recovery at a time ---
+        require!(
+            recovery_config.recovery_initiated_at == 0,
+            VaultError::RecoveryAlreadyInitiated
+        );
+
+        recovery_config.recovery_initiated_at = Clock::get()?.unix_timestamp;
+        recovery_config.new_authority_proposed = new_authority;
+        recovery_config.approvals = vec![guardian]; // The initiator approves implicitly
+
+        msg!("Recovery to {} initiated by guardian: {}", new_authority, guardian);
+        Ok(())
+    }
+
+    // Instruction 63: Approve the pending Recovery (any guardian)
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+        let recovery_config = &mut ctx.accounts.recovery_config;
+        let guardian = ctx.accounts.guardian.key();
+
+        // --- Security Check 1: Only a guardian can approve ---
+        require!(
+            recovery_config.guardians.contains(&guardian),
+            VaultError::NotRecoveryGuardian
+        );
+        require!(
+            recovery_config.recovery_initiated_at != 0,
+            VaultError::RecoveryNotInitiated
+        );
+
+        // --- Security Check 2: Each guardian approves once ---
+        require!(
+            !recovery_config.approvals.contains(&guardian),
+            VaultError::AlreadyApproved
+        );
+
+        recovery_config.approvals.push(guardian);
+
+        msg!(
+            "Recovery approved by guardian: {} ({} approvals)",
+            guardian,
+            recovery_config.approvals.len()
+        );
+        Ok(())
+    }
+
+    // Instruction 64: Execute the Recovery once approved and the delay has passed (anyone)
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        let recovery_config = &mut ctx.accounts.recovery_config;
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        require!(
+            recovery_config.recovery_initiated_at != 0,
+            VaultError::RecoveryNotInitiated
+        );
+
+        // --- Security Check 1: Enough guardians approved ---
+        require!(
+            recovery_config.approvals.len() >= recovery_config.threshold as usize,
+            VaultError::RecoveryThresholdNotMet
+        );
+
+        // --- Security Check 2: The owner had time to cancel ---
+        let executable_at = recovery_config
+            .recovery_initiated_at
+            .checked_add(RECOVERY_DELAY_SECS)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            clock.unix_timestamp >= executable_at,
+            VaultError::RecoveryTimelockActive
+        );
+
+        let old_authority = vault.authority;
+        vault.authority = recovery_config.new_authority_proposed;
+        vault.pending_authority = Pubkey::default(); // A change requested with the lost key dies with it
+        vault.authority_change_unlocks_at = 0;
+        recovery_config.clear();
+
+        msg!("Vault recovered from {} to {}", old_authority, vault.authority);
+
+        emit!(AuthorityTransferred {
+            vault: vault.key(),
+            old_authority,
+            new_authority: vault.authority,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 65: Cancel a pending Recovery (the current authority still holds the key)
+    pub fn cancel_recovery(ctx: Context<CancelRecovery>) -> Result<()> {
+        ctx.accounts.recovery_config.clear();
+
+        msg!("Recovery cancelled by the vault authority.");
+        Ok(())
+    }
+
+    // Instruction 66: Configure the Dead-Man's Switch
+    pub fn configure_dead_man_switch(
+        ctx: Context<ConfigureDeadManSwitch>,
+        inactivity_period_secs: i64, // 0 = disabled
+        dead_man_beneficiary: Pubkey,
+    ) -> Result<()> {
+        require!(inactivity_period_secs >= 0, VaultError::InvalidUnlockTime);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.inactivity_period_secs = inactivity_period_secs;
+        vault.dead_man_beneficiary = dead_man_beneficiary;
+        vault.last_activity_timestamp = Clock::get()?.unix_timestamp; // Configuring counts as activity
+
+        msg!(
+            "Dead-man's switch set: {} after {} seconds of inactivity (0 = disabled)",
+            dead_man_beneficiary,
+            inactivity_period_secs
+        );
+        Ok(())
+    }
+
+    // Instruction 67: Claim the whole Vault after the owner has been inactive (beneficiary only)
+    pub fn dead_man_claim(ctx: Context<DeadManClaim>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: A switch must be armed ---
+        require!(
+            vault.inactivity_period_secs > 0 && vault.dead_man_beneficiary != Pubkey::default(),
+            VaultError::NoBeneficiaryConfigured
+        );
+
+        // --- Security Check 3: Only the designated beneficiary can claim ---
+        require_keys_eq!(
+            ctx.accounts.claimant.key(),
+            vault.dead_man_beneficiary,
+            VaultError::NotBeneficiary
+        );
+
+        // --- Security Check 4: The owner has been inactive long enough ---
+        let inactive_for = clock
+            .unix_timestamp
+            .checked_sub(vault.last_activity_timestamp)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            inactive_for >= vault.inactivity_period_secs,
+            VaultError::InactivityPeriodNotElapsed
+        );
+
+        let amount = ctx.accounts.vault_token_account.amount;
+        // Everything the lock does not hold back
+        let amount = if vault.is_locked && clock.unix_timestamp < vault.unlock_timestamp {
+            amount.saturating_sub(vault.locked_amount)
+        } else {
+            amount
+        };
+
+        // --- Security Check 5: The checks every claim shares, see 'check_claim_gate' ---
+        check_claim_gate(
+            vault,
+            &ctx.accounts.program_config,
+            ctx.accounts.vault_token_account.amount,
+            amount,
+            clock.unix_timestamp,
+            false,
+        )?;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.beneficiary_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        msg!("Dead-man's switch claimed {} tokens after {} seconds of inactivity.", amount, inactive_for);
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.beneficiary_token_account.key(),
+            amount,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 68: Configure the Recipients that share every Withdrawal
+    pub fn configure_split(
+        ctx: Context<ConfigureSplit>,
+        recipients: Vec<SplitRecipient>,
+        recipient_token_accounts: Vec<Pubkey>,
+    ) -> Result<()> {
+        // --- Security Check 1: Bounded, one token account per recipient ---
+        require!(
+            recipients.len() <= BeneficiarySplit::MAX_RECIPIENTS,
+            VaultError::TooManyRecipients
+        );
+        require!(
+            recipients.len() == recipient_token_accounts.len(),
+            VaultError::SplitRecipientMismatch
+        );
+
+        // --- Security Check 2: Shares must add up to exactly 100% ---
+        let total_bps = recipients
+            .iter()
+            .try_fold(0u64, |total, recipient| total.checked_add(recipient.bps as u64))
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(total_bps == BPS_DENOMINATOR, VaultError::SplitBpsDoNotSum);
+
+        let beneficiary_split = &mut ctx.accounts.beneficiary_split;
+        beneficiary_split.vault = ctx.accounts.vault.key();
+        beneficiary_split.recipients = recipients;
+        beneficiary_split.recipient_token_accounts = recipient_token_accounts;
+
+        ctx.accounts.vault.beneficiary_split_enabled = true;
+
+        msg!("Withdrawals now split across {} recipients", beneficiary_split.recipients.len());
+        Ok(())
+    }
+
+    // Instruction 69: Gate Withdrawals on a Pyth Price crossing a Threshold
+    pub fn set_oracle_unlock(
+        ctx: Context<SetOracleUnlock>,
+        oracle_feed: Pubkey, // Pubkey::default() = disabled
+        oracle_program: Pubkey,
+        oracle_unlock_price: i64,
+        oracle_unlock_direction: u8,
+    ) -> Result<()> {
+        require!(
+            oracle_unlock_direction == ORACLE_UNLOCK_ABOVE || oracle_unlock_direction == ORACLE_UNLOCK_BELOW,
+            VaultError::InvalidOracleDirection
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        vault.oracle_feed = oracle_feed;
+        vault.oracle_program = oracle_program;
+        vault.oracle_unlock_price = oracle_unlock_price;
+        vault.oracle_unlock_direction = oracle_unlock_direction;
+
+        msg!(
+            "Oracle unlock set: feed {} must be {} {}",
+            oracle_feed,
+            if oracle_unlock_direction == ORACLE_UNLOCK_ABOVE { "above" } else { "below" },
+            oracle_unlock_price
+        );
+        Ok(())
+    }
+
+    // Instruction 70: Set the Per-Epoch Withdrawal Limit
+    pub fn set_epoch_withdrawal_limit(
+        ctx: Context<SetEpochWithdrawalLimit>,
+        epoch_withdrawal_limit: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.epoch_withdrawal_limit = epoch_withdrawal_limit;
+
+        msg!("Epoch withdrawal limit set to: {} (0 = unlimited)", epoch_withdrawal_limit);
+        Ok(())
+    }
+
+    // Instruction 71: Grow a Vault created under an older, smaller layout
+    pub fn realloc_vault(ctx: Context<ReallocVault>, _original_authority: Pubkey) -> Result<()> {
+        let vault_info = ctx.accounts.vault.to_account_info();
+
+        // Top up the rent for the new size, then grow the account. New bytes are zero-filled,
+        // so the new fields deserialize as defaults.
+        let new_len = 8 + Vault::INIT_SPACE;
+        let rent_shortfall = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(vault_info.lamports());
+        if rent_shortfall > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: vault_info.clone(),
+                    },
+                ),
+                rent_shortfall,
+            )?;
+        }
+        vault_info.resize(new_len)?;
+        let mut vault = Vault::try_deserialize(&mut &vault_info.try_borrow_data()?[..])?;
+
+        // --- Security Check 1: Only the vault authority can pay for the upgrade ---
+        // A failed check reverts the resize and the rent top-up with it
+        require_keys_eq!(
+            vault.authority,
+            ctx.accounts.authority.key(),
+            VaultError::UnauthorizedAccess
+        );
+
+        vault.version = vault
+            .version
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        vault.schema_version = vault
+            .schema_version
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        vault.try_serialize(&mut &mut vault_info.try_borrow_mut_data()?[..])?;
+
+        msg!("Vault resized to {} bytes, layout version {}", vault_info.data_len(), vault.version);
+        Ok(())
+    }
+
+    // Instruction 72: Migrate a Vault from the original v1 layout into a VaultV2 account
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        // Copy out of the packed v1 layout before comparing, references to packed fields are not allowed
+        let legacy_vault = ctx.accounts.legacy_vault.load()?;
+        let authority = legacy_vault.authority;
+        let token_account = legacy_vault.token_account;
+        let bump = legacy_vault.bump;
+        let authority_bump = legacy_vault.authority_bump;
+        let is_locked = legacy_vault.is_locked != 0;
+        let unlock_timestamp = legacy_vault.unlock_timestamp;
+        drop(legacy_vault); // Release the borrow before 'close' runs on exit
+
+        // --- Security Check 1: Only the legacy vault's authority can migrate it ---
+        require_keys_eq!(authority, ctx.accounts.authority.key(), VaultError::UnauthorizedAccess);
+
+        let vault_v2 = &mut ctx.accounts.vault_v2;
+        vault_v2.schema_version = VaultV2::SCHEMA_VERSION;
+        vault_v2.authority = authority;
+        vault_v2.token_account = token_account;
+        vault_v2.bump = bump;
+        vault_v2.authority_bump = authority_bump; // Still derived from the legacy vault key
+        vault_v2.is_locked = is_locked;
+        vault_v2.unlock_timestamp = unlock_timestamp;
+        vault_v2.legacy_vault = ctx.accounts.legacy_vault.key();
+
+        msg!("Vault {} migrated to v2 at {}", vault_v2.legacy_vault, vault_v2.key());
+
+        emit!(VaultMigrated {
+            legacy_vault: vault_v2.legacy_vault,
+            vault_v2: vault_v2.key(),
+            authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 73: Create the Registry that lists every Vault an authority creates
+    pub fn create_vault_registry(ctx: Context<CreateVaultRegistry>) -> Result<()> {
+        let vault_registry = &mut ctx.accounts.vault_registry;
+        vault_registry.authority = ctx.accounts.authority.key();
+        vault_registry.vault_count = 0;
+        vault_registry.bump = ctx.bumps.vault_registry;
+
+        msg!("Vault registry created for: {}", vault_registry.authority);
+        Ok(())
+    }
+
+    // Instruction 74: Log every Vault in a Registry (read-only)
+    pub fn get_registry(ctx: Context<GetRegistry>) -> Result<()> {
+        let vault_registry = &ctx.accounts.vault_registry;
+
+        msg!("Authority: {}", vault_registry.authority);
+        msg!("Vault count: {}", vault_registry.vault_count);
+        for entry in vault_registry.vaults.iter() {
+            msg!("Vault {}: {}", entry.vault_index, entry.vault);
+        }
+        Ok(())
+    }
+
+    // Instruction 75: Set the Early-Unlock Penalty charged before the Recommended Hold ends
+    pub fn set_early_unlock_penalty(
+        ctx: Context<SetEarlyUnlockPenalty>,
+        recommended_hold_until: i64,
+        early_unlock_penalty_bps: u16,
+    ) -> Result<()> {
+        // --- Security Check 1: Penalties are capped like fees ---
+        require!(early_unlock_penalty_bps <= MAX_FEE_BPS, VaultError::FeeTooHigh);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.recommended_hold_until = recommended_hold_until;
+        vault.early_unlock_penalty_bps = early_unlock_penalty_bps;
+
+        msg!(
+            "Early-unlock penalty set to {} bps until timestamp: {}",
+            early_unlock_penalty_bps,
+            recommended_hold_until
+        );
+        Ok(())
+    }
+
+    // Instruction 76: Pay a Referrer its earned Fees out of the Protocol Fee Account
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+        let referral_record = &mut ctx.accounts.referral_record;
+        let amount = referral_record.earned_fees;
+        require!(amount > 0, VaultError::NothingToClaim);
+
+        // The treasury owner co-signs, the fees were collected into its account
+        transfer_from_user(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.fee_treasury_token_account.to_account_info(),
+            ctx.accounts.referrer_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.treasury_authority.to_account_info(),
+            amount,
+        )?;
+        referral_record.earned_fees = 0;
+
+        msg!("Paid {} referral fee tokens to: {}", amount, referral_record.referrer);
+        Ok(())
+    }
+
+    // Instruction 77: Approve an SPL Delegate on the Vault Token Account
+    pub fn grant_vault_delegation(
+        ctx: Context<GrantVaultDelegation>,
+        delegate_key: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: A delegate can never spend what the time lock holds back ---
+        // Same rules as 'withdraw': a full lock blocks any approval, a partial lock caps it
+        // at the balance above `locked_amount`
+        if vault.is_locked && Clock::get()?.unix_timestamp < vault.unlock_timestamp {
+            if vault.locked_amount == 0 {
+                vault.log_locked_message();
+                return err!(VaultError::VaultStillLocked);
+            }
+            require!(
+                amount <= ctx.accounts.vault_token_account.amount.saturating_sub(vault.locked_amount),
+                VaultError::WithdrawWouldViolateLock
+            );
+        }
+
+        let vault_key = vault.key();
+        let authority_seed = &[b"authority", vault_key.as_ref(), &[vault.authority_bump]];
+        let signer = &[&authority_seed[..]];
+        token_interface::approve(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Approve {
+                    to: ctx.accounts.vault_token_account.to_account_info(),
+                    delegate: ctx.accounts.delegate.to_account_info(),
+                    authority: ctx.accounts.vault_authority.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+        )?;
+
+        vault.current_delegate = Some(delegate_key);
+        vault.delegated_amount = amount;
+
+        msg!("Vault token account delegated to {} for up to {} tokens", delegate_key, amount);
+        Ok(())
+    }
+
+    // Instruction 78: Revoke the SPL Delegate on the Vault Token Account
+    pub fn revoke_vault_delegation(ctx: Context<RevokeVaultDelegation>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        let vault_key = vault.key();
+        let authority_seed = &[b"authority", vault_key.as_ref(), &[vault.authority_bump]];
+        let signer = &[&authority_seed[..]];
+        token_interface::revoke(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Revoke {
+                source: ctx.accounts.vault_token_account.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        vault.current_delegate = None;
+        vault.delegated_amount = 0;
+
+        msg!("Vault token account delegation revoked.");
+        Ok(())
+    }
+
+    // Instruction 79: Configure the Counterparties who can jointly approve an early Unlock
+    pub fn configure_unlock_vote(
+        ctx: Context<ConfigureUnlockVote>,
+        required_signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        // --- Security Check 1: Signer set must be valid ---
+        require!(
+            !required_signers.is_empty() && required_signers.len() <= UnlockVote::MAX_SIGNERS,
+            VaultError::InvalidUnlockVoteConfig
+        );
+        for (i, signer) in required_signers.iter().enumerate() {
+            require!(
+                !required_signers[..i].contains(signer),
+                VaultError::InvalidUnlockVoteConfig
+            );
+        }
+
+        // --- Security Check 2: Threshold must be reachable ---
+        require!(
+            threshold > 0 && threshold as usize <= required_signers.len(),
+            VaultError::InvalidUnlockVoteConfig
+        );
+
+        let unlock_vote = &mut ctx.accounts.unlock_vote;
+        unlock_vote.vault = ctx.accounts.vault.key();
+        unlock_vote.required_signers = required_signers;
+        unlock_vote.threshold = threshold;
+        unlock_vote.approvals_bitmask = 0; // Reconfiguring discards earlier votes
+
+        msg!(
+            "Unlock vote configured: {}-of-{} signers",
+            threshold,
+            unlock_vote.required_signers.len()
+        );
+        Ok(())
+    }
+
+    // Instruction 80: Vote to Unlock the Vault early (required signers only)
+    pub fn cast_unlock_vote(ctx: Context<CastUnlockVote>) -> Result<()> {
+        let unlock_vote = &mut ctx.accounts.unlock_vote;
+        let signer = ctx.accounts.signer.key();
+
+        // --- Security Check 1: Only a required signer can vote ---
+        let index = unlock_vote
+            .required_signers
+            .iter()
+            .position(|required| *required == signer)
+            .ok_or(VaultError::NotARequiredSigner)?;
+
+        // --- Security Check 2: Each signer votes once ---
+        let bit = 1u8 << index;
+        require!(unlock_vote.approvals_bitmask & bit == 0, VaultError::AlreadyVoted);
+        unlock_vote.approvals_bitmask |= bit;
+
+        msg!(
+            "Unlock vote cast by: {} ({} of {} needed)",
+            signer,
+            unlock_vote.approvals_bitmask.count_ones(),
+            unlock_vote.threshold
+        );
+        Ok(())
+    }
+
+    // Instruction 81: Set the Merkle Root of the Depositor Whitelist
+    pub fn set_whitelist_root(ctx: Context<SetWhitelistRoot>, whitelist_merkle_root: [u8; 32]) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.whitelist_merkle_root = whitelist_merkle_root;
+
+        msg!("Whitelist merkle root set (all zeroes = disabled)");
+        Ok(())
+    }
+
+    // Instruction 82: Set the Reward Paid to Keepers for Cranking the Unlock
+    pub fn set_crank_reward(ctx: Context<SetCrankReward>, crank_reward_lamports: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.crank_reward_lamports = crank_reward_lamports;
+
+        msg!("Crank reward set to {} lamports", crank_reward_lamports);
+        Ok(())
+    }
+
+    // Instruction 83: Unlock an Expired Vault (Permissionless, Keeper-Rewarded)
+    pub fn crank_unlock_vault(ctx: Context<CrankUnlockVault>) -> Result<()> {
+        let clock = Clock::get()?;
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 0: The program is not paused ---
+        require!(!ctx.accounts.program_config.is_paused, VaultError::ProgramPaused);
+
+        // --- Security Check 1: Only a locked vault pays out, or the reward could be farmed ---
+        require!(vault.is_locked, VaultError::VaultNotLocked);
+
+        // --- Security Check 2: The lock has expired ---
+        require!(
+            clock.unix_timestamp >= vault.unlock_timestamp,
+            VaultError::VaultStillLocked
+        );
+
+        // --- Security Check 3: The authority opted in to paying keepers ---
+        let reward = vault.crank_reward_lamports;
+        require!(reward > 0, VaultError::CrankRewardNotConfigured);
+
+        // --- Security Check 4: The reward comes out of lamports above the rent-exempt minimum ---
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(vault_info.data_len());
+        require!(
+            vault_info.lamports().saturating_sub(rent_exempt_minimum) >= reward,
+            VaultError::InsufficientFunds
+        );
+
+        vault.is_locked = false;
+        vault.unlock_timestamp = 0; // Reset timestamp
+        vault.locked_amount = 0;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // The vault carries data, so the System Program can't debit it; move the lamports directly
+        vault_info.sub_lamports(reward)?;
+        ctx.accounts.crank.add_lamports(reward)?;
+
+        msg!("Vault unlocked by crank {}, rewarded {} lamports", ctx.accounts.crank.key(), reward);
+
+        emit!(VaultUnlocked {
+            vault: vault.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 84: Propose New Deposit/Withdraw Fees for LP Holders to Vote On
+    pub fn create_fee_proposal(
+        ctx: Context<CreateFeeProposal>,
+        proposal_id: u64,
+        proposed_deposit_fee_bps: u16,
+        proposed_withdraw_fee_bps: u16,
+        voting_period_secs: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Only LP holders can vote, so the vault needs an LP mint ---
+        require!(ctx.accounts.vault.lp_mint.is_some(), VaultError::LpMintNotConfigured);
+
+        // --- Security Check 2: Fees are capped ---
+        require!(
+            proposed_deposit_fee_bps <= MAX_FEE_BPS && proposed_withdraw_fee_bps <= MAX_FEE_BPS,
+            VaultError::FeeTooHigh
+        );
+
+        let voting_period = i64::try_from(voting_period_secs).map_err(|_| VaultError::ArithmeticOverflow)?;
+        let fee_proposal = &mut ctx.accounts.fee_proposal;
+        fee_proposal.vault = ctx.accounts.vault.key();
+        fee_proposal.proposal_id = proposal_id;
+        fee_proposal.proposed_deposit_fee_bps = proposed_deposit_fee_bps;
+        fee_proposal.proposed_withdraw_fee_bps = proposed_withdraw_fee_bps;
+        fee_proposal.votes_for = 0;
+        fee_proposal.votes_against = 0;
+        fee_proposal.voting_ends_at = clock
+            .unix_timestamp
+            .checked_add(voting_period)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        fee_proposal.executed = false;
+
+        msg!(
+            "Fee proposal {} created: deposit {} bps, withdraw {} bps, voting ends at {}",
+            proposal_id,
+            proposed_deposit_fee_bps,
+            proposed_withdraw_fee_bps,
+            fee_proposal.voting_ends_at
+        );
+        Ok(())
+    }
+
+    // Instruction 85: Vote on a Fee Proposal, Weighted by the Voter's LP Balance
+    pub fn vote_on_fee_proposal(ctx: Context<VoteOnFeeProposal>, _proposal_id: u64, approve: bool) -> Result<()> {
+        let fee_proposal = &mut ctx.accounts.fee_proposal;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The voting window is still open ---
+        require!(clock.unix_timestamp < fee_proposal.voting_ends_at, VaultError::VotingClosed);
+
+        // --- Security Check 2: The voter holds at least one whole LP share ---
+        // One vote per whole share keeps the tallies in a u32
+        let share_units = 10u64
+            .checked_pow(ctx.accounts.lp_mint.decimals as u32)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let shares = ctx.accounts.voter_lp_token_account.amount / share_units;
+        require!(shares > 0, VaultError::NoVotingPower);
+        let weight = u32::try_from(shares).map_err(|_| VaultError::ArithmeticOverflow)?;
+
+        // The shares are escrowed until voting ends, so they cannot be moved to another wallet and vote again
+        let escrowed = shares
+            .checked_mul(share_units)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        transfer_from_user(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.voter_lp_token_account.to_account_info(),
+            ctx.accounts.vote_escrow.to_account_info(),
+            &ctx.accounts.lp_mint,
+            ctx.accounts.voter.to_account_info(),
+            escrowed,
+        )?;
+
+        // The vote record PDA is 'init', so a second vote from the same wallet fails
+        let fee_vote_record = &mut ctx.accounts.fee_vote_record;
+        fee_vote_record.voter = ctx.accounts.voter.key();
+        fee_vote_record.approve = approve;
+        fee_vote_record.weight = weight;
+        if approve {
+            fee_proposal.votes_for = fee_proposal.votes_for.checked_add(weight).ok_or(VaultError::ArithmeticOverflow)?;
+        } else {
+            fee_proposal.votes_against = fee_proposal
+                .votes_against
+                .checked_add(weight)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+        }
+
+        msg!("Voter {} voted {} with weight {}", ctx.accounts.voter.key(), approve, weight);
+        Ok(())
+    }
+
+    // Instruction 86: Apply a Fee Proposal Once Voting Has Ended (Permissionless)
+    pub fn execute_fee_proposal(ctx: Context<ExecuteFeeProposal>, _proposal_id: u64) -> Result<()> {
+        let fee_proposal = &mut ctx.accounts.fee_proposal;
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Voting has ended ---
+        require!(clock.unix_timestamp >= fee_proposal.voting_ends_at, VaultError::VotingStillActive);
+
+        // --- Security Check 2: Each proposal applies once ---
+        require!(!fee_proposal.executed, VaultError::ProposalAlreadyExecuted);
+
+        // --- Security Check 3: The proposal carried a strict majority ---
+        require!(
+            fee_proposal.votes_for > fee_proposal.votes_against,
+            VaultError::NotEnoughApprovals
+        );
+
+        vault.deposit_fee_bps = fee_proposal.proposed_deposit_fee_bps;
+        vault.withdraw_fee_bps = fee_proposal.proposed_withdraw_fee_bps;
+        fee_proposal.executed = true;
+
+        msg!(
+            "Fee proposal {} executed: deposit {} bps, withdraw {} bps",
+            fee_proposal.proposal_id,
+            vault.deposit_fee_bps,
+            vault.withdraw_fee_bps
+        );
+        Ok(())
+    }
+
+    // Instruction 87: Enable or Disable Confidential Deposits (Token-2022 Only)
+    pub fn set_confidential_transfers(ctx: Context<SetConfidentialTransfers>, enabled: bool) -> Result<()> {
+        // --- Security Check 1: The mint carries the confidential transfer extension ---
+        if enabled {
+            require!(
+                mint_supports_confidential_transfers(&ctx.accounts.mint.to_account_info())?,
+                VaultError::MintDoesNotSupportConfidentialTransfers
+            );
+        }
+
+        ctx.accounts.vault.confidential_transfers_enabled = enabled;
+
+        msg!("Confidential transfers enabled: {}", enabled);
+        Ok(())
+    }
+
+    // Instruction 88: Close Cancelled or Long-Expired Scheduled Withdrawals and Reclaim their Rent
+    pub fn sweep_expired_scheduled_withdrawals<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpiredScheduledWithdrawals<'info>>,
+        nonces: Vec<u64>, // One per remaining account, in the same order
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: One nonce per account, bounded batch ---
+        require!(
+            nonces.len() == ctx.remaining_accounts.len() && nonces.len() <= MAX_SWEEP_BATCH,
+            VaultError::WithdrawalNotSweepable
+        );
+
+        for (scheduled_info, nonce) in ctx.remaining_accounts.iter().zip(nonces.iter()) {
+            // --- Security Check 2: The account is this vault's Scheduled Withdrawal PDA ---
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"scheduled", vault.key().as_ref(), nonce.to_le_bytes().as_ref()],
+                ctx.program_id,
+            );
+            require_keys_eq!(scheduled_info.key(), expected, VaultError::WithdrawalNotSweepable);
+            require_keys_eq!(*scheduled_info.owner, *ctx.program_id, VaultError::WithdrawalNotSweepable);
+
+            // --- Security Check 3: Cancelled, or unclaimed well past its execution time ---
+            let scheduled = ScheduledWithdrawal::try_deserialize(&mut &scheduled_info.try_borrow_data()?[..])?;
+            require!(is_sweepable(&scheduled, &clock), VaultError::WithdrawalNotSweepable);
+
+            close_program_account(scheduled_info, &ctx.accounts.authority.to_account_info())?;
+            vault.pending_scheduled_withdrawal_count = vault.pending_scheduled_withdrawal_count.saturating_sub(1);
+        }
+
+        msg!("Swept {} scheduled withdrawals.", nonces.len());
+        Ok(())
+    }
+
+    // Instruction 89: Fund several Vaults from one Signer in a single Transaction
+    // Every deposit passes the same gates as 'deposit'. 'remaining_accounts' holds one group per amount:
+    // (vault, user token account, vault token account, depositor access), where the access account is the
+    // vault's Role Config PDA or the signer's Whitelist Entry PDA, or this program's ID for neither.
+    pub fn batch_deposit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchDeposit<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let depositor = ctx.accounts.authority.key();
+
+        // --- Security Check 1: Bounded batch, one group of accounts per amount ---
+        require!(amounts.len() <= MAX_BATCH_DEPOSITS, VaultError::BatchTooLarge);
+        require!(
+            amounts.len() * BATCH_DEPOSIT_GROUP_LEN == ctx.remaining_accounts.len(),
+            VaultError::BatchSizeMismatch
+        );
+
+        let mut total_amount: u64 = 0;
+        for (group, &amount) in ctx
+            .remaining_accounts
+            .chunks_exact(BATCH_DEPOSIT_GROUP_LEN)
+            .zip(amounts.iter())
+        {
+            let [vault_info, user_token_account, vault_token_account_info, access_info] = group else {
+                return err!(VaultError::BatchSizeMismatch);
+            };
+
+            // --- Security Check 2: Each group pays into a vault of this program, through its own token account ---
+            require_keys_eq!(*vault_info.owner, crate::ID, VaultError::UnauthorizedAccess);
+            let mut vault = Vault::try_deserialize(&mut &vault_info.try_borrow_data()?[..])?;
+            require_keys_eq!(
+                vault_token_account_info.key(),
+                vault.token_account,
+                VaultError::BatchDepositAccountMismatch
+            );
+            require_keys_eq!(
+                vault.token_program_id,
+                ctx.accounts.token_program.key(),
+                VaultError::TokenProgramMismatch
+            );
+            let mut vault_token_account = InterfaceAccount::<TokenAccount>::try_from(vault_token_account_info)?;
+            require_keys_eq!(vault_token_account.mint, ctx.accounts.mint.key(), VaultError::VaultMintMismatch);
+
+            // The access account stands in for the optional 'role_config' / 'whitelist_entry' of 'deposit'
+            let vault_key = vault_info.key();
+            let mut role_config = None;
+            let mut whitelist_entry = None;
+            if access_info.key() != crate::ID {
+                require_keys_eq!(*access_info.owner, crate::ID, VaultError::BatchDepositAccountMismatch);
+                let data = access_info.try_borrow_data()?;
+                if access_info.key() == Pubkey::find_program_address(&[b"roles", vault_key.as_ref()], &crate::ID).0 {
+                    role_config = Some(RoleConfig::try_deserialize(&mut &data[..])?);
+                } else if access_info.key()
+                    == Pubkey::find_program_address(&[b"whitelist", vault_key.as_ref(), depositor.as_ref()], &crate::ID).0
+                {
+                    whitelist_entry = Some(WhitelistEntry::try_deserialize(&mut &data[..])?);
+                } else {
+                    return err!(VaultError::BatchDepositAccountMismatch);
+                }
+            }
+
+            // --- Security Check 3: The checks every deposit shares, see 'check_deposit_gates' ---
+            let fee = check_deposit_gates(
+                &vault,
+                DepositGate {
+                    program_config: &ctx.accounts.program_config,
+                    depositor,
+                    amount,
+                    vault_balance: vault_token_account.amount,
+                    role_config: role_config.as_ref(),
+                    whitelist_entry: whitelist_entry.as_ref(),
+                    merkle_proof: &[],
+                    memo_attached: false,
+                    depositor_record: None,
+                    depositor_rate_limit: None,
+                },
+                clock.unix_timestamp,
+            )?;
+
+            // --- Security Check 4: Fees, LP shares and confidential balances need the accounts of 'deposit' ---
+            require!(
+                fee == 0 && vault.lp_mint.is_none() && !vault.confidential_transfers_enabled,
+                VaultError::BatchDepositUnsupported
+            );
+
+            transfer_from_user(
+                ctx.accounts.token_program.to_account_info(),
+                user_token_account.clone(),
+                vault_token_account_info.clone(),
+                &ctx.accounts.mint,
+                ctx.accounts.authority.to_account_info(),
+                amount,
+            )?;
+
+            vault_token_account.reload()?;
+            vault.record_deposit(vault_key, depositor, amount, vault_token_account.amount, clock.unix_timestamp)?;
+            vault.try_serialize(&mut &mut vault_info.try_borrow_mut_data()?[..])?;
+
+            total_amount = total_amount
+                .checked_add(amount)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+        }
+
+        msg!("Batch deposited {} tokens into {} vaults.", total_amount, amounts.len());
+
+        emit!(BatchDepositCompleted {
+            authority: ctx.accounts.authority.key(),
+            deposit_count: amounts.len() as u8,
+            total_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 90: Pay several Recipients from the Vault in a single Transaction
+    pub fn batch_withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchWithdraw<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Per-recipient fees and accounts only exist on 'withdraw' ---
+        require!(
+            vault.withdraw_fee_bps == 0
+                && clock.unix_timestamp >= vault.recommended_hold_until
+                && vault.lp_mint.is_none()
+                && !vault.withdrawal_destination_whitelist_enabled
+                && !vault.beneficiary_split_enabled
+                && !vault.confidential_transfers_enabled,
+            VaultError::BatchWithdrawUnsupported
+        );
+
+        // --- Security Check 2: The whole batch is covered ---
+        let total_withdrawn = amounts.iter().try_fold(0u64, |total, amount| {
+            total.checked_add(*amount).ok_or(VaultError::ArithmeticOverflow)
+        })?;
+        require!(
+            ctx.accounts.vault_token_account.amount >= total_withdrawn,
+            VaultError::BatchWithdrawTotalExceedsBalance
+        );
+
+        // --- Security Check 3: The checks every payout shares, applied to the batch as one withdrawal ---
+        // The oracle feed and the price floor aggregator lead 'remaining_accounts', recipients follow
+        let recipient_token_accounts = check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount: total_withdrawn,
+                destination_entry: None,
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        // --- Security Check 4: Bounded batch, one recipient token account per amount ---
+        require!(amounts.len() <= MAX_BATCH_WITHDRAWALS, VaultError::BatchTooLarge);
+        require!(
+            amounts.len() == recipient_token_accounts.len(),
+            VaultError::BatchSizeMismatch
+        );
+
+        for (recipient_token_account, amount) in recipient_token_accounts.iter().zip(amounts.iter()) {
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                recipient_token_account.clone(),
+                &ctx.accounts.mint,
+                ctx.accounts.vault_authority.to_account_info(),
+                vault,
+                *amount,
+            )?;
+        }
+        vault.all_time_withdrawal_count = vault
+            .all_time_withdrawal_count
+            .checked_add(amounts.len() as u64)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!("Batch withdrew {} tokens to {} recipients.", total_withdrawn, amounts.len());
+
+        emit!(BatchWithdrawCompleted {
+            vault: vault.key(),
+            total_withdrawn,
+            recipient_count: amounts.len() as u8,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 91: Set the Daily Interest Rate, creating the Interest Reserve on first use
+    pub fn set_interest_rate(ctx: Context<SetInterestRate>, interest_rate_bps_per_day: u16) -> Result<()> {
+        // --- Security Check 1: The rate is capped like the fees ---
+        require!(interest_rate_bps_per_day <= MAX_FEE_BPS, VaultError::FeeTooHigh);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.interest_rate_bps_per_day = interest_rate_bps_per_day;
+
+        msg!(
+            "Interest rate set to {} bps per day, reserve: {}",
+            interest_rate_bps_per_day,
+            ctx.accounts.interest_reserve.key()
+        );
+        Ok(())
+    }
+
+    // Instruction 92: Accrue Interest for every whole Day since the last Update (permissionless crank)
+    pub fn accrue_interest(ctx: Context<AccrueInterest>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // The first crank only starts the clock
+        if vault.last_interest_update == 0 {
+            vault.last_interest_update = clock.unix_timestamp;
+            msg!("Interest accrual started at {}", clock.unix_timestamp);
+            return Ok(());
+        }
+
+        let days_elapsed = clock
+            .unix_timestamp
+            .saturating_sub(vault.last_interest_update)
+            .checked_div(SECONDS_PER_DAY)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let new_interest = interest_for_days(
+            ctx.accounts.vault_token_account.amount,
+            vault.interest_rate_bps_per_day,
+            days_elapsed,
+        )?;
+
+        vault.interest_accrued = vault
+            .interest_accrued
+            .checked_add(new_interest)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        // Only whole days are consumed, the partial day carries over to the next crank
+        vault.last_interest_update = vault
+            .last_interest_update
+            .checked_add(
+                days_elapsed
+                    .checked_mul(SECONDS_PER_DAY)
+                    .ok_or(VaultError::ArithmeticOverflow)?,
+            )
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!("Accrued {} interest over {} days", new_interest, days_elapsed);
+        Ok(())
+    }
+
+    // Instruction 93: Claim Accrued Interest out of the externally funded Interest Reserve
+    pub fn claim_interest(ctx: Context<ClaimInterest>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let amount = vault.interest_accrued;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Something has accrued ---
+        require!(amount > 0, VaultError::NothingToClaim);
+
+        // --- Security Check 3: The protocol treasury has funded the reserve ---
+        require!(
+            ctx.accounts.interest_reserve.amount >= amount,
+            VaultError::InsufficientInterestReserves
+        );
+
+        vault.interest_accrued = 0;
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.interest_reserve.to_account_info(),
+            ctx.accounts.authority_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        msg!("Claimed {} tokens of interest", amount);
+        Ok(())
+    }
+
+    // Instruction 94: Grant the Depositor Role
+    pub fn grant_depositor_role(ctx: Context<GrantDepositorRole>, member: Pubkey) -> Result<()> {
+        let role_config = &mut ctx.accounts.role_config;
+        role_config.vault = ctx.accounts.vault.key();
+
+        // --- Security Check 1: No duplicates, bounded list ---
+        require!(!role_config.depositors.contains(&member), VaultError::RoleAlreadyGranted);
+        require!(
+            role_config.depositors.len() < RoleConfig::MAX_MEMBERS,
+            VaultError::TooManyRoleMembers
+        );
+        role_config.depositors.push(member);
+
+        msg!("Depositor role granted: {}", member);
+        Ok(())
+    }
+
+    // Instruction 95: Revoke the Depositor Role
+    pub fn revoke_depositor_role(ctx: Context<RevokeDepositorRole>, member: Pubkey) -> Result<()> {
+        let role_config = &mut ctx.accounts.role_config;
+
+        // --- Security Check 1: The member holds the role ---
+        let position = role_config
+            .depositors
+            .iter()
+            .position(|key| *key == member)
+            .ok_or(VaultError::MissingDepositorRole)?;
+        role_config.depositors.swap_remove(position);
+
+        msg!("Depositor role revoked: {}", member);
+        Ok(())
+    }
+
+    // Instruction 96: Grant the Withdrawer Role
+    pub fn grant_withdrawer_role(ctx: Context<GrantWithdrawerRole>, member: Pubkey) -> Result<()> {
+        let role_config = &mut ctx.accounts.role_config;
+        role_config.vault = ctx.accounts.vault.key();
+
+        // --- Security Check 1: No duplicates, bounded list ---
+        require!(!role_config.withdrawers.contains(&member), VaultError::RoleAlreadyGranted);
+        require!(
+            role_config.withdrawers.len() < RoleConfig::MAX_MEMBERS,
+            VaultError::TooManyRoleMembers
+        );
+        role_config.withdrawers.push(member);
+
+        msg!("Withdrawer role granted: {}", member);
+        Ok(())
+    }
+
+    // Instruction 97: Revoke the Withdrawer Role
+    pub fn revoke_withdrawer_role(ctx: Context<RevokeWithdrawerRole>, member: Pubkey) -> Result<()> {
+        let role_config = &mut ctx.accounts.role_config;
+
+        // --- Security Check 1: The member holds the role ---
+        let position = role_config
+            .withdrawers
+            .iter()
+            .position(|key| *key == member)
+            .ok_or(VaultError::MissingWithdrawerRole)?;
+        role_config.withdrawers.swap_remove(position);
+
+        msg!("Withdrawer role revoked: {}", member);
+        Ok(())
+    }
+
+    // Instruction 98: Grant a Clawback Authority over Unvested Tokens (irrevocable by the authority)
+    pub fn enable_clawback(ctx: Context<EnableClawback>, clawback_authority: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: The vesting owner cannot swap out the clawback authority later ---
+        require!(!vault.clawback_enabled, VaultError::ClawbackAlreadyEnabled);
+
+        // --- Security Check 2: The owner cannot appoint itself and claw the grant back to its own wallet ---
+        require_keys_neq!(clawback_authority, vault.authority, VaultError::ClawbackAuthorityIsOwner);
+
+        vault.clawback_enabled = true;
+        vault.clawback_authority = clawback_authority;
+
+        msg!("Clawback enabled for {}", clawback_authority);
+        Ok(())
+    }
+
+    // Instruction 99: Reclaim the Unvested Remainder and end the Vesting Schedule
+    pub fn clawback(ctx: Context<Clawback>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Clawback is enabled and called by the clawback authority ---
+        require!(vault.clawback_enabled, VaultError::ClawbackNotEnabled);
+        require_keys_eq!(
+            ctx.accounts.clawback_authority.key(),
+            vault.clawback_authority,
+            VaultError::UnauthorizedAccess
+        );
+
+        // --- Security Check 3: Something is still unvested ---
+        require!(vault.total_vested_amount > 0, VaultError::NothingToClawback);
+        let vested = vault.vested_amount(clock.unix_timestamp)?;
+        let unvested = vault
+            .total_vested_amount
+            .checked_sub(vested)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(unvested > 0, VaultError::NothingToClawback);
+
+        // Vested but unclaimed tokens stay in the vault and remain claimable by the beneficiary
+        let unclaimed = vested
+            .checked_sub(vault.already_claimed)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.clawback_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            unvested,
+        )?;
+
+        // Only the lock 'setup_vesting' placed is lifted, a lock set on its own stays in force
+        if vault.is_locked && vault.unlock_timestamp == vault.vesting_end && vault.locked_amount == 0 {
+            vault.is_locked = false;
+            vault.unlock_timestamp = 0;
+        }
+
+        if unclaimed > 0 {
+            // The schedule ends now: everything vested so far is all that will ever vest
+            vault.total_vested_amount = vested;
+            vault.vesting_end = clock.unix_timestamp;
+        } else {
+            // The schedule is over: no further claims
+            vault.vesting_start = 0;
+            vault.vesting_end = 0;
+            vault.total_vested_amount = 0;
+            vault.already_claimed = 0;
+            vault.cliff_timestamp = 0;
+            vault.cliff_amount = 0;
+            vault.cliff_claimed = false;
+        }
+
+        msg!("Clawed back {} unvested tokens, {} vested tokens left to claim", unvested, unclaimed);
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.clawback_token_account.key(),
+            amount: unvested,
+            fee: 0,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 100: Update the Minimum Lock Duration
+    pub fn update_min_lock_duration(ctx: Context<UpdateMinLockDuration>, min_lock_duration_secs: i64) -> Result<()> {
+        // --- Security Check 1: A negative minimum would make every lock pass ---
+        require!(min_lock_duration_secs >= 0, VaultError::LockDurationTooShort);
+
+        ctx.accounts.vault.min_lock_duration_secs = min_lock_duration_secs;
+
+        msg!("Minimum lock duration set to {} seconds", min_lock_duration_secs);
+        Ok(())
+    }
+
+    // Instruction 101: Move a Vesting Position to a new Beneficiary Wallet
+    pub fn transfer_vesting_position(ctx: Context<TransferVestingPosition>, new_beneficiary: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: Vesting must be configured ---
+        require!(vault.total_vested_amount > 0, VaultError::VestingNotConfigured);
+
+        // --- Security Check 2: The position actually moves ---
+        let old_beneficiary = vault.beneficiary;
+        require_keys_neq!(new_beneficiary, old_beneficiary, VaultError::SameBeneficiary);
+
+        // 'already_claimed' is untouched, so the new wallet only receives what is left
+        vault.beneficiary = new_beneficiary;
+        let remaining_claimable = vault
+            .total_vested_amount
+            .checked_sub(vault.already_claimed)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!(
+            "Vesting position moved from {} to {}, {} tokens left to claim",
+            old_beneficiary,
+            new_beneficiary,
+            remaining_claimable
+        );
+
+        emit!(VestingPositionTransferred {
+            vault: vault.key(),
+            old_beneficiary,
+            new_beneficiary,
+            remaining_claimable,
+        });
+        Ok(())
+    }
+
+    // Instruction 102: Snapshot the Vault Balance into a Proof-of-Reserve PDA (permissionless)
+    pub fn generate_proof_of_reserve(ctx: Context<GenerateProofOfReserve>, proof_nonce: u32) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Nonce must be the next one in sequence ---
+        // Two callers racing for the same nonce would otherwise both think they wrote it
+        require!(proof_nonce == vault.proof_count, VaultError::ProofNonceMismatch);
+
+        let proof = &mut ctx.accounts.proof_of_reserve;
+        proof.vault = vault.key();
+        proof.token_balance = ctx.accounts.vault_token_account.amount;
+        proof.is_locked = vault.is_locked;
+        proof.unlock_timestamp = vault.unlock_timestamp;
+        proof.snapshot_timestamp = clock.unix_timestamp;
+        proof.snapshot_slot = clock.slot;
+
+        vault.proof_count = vault
+            .proof_count
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!(
+            "Proof of reserve #{}: {} tokens at slot {}",
+            proof_nonce,
+            proof.token_balance,
+            proof.snapshot_slot
+        );
+        Ok(())
+    }
+
+    // Instruction 103: Donate Tokens to a Vault (permissionless)
+    pub fn tip(ctx: Context<TipAccounts>, amount: u64) -> Result<()> {
+        // --- Security Check 1: Non-zero tip ---
+        require!(amount > 0, VaultError::TipAmountZero);
+
+        // No capacity check: tips are tracked separately and never count toward 'max_capacity'
+        transfer_from_user(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.tipper_token_account.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.tipper.to_account_info(),
+            amount,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.total_received_tips = vault
+            .total_received_tips
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!("Vault tipped {} tokens by {}", amount, ctx.accounts.tipper.key());
+
+        emit!(VaultTipped {
+            tipper: ctx.accounts.tipper.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    // Instruction 104: Set the Maximum Lock Duration of a Vault (protocol admin only)
+    pub fn set_max_lock_duration(ctx: Context<SetMaxLockDuration>, max_lock_duration_secs: i64) -> Result<()> {
+        // --- Security Check 1: Non-negative ---
+        require!(max_lock_duration_secs >= 0, VaultError::LockDurationTooLong);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.max_lock_duration_secs = max_lock_duration_secs;
+
+        msg!("Max lock duration set to {} seconds (0 = unlimited)", max_lock_duration_secs);
+        Ok(())
+    }
+
+    // Instruction 105: Hand the Protocol Admin role to another key (program admin only)
+    pub fn set_protocol_admin(ctx: Context<SetProtocolAdmin>, protocol_admin: Pubkey) -> Result<()> {
+        ctx.accounts.program_config.protocol_admin = protocol_admin;
+
+        msg!("Protocol admin set to: {}", protocol_admin);
+        Ok(())
+    }
+
+    // Instruction 106: Move a Vault into a newly deployed Program in one Transaction
+    // Side PDAs (stats, depositor records, LP mint, slots, ...) stay with this program.
+    pub fn close_and_migrate_to_new_vault(
+        ctx: Context<CloseAndMigrateToNewVault>,
+        new_program_id: Pubkey,
+    ) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let new_program = &ctx.accounts.new_program;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: The target is another deployed program ---
+        require_keys_eq!(new_program.key(), new_program_id, VaultError::MigrationTargetInvalid);
+        require_keys_neq!(new_program_id, crate::ID, VaultError::MigrationTargetInvalid);
+        require!(new_program.executable, VaultError::MigrationTargetInvalid);
+
+        // --- Security Check 3: The protocol admin has approved the target program ---
+        require!(
+            ctx.accounts.program_config.approved_migration_targets.contains(&new_program_id),
+            VaultError::MigrationTargetNotApproved
+        );
+
+        // --- Security Check 4: The program is not paused and the vault token account is not frozen ---
+        require!(!ctx.accounts.program_config.is_paused, VaultError::ProgramPaused);
+        require!(!vault.token_account_frozen, VaultError::TokenAccountFrozen);
+
+        // --- Security Check 5: Every token leaves, so no time lock may be running ---
+        let balance = ctx.accounts.vault_token_account.amount;
+        check_time_lock(vault, balance, balance, Clock::get()?.unix_timestamp)?;
+
+        // 1. Re-create the vault in the new program with the current state.
+        // The discriminator is fixed by the 'initialize_vault_from_migration' name, a program
+        // without that instruction rejects the CPI.
+        let mut data = crate::instruction::InitializeVaultFromMigration::DISCRIMINATOR.to_vec();
+        let state: &Vault = vault;
+        state.serialize(&mut data)?;
+        let migrate_ix = Instruction {
+            program_id: new_program_id,
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.new_vault.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.new_vault_authority.key(), false),
+                AccountMeta::new(ctx.accounts.new_vault_token_account.key(), true),
+                AccountMeta::new_readonly(ctx.accounts.mint.key(), false),
+                AccountMeta::new(ctx.accounts.authority.key(), true),
+                AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.new_program_config.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.instructions.key(), false),
+            ],
+            data,
+        };
+        invoke(
+            &migrate_ix,
+            &[
+                ctx.accounts.new_vault.to_account_info(),
+                ctx.accounts.new_vault_authority.to_account_info(),
+                ctx.accounts.new_vault_token_account.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.new_program_config.to_account_info(),
+                ctx.accounts.instructions.to_account_info(),
+                new_program.to_account_info(),
+            ],
+        )?;
+
+        // 2. Move every token across
+        let amount = ctx.accounts.vault_token_account.amount;
+        if amount > 0 {
+            transfer_from_vault(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault_token_account.to_account_info(),
+                ctx.accounts.new_vault_token_account.to_account_info(),
+                &ctx.accounts.mint,
+                ctx.accounts.vault_authority.to_account_info(),
+                vault,
+                amount,
+            )?;
+        }
+
+        // 3. Close the now empty token account, rent goes back to the authority
+        let vault_key = vault.key();
+        let authority_seed = &[
+            b"authority",
+            vault_key.as_ref(),
+            &[vault.authority_bump],
+        ];
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.vault_token_account.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            &[&authority_seed[..]],
+        ))?;
+
+        // 4. The Vault PDA itself is closed by the `close = authority` constraint
+        msg!(
+            "Vault {} migrated to {} under program {} with {} tokens",
+            vault_key,
+            ctx.accounts.new_vault.key(),
+            new_program_id,
+            amount
+        );
+        Ok(())
+    }
+
+    // Instruction 107: Receive a Vault migrated from a previous Program deployment
+    // Called by 'close_and_migrate_to_new_vault' of an approved source program, with the authority as signer.
+    pub fn initialize_vault_from_migration(ctx: Context<InitializeVaultFromMigration>, state: Vault) -> Result<()> {
+        // --- Security Check 1: Only an approved source program can hand over a vault ---
+        // Called directly, the owner could write any state. The call must be a CPI straight from the
+        // top-level instruction, so that instruction's program is the one migrating the vault.
+        require!(
+            get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT + 1,
+            VaultError::MigrationSourceNotApproved
+        );
+        let instructions = ctx.accounts.instructions.to_account_info();
+        let current_index = load_current_index_checked(&instructions)? as usize;
+        let source_program = load_instruction_at_checked(current_index, &instructions)?.program_id;
+        require!(
+            ctx.accounts.program_config.approved_migration_sources.contains(&source_program),
+            VaultError::MigrationSourceNotApproved
+        );
+
+        // --- Security Check 2: The signer owns the migrated vault ---
+        require_keys_eq!(state.authority, ctx.accounts.authority.key(), VaultError::UnauthorizedAccess);
+        require_keys_eq!(
+            state.token_program_id,
+            ctx.accounts.token_program.key(),
+            VaultError::TokenProgramMismatch
+        );
+
+        // --- Security Check 3: The owner is not the clawback authority, as in 'enable_clawback' ---
+        if state.clawback_enabled {
+            require_keys_neq!(state.clawback_authority, state.authority, VaultError::ClawbackAuthorityIsOwner);
+        }
+
+        // Everything carries over except what is tied to the new addresses
+        let vault = &mut ctx.accounts.vault;
+        vault.set_inner(state);
+        vault.original_authority = ctx.accounts.authority.key(); // Seeds: ["vault", authority, index]
+        vault.bump = ctx.bumps.vault;
+        vault.authority_bump = ctx.bumps.vault_authority;
+        vault.token_account = ctx.accounts.token_account.key();
+        vault.last_activity_timestamp = Clock::get()?.unix_timestamp;
+        vault.token_account_frozen = false;
+        vault.current_delegate = None;
+        vault.delegated_amount = 0;
+        vault.flash_loan_active = false;
+        vault.flash_loan_amount = 0;
+
+        // ...and what this deployment's admins set, they decide again
+        vault.compliance_officer = Pubkey::default();
+        vault.risk_score = 0;
+        vault.max_lock_duration_secs = 0;
+        vault.total_received_tips = 0;
+
+        msg!("Migrated vault initialized at: {}", vault.key());
+        Ok(())
+    }
+
+    // Instruction 108: Check the Vault's Invariants and log a Health Report (read-only)
+    pub fn vault_health_check(ctx: Context<VaultHealthCheck>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let token_account = &ctx.accounts.vault_token_account;
+
+        let (expected_vault, expected_bump) =
+            derive_indexed_vault_pda(&vault.original_authority, vault.vault_index, &crate::ID);
+        let (expected_authority, expected_authority_bump) = derive_vault_authority_pda(&vault.key(), &crate::ID);
+
+        let checks = [
+            (
+                "token account owned by vault authority PDA",
+                token_account.owner == expected_authority && vault.authority_bump == expected_authority_bump,
+            ),
+            ("stored token account matches", vault.token_account == token_account.key()),
+            ("claimed within vested total", vault.already_claimed <= vault.total_vested_amount),
+            ("vault bump matches", vault.key() == expected_vault && vault.bump == expected_bump),
+            (
+                "daily withdrawals within limit",
+                vault.daily_withdrawal_limit == 0 || vault.current_day_withdrawn <= vault.daily_withdrawal_limit,
+            ),
+        ];
+
+        msg!("Health report for vault: {}", vault.key());
+        for (name, ok) in checks.iter() {
+            msg!("  {}: {}", name, if *ok { "OK" } else { "VIOLATED" });
+        }
+
+        require!(checks.iter().all(|(_, ok)| *ok), VaultError::InvariantViolation);
+        Ok(())
+    }
+
+    // Instruction 109: Report the Program Version (no-op, for client compatibility checks)
+    pub fn vault_version(_ctx: Context<GetVaultVersion>) -> Result<u8> {
+        msg!("Vault program version {}. {}", VAULT_VERSION, CHANGELOG);
+        Ok(VAULT_VERSION)
+    }
+
+    // Instruction 110: Set the Large-Withdrawal Threshold and its Cool-off
+    pub fn set_large_withdrawal_threshold(
+        ctx: Context<SetLargeWithdrawalThreshold>,
+        threshold: u64,     // 0 = every withdrawal uses 'withdraw'
+        cooloff_secs: i64,
+    ) -> Result<()> {
+        // --- Security Check 1: Non-negative cool-off ---
+        require!(cooloff_secs >= 0, VaultError::InvalidUnlockTime);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.large_withdrawal_threshold = threshold;
+        vault.large_withdrawal_cooloff_secs = cooloff_secs;
+
+        msg!(
+            "Withdrawals above {} tokens now wait {} seconds (0 = no queue)",
+            threshold,
+            cooloff_secs
+        );
+        Ok(())
+    }
+
+    // Instruction 111: Queue a Withdrawal above the Large-Withdrawal Threshold
+    pub fn request_large_withdrawal(ctx: Context<RequestLargeWithdrawal>, seq: u64, amount: u64) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+
+        // --- Security Check 1: Only amounts above the threshold are queued ---
+        require!(
+            vault.large_withdrawal_threshold > 0 && amount > vault.large_withdrawal_threshold,
+            VaultError::BelowLargeWithdrawalThreshold
+        );
+
+        let request = &mut ctx.accounts.withdrawal_request;
+        request.amount = amount;
+        request.requester = ctx.accounts.authority.key();
+        request.requested_at = Clock::get()?.unix_timestamp;
+        request.cooloff_seconds = vault.large_withdrawal_cooloff_secs;
+        request.fulfilled = false;
+
+        msg!(
+            "Withdrawal request #{} for {} tokens, fulfillable after {} seconds",
+            seq,
+            amount,
+            request.cooloff_seconds
+        );
+        Ok(())
+    }
+
+    // Instruction 112: Pay out a Queued Withdrawal once its Cool-off has elapsed
+    pub fn fulfill_withdrawal_request(ctx: Context<FulfillWithdrawalRequest>, _seq: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let request = &mut ctx.accounts.withdrawal_request;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: Not yet paid and the review window is over ---
+        require!(!request.fulfilled, VaultError::WithdrawalRequestFulfilled);
+        let fulfillable_at = request
+            .requested_at
+            .checked_add(request.cooloff_seconds)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(clock.unix_timestamp >= fulfillable_at, VaultError::WithdrawalNotReady);
+
+        // --- Security Check 2: The checks every payout shares, see 'check_withdraw_gates' ---
+        // The queue already enforced its cool-off, so the large-withdrawal threshold does not apply
+        check_withdraw_gates(
+            vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount: request.amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: true,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )?;
+        vault.last_activity_timestamp = clock.unix_timestamp;
+
+        let (net_amount, treasury_amount) = pay_out_with_fees(
+            Payout {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                to: ctx.accounts.requester_token_account.to_account_info(),
+                fee_treasury_token_account: ctx.accounts.fee_treasury_token_account.as_ref().map(|a| a.to_account_info()),
+                mint: &ctx.accounts.mint,
+                vault_authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault,
+            request.amount,
+            clock.unix_timestamp,
+        )?;
+
+        // The PDA stays as the audit record of the request
+        request.fulfilled = true;
+
+        msg!("Fulfilled withdrawal request of {} tokens (fees: {}).", net_amount, treasury_amount);
+
+        emit!(TokensWithdrawn {
+            vault: vault.key(),
+            destination: ctx.accounts.requester_token_account.key(),
+            amount: net_amount,
+            fee: treasury_amount,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 113: Appoint the Compliance Officer (protocol admin only)
+    pub fn set_compliance_officer(ctx: Context<SetComplianceOfficer>, compliance_officer: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.compliance_officer = compliance_officer;
+
+        msg!("Compliance officer set to: {}", compliance_officer);
+        Ok(())
+    }
+
+    // Instruction 114: Freeze the Vault and move Funds under a Legal Order (compliance officer only)
+    pub fn revoke_and_redistribute(
+        ctx: Context<RevokeAndRedistribute>,
+        beneficiary_token_account_key: Pubkey,
+        amount: u64,
+        reason_hash: [u8; 32], // Hash of the off-chain legal document
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: A compliance officer is appointed and is the signer ---
+        require!(
+            vault.compliance_officer != Pubkey::default(),
+            VaultError::ComplianceOfficerNotConfigured
+        );
+        require_keys_eq!(
+            ctx.accounts.compliance_officer.key(),
+            vault.compliance_officer,
+            VaultError::UnauthorizedAccess
+        );
+
+        // --- Security Check 2: Insufficient Funds (the time lock is bypassed on purpose) ---
+        require!(
+            ctx.accounts.vault_token_account.amount >= amount,
+            VaultError::InsufficientFunds
+        );
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.beneficiary_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        // Freeze whatever is left, the owner can no longer withdraw
+        vault.is_locked = true;
+        vault.unlock_timestamp = i64::MAX;
+        vault.locked_amount = 0;
+
+        msg!(
+            "!!! COMPLIANCE ACTION !!! Officer {} moved {} tokens to {}, vault frozen",
+            vault.compliance_officer,
+            amount,
+            beneficiary_token_account_key
+        );
+
+        emit!(ComplianceAction {
+            officer: vault.compliance_officer,
+            amount,
+            reason_hash,
+        });
+        Ok(())
+    }
+
+    // Instruction 115: Attach an external Yield Strategy to the Vault
+    pub fn initialize_yield_strategy(
+        ctx: Context<InitializeYieldStrategy>,
+        strategy_program: Pubkey,
+        stake_account: Pubkey, // The position in 'strategy_program', owned by the vault authority PDA
+    ) -> Result<()> {
+        // --- Security Check 1: The protocol admin has approved the yield protocol ---
+        require!(
+            ctx.accounts.program_config.approved_strategy_programs.contains(&strategy_program),
+            VaultError::StrategyProgramNotApproved
+        );
+
+        let strategy = &mut ctx.accounts.yield_strategy;
+        strategy.strategy_program = strategy_program;
+        strategy.stake_account = stake_account;
+        strategy.last_harvest = 0;
+        strategy.accumulated_yield = 0;
+
+        msg!("Yield strategy {} set up with stake account {}", strategy_program, stake_account);
+        Ok(())
+    }
+
+    // Instruction 116: Harvest Yield from the Strategy into the Yield Account (permissionless crank)
+    pub fn harvest_yield<'info>(ctx: Context<'_, '_, 'info, 'info, HarvestYield<'info>>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        let strategy = ctx
+            .accounts
+            .yield_strategy
+            .as_mut()
+            .ok_or(VaultError::YieldStrategyNotConfigured)?;
+        let clock = Clock::get()?;
+
+        // --- Security Check 2: The accounts are the strategy's ---
+        require_keys_eq!(
+            ctx.accounts.strategy_program.key(),
+            strategy.strategy_program,
+            VaultError::YieldStrategyNotConfigured
+        );
+        require_keys_eq!(
+            ctx.accounts.stake_account.key(),
+            strategy.stake_account,
+            VaultError::YieldStrategyNotConfigured
+        );
+
+        // --- Security Check 3: The yield protocol is still approved by the protocol admin ---
+        require!(
+            ctx.accounts.program_config.approved_strategy_programs.contains(&strategy.strategy_program),
+            VaultError::StrategyProgramNotApproved
+        );
+
+        // --- Security Check 4: At most one harvest per interval ---
+        require!(
+            strategy.last_harvest == 0
+                || clock.unix_timestamp.saturating_sub(strategy.last_harvest) >= MIN_HARVEST_INTERVAL_SECS,
+            VaultError::HarvestCooldownActive
+        );
+
+        let balance_before = ctx.accounts.yield_token_account.amount;
+        let vault_balance_before = ctx.accounts.vault_token_account.amount;
+
+        let extra = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: false,
+                is_writable: account.is_writable,
+            })
+            .collect();
+        let harvest_ix = GenericYieldProtocol::harvest_instruction(
+            strategy.strategy_program,
+            strategy.stake_account,
+            ctx.accounts.vault_authority.key(),
+            ctx.accounts.yield_token_account.key(),
+            ctx.accounts.token_program.key(),
+            extra,
+        );
+        let mut account_infos = vec![
+            ctx.accounts.stake_account.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            ctx.accounts.yield_token_account.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.strategy_program.to_account_info(),
+        ];
+        account_infos.extend(ctx.remaining_accounts.iter().cloned());
+
+        let vault_key = vault.key();
+        let authority_seed = &[
+            b"authority",
+            vault_key.as_ref(),
+            &[vault.authority_bump],
+        ];
+        invoke_signed(&harvest_ix, &account_infos, &[&authority_seed[..]])?;
+
+        // --- Security Check 5: The vault authority's signature did not move the vault's own tokens ---
+        ctx.accounts.vault_token_account.reload()?;
+        require!(
+            ctx.accounts.vault_token_account.amount == vault_balance_before,
+            VaultError::VaultBalanceChangedByStrategy
+        );
+
+        ctx.accounts.yield_token_account.reload()?;
+        let received = ctx
+            .accounts
+            .yield_token_account
+            .amount
+            .checked_sub(balance_before)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        strategy.last_harvest = clock.unix_timestamp;
+        strategy.accumulated_yield = strategy
+            .accumulated_yield
+            .checked_add(received)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!("Harvested {} tokens of yield ({} in total)", received, strategy.accumulated_yield);
+        Ok(())
+    }
+
+    // Instruction 117: Move harvested Yield from the Yield Account into the Vault (permissionless)
+    pub fn reinvest_yield(ctx: Context<ReinvestYield>) -> Result<()> {
+        let amount = ctx.accounts.yield_token_account.amount;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(&ctx.accounts.vault)?;
+
+        // --- Security Check 2: Something has been harvested ---
+        require!(amount > 0, VaultError::NothingToClaim);
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.yield_token_account.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            &ctx.accounts.vault,
+            amount,
+        )?;
+
+        msg!("Reinvested {} tokens of yield into the vault", amount);
+        Ok(())
+    }
+
+    // Instruction 118: Preview a Withdrawal (read-only, returns what 'withdraw' would pay out)
+    // Runs 'check_withdraw_gates' on a copy of the vault, then the LP share and Token-2022 transfer fee
+    // checks of 'withdraw', so nothing is written. 'remaining_accounts' carries the oracle feed and the
+    // price floor aggregator, as for 'withdraw'.
+    pub fn simulate_withdraw(ctx: Context<SimulateWithdraw>, amount: u64) -> Result<WithdrawSimulation> {
+        let clock = Clock::get()?;
+        let mut vault = (*ctx.accounts.vault).clone();
+
+        let fees = withdrawal_fees(&vault, amount, clock.unix_timestamp);
+        let (fee, penalty) = fees.as_ref().ok().copied().unwrap_or_default();
+        let net_amount = amount.saturating_sub(fee).saturating_sub(penalty);
+
+        let outcome = check_withdraw_gates(
+            &mut vault,
+            WithdrawGate {
+                program_config: &ctx.accounts.program_config,
+                vault_balance: ctx.accounts.vault_token_account.amount,
+                amount,
+                destination_entry: ctx.accounts.destination_entry.as_deref(),
+                queued: false,
+            },
+            &clock,
+            ctx.remaining_accounts,
+        )
+        .and_then(|_| {
+            // LP vaults burn the shares backing the amount, the user has to hold them
+            if vault.lp_mint.is_some() {
+                let lp_mint = ctx.accounts.lp_mint.as_ref().ok_or(VaultError::LpMintNotConfigured)?;
+                let user_lp_token_account = ctx
+                    .accounts
+                    .user_lp_token_account
+                    .as_ref()
+                    .ok_or(VaultError::LpMintNotConfigured)?;
+                let shares = lp_shares(amount, lp_mint.supply, ctx.accounts.vault_token_account.amount, true)?;
+                require!(user_lp_token_account.amount >= shares, VaultError::InsufficientLpTokens);
+            }
+            fees.map(|_| ())
+        })
+        .and_then(|()| {
+            // The vault also covers the Token-2022 transfer fee on the net amount
+            if !vault.beneficiary_split_enabled {
+                let transfer_fee = get_transfer_fee_for_amount(&ctx.accounts.mint.to_account_info(), net_amount)?;
+                let total_debit = amount
+                    .checked_add(transfer_fee)
+                    .ok_or(VaultError::ArithmeticOverflow)?;
+                require!(
+                    ctx.accounts.vault_token_account.amount >= total_debit,
+                    VaultError::InsufficientFunds
+                );
+            }
+            Ok(())
+        });
+        let failure_reason = match outcome {
+            Ok(()) => None,
+            Err(Error::AnchorError(error)) => {
+                Some(error.error_code_number.saturating_sub(anchor_lang::error::ERROR_CODE_OFFSET) as u8)
+            }
+            Err(_) => Some(u8::MAX),
+        };
+
+        let simulation = WithdrawSimulation {
+            gross_amount: amount,
+            fee_deducted: fee,
+            penalty_deducted: penalty,
+            net_amount,
+            will_succeed: failure_reason.is_none(),
+            failure_reason,
+        };
+        msg!(
+            "Simulated withdrawal of {}: fee {}, penalty {}, net {}, succeeds: {}",
+            amount,
+            fee,
+            penalty,
+            net_amount,
+            simulation.will_succeed
+        );
+        Ok(simulation)
+    }
+
+    // Instruction 119: Freeze the Vault Token Account (the mint's freeze authority must be the vault authority PDA)
+    pub fn freeze_vault_token_account(ctx: Context<FreezeVaultTokenAccount>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        let vault_key = vault.key();
+        let authority_seed = &[
+            b"authority",
+            vault_key.as_ref(),
+            &[vault.authority_bump],
+        ];
+        token_interface::freeze_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            FreezeAccount {
+                account: ctx.accounts.vault_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            &[&authority_seed[..]],
+        ))?;
+        vault.token_account_frozen = true;
+
+        msg!("Vault token account frozen: {}", vault.token_account);
+        Ok(())
+    }
+
+    // Instruction 120: Thaw the Vault Token Account
+    pub fn unfreeze_vault_token_account(ctx: Context<FreezeVaultTokenAccount>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        let vault_key = vault.key();
+        let authority_seed = &[
+            b"authority",
+            vault_key.as_ref(),
+            &[vault.authority_bump],
+        ];
+        token_interface::thaw_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            ThawAccount {
+                account: ctx.accounts.vault_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            &[&authority_seed[..]],
+        ))?;
+        vault.token_account_frozen = false;
+
+        msg!("Vault token account thawed: {}", vault.token_account);
+        Ok(())
+    }
+
+    // Instruction 121: Wrap native SOL into the depositor's wSOL account, then deposit it
+    pub fn deposit_with_sol_wrap(
+        ctx: Context<Deposit>,
+        lamports: u64,
+        referrer: Option<Pubkey>,
+        merkle_proof: Option<Vec<[u8; 32]>>,
+        memo_data: Option<Vec<u8>>,
+    ) -> Result<()> {
+        // --- Security Check: Only wSOL vaults can be funded with native SOL ---
+        require_keys_eq!(ctx.accounts.mint.key(), native_mint::ID, VaultError::MintIsNotNative);
+
+        // Move the lamports into the depositor's wSOL account, then sync its token balance
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+        token_interface::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.user_token_account.to_account_info(),
+            },
+        ))?;
+        ctx.accounts.user_token_account.reload()?;
+
+        // The wrapped SOL goes through the regular deposit checks, fees and accounting
+        deposit(ctx, lamports, referrer, merkle_proof, memo_data)
+    }
+
+    // Instruction 122: Set the grace period in which a depositor can reverse a deposit
+    pub fn set_deposit_clawback_window(
+        ctx: Context<SetDepositClawbackWindow>,
+        clawback_window_secs: i64, // 0 = deposits are final
+    ) -> Result<()> {
+        // --- Security Check 1: Non-negative window ---
+        require!(clawback_window_secs >= 0, VaultError::InvalidUnlockTime);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.clawback_window_secs = clawback_window_secs;
+
+        msg!("Deposit clawback window set to {} seconds", clawback_window_secs);
+        Ok(())
+    }
+
+    // Instruction 123: Reverse the latest Deposit within its grace period
+    pub fn clawback_deposit(ctx: Context<ClawbackDeposit>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Only the latest depositor can claw back ---
+        require_keys_eq!(
+            ctx.accounts.depositor.key(),
+            vault.clawback_depositor,
+            VaultError::UnauthorizedAccess
+        );
+
+        // --- Security Check 3: The grace period is still open (the time lock is bypassed on purpose) ---
+        require!(
+            clock.unix_timestamp <= vault.deposit_clawback_until,
+            VaultError::ClawbackWindowExpired
+        );
+
+        // --- Security Check 4: No more than the deposit itself ---
+        require!(amount <= vault.clawback_amount, VaultError::InsufficientFunds);
+        require!(
+            ctx.accounts.vault_token_account.amount >= amount,
+            VaultError::InsufficientFunds
+        );
+
+        // --- Security Check 5: The counters the deposit went into are passed to be rolled back ---
+        require!(
+            !vault.clawback_in_stats || ctx.accounts.vault_stats.is_some(),
+            VaultError::ClawbackAccountsRequired
+        );
+        require!(
+            !vault.clawback_in_record || ctx.accounts.depositor_record.is_some(),
+            VaultError::ClawbackAccountsRequired
+        );
+
+        // LP vaults burn the shares the deposit minted, priced like a withdrawal of the same amount
+        if vault.lp_mint.is_some() {
+            let lp_mint = ctx.accounts.lp_mint.as_ref().ok_or(VaultError::LpMintNotConfigured)?;
+            let depositor_lp_token_account = ctx
+                .accounts
+                .depositor_lp_token_account
+                .as_ref()
+                .ok_or(VaultError::LpMintNotConfigured)?;
+
+            let shares = lp_shares(
+                amount,
+                lp_mint.supply,
+                ctx.accounts.vault_token_account.amount,
+                true,
+            )?;
+            require!(depositor_lp_token_account.amount >= shares, VaultError::InsufficientLpTokens);
+
+            token_interface::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Burn {
+                        mint: lp_mint.to_account_info(),
+                        from: depositor_lp_token_account.to_account_info(),
+                        authority: ctx.accounts.depositor.to_account_info(),
+                    },
+                ),
+                shares,
+            )?;
+            msg!("Burned {} LP shares.", shares);
+        }
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.depositor_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        // The deposit is undone, so is its bookkeeping
+        vault.clawback_amount = vault
+            .clawback_amount
+            .checked_sub(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        vault.deposit_amount_original = vault.deposit_amount_original.saturating_sub(amount);
+        if let Some(depositor_record) = ctx.accounts.depositor_record.as_mut() {
+            depositor_record.record_clawback(amount)?;
+        }
+        if let Some(vault_stats) = ctx.accounts.vault_stats.as_mut() {
+            vault_stats.record_clawback(amount, vault.clawback_amount == 0, clock.unix_timestamp)?;
+        }
+
+        msg!("Clawed back {} tokens to depositor {}", amount, vault.clawback_depositor);
+        Ok(())
+    }
+
+    // Instruction 124: Set the message logged when a withdrawal hits the time lock
+    pub fn set_custom_locked_message(ctx: Context<SetCustomLockedMessage>, message: String) -> Result<()> {
+        // --- Security Check 1: Fits the fixed-size field ---
+        let bytes = message.as_bytes();
+        require!(bytes.len() <= MAX_LOCKED_MESSAGE_LEN, VaultError::MessageTooLong);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.custom_locked_message = [0u8; MAX_LOCKED_MESSAGE_LEN];
+        vault.custom_locked_message[..bytes.len()].copy_from_slice(bytes);
+
+        msg!("Custom locked message set ({} bytes)", bytes.len());
+        Ok(())
+    }
+
+    // Instruction 125: Commit to an Airdrop out of the Vault's balance as a merkle root
+    pub fn initialize_airdrop(
+        ctx: Context<InitializeAirdrop>,
+        merkle_root: [u8; 32],
+        total_allocated: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: The vault can cover every claim ---
+        require!(
+            ctx.accounts.vault_token_account.amount >= total_allocated,
+            VaultError::InsufficientFunds
+        );
+
+        vault.airdrop_merkle_root = merkle_root;
+        vault.airdrop_total_allocated = total_allocated;
+        vault.airdrop_total_claimed = 0;
+
+        msg!("Airdrop of {} tokens initialized", total_allocated);
+        Ok(())
+    }
+
+    // Instruction 126: Claim an Airdrop allocation with a merkle proof
+    pub fn claim_airdrop(ctx: Context<ClaimAirdrop>, amount: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let airdrop_claim = &mut ctx.accounts.airdrop_claim;
+
+        // --- Security Check 1: No flash loan is outstanding ---
+        check_no_flash_loan(vault)?;
+
+        // --- Security Check 2: Each recipient claims once ---
+        require!(!airdrop_claim.claimed, VaultError::AlreadyClaimed);
+
+        // --- Security Check 3: The allocation is part of the committed tree ---
+        require!(vault.airdrop_merkle_root != [0u8; 32], VaultError::MerkleProofInvalid);
+        let recipient = ctx.accounts.recipient.key();
+        let leaf = keccak::hashv(&[recipient.as_ref(), &amount.to_le_bytes()]).to_bytes();
+        require!(
+            merkle::verify_merkle_proof(vault.airdrop_merkle_root, leaf, &proof),
+            VaultError::MerkleProofInvalid
+        );
+
+        // --- Security Check 4: Claims never exceed the allocation ---
+        vault.airdrop_total_claimed = vault
+            .airdrop_total_claimed
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            vault.airdrop_total_claimed <= vault.airdrop_total_allocated,
+            VaultError::InsufficientFunds
+        );
+
+        // --- Security Check 5: The checks every claim shares, see 'check_claim_gate' ---
+        check_claim_gate(
+            vault,
+            &ctx.accounts.program_config,
+            ctx.accounts.vault_token_account.amount,
+            amount,
+            Clock::get()?.unix_timestamp,
+            false,
+        )?;
+
+        airdrop_claim.claimed = true;
+        airdrop_claim.amount = amount;
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.recipient_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            vault,
+            amount,
+        )?;
+
+        msg!("Airdrop of {} tokens claimed by {}", amount, recipient);
+        Ok(())
+    }
+
+    // Instruction 127: Create the Global Stats PDA for protocol-wide dashboards (program admin only)
+    pub fn initialize_global_stats(ctx: Context<InitializeGlobalStats>) -> Result<()> {
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_vaults = 0;
+        global_stats.total_value_locked_approx = 0;
+        global_stats.total_deposit_events = 0;
+        global_stats.bump = ctx.bumps.global_stats;
+
+        msg!("Global stats initialized: {}", global_stats.key());
+        Ok(())
+    }
+
+    // Instruction 128: Log the aggregated Global Stats
+    pub fn get_global_stats(ctx: Context<GetGlobalStats>) -> Result<()> {
+        let global_stats = &ctx.accounts.global_stats;
+
+        msg!("Total vaults: {}", global_stats.total_vaults);
+        msg!("Total value locked (approx, raw amounts): {}", global_stats.total_value_locked_approx);
+        msg!("Total deposit events: {}", global_stats.total_deposit_events);
+        Ok(())
+    }
+
+    // Instruction 129: Replace client-supplied bumps with the canonical ones
+    // 'initialize_vault' stores the vault bump passed by the client, a wrong one bricks every
+    // 'bump = vault.bump' check. This only corrects the stored bumps: they are kept, and every other
+    // instruction still checks against them, which saves a 'find_program_address' per PDA per call.
+    pub fn migrate_to_canonical_bumps(ctx: Context<MigrateToCanonicalBumps>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        if vault.bump != ctx.bumps.vault {
+            msg!(
+                "WARNING: stored vault bump {} is not canonical, migrating to {}",
+                vault.bump,
+                ctx.bumps.vault
+            );
+            vault.bump = ctx.bumps.vault;
+        }
+
+        // The token account is owned by the address of the stored authority bump, so it only
+        // moves to the canonical bump if that is the same address
+        if vault.authority_bump != ctx.bumps.vault_authority {
+            if ctx.accounts.vault_token_account.owner == ctx.accounts.vault_authority.key() {
+                msg!(
+                    "WARNING: stored authority bump {} is not canonical, migrating to {}",
+                    vault.authority_bump,
+                    ctx.bumps.vault_authority
+                );
+                vault.authority_bump = ctx.bumps.vault_authority;
+            } else {
+                msg!(
+                    "WARNING: stored authority bump {} is not canonical but owns the token account, kept",
+                    vault.authority_bump
+                );
+            }
+        }
+        vault.bumps_migrated = true;
+
+        msg!("Vault bumps migrated: {}", vault.key());
+        Ok(())
+    }
+
+    // Instruction 130: Rotate the Fee Treasury behind a 48-hour Timelock
+    // The first call proposes 'new_treasury', calling again with it once the timelock has passed
+    // finalizes the transfer. Proposing a different treasury restarts the timelock.
+    pub fn transfer_fee_treasury(ctx: Context<TransferFeeTreasury>, new_treasury: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: A treasury is always set, the default key would skip the timelock ---
+        require_keys_neq!(new_treasury, Pubkey::default(), VaultError::FeeTreasuryRequired);
+
+        if vault.pending_treasury != new_treasury {
+            vault.pending_treasury = new_treasury;
+            vault.treasury_transfer_unlocks_at = clock
+                .unix_timestamp
+                .checked_add(TREASURY_TRANSFER_TIMELOCK_SECS)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+
+            msg!(
+                "Fee treasury transfer to {} proposed, unlocks at {}",
+                new_treasury,
+                vault.treasury_transfer_unlocks_at
+            );
+            return Ok(());
+        }
+
+        // --- Security Check 2: The timelock has passed ---
+        require!(
+            clock.unix_timestamp >= vault.treasury_transfer_unlocks_at,
+            VaultError::TreasuryTransferTimelockActive
+        );
+
+        let old_treasury = vault.fee_treasury;
+        vault.fee_treasury = new_treasury;
+        vault.pending_treasury = Pubkey::default();
+        vault.treasury_transfer_unlocks_at = 0;
+
+        msg!("Fee treasury transferred from {} to {}", old_treasury, new_treasury);
+
+        emit!(FeeTreasuryTransferred {
+            vault: vault.key(),
+            old_treasury,
+            new_treasury,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 131: Require a memo on Deposits above a Threshold
+    pub fn set_memo_required_above(ctx: Context<SetMemoRequiredAbove>, memo_required_above: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.memo_required_above = memo_required_above;
+
+        msg!("Deposits above {} tokens now require a memo (0 = never)", memo_required_above);
+        Ok(())
+    }
+
+    // Instruction 132: Set the Cooldown between Heartbeats
+    pub fn set_heartbeat_min_interval(
+        ctx: Context<Heartbeat>,
+        heartbeat_min_interval_secs: i64, // 0 = no cooldown
+    ) -> Result<()> {
+        // --- Security Check 1: Non-negative interval ---
+        require!(heartbeat_min_interval_secs >= 0, VaultError::InvalidUnlockTime);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.heartbeat_min_interval_secs = heartbeat_min_interval_secs;
+
+        msg!("Heartbeat interval set to {} seconds", heartbeat_min_interval_secs);
+        Ok(())
+    }
+
+    // Instruction 133: Prove Liveness to the Dead-Man's Switch without moving Funds
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: No spamming inside the cooldown ---
+        if vault.last_heartbeat_timestamp > 0 {
+            let next_heartbeat = vault
+                .last_heartbeat_timestamp
+                .checked_add(vault.heartbeat_min_interval_secs)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+            require!(clock.unix_timestamp >= next_heartbeat, VaultError::HeartbeatTooSoon);
+        }
+
+        vault.last_activity_timestamp = clock.unix_timestamp;
+        vault.last_heartbeat_timestamp = clock.unix_timestamp;
+
+        msg!("Heartbeat received from {}", ctx.accounts.authority.key());
+
+        emit!(HeartbeatReceived {
+            vault: vault.key(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 134: Cap how much any one Address can deposit in Total
+    pub fn set_per_depositor_cap(ctx: Context<SetPerDepositorCap>, max_per_depositor: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.max_per_depositor = max_per_depositor;
+
+        msg!("Per-depositor cap set to {} (0 = no cap)", max_per_depositor);
+        Ok(())
+    }
+
+    // Instruction 135: Initialize a Vault and apply its whole Configuration in one Transaction
+    // Same accounts as 'initialize_vault', which also validates the fees and the minimum lock.
+    pub fn initialize_vault_with_config<'info>(
+        ctx: Context<'_, '_, 'info, 'info, InitializeVault<'info>>,
+        vault_bump: u8,
+        authority_bump: u8,
+        vault_index: u8,
+        config: VaultInitConfig,
+    ) -> Result<()> {
+        // --- Security Check 1: The deposit bounds are consistent ---
+        require!(
+            config.min_deposit_amount == 0
+                || config.max_capacity == 0
+                || config.min_deposit_amount <= config.max_capacity,
+            VaultError::InvalidVaultConfig
+        );
+
+        // --- Security Check 2: Non-negative durations, as in the individual setters ---
+        require!(config.auto_lock_duration_secs >= 0, VaultError::InvalidUnlockTime);
+        require!(config.withdrawal_cooldown_secs >= 0, VaultError::InvalidCooldown);
+        require!(config.max_lock_duration_secs >= 0, VaultError::LockDurationTooLong);
+
+        let Context {
+            program_id,
+            accounts,
+            remaining_accounts,
+            bumps,
+            ..
+        } = ctx;
+        initialize_vault(
+            Context::new(program_id, &mut *accounts, remaining_accounts, bumps),
+            vault_bump,
+            authority_bump,
+            vault_index,
+            None,
+            None,
+            None,
+            config.deposit_fee_bps,
+            config.withdraw_fee_bps,
+            (config.fee_treasury != Pubkey::default()).then_some(config.fee_treasury),
+            config.max_capacity,
+            config.min_deposit_amount,
+            false,
+            config.min_lock_duration_secs,
+            0,
+        )?;
+
+        let vault = &mut accounts.vault;
+        vault.auto_lock_duration_secs = config.auto_lock_duration_secs;
+        vault.daily_withdrawal_limit = config.daily_withdrawal_limit;
+        vault.withdrawal_cooldown_secs = config.withdrawal_cooldown_secs;
+        vault.max_lock_duration_secs = config.max_lock_duration_secs;
+
+        msg!("Vault configured at initialization");
+        Ok(())
+    }
+
+    // Instruction 136: Re-attribute Whitelist Entries after an Authority Transfer
+    // Entries are seeded by [vault, depositor], never by the authority, so rotating the authority
+    // leaves them valid and this update is cosmetic. The same goes for the old authority's own
+    // depositor entry: it keeps letting that key deposit until the new authority removes it.
+    pub fn whitelist_transfer_authority(
+        ctx: Context<WhitelistTransferAuthority>,
+        old_authority: Pubkey,
+        new_authority: Pubkey,
+        depositors: Vec<Pubkey>, // One per remaining account, the depositor its entry is seeded by
+    ) -> Result<()> {
+        // --- Security Check 1: Entries can only be handed to the current authority ---
+        require_keys_eq!(new_authority, ctx.accounts.vault.authority, VaultError::UnauthorizedAccess);
+        require_keys_neq!(old_authority, new_authority, VaultError::SameAuthority);
+
+        // --- Security Check 2: Bounded batch ---
+        require!(
+            ctx.remaining_accounts.len() <= MAX_BULK_UPDATE,
+            VaultError::BulkUpdateTooLarge
+        );
+        require!(
+            depositors.len() == ctx.remaining_accounts.len(),
+            VaultError::WhitelistEntryMismatch
+        );
+
+        let vault_key = ctx.accounts.vault.key();
+        for (entry_info, depositor) in ctx.remaining_accounts.iter().zip(depositors.iter()) {
+            // --- Security Check 3: Each account is this vault's whitelist entry for its depositor ---
+            // Entries store no vault key, so only the seeds tie an entry to this vault
+            require_keys_eq!(
+                entry_info.key(),
+                derive_whitelist_entry_pda(&vault_key, depositor, &crate::ID).0,
+                VaultError::WhitelistEntryMismatch
+            );
+
+            // --- Security Check 4: Each account is a whitelist entry of this program, added by the old authority ---
+            require_keys_eq!(*entry_info.owner, crate::ID, VaultError::UnauthorizedAccess);
+            let mut data = entry_info.try_borrow_mut_data()?;
+            let mut entry = WhitelistEntry::try_deserialize(&mut &data[..])?;
+            require_keys_eq!(entry.added_by, old_authority, VaultError::UnauthorizedAccess);
+
+            entry.added_by = new_authority;
+            entry.try_serialize(&mut &mut data[..])?;
+        }
+
+        msg!(
+            "{} whitelist entries moved from {} to {}",
+            ctx.remaining_accounts.len(),
+            old_authority,
+            new_authority
+        );
+        Ok(())
+    }
+
+    // Instruction 137: Enter Shutdown Mode to drain the Vault before decommissioning
+    pub fn enable_shutdown_mode(ctx: Context<SetShutdownMode>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.shutdown_mode = true;
+
+        msg!("Vault in shutdown mode: deposits disabled, withdrawals allowed");
+
+        emit!(ShutdownModeEnabled {
+            vault: vault.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 138: Leave Shutdown Mode (e.g. after a false alarm)
+    pub fn disable_shutdown_mode(ctx: Context<SetShutdownMode>) -> Result<()> {
+        ctx.accounts.vault.shutdown_mode = false;
+
+        msg!("Vault shutdown mode disabled, deposits re-enabled");
+        Ok(())
+    }
+
+    // Instruction 139: View the Vault Balance including Token-2022 Mint Interest
+    // Returns the raw balance for mints without the 'InterestBearingConfig' extension.
+    pub fn get_interest_bearing_balance(ctx: Context<GetInterestBearingBalance>) -> Result<u64> {
+        let balance = ctx.accounts.vault_token_account.amount;
+        let value = interest_bearing_value(&ctx.accounts.token_program, &ctx.accounts.mint, balance)?
+            .unwrap_or(balance);
+
+        msg!(
+            "Balance incl. interest: {} (interest earned: {})",
+            value,
+            value.saturating_sub(ctx.accounts.vault.deposit_amount_original)
+        );
+        Ok(value)
+    }
+
+    // Instruction 140: Recover a foreign Token sent to a Token Account of the Vault Authority by mistake
+    pub fn rescue_wrong_token(ctx: Context<RescueWrongToken>, amount: u64) -> Result<()> {
+        // --- Security Check 1: The vault's own token is never "rescued" ---
+        require_keys_neq!(
+            ctx.accounts.wrong_token_account.mint,
+            ctx.accounts.vault_token_account.mint,
+            VaultError::CannotRescuePrimaryToken
+        );
+
+        // --- Security Check 2: The tokens are held by the vault authority PDA ---
+        require_keys_eq!(
+            ctx.accounts.wrong_token_account.owner,
+            ctx.accounts.vault_authority.key(),
+            VaultError::UnauthorizedAccess
+        );
+
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.wrong_token_account.to_account_info(),
+            ctx.accounts.rescue_destination.to_account_info(),
+            &ctx.accounts.wrong_mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            &ctx.accounts.vault,
+            amount,
+        )?;
+
+        msg!(
+            "Rescued {} tokens of mint {} to {}",
+            amount,
+            ctx.accounts.wrong_mint.key(),
+            ctx.accounts.rescue_destination.key()
+        );
+        Ok(())
+    }
+
+    // Instruction 141: Set the Lock Delegate, a Co-Signer who can lock but not withdraw or unlock
+    pub fn set_lock_delegate(ctx: Context<SetLockDelegate>, lock_delegate: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.lock_delegate = lock_delegate;
+
+        msg!("Lock delegate set to: {} (default = none)", lock_delegate);
+        Ok(())
+    }
+
+    // Instruction 142: Lock several Vaults at once, all or nothing
+    // 'remaining_accounts' holds each vault (writable), its token account and its signing authority or
+    // lock delegate, in groups of three.
+    pub fn batch_lock_vaults<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchLockVaults<'info>>,
+        unlock_timestamps: Vec<i64>,
+    ) -> Result<()> {
+        // --- Security Check 1: One vault, token account and authority per timestamp, bounded ---
+        require!(
+            !unlock_timestamps.is_empty() && ctx.remaining_accounts.len() == unlock_timestamps.len() * 3,
+            VaultError::BatchLockMismatch
+        );
+        require!(unlock_timestamps.len() <= MAX_BATCH_LOCK, VaultError::BulkUpdateTooLarge);
+
+        let clock = Clock::get()?;
+        for (group, &unlock_timestamp) in ctx.remaining_accounts.chunks(3).zip(unlock_timestamps.iter()) {
+            let (vault_info, vault_token_account_info, authority_info) = (&group[0], &group[1], &group[2]);
+
+            // --- Security Check 2: Each vault belongs to this program and is locked by its own signer ---
+            require_keys_eq!(*vault_info.owner, crate::ID, VaultError::UnauthorizedAccess);
+            let mut data = vault_info.try_borrow_mut_data()?;
+            let mut vault = Vault::try_deserialize(&mut &data[..])?;
+            require!(authority_info.is_signer, VaultError::SignerCannotLock);
+            require!(
+                authority_info.key() == vault.authority || authority_info.key() == vault.lock_delegate,
+                VaultError::SignerCannotLock
+            );
+
+            // --- Security Check 3: The token account is the vault's, its balance is checked below ---
+            require_keys_eq!(vault_token_account_info.key(), vault.token_account, VaultError::BatchLockMismatch);
+            let vault_token_account = InterfaceAccount::<TokenAccount>::try_from(vault_token_account_info)?;
+
+            // --- Security Check 4: The checks every lock shares, see 'check_lock_gates' ---
+            check_lock_gates(
+                &vault,
+                LockGate {
+                    program_config: &ctx.accounts.program_config,
+                    vault_balance: vault_token_account.amount,
+                    unlock_timestamp,
+                    locked_amount: 0,
+                },
+                clock.unix_timestamp,
+            )?;
+
+            vault.is_locked = true;
+            vault.unlock_timestamp = unlock_timestamp;
+            vault.locked_amount = 0; // The whole balance
+            vault.last_activity_timestamp = clock.unix_timestamp;
+            vault.try_serialize(&mut &mut data[..])?;
+
+            emit!(VaultLocked {
+                vault: vault_info.key(),
+                unlock_timestamp,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        msg!("{} vaults locked", unlock_timestamps.len());
+        Ok(())
+    }
+
+    // Instruction 143: Request a timelocked Transfer of Vault Ownership
+    pub fn request_authority_change(ctx: Context<RequestAuthorityChange>, new_authority: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: A real, different authority ---
+        require_keys_neq!(new_authority, vault.authority, VaultError::SameAuthority);
+        require_keys_neq!(new_authority, Pubkey::default(), VaultError::UnauthorizedAccess);
+
+        vault.pending_authority = new_authority;
+        vault.authority_change_unlocks_at = clock
+            .unix_timestamp
+            .checked_add(ctx.accounts.program_config.authority_change_delay_secs)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        msg!(
+            "Authority change to {} requested, unlocks at {}",
+            new_authority,
+            vault.authority_change_unlocks_at
+        );
+        Ok(())
+    }
+
+    // Instruction 144: Finalize the requested Authority Change once the Delay has passed (the new authority)
+    pub fn finalize_authority_change(ctx: Context<FinalizeAuthorityChange>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        // --- Security Check 1: The timelock has passed ---
+        require!(
+            clock.unix_timestamp >= vault.authority_change_unlocks_at,
+            VaultError::AuthorityChangeTimelockActive
+        );
+
+        let old_authority = vault.authority;
+        vault.authority = vault.pending_authority;
+        vault.pending_authority = Pubkey::default();
+        vault.authority_change_unlocks_at = 0;
+
+        msg!("Vault authority transferred from {} to {}", old_authority, vault.authority);
+
+        emit!(AuthorityTransferred {
+            vault: vault.key(),
+            old_authority,
+            new_authority: vault.authority,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Instruction 145: Cancel the requested Authority Change
+    pub fn cancel_authority_change(ctx: Context<CancelAuthorityChange>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require_keys_neq!(vault.pending_authority, Pubkey::default(), VaultError::NoPendingAuthorityChange);
+
+        msg!("Authority change to {} cancelled", vault.pending_authority);
+        vault.pending_authority = Pubkey::default();
+        vault.authority_change_unlocks_at = 0;
+        Ok(())
+    }
+
+    // Instruction 146: Set the Program-wide Delay on Authority Changes (program admin)
+    pub fn set_authority_change_delay(ctx: Context<SetAuthorityChangeDelay>, authority_change_delay_secs: i64) -> Result<()> {
+        // --- Security Check 1: Non-negative delay ---
+        require!(authority_change_delay_secs >= 0, VaultError::InvalidUnlockTime);
+
+        ctx.accounts.program_config.authority_change_delay_secs = authority_change_delay_secs;
+
+        msg!("Authority change delay set to {} seconds", authority_change_delay_secs);
+        Ok(())
+    }
+
+    // Instruction 147: Check a Vault for an integrating Program (read-only, returns a 'VaultIntegrityReport')
+    // Callers CPI in through 'token_vault::cpi::verify_vault_integrity' and read the typed return value.
+    pub fn verify_vault_integrity(
+        ctx: Context<VerifyVaultIntegrity>,
+        expected_authority: Pubkey,
+        expected_mint: Pubkey,
+    ) -> Result<VaultIntegrityReport> {
+        let vault = &ctx.accounts.vault;
+        let vault_token_account = &ctx.accounts.vault_token_account;
+
+        // --- Security Check 1: The vault is the one the caller depends on ---
+        require_keys_eq!(vault.authority, expected_authority, VaultError::VaultIntegrityMismatch);
+        require_keys_eq!(vault_token_account.mint, expected_mint, VaultError::VaultIntegrityMismatch);
+
+        Ok(VaultIntegrityReport {
+            is_locked: vault.is_locked,
+            balance: vault_token_account.amount,
+            unlock_timestamp: vault.unlock_timestamp,
+        })
+    }
+
+    // Instruction 148: Clear a Vault's Risk Score after Review (risk admin only)
+    pub fn reset_risk_score(ctx: Context<ResetRiskScore>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.risk_score = 0;
+        vault.risk_last_updated = Clock::get()?.unix_timestamp;
+
+        msg!("Risk score of {} reset by: {}", vault.key(), ctx.accounts.risk_admin.key());
+        Ok(())
+    }
+
+    // Instruction 149: Hand the Risk Admin role to another key (program admin only)
+    pub fn set_risk_admin(ctx: Context<SetRiskAdmin>, risk_admin: Pubkey) -> Result<()> {
+        ctx.accounts.program_config.risk_admin = risk_admin;
+
+        msg!("Risk admin set to: {}", risk_admin);
+        Ok(())
+    }
+
+    // Instruction 150: Guard Withdrawals with a Switchboard Price Floor
+    pub fn set_price_floor_guard(
+        ctx: Context<SetPriceFloorGuard>,
+        price_floor_oracle: Pubkey,       // Switchboard v2 aggregator
+        price_floor_value: i64,           // In the aggregator's mantissa units, scale is not applied
+        price_floor_staleness_secs: i64,  // Oldest confirmed round withdrawals accept
+        price_floor_active: bool,
+    ) -> Result<()> {
+        // --- Security Check 1: An active guard has a feed and a usable staleness bound ---
+        require!(
+            !price_floor_active || (price_floor_oracle != Pubkey::default() && price_floor_staleness_secs > 0),
+            VaultError::InvalidOracleAccount
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        vault.price_floor_oracle = price_floor_oracle;
+        vault.price_floor_value = price_floor_value;
+        vault.price_floor_staleness_secs = price_floor_staleness_secs;
+        vault.price_floor_active = price_floor_active;
+
+        msg!(
+            "Price floor {}: feed {} must stay at or above {} (max age {}s)",
+            if price_floor_active { "active" } else { "inactive" },
+            price_floor_oracle,
+            price_floor_value,
+            price_floor_staleness_secs
+        );
+        Ok(())
+    }
+
+    // Instruction 151: Limit how often any one Address can deposit per Hour
+    pub fn set_deposit_rate_limit(ctx: Context<SetDepositRateLimit>, max_deposits_per_hour: u32) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.max_deposits_per_hour = max_deposits_per_hour;
+
+        msg!("Deposit rate limit set to {} per hour (0 = unlimited)", max_deposits_per_hour);
+        Ok(())
+    }
+
+    // Instruction 152: Commit to a Deposit without revealing its Amount (commit-reveal against frontrunning)
+    // The commitment is keccak(amount_le || nonce), a later commitment replaces a pending one.
+    pub fn commit_deposit(ctx: Context<CommitDeposit>, commitment: [u8; 32]) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let deposit_commitment = &mut ctx.accounts.deposit_commitment;
+
+        deposit_commitment.commitment = commitment;
+        deposit_commitment.committed_at = Clock::get()?.unix_timestamp;
+        deposit_commitment.reveal_window_secs = if vault.reveal_window_secs > 0 {
+            vault.reveal_window_secs
+        } else {
+            DEFAULT_REVEAL_WINDOW_SECS
+        };
+
+        msg!(
+            "Deposit committed by {}, reveal within {} seconds",
+            ctx.accounts.depositor.key(),
+            deposit_commitment.reveal_window_secs
+        );
+        Ok(())
+    }
+
+    // Instruction 153: Reveal a committed Deposit and execute it through the standard 'deposit' path
+    // 'referrer' precedes 'nonce' because the nested 'Deposit' accounts read it from the instruction data.
+    pub fn reveal_deposit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RevealDeposit<'info>>,
+        amount: u64,
+        referrer: Option<Pubkey>,
+        nonce: [u8; 32],
+    ) -> Result<()> {
+        let commitment_info = ctx.accounts.deposit_commitment.to_account_info();
+
+        // --- Security Check 1: Something was committed ---
+        require!(
+            *commitment_info.owner == crate::ID && !commitment_info.data_is_empty(),
+            VaultError::NoPendingCommitment
+        );
+        let deposit_commitment = DepositCommitment::try_deserialize(&mut &commitment_info.try_borrow_data()?[..])?;
+
+        // --- Security Check 2: The amount and nonce are the committed ones ---
+        let revealed = keccak::hashv(&[&amount.to_le_bytes(), &nonce]).to_bytes();
+        require!(revealed == deposit_commitment.commitment, VaultError::CommitmentMismatch);
+
+        // --- Security Check 3: Revealed in time ---
+        let reveal_deadline = deposit_commitment
+            .committed_at
+            .checked_add(deposit_commitment.reveal_window_secs)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            Clock::get()?.unix_timestamp <= reveal_deadline,
+            VaultError::RevealWindowExpired
+        );
+
+        // Everything else follows the standard deposit path
+        let deposit_ctx = Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.deposit,
+            ctx.remaining_accounts,
+            ctx.bumps.deposit,
+        );
+        deposit(deposit_ctx, amount, referrer, None, None)?;
+
+        // The commitment is spent, its rent goes back to the depositor
+        close_program_account(&commitment_info, &ctx.accounts.deposit.authority.to_account_info())?;
+        Ok(())
+    }
+
+    // Instruction 154: Set how long Depositors have to reveal a Commitment
+    pub fn set_reveal_window(ctx: Context<SetRevealWindow>, reveal_window_secs: i64) -> Result<()> {
+        // --- Security Check 1: Non-negative window ---
+        require!(reveal_window_secs >= 0, VaultError::InvalidUnlockTime);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.reveal_window_secs = reveal_window_secs;
+
+        msg!("Reveal window set to {} seconds (0 = the default)", reveal_window_secs);
+        Ok(())
+    }
+
+    // Instruction 155: Approve or revoke a Yield Protocol for 'harvest_yield' (protocol admin only)
+    pub fn set_strategy_program_approval(
+        ctx: Context<UpdateProgramAllowlist>,
+        strategy_program: Pubkey,
+        approved: bool,
+    ) -> Result<()> {
+        set_allowlisted(
+            &mut ctx.accounts.program_config.approved_strategy_programs,
+            strategy_program,
+            approved,
+        )?;
+
+        msg!("Yield protocol {} approved: {}", strategy_program, approved);
+        Ok(())
+    }
+
+    // Instruction 156: Approve or revoke a Program as a Target for 'close_and_migrate_to_new_vault' (protocol admin only)
+    pub fn set_migration_target_approval(
+        ctx: Context<UpdateProgramAllowlist>,
+        target_program: Pubkey,
+        approved: bool,
+    ) -> Result<()> {
+        set_allowlisted(
+            &mut ctx.accounts.program_config.approved_migration_targets,
+            target_program,
+            approved,
+        )?;
+
+        msg!("Migration target {} approved: {}", target_program, approved);
+        Ok(())
+    }
+
+    // Instruction 157: Return a Voter's escrowed LP Shares once Voting has Ended
+    pub fn release_fee_vote_escrow(ctx: Context<ReleaseFeeVoteEscrow>, _proposal_id: u64) -> Result<()> {
+        // --- Security Check 1: Voting has ended, the shares back the vote until then ---
+        require!(
+            Clock::get()?.unix_timestamp >= ctx.accounts.fee_proposal.voting_ends_at,
+            VaultError::VotingStillActive
+        );
+
+        let amount = ctx.accounts.vote_escrow.amount;
+        transfer_from_vault(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vote_escrow.to_account_info(),
+            ctx.accounts.voter_lp_token_account.to_account_info(),
+            &ctx.accounts.lp_mint,
+            ctx.accounts.vault_authority.to_account_info(),
+            &ctx.accounts.vault,
+            amount,
+        )?;
+
+        // The empty escrow is closed, rent goes back to the voter
+        let vault_key = ctx.accounts.vault.key();
+        let authority_seed = &[
+            b"authority",
+            vault_key.as_ref(),
+            &[ctx.accounts.vault.authority_bump],
+        ];
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.vote_escrow.to_account_info(),
+                destination: ctx.accounts.voter.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            &[&authority_seed[..]],
+        ))?;
+
+        msg!("Released {} escrowed LP shares to {}", amount, ctx.accounts.voter.key());
+        Ok(())
+    }
+
+    // Instruction 158: Approve or revoke a Program as a Source for 'initialize_vault_from_migration' (protocol admin only)
+    pub fn set_migration_source_approval(
+        ctx: Context<UpdateProgramAllowlist>,
+        source_program: Pubkey,
+        approved: bool,
+    ) -> Result<()> {
+        set_allowlisted(
+            &mut ctx.accounts.program_config.approved_migration_sources,
+            source_program,
+            approved,
+        )?;
+
+        msg!("Migration source {} approved: {}", source_program, approved);
+        Ok(())
+    }
+}
+
+// --- Account Validation Structs ---
+
+// Accounts for 'initialize_vault'
+#[derive(Accounts)]
+#[instruction(vault_bump: u8, authority_bump: u8, vault_index: u8)]
+pub struct InitializeVault<'info> {
+    // Vault PDA: Creates and funds the vault account
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"vault", payer.key().as_ref(), vault_index_seed(&vault_index)], // Seeds: ["vault", payer_pubkey, index]
+        bump,
+        space = 8 + Vault::INIT_SPACE
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: Owner of the vault's token account
+    /// CHECK: This is safe because we derive it with PDA and only use it as an authority
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()], // Seeds: ["authority", vault_pubkey]
+        bump = authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Token Account: Holds the actual tokens, owned by the Vault Authority PDA
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = vault_authority, // Owned by the PDA
+        token::token_program = token_program,
+    )]
+    // FIX: Box<Account<...>> used for large non-Anchor SPL account
+    pub token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    // Other necessary accounts
+    // FIX: Box<Account<...>> used for large non-Anchor SPL account
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>, 
+    #[account(mut)]
+    pub payer: Signer<'info>, // The wallet paying for the accounts and initializing the vault
+
+    // Optional Multisig Config: if present, the multisig PDA becomes the vault authority
+    #[account(
+        mut,
+        seeds = [b"multisig", payer.key().as_ref()],
+        bump = multisig_config.bump
+    )]
+    pub multisig_config: Option<Account<'info, MultisigConfig>>,
+
+    // Optional Vault Registry PDA: grows by one entry to record the new vault
+    #[account(
+        mut,
+        seeds = [b"registry", payer.key().as_ref()],
+        bump = vault_registry.bump,
+        realloc = VaultRegistry::space(vault_registry.vaults.len() + 1),
+        realloc::payer = payer,
+        realloc::zero = false
+    )]
+    pub vault_registry: Option<Account<'info, VaultRegistry>>,
+
+    pub token_program: Interface<'info, TokenInterface>, // Token or Token-2022
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'deposit'
+#[derive(Accounts)]
+#[instruction(amount: u64, referrer: Option<Pubkey>)]
+pub struct Deposit<'info> {
+    // Vault PDA check: the depositor is checked against the authority or the whitelist in the handler
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // User's token account (from)
+    // FIX: Box<Account<...>> used for large non-Anchor SPL account
+    #[account(mut, token::authority = authority)]
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    // Vault's token account (to)
+    // FIX: Box<Account<...>> used for large non-Anchor SPL account
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, used to validate the fee treasury
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Fee treasury's token account, only required when a deposit fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    // Optional Vault Stats PDA: counters are updated when it is passed
+    #[account(
+        mut,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump
+    )]
+    pub vault_stats: Option<Account<'info, VaultStats>>,
+
+    // Optional Depositor Record PDA: created on the first deposit, paid for by the depositor
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"depositor", vault.key().as_ref(), authority.key().as_ref()],
+        bump,
+        space = 8 + DepositorRecord::INIT_SPACE
+    )]
+    pub depositor_record: Option<Account<'info, DepositorRecord>>,
+
+    // Optional Depositor Rate Limit PDA: created on the first deposit, required while 'max_deposits_per_hour' is set
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"rate_limit", vault.key().as_ref(), authority.key().as_ref()],
+        bump,
+        space = 8 + DepositorRateLimit::INIT_SPACE
+    )]
+    pub depositor_rate_limit: Option<Account<'info, DepositorRateLimit>>,
+
+    // Whitelist Entry PDA: required while the vault whitelist is enabled
+    #[account(
+        seeds = [b"whitelist", vault.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
+    // Optional Role Config PDA: depositor role holders may deposit without a whitelist entry
+    #[account(seeds = [b"roles", vault.key().as_ref()], bump)]
+    pub role_config: Option<Account<'info, RoleConfig>>,
+
+    // Vault Authority PDA: mints LP shares, only needed for LP vaults
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: Option<UncheckedAccount<'info>>,
+
+    // LP Mint PDA and the depositor's LP token account: required once the vault has an LP mint
+    #[account(mut, seeds = [b"lp_mint", vault.key().as_ref()], bump)]
+    pub lp_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(mut)]
+    pub user_lp_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Referral Record PDA: created on the first referred deposit, required when a referrer is given
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"referral", referrer.as_ref().map(|r| r.as_ref()).unwrap_or_default()],
+        bump,
+        space = 8 + ReferralRecord::INIT_SPACE
+    )]
+    pub referral_record: Option<Account<'info, ReferralRecord>>,
+
+    // SPL Memo program: required when a memo is attached
+    /// CHECK: This is safe because the address is checked against the SPL Memo program id
+    #[account(address = SPL_MEMO_PROGRAM_ID)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The user depositing, pays for the depositor record
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'withdraw'
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    // Vault PDA check: only the authority, or a withdrawer role holder, can withdraw (see 'authority')
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    
+    // User's token account (to)
+    // FIX: Box<Account<...>> used for large non-Anchor SPL account
+    #[account(mut, token::authority = authority)]
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    // Vault's token account (from)
+    // FIX: Box<Account<...>> used for large non-Anchor SPL account
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, used to validate the fee treasury
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Optional Vault Stats PDA: counters are updated when it is passed
+    #[account(
+        mut,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump
+    )]
+    pub vault_stats: Option<Account<'info, VaultStats>>,
+
+    // Optional Depositor Record PDA: withdrawals are tracked if it exists
+    #[account(
+        mut,
+        seeds = [b"depositor", vault.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Option<Account<'info, DepositorRecord>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), user_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    // LP Mint PDA and the user's LP token account: required once the vault has an LP mint
+    #[account(mut, seeds = [b"lp_mint", vault.key().as_ref()], bump)]
+    pub lp_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(mut, token::authority = authority)]
+    pub user_lp_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Beneficiary Split PDA: required once a split is configured, recipients go in 'remaining_accounts'
+    #[account(seeds = [b"split", vault.key().as_ref()], bump)]
+    pub beneficiary_split: Option<Account<'info, BeneficiarySplit>>,
+
+    // Optional Role Config PDA: withdrawer role holders may withdraw to their own token account
+    #[account(seeds = [b"roles", vault.key().as_ref()], bump)]
+    pub role_config: Option<Account<'info, RoleConfig>>,
+
+    #[account(
+        constraint = authority.key() == vault.authority
+            || role_config
+                .as_ref()
+                .is_some_and(|roles| roles.withdrawers.contains(&authority.key()))
+            @ VaultError::MissingWithdrawerRole
+    )]
+    pub authority: Signer<'info>, // The user withdrawing
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'hashlock_withdraw': the regular 'withdraw' accounts plus a nonce record
+#[derive(Accounts)]
+#[instruction(amount: u64, preimage: [u8; 32], nonce: u64)]
+pub struct HashlockWithdraw<'info> {
+    pub withdraw: Withdraw<'info>,
+
+    // Instruction Nonce PDA: Seeds: ["nonce", vault_pubkey, nonce]
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"nonce", withdraw.vault.key().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + InstructionNonce::INIT_SPACE
+    )]
+    pub instruction_nonce: Account<'info, InstructionNonce>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>, // Pays for the nonce record, usually the withdrawing authority
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'lock_vault'
+#[derive(Accounts)]
+pub struct LockVault<'info> {
+    // Vault PDA check: Only the vault authority or its lock delegate can lock it
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        constraint = authority.key() == vault.authority
+            || authority.key() == vault.lock_delegate @ VaultError::SignerCannotLock
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault's token account, checked against 'minimum_lock_balance'
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub authority: Signer<'info>, // The user locking, the vault authority or its lock delegate
+}
+
+// Accounts for 'authority_unlock_vault'
+#[derive(Accounts)]
+pub struct AuthorityUnlockVault<'info> {
+    // Vault PDA check: Only the vault authority can unlock it
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Optional Unlock Vote PDA: a passed vote unlocks before the timestamp
+    #[account(
+        mut,
+        seeds = [b"unlock_vote", vault.key().as_ref()],
+        bump
+    )]
+    pub unlock_vote: Option<Account<'info, UnlockVote>>,
+    
+    // Optional Recovery Config PDA for a guardian override is passed (writable) in 'remaining_accounts'
+    pub authority: Signer<'info>, // The user unlocking
+}
+
+
+// Accounts for 'close_vault'
+#[derive(Accounts)]
+pub struct CloseVault<'info> {
+    // Vault PDA check: Only the vault authority can close it; rent lamports go back to them
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority,
+        close = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: Signs the close of the vault's token account
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (closed)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The user closing, receives the rent
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'transfer_vault_authority'
+#[derive(Accounts)]
+pub struct TransferVaultAuthority<'info> {
+    // Vault PDA check: Only the current vault authority can hand over ownership
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the authority change timelock
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub authority: Signer<'info>, // The current owner
+}
+
+// Accounts for 'emergency_withdraw'
+#[derive(Accounts)]
+#[instruction(amount: u64, nonce: u64)]
+pub struct EmergencyWithdraw<'info> {
+    // Vault PDA check: A guardian must have been configured at initialization
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        constraint = vault.guardian != Pubkey::default() @ VaultError::NoGuardianConfigured
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Pre-registered emergency recipient (to)
+    #[account(mut, address = vault.emergency_recipient)]
+    pub emergency_recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only the registered guardian may sign
+    #[account(mut, constraint = guardian.key() == vault.guardian @ VaultError::UnauthorizedGuardian)]
+    pub guardian: Signer<'info>, // Pays for the nonce record
+
+    // Instruction Nonce PDA: Seeds: ["nonce", vault_pubkey, nonce]
+    #[account(
+        init_if_needed,
+        payer = guardian,
+        seeds = [b"nonce", vault.key().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + InstructionNonce::INIT_SPACE
+    )]
+    pub instruction_nonce: Account<'info, InstructionNonce>,
+
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'create_multisig_config'
+#[derive(Accounts)]
+pub struct CreateMultisigConfig<'info> {
+    // Multisig PDA: Seeds: ["multisig", creator_pubkey]
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"multisig", creator.key().as_ref()],
+        bump,
+        space = 8 + MultisigConfig::INIT_SPACE
+    )]
+    pub multisig_config: Account<'info, MultisigConfig>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>, // Pays for the account, later passed as the vault payer
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'propose_action'
+#[derive(Accounts)]
+pub struct ProposeAction<'info> {
+    #[account(mut)]
+    pub multisig_config: Account<'info, MultisigConfig>,
+
+    pub owner: Signer<'info>, // Must be one of the multisig owners
+}
+
+// Accounts for 'approve_action'
+#[derive(Accounts)]
+pub struct ApproveAction<'info> {
+    #[account(mut)]
+    pub multisig_config: Account<'info, MultisigConfig>,
+
+    pub owner: Signer<'info>, // Must be one of the multisig owners
+}
+
+// Accounts for 'execute_action'
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(mut)]
+    pub multisig_config: Account<'info, MultisigConfig>,
+
+    // Vault PDA check: The multisig PDA must be the vault authority
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        constraint = vault.authority == multisig_config.key() @ VaultError::UnauthorizedAccess
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Recipient's token account (to), only needed for withdrawals
+    #[account(mut)]
+    pub recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA of the recipient: required while the destination whitelist is enabled
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    pub owner: Signer<'info>, // Must be one of the multisig owners
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'setup_vesting'
+#[derive(Accounts)]
+pub struct SetupVesting<'info> {
+    // Vault PDA check: Only the vault authority can configure vesting
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The user configuring vesting
+}
+
+// Accounts for 'claim_vested'
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    // Vault PDA check: the claimant is checked against the authority and beneficiary below
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Beneficiary's token account (to), vested tokens follow a transferred vesting position
+    #[account(mut, token::authority = vault.beneficiary)]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = authority.key() == vault.authority || authority.key() == vault.beneficiary
+            @ VaultError::UnauthorizedAccess
+    )]
+    pub authority: Signer<'info>, // The user claiming, the vault authority or the beneficiary
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'set_beneficiary'
+#[derive(Accounts)]
+pub struct SetBeneficiary<'info> {
+    // Vault PDA check: Only the vault authority can change the beneficiary
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'beneficiary_withdraw'
+#[derive(Accounts)]
+pub struct BeneficiaryWithdraw<'info> {
+    // Vault PDA check: Only the registered beneficiary can use this path
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = beneficiary @ VaultError::NotBeneficiary
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Beneficiary's token account (to)
+    #[account(mut, token::authority = beneficiary)]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), beneficiary_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    pub beneficiary: Signer<'info>, // The recipient withdrawing
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'update_fee_config'
+#[derive(Accounts)]
+pub struct UpdateFeeConfig<'info> {
+    // Vault PDA check: Only the vault authority can change fees
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'update_limits'
+#[derive(Accounts)]
+pub struct UpdateLimits<'info> {
+    // Vault PDA check: Only the vault authority can change limits
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_withdrawal_cooldown'
+#[derive(Accounts)]
+pub struct SetWithdrawalCooldown<'info> {
+    // Vault PDA check: Only the vault authority can change the cooldown
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'initialize_vault_stats'
+#[derive(Accounts)]
+pub struct InitializeVaultStats<'info> {
+    // Vault PDA check: Only the vault authority can create its stats account
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Stats PDA: Seeds: ["stats", vault_pubkey]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump,
+        space = 8 + VaultStats::INIT_SPACE
+    )]
+    pub vault_stats: Account<'info, VaultStats>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // Pays for the stats account
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'get_stats'
+#[derive(Accounts)]
+pub struct GetStats<'info> {
+    pub vault_stats: Account<'info, VaultStats>,
+}
+
+
+// Accounts for 'get_depositor_record'
+#[derive(Accounts)]
+pub struct GetDepositorRecord<'info> {
+    pub depositor_record: Account<'info, DepositorRecord>,
+}
+
+
+// Accounts for 'set_max_capacity'
+#[derive(Accounts)]
+pub struct SetMaxCapacity<'info> {
+    // Vault PDA check: Only the vault authority can change the capacity
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'update_min_deposit'
+#[derive(Accounts)]
+pub struct UpdateMinDeposit<'info> {
+    // Vault PDA check: Only the vault authority can change the minimum
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_auto_lock_duration'
+#[derive(Accounts)]
+pub struct SetAutoLockDuration<'info> {
+    // Vault PDA check: Only the vault authority can configure auto-locking
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'lock_vault_for_duration'
+#[derive(Accounts)]
+pub struct LockVaultForDuration<'info> {
+    // Vault PDA check: Only the vault authority can lock it
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault's token account: its balance is checked against 'minimum_lock_balance'
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub authority: Signer<'info>, // The user locking
+}
+
+
+// Accounts for 'extend_lock'
+#[derive(Accounts)]
+pub struct ExtendLock<'info> {
+    // Vault PDA check: Only the vault authority can extend the lock
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The user extending the lock
+}
+
+
+// Accounts for 'add_to_whitelist'
+#[derive(Accounts)]
+pub struct AddToWhitelist<'info> {
+    // Vault PDA check: Only the vault authority can manage the whitelist
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed, any address can be whitelisted
+    pub candidate: UncheckedAccount<'info>,
+
+    // Whitelist Entry PDA: re-adding a removed depositor reactivates the same entry
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"whitelist", vault.key().as_ref(), candidate.key().as_ref()],
+        bump,
+        space = 8 + WhitelistEntry::INIT_SPACE
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the entry
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'remove_from_whitelist'
+#[derive(Accounts)]
+pub struct RemoveFromWhitelist<'info> {
+    // Vault PDA check: Only the vault authority can manage the whitelist
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed
+    pub candidate: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"whitelist", vault.key().as_ref(), candidate.key().as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'enable_whitelist'
+#[derive(Accounts)]
+pub struct EnableWhitelist<'info> {
+    // Vault PDA check: Only the vault authority can enable the whitelist
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'disable_whitelist'
+#[derive(Accounts)]
+pub struct DisableWhitelist<'info> {
+    // Vault PDA check: Only the vault authority can disable the whitelist
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'approve_destination'
+#[derive(Accounts)]
+pub struct ApproveDestination<'info> {
+    // Vault PDA check: Only the vault authority can approve destinations
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed, the token account is validated again on withdraw
+    pub destination_token_account: UncheckedAccount<'info>,
+
+    // Withdrawal Destination Entry PDA: re-approving a revoked destination reuses the entry
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"wd", vault.key().as_ref(), destination_token_account.key().as_ref()],
+        bump,
+        space = 8 + WithdrawalDestinationEntry::INIT_SPACE
+    )]
+    pub destination_entry: Account<'info, WithdrawalDestinationEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the entry
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'revoke_destination'
+#[derive(Accounts)]
+pub struct RevokeDestination<'info> {
+    // Vault PDA check: Only the vault authority can revoke destinations
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed
+    pub destination_token_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"wd", vault.key().as_ref(), destination_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Account<'info, WithdrawalDestinationEntry>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'enable_destination_whitelist'
+#[derive(Accounts)]
+pub struct EnableDestinationWhitelist<'info> {
+    // Vault PDA check: Only the vault authority can enable the whitelist
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'disable_destination_whitelist'
+#[derive(Accounts)]
+pub struct DisableDestinationWhitelist<'info> {
+    // Vault PDA check: Only the vault authority can disable the whitelist
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'grant_delegate_allowance'
+#[derive(Accounts)]
+pub struct GrantDelegateAllowance<'info> {
+    // Vault PDA check: Only the vault authority can grant allowances
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed and stored, the delegate signs when withdrawing
+    pub delegate: UncheckedAccount<'info>,
+
+    // Delegate Config PDA: re-granting overwrites the existing allowance
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"delegate", vault.key().as_ref(), delegate.key().as_ref()],
+        bump,
+        space = 8 + DelegateConfig::INIT_SPACE
+    )]
+    pub delegate_config: Account<'info, DelegateConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the config
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'revoke_delegate'
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    // Vault PDA check: Only the vault authority can revoke delegates
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed
+    pub delegate: UncheckedAccount<'info>,
+
+    // Delegate Config PDA: closed and rent returned to the authority
+    #[account(
+        mut,
+        seeds = [b"delegate", vault.key().as_ref(), delegate.key().as_ref()],
+        bump,
+        close = authority
+    )]
+    pub delegate_config: Account<'info, DelegateConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, receives the rent
+}
+
+// Accounts for 'delegate_withdraw'
+#[derive(Accounts)]
+pub struct DelegateWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Delegate Config PDA check: Only the delegate it was granted to can use it
+    #[account(
+        mut,
+        seeds = [b"delegate", vault.key().as_ref(), delegate.key().as_ref()],
+        bump,
+        has_one = delegate
+    )]
+    pub delegate_config: Account<'info, DelegateConfig>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Delegate's token account (to)
+    #[account(mut, token::authority = delegate)]
+    pub delegate_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), delegate_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    pub delegate: Signer<'info>, // The keeper withdrawing
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'set_hashlock'
+#[derive(Accounts)]
+pub struct SetHashlock<'info> {
+    // Vault PDA check: Only the vault authority can set the hashlock
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'initialize_program_config'
+#[derive(Accounts)]
+pub struct InitializeProgramConfig<'info> {
+    // Program Config PDA: a single global account, Seeds: ["program_config"]
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"program_config"],
+        bump,
+        space = 8 + ProgramConfig::INIT_SPACE
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // Becomes the protocol admin
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'pause_program'
+#[derive(Accounts)]
+pub struct PauseProgram<'info> {
+    // Program Config PDA check: Only the protocol admin can pause
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>, // The protocol admin
+}
+
+// Accounts for 'unpause_program'
+#[derive(Accounts)]
+pub struct UnpauseProgram<'info> {
+    // Program Config PDA check: Only the protocol admin can unpause
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>, // The protocol admin
+}
+
+
+// Accounts for 'add_token_slot'
+#[derive(Accounts)]
+pub struct AddTokenSlot<'info> {
+    // Vault PDA check: Only the vault authority can add slots
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: Owner of every slot token account
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Token Slot PDA: init_if_needed so a duplicate surfaces as 'SlotAlreadyExists'
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"slot", vault.key().as_ref(), mint.key().as_ref()],
+        bump,
+        space = 8 + VaultTokenSlot::INIT_SPACE
+    )]
+    pub slot: Account<'info, VaultTokenSlot>,
+
+    // Slot Token Account: the vault authority's ATA for this mint
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program,
+    )]
+    pub slot_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the slot
+    pub token_program: Interface<'info, TokenInterface>, // Token or Token-2022, per slot
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'deposit_to_slot'
+#[derive(Accounts)]
+pub struct DepositToSlot<'info> {
+    // Vault PDA check: only the correct authority can deposit
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Token Slot check: must belong to this vault and mint
+    #[account(
+        has_one = vault @ VaultError::SlotNotFound,
+        has_one = mint @ VaultError::SlotNotFound
+    )]
+    pub slot: Account<'info, VaultTokenSlot>,
+
+    // User's token account (from)
+    #[account(mut, token::mint = mint, token::authority = authority)]
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Slot's token account (to)
+    #[account(mut, address = slot.token_account)]
+    pub slot_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>, // The user depositing
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'withdraw_from_slot'
+#[derive(Accounts)]
+pub struct WithdrawFromSlot<'info> {
+    // Vault PDA check: only the correct authority can withdraw
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the slot
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Token Slot check: must belong to this vault and mint
+    #[account(
+        has_one = vault @ VaultError::SlotNotFound,
+        has_one = mint @ VaultError::SlotNotFound
+    )]
+    pub slot: Account<'info, VaultTokenSlot>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // User's token account (to)
+    #[account(mut, token::mint = mint, token::authority = authority)]
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), user_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    // Slot's token account (from)
+    #[account(mut, address = slot.token_account)]
+    pub slot_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>, // The user withdrawing
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'deposit_sol'
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+    // Vault PDA check: only the correct authority can deposit
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault's wSOL token account (to), its mint was checked when the SOL vault was created
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = native_mint::ID)]
+    pub native_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The user depositing lamports
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'withdraw_sol'
+#[derive(Accounts)]
+pub struct WithdrawSol<'info> {
+    // Vault PDA check: only the correct authority can withdraw
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's wSOL token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // User's wSOL token account: receives the wSOL and is closed to unwrap it
+    #[account(mut, token::mint = native_mint, token::authority = authority)]
+    pub user_wsol_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = native_mint::ID)]
+    pub native_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Fee treasury's wSOL account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = native_mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), user_wsol_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The user withdrawing, receives the lamports
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'vault_to_vault_transfer'
+#[derive(Accounts)]
+pub struct VaultToVaultTransfer<'info> {
+    // Source Vault PDA check: Only the shared authority can move funds
+    #[account(
+        mut,
+        seeds = [b"vault", source_vault.original_authority.as_ref(), source_vault.index_seed()],
+        bump = source_vault.bump,
+        has_one = authority
+    )]
+    pub source_vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Source Vault Authority PDA: The signer for the transfer out of the source
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", source_vault.key().as_ref()],
+        bump = source_vault.authority_bump
+    )]
+    pub source_vault_authority: UncheckedAccount<'info>,
+
+    // Source vault's token account (from)
+    #[account(mut, address = source_vault.token_account)]
+    pub source_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Destination Vault PDA check: Must be owned by the same authority
+    #[account(
+        mut,
+        seeds = [b"vault", destination_vault.original_authority.as_ref(), destination_vault.index_seed()],
+        bump = destination_vault.bump,
+        has_one = authority
+    )]
+    pub destination_vault: Account<'info, Vault>,
+
+    // Destination vault's token account (to), must hold the same mint
+    #[account(
+        mut,
+        address = destination_vault.token_account,
+        constraint = destination_vault_token_account.mint == source_vault_token_account.mint
+            @ VaultError::VaultMintMismatch
+    )]
+    pub destination_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Shared mint, required by 'transfer_checked'
+    #[account(address = source_vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = source_vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", source_vault.key().as_ref(), destination_vault_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    pub authority: Signer<'info>, // Owner of both vaults
+    #[account(address = source_vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'split_vault'
+#[derive(Accounts)]
+#[instruction(split_amount: u64, new_authority_bump: u8, split_index: u8)]
+pub struct SplitVault<'info> {
+    // Source Vault PDA check: Only the vault authority can split it
+    #[account(
+        mut,
+        seeds = [b"vault", source_vault.original_authority.as_ref(), source_vault.index_seed()],
+        bump = source_vault.bump,
+        has_one = authority
+    )]
+    pub source_vault: Box<Account<'info, Vault>>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Source Vault Authority PDA: The signer for the transfer out of the source
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", source_vault.key().as_ref()],
+        bump = source_vault.authority_bump
+    )]
+    pub source_vault_authority: UncheckedAccount<'info>,
+
+    // Source vault's token account (from)
+    #[account(mut, address = source_vault.token_account)]
+    pub source_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Split Seed PDA: never created, only stands in for 'original_authority' of the new vault
+    /// CHECK: This is safe because it is a verified PDA and holds no data
+    #[account(
+        seeds = [b"split", source_vault.key().as_ref(), &[split_index]], // Seeds: ["split", source_vault, index]
+        bump
+    )]
+    pub split_seed: UncheckedAccount<'info>,
+
+    // New Vault PDA: Seeds: ["vault", split_seed]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vault", split_seed.key().as_ref()],
+        bump,
+        space = 8 + Vault::INIT_SPACE
+    )]
+    pub new_vault: Box<Account<'info, Vault>>,
+
+    // New Vault Authority PDA: Owner of the new vault's token account
+    /// CHECK: This is safe because we derive it with PDA and only use it as an authority
+    #[account(
+        seeds = [b"authority", new_vault.key().as_ref()],
+        bump = new_authority_bump
+    )]
+    pub new_vault_authority: UncheckedAccount<'info>,
+
+    // New Token Account: Holds the split tokens, owned by the new Vault Authority PDA
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = new_vault_authority,
+        token::token_program = token_program,
+    )]
+    pub new_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Source vault's mint, shared by the new vault
+    #[account(address = source_vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = source_vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", source_vault.key().as_ref(), new_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The source vault owner, pays for the new accounts
+    #[account(address = source_vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'initialize_lp_mint'
+#[derive(Accounts)]
+pub struct InitializeLpMint<'info> {
+    // Vault PDA check: Only the vault authority can add an LP mint
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: Mint authority of the LP mint
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // LP Mint PDA: Seeds: ["lp_mint", vault_pubkey], same decimals as the underlying
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"lp_mint", vault.key().as_ref()],
+        bump,
+        mint::decimals = mint.decimals,
+        mint::authority = vault_authority,
+        mint::token_program = token_program,
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the mint
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'set_flash_loan_fee'
+#[derive(Accounts)]
+pub struct SetFlashLoanFee<'info> {
+    // Vault PDA check: Only the vault authority can change fees
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'flash_loan'
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    // Vault PDA check: anyone may borrow, repayment is enforced by introspection
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Borrower's token account (to)
+    #[account(mut, token::mint = mint)]
+    pub borrower_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub borrower: Signer<'info>,
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Instructions Sysvar: used to find the matching 'flash_loan_repay'
+    /// CHECK: This is safe because the address is checked against the sysvar id
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+// Accounts for 'flash_loan_repay'
+#[derive(Accounts)]
+pub struct FlashLoanRepay<'info> {
+    // Vault PDA check: must be the first account, 'flash_loan' looks for it
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault's token account (to)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Borrower's token account (from)
+    #[account(mut, token::mint = mint, token::authority = borrower)]
+    pub borrower_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub borrower: Signer<'info>,
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'create_stream'
+#[derive(Accounts)]
+pub struct CreateStream<'info> {
+    // Vault PDA check: Only the vault authority can create streams
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: Only used as a seed and stored, the recipient signs when claiming
+    pub recipient: UncheckedAccount<'info>,
+
+    // Stream Config PDA: Seeds: ["stream", vault_pubkey, recipient_pubkey]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"stream", vault.key().as_ref(), recipient.key().as_ref()],
+        bump,
+        space = 8 + StreamConfig::INIT_SPACE
+    )]
+    pub stream: Account<'info, StreamConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the stream
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'claim_stream'
+#[derive(Accounts)]
+pub struct ClaimStream<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Stream Config PDA check: Only the stream recipient can claim
+    #[account(
+        mut,
+        seeds = [b"stream", vault.key().as_ref(), recipient.key().as_ref()],
+        bump,
+        has_one = recipient
+    )]
+    pub stream: Account<'info, StreamConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Recipient's token account (to)
+    #[account(mut, token::mint = mint, token::authority = recipient)]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub recipient: Signer<'info>, // The stream recipient claiming
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'cancel_stream'
+#[derive(Accounts)]
+pub struct CancelStream<'info> {
+    // Vault PDA check: Only the vault authority can cancel streams
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"stream", vault.key().as_ref(), stream.recipient.as_ref()],
+        bump
+    )]
+    pub stream: Account<'info, StreamConfig>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'create_scheduled_withdrawal'
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CreateScheduledWithdrawal<'info> {
+    // Vault PDA check: Only the vault authority can schedule withdrawals
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Scheduled Withdrawal PDA: Seeds: ["scheduled", vault_pubkey, nonce]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"scheduled", vault.key().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + ScheduledWithdrawal::INIT_SPACE
+    )]
+    pub scheduled_withdrawal: Account<'info, ScheduledWithdrawal>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the schedule
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'execute_scheduled_withdrawal'
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct ExecuteScheduledWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Scheduled Withdrawal PDA: closed once executed, rent goes back to the vault authority
+    #[account(
+        mut,
+        seeds = [b"scheduled", vault.key().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump,
+        close = rent_recipient
+    )]
+    pub scheduled_withdrawal: Account<'info, ScheduledWithdrawal>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Recipient's token account (to), fixed when the withdrawal was scheduled
+    #[account(mut, address = scheduled_withdrawal.recipient_token_account)]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), recipient_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    /// CHECK: Receives the schedule's rent, must be the vault authority who paid for it
+    #[account(mut, address = vault.authority)]
+    pub rent_recipient: UncheckedAccount<'info>,
+
+    pub cranker: Signer<'info>, // Anyone may execute a due withdrawal
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'cancel_scheduled_withdrawal'
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CancelScheduledWithdrawal<'info> {
+    // Vault PDA check: Only the vault authority can cancel
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"scheduled", vault.key().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub scheduled_withdrawal: Account<'info, ScheduledWithdrawal>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'setup_recovery_config'
+#[derive(Accounts)]
+pub struct SetupRecoveryConfig<'info> {
+    // Vault PDA check: Only the vault authority can choose guardians
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Recovery Config PDA: Seeds: ["recovery", vault_pubkey]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump,
+        space = 8 + RecoveryConfig::INIT_SPACE
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the config
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'initiate_recovery'
+#[derive(Accounts)]
+pub struct InitiateRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"recovery", recovery_config.vault.as_ref()],
+        bump
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    pub guardian: Signer<'info>, // Checked against the guardian list in the handler
+}
+
+// Accounts for 'approve_recovery'
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"recovery", recovery_config.vault.as_ref()],
+        bump
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    pub guardian: Signer<'info>, // Checked against the guardian list in the handler
+}
+
+// Accounts for 'execute_recovery'
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    pub caller: Signer<'info>, // Anyone may execute an approved recovery
+}
+
+// Accounts for 'cancel_recovery'
+#[derive(Accounts)]
+pub struct CancelRecovery<'info> {
+    // Vault PDA check: Only the current vault authority can cancel
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'configure_dead_man_switch'
+#[derive(Accounts)]
+pub struct ConfigureDeadManSwitch<'info> {
+    // Vault PDA check: Only the vault authority can arm the switch
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'dead_man_claim'
+#[derive(Accounts)]
+pub struct DeadManClaim<'info> {
+    // Vault PDA check: No authority check, the beneficiary is checked in the handler
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Beneficiary's token account (to)
+    #[account(mut, token::authority = claimant)]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub claimant: Signer<'info>, // Must be the dead-man beneficiary
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'configure_split'
+#[derive(Accounts)]
+pub struct ConfigureSplit<'info> {
+    // Vault PDA check: Only the vault authority can configure the split
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Beneficiary Split PDA: Seeds: ["split", vault_pubkey], re-configurable
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"split", vault.key().as_ref()],
+        bump,
+        space = 8 + BeneficiarySplit::INIT_SPACE
+    )]
+    pub beneficiary_split: Account<'info, BeneficiarySplit>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the split account
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'set_oracle_unlock'
+#[derive(Accounts)]
+pub struct SetOracleUnlock<'info> {
+    // Vault PDA check: Only the vault authority can set the oracle condition
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_epoch_withdrawal_limit'
+#[derive(Accounts)]
+pub struct SetEpochWithdrawalLimit<'info> {
+    // Vault PDA check: Only the vault authority can change limits
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'realloc_vault'
+#[derive(Accounts)]
+#[instruction(original_authority: Pubkey)]
+pub struct ReallocVault<'info> {
+    // Vault PDA: Unchecked because an old-layout vault no longer deserializes as 'Vault'.
+    // The handler checks the authority, tops up the rent and grows the account.
+    /// CHECK: PDA of this program, the discriminator is checked when deserializing in the handler
+    #[account(
+        mut,
+        seeds = [b"vault", original_authority.as_ref()],
+        bump,
+        owner = crate::ID,
+        constraint = vault.data_len() < 8 + Vault::INIT_SPACE @ VaultError::AlreadyCorrectSize
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays the extra rent
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'migrate_vault'
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    // Legacy Vault PDA: loaded through its v1 layout, rent is returned to the authority.
+    // Vaults on a newer layout share the discriminator but are larger than v1.
+    #[account(
+        mut,
+        seeds = [b"vault", authority.key().as_ref()],
+        bump,
+        close = authority,
+        constraint = legacy_vault.to_account_info().data_len() == 8 + std::mem::size_of::<VaultV1>()
+            @ VaultError::MigrationAlreadyDone
+    )]
+    pub legacy_vault: AccountLoader<'info, VaultV1>,
+
+    // Vault V2 PDA: Seeds: ["vault_v2", legacy_vault_pubkey]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vault_v2", legacy_vault.key().as_ref()],
+        bump,
+        space = 8 + VaultV2::INIT_SPACE
+    )]
+    pub vault_v2: Account<'info, VaultV2>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The legacy vault owner, pays for the v2 account
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'create_vault_registry'
+#[derive(Accounts)]
+pub struct CreateVaultRegistry<'info> {
+    // Vault Registry PDA: Seeds: ["registry", authority_pubkey], starts empty
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"registry", authority.key().as_ref()],
+        bump,
+        space = VaultRegistry::space(0)
+    )]
+    pub vault_registry: Account<'info, VaultRegistry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // Future vault payer, pays for the registry
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'get_registry'
+#[derive(Accounts)]
+pub struct GetRegistry<'info> {
+    #[account(
+        seeds = [b"registry", vault_registry.authority.as_ref()],
+        bump = vault_registry.bump
+    )]
+    pub vault_registry: Account<'info, VaultRegistry>,
+}
+
+
+// Accounts for 'set_early_unlock_penalty'
+#[derive(Accounts)]
+pub struct SetEarlyUnlockPenalty<'info> {
+    // Vault PDA check: Only the vault authority can configure the penalty
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'claim_referral_fees'
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"referral", referrer.key().as_ref()],
+        bump,
+        has_one = referrer
+    )]
+    pub referral_record: Account<'info, ReferralRecord>,
+
+    // Protocol fee account (from), its owner signs the payout
+    #[account(mut, token::authority = treasury_authority)]
+    pub fee_treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Referrer's token account (to)
+    #[account(mut, token::mint = mint, token::authority = referrer)]
+    pub referrer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Fee mint, required by 'transfer_checked'
+    #[account(address = fee_treasury_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub referrer: Signer<'info>,           // The referrer claiming
+    pub treasury_authority: Signer<'info>, // Owner of the protocol fee account
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'grant_vault_delegation'
+#[derive(Accounts)]
+#[instruction(delegate_key: Pubkey)]
+pub struct GrantVaultDelegation<'info> {
+    // Vault PDA check: Only the vault authority can delegate its tokens
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: Current owner of the vault token account
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Only recorded as the SPL delegate, must match the 'delegate_key' argument
+    #[account(address = delegate_key)]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>, // The vault owner
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'revoke_vault_delegation'
+#[derive(Accounts)]
+pub struct RevokeVaultDelegation<'info> {
+    // Vault PDA check: Only the vault authority can revoke
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: Current owner of the vault token account
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>, // The vault owner
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'configure_unlock_vote'
+#[derive(Accounts)]
+pub struct ConfigureUnlockVote<'info> {
+    // Vault PDA check: Only the vault authority can choose the signers
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Unlock Vote PDA: Seeds: ["unlock_vote", vault_pubkey], re-configurable
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"unlock_vote", vault.key().as_ref()],
+        bump,
+        space = 8 + UnlockVote::INIT_SPACE
+    )]
+    pub unlock_vote: Account<'info, UnlockVote>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the vote account
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'cast_unlock_vote'
+#[derive(Accounts)]
+pub struct CastUnlockVote<'info> {
+    #[account(
+        mut,
+        seeds = [b"unlock_vote", unlock_vote.vault.as_ref()],
+        bump
+    )]
+    pub unlock_vote: Account<'info, UnlockVote>,
+
+    pub signer: Signer<'info>, // Checked against the required signers in the handler
+}
+
+
+// Accounts for 'set_whitelist_root'
+#[derive(Accounts)]
+pub struct SetWhitelistRoot<'info> {
+    // Vault PDA check: Only the vault authority can manage the whitelist
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_crank_reward'
+#[derive(Accounts)]
+pub struct SetCrankReward<'info> {
+    // Vault PDA check: Only the vault authority can set the crank reward
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'crank_unlock_vault'
+#[derive(Accounts)]
+pub struct CrankUnlockVault<'info> {
+    // Vault PDA check: Any vault may be cranked, the reward is paid from its lamports
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub crank: Signer<'info>, // The keeper, receives the reward
+}
+
+
+// Accounts for 'create_fee_proposal'
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CreateFeeProposal<'info> {
+    // Vault PDA check: Only the vault authority can put fees to a vote
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Fee Proposal PDA: Seeds: ["fee_proposal", vault_pubkey, proposal_id]
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeProposal::INIT_SPACE,
+        seeds = [b"fee_proposal", vault.key().as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub fee_proposal: Account<'info, FeeProposal>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the proposal
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'vote_on_fee_proposal'
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct VoteOnFeeProposal<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_proposal", vault.key().as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub fee_proposal: Account<'info, FeeProposal>,
+
+    // Fee Vote Record PDA: Seeds: ["fee_vote", proposal_pubkey, voter_pubkey], one vote per wallet
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + FeeVoteRecord::INIT_SPACE,
+        seeds = [b"fee_vote", fee_proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub fee_vote_record: Account<'info, FeeVoteRecord>,
+
+    // Vault Authority PDA: owns the vote escrow
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // LP Mint PDA: Seeds: ["lp_mint", vault_pubkey]
+    #[account(seeds = [b"lp_mint", vault.key().as_ref()], bump)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // The voter's vault LP shares, their balance is the vote weight
+    #[account(
+        mut,
+        token::mint = lp_mint,
+        token::authority = voter,
+    )]
+    pub voter_lp_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vote Escrow PDA: Seeds: ["fee_vote_escrow", proposal_pubkey, voter_pubkey], holds the voted shares
+    #[account(
+        init,
+        payer = voter,
+        seeds = [b"fee_vote_escrow", fee_proposal.key().as_ref(), voter.key().as_ref()],
+        bump,
+        token::mint = lp_mint,
+        token::authority = vault_authority,
+        token::token_program = token_program,
+    )]
+    pub vote_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>, // LP holder, pays for the vote record and the escrow
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'release_fee_vote_escrow'
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ReleaseFeeVoteEscrow<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        seeds = [b"fee_proposal", vault.key().as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub fee_proposal: Account<'info, FeeProposal>,
+
+    // Vault Authority PDA: Signs the transfer out of the escrow and its close
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"lp_mint", vault.key().as_ref()], bump)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Vote Escrow PDA: only the voter's own escrow matches the seeds
+    #[account(
+        mut,
+        seeds = [b"fee_vote_escrow", fee_proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The voter's LP token account (to)
+    #[account(mut, token::mint = lp_mint)]
+    pub voter_lp_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>, // Receives the escrow rent
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'execute_fee_proposal'
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteFeeProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_proposal", vault.key().as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub fee_proposal: Account<'info, FeeProposal>,
+}
+
+
+// Accounts for 'set_confidential_transfers'
+#[derive(Accounts)]
+pub struct SetConfidentialTransfers<'info> {
+    // Vault PDA check: Only the vault authority can toggle confidential transfers
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'sweep_expired_scheduled_withdrawals'
+#[derive(Accounts)]
+pub struct SweepExpiredScheduledWithdrawals<'info> {
+    // Vault PDA check: Only the vault authority, who paid the rent, can sweep
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, receives the reclaimed rent
+    // Scheduled Withdrawal PDAs to close are passed (writable) in 'remaining_accounts'
+}
+
+
+// Accounts for 'batch_deposit'
+#[derive(Accounts)]
+pub struct BatchDeposit<'info> {
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Shared mint of every pair, required by 'transfer_checked'
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>, // Owns every source token account
+    pub token_program: Interface<'info, TokenInterface>,
+    // (vault, user token account, vault token account, depositor access) groups are passed in 'remaining_accounts'
+}
+
+
+// Accounts for 'batch_withdraw'
+#[derive(Accounts)]
+pub struct BatchWithdraw<'info> {
+    // Vault PDA check: Only the vault authority can pay out a batch
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for every transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>, // The vault owner
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    // Recipient token accounts are passed (writable) in 'remaining_accounts', after the oracle feed
+    // and the price floor aggregator when those are configured
+}
+
+
+// Accounts for 'set_interest_rate'
+#[derive(Accounts)]
+pub struct SetInterestRate<'info> {
+    // Vault PDA check: Only the vault authority can set the interest rate
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Interest Reserve PDA: Seeds: ["interest_reserve", vault_pubkey], funded by the protocol treasury
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"interest_reserve", vault.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault_authority,
+        token::token_program = token_program,
+    )]
+    pub interest_reserve: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the reserve
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'accrue_interest'
+#[derive(Accounts)]
+pub struct AccrueInterest<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Interest is paid on the vault's balance
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub cranker: Signer<'info>, // Anyone may accrue interest
+}
+
+// Accounts for 'claim_interest'
+#[derive(Accounts)]
+pub struct ClaimInterest<'info> {
+    // Vault PDA check: Only the vault authority can claim interest
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"interest_reserve", vault.key().as_ref()],
+        bump
+    )]
+    pub interest_reserve: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = interest_reserve.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::mint = mint, token::authority = authority)]
+    pub authority_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>, // The vault owner
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'grant_depositor_role'
+#[derive(Accounts)]
+pub struct GrantDepositorRole<'info> {
+    // Vault PDA check: Only the vault authority can grant roles
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Role Config PDA: Seeds: ["roles", vault_pubkey], created by the first grant
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + RoleConfig::INIT_SPACE,
+        seeds = [b"roles", vault.key().as_ref()],
+        bump
+    )]
+    pub role_config: Account<'info, RoleConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the role config
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'revoke_depositor_role'
+#[derive(Accounts)]
+pub struct RevokeDepositorRole<'info> {
+    // Vault PDA check: Only the vault authority can revoke roles
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"roles", vault.key().as_ref()],
+        bump
+    )]
+    pub role_config: Account<'info, RoleConfig>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'grant_withdrawer_role'
+#[derive(Accounts)]
+pub struct GrantWithdrawerRole<'info> {
+    // Vault PDA check: Only the vault authority can grant roles
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Role Config PDA: Seeds: ["roles", vault_pubkey], created by the first grant
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + RoleConfig::INIT_SPACE,
+        seeds = [b"roles", vault.key().as_ref()],
+        bump
+    )]
+    pub role_config: Account<'info, RoleConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the role config
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'revoke_withdrawer_role'
+#[derive(Accounts)]
+pub struct RevokeWithdrawerRole<'info> {
+    // Vault PDA check: Only the vault authority can revoke roles
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"roles", vault.key().as_ref()],
+        bump
+    )]
+    pub role_config: Account<'info, RoleConfig>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'enable_clawback'
+#[derive(Accounts)]
+pub struct EnableClawback<'info> {
+    // Vault PDA check: Only the vault authority can enable clawback
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'clawback'
+#[derive(Accounts)]
+pub struct Clawback<'info> {
+    // Vault PDA check: the signer is checked against the clawback authority in the handler
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Vault's token account (from)
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, required by 'transfer_checked'
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Clawback authority's token account (to)
+    #[account(mut, token::authority = clawback_authority)]
+    pub clawback_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub clawback_authority: Signer<'info>,
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'update_min_lock_duration'
+#[derive(Accounts)]
+pub struct UpdateMinLockDuration<'info> {
+    // Vault PDA check: Only the vault authority can change the minimum lock duration
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'transfer_vesting_position'
+#[derive(Accounts)]
+pub struct TransferVestingPosition<'info> {
+    // Vault PDA check: Only the current beneficiary can hand over their vesting position
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = beneficiary
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub beneficiary: Signer<'info>, // The current beneficiary
+}
+
+
+// Accounts for 'generate_proof_of_reserve'
+#[derive(Accounts)]
+#[instruction(proof_nonce: u32)]
+pub struct GenerateProofOfReserve<'info> {
+    // Vault PDA check: Anyone may snapshot any vault
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Proof of Reserve PDA: Seeds: ["proof", vault_pubkey, proof_nonce]
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"proof", vault.key().as_ref(), proof_nonce.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + ProofOfReserve::INIT_SPACE
+    )]
+    pub proof_of_reserve: Account<'info, ProofOfReserve>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>, // Whoever requests the snapshot, pays its rent
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'tip'
+#[derive(Accounts)]
+pub struct TipAccounts<'info> {
+    // Vault PDA check: Any vault can receive tips, no authority involved
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut, token::authority = tipper)]
+    pub tipper_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub tipper: Signer<'info>, // Anyone
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'set_max_lock_duration'
+#[derive(Accounts)]
+pub struct SetMaxLockDuration<'info> {
+    // Vault PDA check: Any vault, the vault owner has no say
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA check: Only the protocol admin can bound lock lengths
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = protocol_admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub protocol_admin: Signer<'info>, // The protocol admin
+}
+
+// Accounts for 'set_protocol_admin'
+#[derive(Accounts)]
+pub struct SetProtocolAdmin<'info> {
+    // Program Config PDA check: Only the program admin can reassign the protocol admin
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>, // The program admin
+}
+
+
+// Accounts for 'close_and_migrate_to_new_vault'
+#[derive(Accounts)]
+pub struct CloseAndMigrateToNewVault<'info> {
+    // Vault PDA check: Only the vault authority can migrate; rent lamports go back to them
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority,
+        close = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch and the approved migration targets
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: Signs the transfer out and the close of the token account
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Checked against 'new_program_id' and for being executable in the handler
+    pub new_program: UncheckedAccount<'info>,
+
+    // The new program's Vault PDA, Vault Authority PDA and token account, created by its CPI
+    /// CHECK: Validated by the new program's seeds
+    #[account(mut)]
+    pub new_vault: UncheckedAccount<'info>,
+    /// CHECK: Validated by the new program's seeds
+    pub new_vault_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub new_vault_token_account: Signer<'info>, // Fresh keypair, like 'initialize_vault'
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the new accounts
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    // The new program's Program Config PDA, which must list this program as a migration source
+    /// CHECK: Validated by the new program's seeds
+    pub new_program_config: UncheckedAccount<'info>,
+
+    // Instructions Sysvar: lets the new program see which program is migrating the vault
+    /// CHECK: This is safe because the address is checked against the sysvar id
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+// Accounts for 'initialize_vault_from_migration'
+#[derive(Accounts)]
+#[instruction(state: Vault)]
+pub struct InitializeVaultFromMigration<'info> {
+    // Vault PDA: Seeds: ["vault", authority_pubkey, index], the migrating authority becomes the original authority
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vault", authority.key().as_ref(), state.index_seed()],
+        bump,
+        space = 8 + Vault::INIT_SPACE
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: This is safe because we derive it with PDA and only use it as an authority
+    #[account(seeds = [b"authority", vault.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = vault_authority,
+        token::token_program = token_program,
+    )]
+    pub token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The migrating vault owner
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    // Program Config PDA: the programs vaults may be migrated from
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Instructions Sysvar: used to find the program that is migrating the vault
+    /// CHECK: This is safe because the address is checked against the sysvar id
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+
+// Accounts for 'vault_health_check'
+#[derive(Accounts)]
+pub struct VaultHealthCheck<'info> {
+    // No seeds or address constraints on purpose: the handler re-derives and reports them
+    pub vault: Account<'info, Vault>,
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+
+// Accounts for 'vault_version'
+#[derive(Accounts)]
+pub struct GetVaultVersion<'info> {
+    // Program Config PDA: read-only, the instruction needs at least one account
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+
+// Accounts for 'set_large_withdrawal_threshold'
+#[derive(Accounts)]
+pub struct SetLargeWithdrawalThreshold<'info> {
+    // Vault PDA check: Only the vault authority can change the queue threshold
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'request_large_withdrawal'
+#[derive(Accounts)]
+#[instruction(seq: u64)]
+pub struct RequestLargeWithdrawal<'info> {
+    // Vault PDA check: Only the vault authority can queue withdrawals
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Withdrawal Request PDA: Seeds: ["wr", vault_pubkey, seq]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"wr", vault.key().as_ref(), seq.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + WithdrawalRequest::INIT_SPACE
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the request
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'fulfill_withdrawal_request'
+#[derive(Accounts)]
+#[instruction(seq: u64)]
+pub struct FulfillWithdrawalRequest<'info> {
+    // Vault PDA check: Only the vault authority who queued the request can collect it
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"wr", vault.key().as_ref(), seq.to_le_bytes().as_ref()],
+        bump,
+        constraint = withdrawal_request.requester == authority.key() @ VaultError::UnauthorizedAccess
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::authority = authority)]
+    pub requester_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Fee treasury's token account, only required when a withdrawal fee is charged
+    #[account(mut, token::mint = mint, address = vault.fee_treasury)]
+    pub fee_treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Withdrawal Destination Entry PDA: required while the destination whitelist is enabled
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), requester_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    pub authority: Signer<'info>, // The vault owner
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'set_compliance_officer'
+#[derive(Accounts)]
+pub struct SetComplianceOfficer<'info> {
+    // Vault PDA check: The officer can bypass the time lock, so the vault owner cannot appoint one
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA check: Only the protocol admin can appoint a compliance officer
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>, // The protocol admin
+}
+
+// Accounts for 'revoke_and_redistribute'
+#[derive(Accounts)]
+#[instruction(beneficiary_token_account_key: Pubkey)]
+pub struct RevokeAndRedistribute<'info> {
+    // Vault PDA check: The compliance officer is checked in the handler
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Designated by the compliance officer in the instruction data
+    #[account(mut, address = beneficiary_token_account_key)]
+    pub beneficiary_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub compliance_officer: Signer<'info>,
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'initialize_yield_strategy'
+#[derive(Accounts)]
+pub struct InitializeYieldStrategy<'info> {
+    // Vault PDA check: Only the vault authority can choose a yield strategy
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the approved yield protocols
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Yield Strategy PDA: Seeds: ["strategy", vault_pubkey]
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"strategy", vault.key().as_ref()],
+        bump,
+        space = 8 + YieldStrategy::INIT_SPACE
+    )]
+    pub yield_strategy: Account<'info, YieldStrategy>,
+
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // Yield Token PDA: Seeds: ["yield", vault_pubkey], receives harvests until they are reinvested
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"yield", vault.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault_authority,
+        token::token_program = token_program,
+    )]
+    pub yield_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // The vault owner, pays for the strategy accounts
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'harvest_yield'
+#[derive(Accounts)]
+pub struct HarvestYield<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the approved yield protocols
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Yield Strategy PDA: 'YieldStrategyNotConfigured' when omitted
+    #[account(mut, seeds = [b"strategy", vault.key().as_ref()], bump)]
+    pub yield_strategy: Option<Account<'info, YieldStrategy>>,
+
+    // Vault Authority PDA: Signs the harvest as the stake owner
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"yield", vault.key().as_ref()], bump)]
+    pub yield_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's token account, must come out of the harvest untouched
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Checked against the strategy in the handler
+    pub strategy_program: UncheckedAccount<'info>,
+    /// CHECK: Checked against the strategy in the handler, validated by the strategy program
+    #[account(mut)]
+    pub stake_account: UncheckedAccount<'info>,
+
+    pub cranker: Signer<'info>, // Anyone may harvest
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts for 'reinvest_yield'
+#[derive(Accounts)]
+pub struct ReinvestYield<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"yield", vault.key().as_ref()], bump)]
+    pub yield_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub cranker: Signer<'info>, // Anyone may reinvest, the tokens only move into the vault
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'simulate_withdraw', all read-only
+#[derive(Accounts)]
+pub struct SimulateWithdraw<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault's mint, read for any Token-2022 transfer fee
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // The token account the withdrawal would pay into, checked against the destination whitelist
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"wd", vault.key().as_ref(), user_token_account.key().as_ref()],
+        bump
+    )]
+    pub destination_entry: Option<Account<'info, WithdrawalDestinationEntry>>,
+
+    // LP Mint PDA and the user's LP token account: required once the vault has an LP mint
+    #[account(seeds = [b"lp_mint", vault.key().as_ref()], bump)]
+    pub lp_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(token::authority = user_token_account.owner)]
+    pub user_lp_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+}
+
+
+// Accounts for 'freeze_vault_token_account' / 'unfreeze_vault_token_account'
+#[derive(Accounts)]
+pub struct FreezeVaultTokenAccount<'info> {
+    // Vault PDA check: Only the vault authority can freeze or thaw
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: the mint's freeze authority
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>, // The vault owner
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'set_deposit_clawback_window'
+#[derive(Accounts)]
+pub struct SetDepositClawbackWindow<'info> {
+    // Vault PDA check: Only the vault authority can change the clawback window
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'clawback_deposit'
+#[derive(Accounts)]
+pub struct ClawbackDeposit<'info> {
+    // Vault PDA check: The latest depositor is checked in the handler
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Depositor's token account (to)
+    #[account(mut, token::mint = mint, token::authority = depositor)]
+    pub depositor_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub depositor: Signer<'info>,
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Optional Vault Stats PDA: the deposit is taken back out of the counters when it is passed
+    #[account(
+        mut,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump
+    )]
+    pub vault_stats: Option<Account<'info, VaultStats>>,
+
+    // Optional Depositor Record PDA: the deposit is taken back out of the depositor's total
+    #[account(
+        mut,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Option<Account<'info, DepositorRecord>>,
+
+    // LP Mint PDA and the depositor's LP token account: required once the vault has an LP mint
+    #[account(mut, seeds = [b"lp_mint", vault.key().as_ref()], bump)]
+    pub lp_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(mut, token::authority = depositor)]
+    pub depositor_lp_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+}
+
+
+// Accounts for 'set_custom_locked_message'
+#[derive(Accounts)]
+pub struct SetCustomLockedMessage<'info> {
+    // Vault PDA check: Only the vault authority can change the locked message
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'initialize_airdrop'
+#[derive(Accounts)]
+pub struct InitializeAirdrop<'info> {
+    // Vault PDA check: Only the vault authority can start an airdrop
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+// Accounts for 'claim_airdrop'
+#[derive(Accounts)]
+pub struct ClaimAirdrop<'info> {
+    // Vault PDA check: Anyone in the merkle tree can claim, the proof is checked in the handler
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Airdrop Claim PDA: Seeds: ["airdrop_claim", vault_pubkey, recipient_pubkey]
+    // init_if_needed so a second claim fails with 'AlreadyClaimed' instead of a system error
+    #[account(
+        init_if_needed,
+        payer = recipient,
+        seeds = [b"airdrop_claim", vault.key().as_ref(), recipient.key().as_ref()],
+        bump,
+        space = 8 + AirdropClaim::INIT_SPACE
+    )]
+    pub airdrop_claim: Account<'info, AirdropClaim>,
+
+    // Vault Authority PDA: The signer for the transfer out of the vault
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut, address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Recipient's token account (to)
+    #[account(mut, token::mint = mint, token::authority = recipient)]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>, // Pays for the claim PDA
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+
+// Accounts for 'initialize_global_stats'
+#[derive(Accounts)]
+pub struct InitializeGlobalStats<'info> {
+    // Program Config PDA check: Only the program admin can create the global stats
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    // Global Stats PDA: a single global account, Seeds: ["global_stats", program_id]
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"global_stats", crate::ID.as_ref()],
+        bump,
+        space = 8 + GlobalStats::INIT_SPACE
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // The program admin, pays for the global stats
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'get_global_stats'
+#[derive(Accounts)]
+pub struct GetGlobalStats<'info> {
+    #[account(seeds = [b"global_stats", crate::ID.as_ref()], bump = global_stats.bump)]
+    pub global_stats: Account<'info, GlobalStats>,
+}
+
+
+// Accounts for 'migrate_to_canonical_bumps'
+#[derive(Accounts)]
+pub struct MigrateToCanonicalBumps<'info> {
+    // Vault PDA check: Only the vault authority can migrate; derived with the canonical bump,
+    // since the stored one may be wrong
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: derived with the canonical bump
+    /// CHECK: This is safe because it is a verified PDA, only its address is read
+    #[account(seeds = [b"authority", vault.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'transfer_fee_treasury'
+#[derive(Accounts)]
+pub struct TransferFeeTreasury<'info> {
+    // Vault PDA check: Only the vault authority can rotate the fee treasury
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_memo_required_above'
+#[derive(Accounts)]
+pub struct SetMemoRequiredAbove<'info> {
+    // Vault PDA check: Only the vault authority can change the memo threshold
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'heartbeat' and 'set_heartbeat_min_interval'
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    // Vault PDA check: Only the vault authority can prove liveness
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_per_depositor_cap'
+#[derive(Accounts)]
+pub struct SetPerDepositorCap<'info> {
+    // Vault PDA check: Only the vault authority can change the per-depositor cap
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'whitelist_transfer_authority'
+#[derive(Accounts)]
+pub struct WhitelistTransferAuthority<'info> {
+    // Vault PDA check: Only the (new) vault authority can re-attribute the whitelist
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Whitelist Entry PDAs to update are passed (writable) in 'remaining_accounts'
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'enable_shutdown_mode' and 'disable_shutdown_mode'
+#[derive(Accounts)]
+pub struct SetShutdownMode<'info> {
+    // Vault PDA check: Only the vault authority can shut the vault down
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'get_interest_bearing_balance'
+#[derive(Accounts)]
+pub struct GetInterestBearingBalance<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vault_token_account.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = vault.token_program_id @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'rescue_wrong_token'
+#[derive(Accounts)]
+pub struct RescueWrongToken<'info> {
+    // Vault PDA check: Only the vault authority can rescue tokens
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Vault Authority PDA: The signer for the transfer out of the wrong-token account
+    /// CHECK: This is safe because it is a verified PDA
+    #[account(
+        seeds = [b"authority", vault.key().as_ref()],
+        bump = vault.authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // A token account of another mint, owned by the vault authority PDA (checked in the handler)
+    #[account(mut)]
+    pub wrong_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = wrong_token_account.mint)]
+    pub wrong_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::mint = wrong_mint)]
+    pub rescue_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>, // The vault owner
+    // The wrong token may live under either token program, it must own the wrong-token account
+    #[account(constraint = *wrong_token_account.to_account_info().owner == token_program.key() @ VaultError::TokenProgramMismatch)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// Accounts for 'set_lock_delegate'
+#[derive(Accounts)]
+pub struct SetLockDelegate<'info> {
+    // Vault PDA check: Only the vault authority can choose the lock delegate
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'batch_lock_vaults'
+#[derive(Accounts)]
+pub struct BatchLockVaults<'info> {
+    // Program Config PDA: the global pause switch
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+    // Vault PDAs, their token accounts and their signing authorities are passed in 'remaining_accounts'
+}
+
+
+// Accounts for 'request_authority_change'
+#[derive(Accounts)]
+pub struct RequestAuthorityChange<'info> {
+    // Vault PDA check: Only the current vault authority can request a change
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA: the authority change timelock
+    #[account(seeds = [b"program_config"], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub authority: Signer<'info>, // The current owner
+}
+
+// Accounts for 'finalize_authority_change'
+#[derive(Accounts)]
+pub struct FinalizeAuthorityChange<'info> {
+    // Vault PDA check: Only the pending authority can accept the vault
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        constraint = vault.pending_authority != Pubkey::default() @ VaultError::NoPendingAuthorityChange,
+        constraint = vault.pending_authority == pending_authority.key() @ VaultError::UnauthorizedAccess
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub pending_authority: Signer<'info>, // The requested new owner
+}
+
+// Accounts for 'cancel_authority_change'
+#[derive(Accounts)]
+pub struct CancelAuthorityChange<'info> {
+    // Vault PDA check: Only the current vault authority can cancel
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The current owner
+}
+
+// Accounts for 'set_authority_change_delay'
+#[derive(Accounts)]
+pub struct SetAuthorityChangeDelay<'info> {
+    // Program Config PDA check: Only the program admin can change the delay
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>, // The program admin
+}
+
+
+// Accounts for 'verify_vault_integrity'
+#[derive(Accounts)]
+pub struct VerifyVaultIntegrity<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(address = vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+
+// Accounts for 'reset_risk_score'
+#[derive(Accounts)]
+pub struct ResetRiskScore<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Program Config PDA check: Only the risk admin can clear a score
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = risk_admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub risk_admin: Signer<'info>, // The risk admin
+}
+
+// Accounts for 'set_risk_admin'
+#[derive(Accounts)]
+pub struct SetRiskAdmin<'info> {
+    // Program Config PDA check: Only the program admin can reassign the risk admin
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>, // The program admin
+}
+
+
+// Accounts for 'set_price_floor_guard'
+#[derive(Accounts)]
+pub struct SetPriceFloorGuard<'info> {
+    // Vault PDA check: Only the vault authority can configure the price floor
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_deposit_rate_limit'
+#[derive(Accounts)]
+pub struct SetDepositRateLimit<'info> {
+    // Vault PDA check: Only the vault authority can change the rate limit
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'commit_deposit'
+#[derive(Accounts)]
+pub struct CommitDeposit<'info> {
+    #[account(
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Deposit Commitment PDA: Seeds: ["commit", vault_pubkey, depositor_pubkey]
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        seeds = [b"commit", vault.key().as_ref(), depositor.key().as_ref()],
+        bump,
+        space = 8 + DepositCommitment::INIT_SPACE
+    )]
+    pub deposit_commitment: Account<'info, DepositCommitment>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>, // The future depositor, pays for the commitment
+    pub system_program: Program<'info, System>,
+}
+
+// Accounts for 'reveal_deposit': the regular 'deposit' accounts plus the commitment
+#[derive(Accounts)]
+pub struct RevealDeposit<'info> {
+    pub deposit: Deposit<'info>,
+
+    // Deposit Commitment PDA, closed to the depositor once revealed
+    /// CHECK: Deserialized in the handler, an empty account means nothing is committed
+    #[account(
+        mut,
+        seeds = [b"commit", deposit.vault.key().as_ref(), deposit.authority.key().as_ref()],
+        bump
+    )]
+    pub deposit_commitment: UncheckedAccount<'info>,
+}
+
+// Accounts for 'set_reveal_window'
+#[derive(Accounts)]
+pub struct SetRevealWindow<'info> {
+    // Vault PDA check: Only the vault authority can change the reveal window
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_authority.as_ref(), vault.index_seed()],
+        bump = vault.bump,
+        has_one = authority
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>, // The vault owner
+}
+
+
+// Accounts for 'set_strategy_program_approval' / 'set_migration_target_approval'
+#[derive(Accounts)]
+pub struct UpdateProgramAllowlist<'info> {
+    // Program Config PDA check: Only the protocol admin decides which programs vaults may call
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        has_one = protocol_admin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub protocol_admin: Signer<'info>, // The protocol admin
+}
+
+
+// --- Account Data Structure ---
+
+#[account]
+#[derive(InitSpace)]
+pub struct Vault {
+    pub authority: Pubkey,
+    pub original_authority: Pubkey, // Initial payer, keeps the vault PDA derivable after ownership changes
+    pub token_account: Pubkey,
+    pub bump: u8,
+    pub authority_bump: u8,
+    pub is_locked: bool,
+    pub unlock_timestamp: i64,
+    pub guardian: Pubkey,            // Pubkey::default() = no guardian
+    pub emergency_recipient: Pubkey, // Token account for 'emergency_withdraw'
+    pub vesting_start: i64,
+    pub vesting_end: i64,
+    pub total_vested_amount: u64, // 0 = vesting not configured
+    pub already_claimed: u64,
+    pub cliff_timestamp: i64, // 0 = pure linear vesting
+    pub cliff_amount: u64,
+    pub cliff_claimed: bool,
+    pub beneficiary: Pubkey, // Receives funds via 'beneficiary_withdraw'
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+    pub fee_treasury: Pubkey, // Token account receiving fees
+    pub daily_withdrawal_limit: u64, // 0 = unlimited
+    pub current_day_withdrawn: u64,
+    pub last_withdrawal_day: i64,    // unix_timestamp / SECONDS_PER_DAY
+    pub withdrawal_cooldown_secs: i64, // 0 = no cooldown
+    pub last_withdrawal_timestamp: i64,
+    pub token_program_id: Pubkey, // Token program the vault was created with
+    pub max_capacity: u64,        // 0 = unlimited
+    pub min_deposit_amount: u64,  // 0 = no minimum
+    pub auto_lock_duration_secs: i64, // 0 = deposits do not lock the vault
+    pub whitelist_enabled: bool,      // Only whitelisted depositors may deposit
+    pub withdrawal_destination_whitelist_enabled: bool, // Only approved token accounts may receive withdrawals
+    pub hashlock: Option<[u8; 32]>, // SHA-256 of the HTLC preimage
+    pub is_sol_vault: bool,         // Funded via 'deposit_sol'/'withdraw_sol'
+    pub lp_mint: Option<Pubkey>,    // Share mint, deposits mint and withdrawals burn
+    pub flash_loan_active: bool,
+    pub flash_loan_amount: u64,
+    pub flash_loan_fee_bps: u16,
+    pub inactivity_period_secs: i64,   // 0 = dead-man's switch disabled
+    pub last_activity_timestamp: i64,  // Last deposit/withdraw/lock/unlock by the owner
+    pub dead_man_beneficiary: Pubkey,  // Claims everything once the owner goes quiet
+    pub beneficiary_split_enabled: bool, // Withdrawals fan out through the BeneficiarySplit PDA
+    pub oracle_feed: Pubkey,          // Pubkey::default() = no oracle condition
+    pub oracle_program: Pubkey,       // Owner every 'oracle_feed' account must have
+    pub oracle_unlock_price: i64,     // In the feed's own exponent
+    pub oracle_unlock_direction: u8,  // ORACLE_UNLOCK_ABOVE or ORACLE_UNLOCK_BELOW
+    pub epoch_withdrawal_limit: u64,  // 0 = unlimited
+    pub current_epoch_withdrawn: u64,
+    pub last_epoch: u64,              // Clock::epoch of the last withdrawal
+    pub version: u8, // Bumped by 'realloc_vault' each time the account is grown to a new layout
+    pub recommended_hold_until: i64,  // Withdrawals before this pay the early-unlock penalty
+    pub early_unlock_penalty_bps: u16, // 0 = no penalty
+    pub high_water_mark: u64, // Peak token balance ever held, only increases
+    pub all_time_deposit_count: u64,
+    pub all_time_withdrawal_count: u64,
+    pub current_delegate: Option<Pubkey>, // SPL delegate on the vault token account, informational
+    pub delegated_amount: u64,
+    pub whitelist_merkle_root: [u8; 32], // [0; 32] = no merkle whitelist
+    pub crank_reward_lamports: u64, // Paid from the vault's own lamports to whoever cranks the unlock, 0 = no crank
+    pub confidential_transfers_enabled: bool, // Deposits are shielded into the vault's confidential balance
+    pub pending_scheduled_withdrawal_count: u32, // Open 'ScheduledWithdrawal' PDAs
+    pub interest_rate_bps_per_day: u16, // Lock-in reward, 0 = no interest
+    pub interest_accrued: u64,          // Owed to the authority, paid from the interest reserve
+    pub last_interest_update: i64,      // 0 until the first 'accrue_interest'
+    pub clawback_enabled: bool,    // Set once by the authority, cleared only by a clawback
+    pub clawback_authority: Pubkey, // May reclaim unvested tokens, e.g. the employer
+    pub min_lock_duration_secs: i64, // Locks shorter than this are rejected
+    pub locked_amount: u64, // Portion of the balance held by the lock, 0 = the whole balance
+    pub proof_count: u32, // Next proof-of-reserve nonce
+    pub total_received_tips: u64, // Donations via 'tip', excluded from 'max_capacity'
+    pub max_lock_duration_secs: i64, // 0 = unlimited, set by the protocol admin
+    pub schema_version: u8, // Layout clients should expect, 0 = created before this field existed
+    pub large_withdrawal_threshold: u64, // Withdrawals above this go through the request queue, 0 = no queue
+    pub large_withdrawal_cooloff_secs: i64, // Review window before a queued request can be fulfilled
+    pub compliance_officer: Pubkey, // May freeze and redistribute under a legal order, default = none
+    pub minimum_lock_balance: u64, // 'lock_vault' refuses to lock less than this, 0 = no minimum
+    pub token_account_frozen: bool, // Set by 'freeze_vault_token_account', blocks deposits and withdrawals
+    pub clawback_window_secs: i64, // Grace period in which the latest deposit can be reversed, 0 = off
+    pub deposit_clawback_until: i64, // End of the current grace period
+    pub clawback_depositor: Pubkey, // Made the latest deposit, the only key that can claw it back
+    pub clawback_amount: u64, // Still reversible out of the latest deposit
+    pub custom_locked_message: [u8; 64], // Logged alongside 'VaultStillLocked' for wallet UIs, zero-padded
+    pub airdrop_merkle_root: [u8; 32], // Leaves are keccak(recipient || amount_le), [0; 32] = no airdrop
+    pub airdrop_total_allocated: u64, // Sum of every leaf amount
+    pub airdrop_total_claimed: u64, // Paid out so far, never exceeds the allocation
+    pub bumps_migrated: bool, // 'bump' and 'authority_bump' have been checked against the canonical ones
+    pub pending_treasury: Pubkey, // Proposed by 'transfer_fee_treasury', default = none
+    pub treasury_transfer_unlocks_at: i64, // When the pending treasury can be finalized
+    pub memo_required_above: u64, // Deposits above this must carry a memo, 0 = never required
+    pub last_memo_hash: [u8; 32], // keccak of the latest deposit memo
+    pub heartbeat_min_interval_secs: i64, // Spacing between heartbeats, 0 = no cooldown
+    pub last_heartbeat_timestamp: i64, // 0 until the first 'heartbeat'
+    pub max_per_depositor: u64, // Lifetime deposit cap per address, tracked by 'DepositorRecord', 0 = no cap
+    pub shutdown_mode: bool, // Decommissioning: deposits are refused, withdrawals keep working
+    pub deposit_amount_original: u64, // Raw amount deposited net of withdrawals, before any mint interest
+    pub vault_index: u8, // Third PDA seed, lets one original authority own up to 256 vaults
+    pub lock_delegate: Pubkey, // May call 'lock_vault' and nothing else, default = none
+    pub pending_authority: Pubkey, // Requested by 'request_authority_change', default = none
+    pub authority_change_unlocks_at: i64, // When the pending authority can finalize the change
+    pub risk_score: u8, // 0-100, raised by large or rapid withdrawals, see 'record_withdrawal_risk'
+    pub risk_last_updated: i64,
+    pub price_floor_oracle: Pubkey, // Switchboard v2 aggregator checked by 'withdraw'
+    pub price_floor_value: i64,
+    pub price_floor_staleness_secs: i64,
+    pub price_floor_active: bool,
+    pub max_deposits_per_hour: u32, // Per depositor, tracked by 'DepositorRateLimit', 0 = unlimited
+    pub reveal_window_secs: i64, // Copied into each 'DepositCommitment', 0 = DEFAULT_REVEAL_WINDOW_SECS
+    pub clawback_in_stats: bool, // The latest deposit was counted in the Vault Stats PDA
+    pub clawback_in_record: bool, // The latest deposit was counted in the depositor's Depositor Record PDA
+}
+
+impl Vault {
+    // The index seed as stored, see 'vault_index_seed'
+    pub fn index_seed(&self) -> &[u8] {
+        vault_index_seed(&self.vault_index)
+    }
+
+    // Surfaces the deployment's own explanation of the lock in the program logs
+    pub fn log_locked_message(&self) {
+        let len = self
+            .custom_locked_message
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_LOCKED_MESSAGE_LEN);
+        if let Ok(message) = core::str::from_utf8(&self.custom_locked_message[..len]) {
+            if !message.is_empty() {
+                msg!("{}", message);
+            }
+        }
+    }
+
+    // Deposit bookkeeping once the tokens have arrived: counters, peak TVL, the clawback window and
+    // auto-lock, which re-locks on every deposit but never shortens an existing lock
+    pub fn record_deposit(
+        &mut self,
+        vault_key: Pubkey,
+        depositor: Pubkey,
+        net_amount: u64,
+        balance: u64,
+        now: i64,
+    ) -> Result<()> {
+        self.all_time_deposit_count = self
+            .all_time_deposit_count
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.deposit_amount_original = self
+            .deposit_amount_original
+            .checked_add(net_amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        if balance > self.high_water_mark {
+            self.high_water_mark = balance;
+
+            emit!(HighWaterMarkReached {
+                vault: vault_key,
+                high_water_mark: balance,
+                timestamp: now,
+            });
+        }
+
+        // Escrow grace period: only the latest deposit can be clawed back, a new one restarts the window
+        if self.clawback_window_secs > 0 {
+            self.deposit_clawback_until = now
+                .checked_add(self.clawback_window_secs)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+            self.clawback_depositor = depositor;
+            self.clawback_amount = net_amount;
+            self.clawback_in_stats = false;
+            self.clawback_in_record = false;
+        }
+
+        self.last_activity_timestamp = now;
+        if self.auto_lock_duration_secs > 0 {
+            let unlock_timestamp = now
+                .checked_add(self.auto_lock_duration_secs)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+            if !self.is_locked || unlock_timestamp > self.unlock_timestamp {
+                self.is_locked = true;
+                self.unlock_timestamp = unlock_timestamp;
+
+                msg!("Vault auto-locked until timestamp: {}", unlock_timestamp);
+
+                emit!(VaultLocked {
+                    vault: vault_key,
+                    unlock_timestamp,
+                    timestamp: now,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Adds `amount` to today's running total, resetting it on a new day, and enforces the limit
+    pub fn record_daily_withdrawal(&mut self, now: i64, amount: u64) -> Result<()> {
+        let today = now / SECONDS_PER_DAY;
+        if today != self.last_withdrawal_day {
+            self.current_day_withdrawn = 0;
+            self.last_withdrawal_day = today;
+        }
+
+        self.current_day_withdrawn = self
+            .current_day_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            self.daily_withdrawal_limit == 0 || self.current_day_withdrawn <= self.daily_withdrawal_limit,
+            VaultError::DailyLimitExceeded
+        );
+        Ok(())
+    }
+
+    // Same as the daily limit, but keyed on the cluster epoch so it cannot be timed around
+    pub fn record_epoch_withdrawal(&mut self, epoch: u64, amount: u64) -> Result<()> {
+        if epoch != self.last_epoch {
+            self.current_epoch_withdrawn = 0;
+            self.last_epoch = epoch;
+        }
+
+        self.current_epoch_withdrawn = self
+            .current_epoch_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            self.epoch_withdrawal_limit == 0 || self.current_epoch_withdrawn <= self.epoch_withdrawal_limit,
+            VaultError::EpochLimitExceeded
+        );
+        Ok(())
+    }
+
+    // Enforces the cooldown since the previous withdrawal and records `now` as the latest one
+    pub fn record_withdrawal_time(&mut self, now: i64) -> Result<()> {
+        if self.withdrawal_cooldown_secs > 0 {
+            let next_allowed = self
+                .last_withdrawal_timestamp
+                .checked_add(self.withdrawal_cooldown_secs)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+            require!(now >= next_allowed, VaultError::WithdrawalCooldownActive);
+            if self.risk_score >= HIGH_RISK_SCORE {
+                let next_allowed = next_allowed
+                    .checked_add(self.withdrawal_cooldown_secs)
+                    .ok_or(VaultError::ArithmeticOverflow)?;
+                require!(now >= next_allowed, VaultError::HighRiskCooldownActive);
+            }
+        }
+        self.last_withdrawal_timestamp = now;
+        Ok(())
+    }
+
+    // Scores a completed withdrawal: more than half the balance and rapid repeats add risk, anything else decays it
+    pub fn record_withdrawal_risk(&mut self, amount: u64, balance_before: u64, previous_withdrawal: i64, now: i64) {
+        let large = amount > balance_before / 2;
+        let rapid = now.saturating_sub(previous_withdrawal) < RISK_RAPID_WITHDRAWAL_SECS;
+        self.risk_score = match (large, rapid) {
+            (false, false) => self.risk_score.saturating_sub(5),
+            _ => self
+                .risk_score
+                .saturating_add(if large { 20 } else { 0 })
+                .saturating_add(if rapid { 10 } else { 0 })
+                .min(MAX_RISK_SCORE),
+        };
+        self.risk_last_updated = now;
+    }
+
+    // Total amount vested at `now` (claimed or not).
+    // Without a cliff the whole amount vests linearly from `vesting_start`; with a cliff,
+    // `cliff_amount` is released at the cliff and the rest vests linearly from there.
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        let (linear_start, upfront) = if self.cliff_timestamp == 0 {
+            (self.vesting_start, 0)
+        } else {
+            if now < self.cliff_timestamp {
+                return Ok(0);
+            }
+            (self.cliff_timestamp, self.cliff_amount)
+        };
+
+        if now >= self.vesting_end {
+            return Ok(self.total_vested_amount);
+        }
+        if now <= linear_start {
+            return Ok(upfront);
+        }
+
+        // linear_total * elapsed / duration, widened to u128 so the product cannot overflow
+        let linear_total = self
+            .total_vested_amount
+            .checked_sub(upfront)
+            .ok_or(VaultError::ArithmeticOverflow)? as u128;
+        let elapsed = now
+            .checked_sub(linear_start)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let elapsed = u128::try_from(elapsed).map_err(|_| VaultError::ArithmeticOverflow)?;
+        let duration = self
+            .vesting_end
+            .checked_sub(linear_start)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let duration = u128::try_from(duration).map_err(|_| VaultError::ArithmeticOverflow)?;
+        let linear_vested = linear_total
+            .checked_mul(elapsed)
+            .and_then(|v| v.checked_div(duration))
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        let vested = u64::try_from(linear_vested).map_err(|_| error!(VaultError::ArithmeticOverflow))?;
+        vested
+            .checked_add(upfront)
+            .ok_or_else(|| error!(VaultError::ArithmeticOverflow))
+    }
+}
+
+// Aggregate counters, kept apart from 'Vault' so the u128 fields do not force a realloc
+#[account]
+#[derive(InitSpace)]
+pub struct VaultStats {
+    pub vault: Pubkey,
+    pub total_deposits: u64,
+    pub total_withdrawals: u64,
+    pub cumulative_deposit_volume: u128,
+    pub cumulative_withdrawal_volume: u128,
+    pub unique_depositor_count: u32,
+    pub last_updated: i64,
+}
+
+impl VaultStats {
+    pub fn record_deposit(&mut self, amount: u64, new_depositor: bool, now: i64) -> Result<()> {
+        if new_depositor {
+            self.unique_depositor_count = self
+                .unique_depositor_count
+                .checked_add(1)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+        }
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.cumulative_deposit_volume = self
+            .cumulative_deposit_volume
+            .checked_add(amount as u128)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.last_updated = now;
+        Ok(())
+    }
+
+    // Reverses 'record_deposit' for a clawed back amount, the deposit only stops counting once fully reversed
+    pub fn record_clawback(&mut self, amount: u64, fully_reversed: bool, now: i64) -> Result<()> {
+        if fully_reversed {
+            self.total_deposits = self
+                .total_deposits
+                .checked_sub(1)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+        }
+        self.cumulative_deposit_volume = self
+            .cumulative_deposit_volume
+            .checked_sub(amount as u128)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.last_updated = now;
+        Ok(())
+    }
+
+    pub fn record_withdrawal(&mut self, amount: u64, now: i64) -> Result<()> {
+        self.total_withdrawals = self
+            .total_withdrawals
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.cumulative_withdrawal_volume = self
+            .cumulative_withdrawal_volume
+            .checked_add(amount as u128)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.last_updated = now;
+        Ok(())
+    }
+}
+
+// Global protocol switches, Seeds: ["program_config"]
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    pub is_paused: bool,
+    pub bump: u8,
+    pub protocol_admin: Pubkey, // Sets protocol-wide vault limits such as 'max_lock_duration_secs'
+    pub authority_change_delay_secs: i64, // Timelock on vault ownership changes, 0 = 'transfer_vault_authority' is immediate
+    pub risk_admin: Pubkey, // Clears vault risk scores after reviewing the activity
+    #[max_len(MAX_ALLOWLISTED_PROGRAMS)]
+    pub approved_strategy_programs: Vec<Pubkey>, // Yield protocols 'harvest_yield' may call, set by the protocol admin
+    #[max_len(MAX_ALLOWLISTED_PROGRAMS)]
+    pub approved_migration_targets: Vec<Pubkey>, // Programs 'close_and_migrate_to_new_vault' may move vaults into
+    #[max_len(MAX_ALLOWLISTED_PROGRAMS)]
+    pub approved_migration_sources: Vec<Pubkey>, // Programs 'initialize_vault_from_migration' accepts vaults from
+}
+
+// Protocol-wide dashboard counters, Seeds: ["global_stats", program_id]
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalStats {
+    pub total_vaults: u32,
+    pub total_value_locked_approx: u128, // Raw token amounts summed across every mint
+    pub total_deposit_events: u64,
+    pub bump: u8,
+}
+
+// A hidden pending deposit, Seeds: ["commit", vault_pubkey, depositor_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct DepositCommitment {
+    pub commitment: [u8; 32], // keccak(amount_le || nonce)
+    pub committed_at: i64,
+    pub reveal_window_secs: i64,
+}
+
+// Per-depositor deposit counter, Seeds: ["rate_limit", vault_pubkey, depositor_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct DepositorRateLimit {
+    pub deposit_count_this_hour: u32,
+    pub window_start: i64,
+}
+
+impl DepositorRateLimit {
+    // Starts a fresh window once the current one is over, then counts the deposit against `max_per_window`
+    pub fn record_deposit(&mut self, now: i64, max_per_window: u32) -> Result<()> {
+        if now.saturating_sub(self.window_start) > DEPOSIT_RATE_LIMIT_WINDOW_SECS {
+            self.deposit_count_this_hour = 0;
+            self.window_start = now;
+        }
+        require!(
+            self.deposit_count_this_hour < max_per_window,
+            VaultError::DepositRateLimitExceeded
+        );
+        self.deposit_count_this_hour = self
+            .deposit_count_this_hour
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+// Per-depositor accounting, Seeds: ["depositor", vault_pubkey, depositor_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct DepositorRecord {
+    pub depositor: Pubkey,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub last_deposit_timestamp: i64,
+}
+
+impl DepositorRecord {
+    pub fn record_deposit(&mut self, depositor: Pubkey, amount: u64, now: i64) -> Result<()> {
+        self.depositor = depositor;
+        self.total_deposited = self
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.last_deposit_timestamp = now;
+        Ok(())
+    }
+
+    pub fn record_clawback(&mut self, amount: u64) -> Result<()> {
+        self.total_deposited = self
+            .total_deposited
+            .checked_sub(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_withdrawal(&mut self, amount: u64) -> Result<()> {
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+// Approved depositor, Seeds: ["whitelist", vault_pubkey, depositor_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct WhitelistEntry {
+    pub is_active: bool,
+    pub added_by: Pubkey,
+}
+
+// Approved withdrawal target, Seeds: ["wd", vault_pubkey, destination_token_account]
+#[account]
+#[derive(InitSpace)]
+pub struct WithdrawalDestinationEntry {
+    pub is_approved: bool,
+}
+
+// Capped withdrawal grant, Seeds: ["delegate", vault_pubkey, delegate_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct DelegateConfig {
+    pub delegate: Pubkey,
+    pub allowance: u64,
+    pub used: u64,
+    pub expires_at: i64,
+}
+
+// Extra mint held by a vault, Seeds: ["slot", vault_pubkey, mint_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct VaultTokenSlot {
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey, // Vault authority's ATA for 'mint'
+    pub slot_bump: u8,
+}
+
+// Continuous payment, Seeds: ["stream", vault_pubkey, recipient_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct StreamConfig {
+    pub recipient: Pubkey,
+    pub rate_per_second: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub last_claimed_at: i64,
+    pub total_claimed: u64,
+}
+
+impl StreamConfig {
+    // Tokens accrued between the last claim and `now`, capped at the stream end
+    pub fn claimable(&self, now: i64) -> Result<u64> {
+        let elapsed = now.min(self.end_time).saturating_sub(self.last_claimed_at).max(0);
+        let claimable = u64::try_from(elapsed)
+            .map_err(|_| VaultError::ArithmeticOverflow)?
+            .checked_mul(self.rate_per_second)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        Ok(claimable)
+    }
+}
+
+// Pre-authorized future payout, Seeds: ["scheduled", vault_pubkey, nonce]
+#[account]
+#[derive(InitSpace)]
+pub struct ScheduledWithdrawal {
+    pub amount: u64,
+    pub execute_after: i64,
+    pub recipient_token_account: Pubkey,
+    pub cancelled: bool,
+}
+
+// Queued large withdrawal, Seeds: ["wr", vault_pubkey, seq]
+#[account]
+#[derive(InitSpace)]
+pub struct WithdrawalRequest {
+    pub amount: u64,
+    pub requester: Pubkey,
+    pub requested_at: i64,
+    pub cooloff_seconds: i64,
+    pub fulfilled: bool,
+}
+
+// External yield source, Seeds: ["strategy", vault_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct YieldStrategy {
+    pub strategy_program: Pubkey, // Implements 'utils::YieldProtocol'
+    pub stake_account: Pubkey,
+    pub last_harvest: i64, // 0 = never harvested
+    pub accumulated_yield: u64,
+}
+
+// Argument of 'initialize_vault_with_config', 0 / Pubkey::default() = not configured
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultInitConfig {
+    pub min_deposit_amount: u64,
+    pub max_capacity: u64,
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+    pub fee_treasury: Pubkey,
+    pub auto_lock_duration_secs: i64,
+    pub daily_withdrawal_limit: u64,
+    pub withdrawal_cooldown_secs: i64,
+    pub min_lock_duration_secs: i64,
+    pub max_lock_duration_secs: i64,
+}
+
+// Return value of 'verify_vault_integrity'
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultIntegrityReport {
+    pub is_locked: bool,
+    pub balance: u64,
+    pub unlock_timestamp: i64,
+}
+
+// Return value of 'simulate_withdraw'
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WithdrawSimulation {
+    pub gross_amount: u64,
+    pub fee_deducted: u64,
+    pub penalty_deducted: u64,
+    pub net_amount: u64,
+    pub will_succeed: bool,
+    pub failure_reason: Option<u8>, // 'VaultError' index, the error code minus 6000
+}
+
+// One recipient's airdrop claim, Seeds: ["airdrop_claim", vault_pubkey, recipient_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct AirdropClaim {
+    pub claimed: bool,
+    pub amount: u64,
+}
+
+// Guardian-based key recovery, Seeds: ["recovery", vault_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct RecoveryConfig {
+    pub vault: Pubkey,
+    #[max_len(5)]
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub recovery_initiated_at: i64, // 0 = no recovery in progress
+    pub new_authority_proposed: Pubkey,
+    #[max_len(5)]
+    pub approvals: Vec<Pubkey>,
+}
+
+impl RecoveryConfig {
+    pub const MAX_GUARDIANS: usize = 5;
+
+    // Drops any in-progress recovery
+    pub fn clear(&mut self) {
+        self.recovery_initiated_at = 0;
+        self.new_authority_proposed = Pubkey::default();
+        self.approvals.clear();
+    }
+}
+
+// Fixed-ratio payout of withdrawals, Seeds: ["split", vault_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct BeneficiarySplit {
+    pub vault: Pubkey,
+    #[max_len(10)]
+    pub recipients: Vec<SplitRecipient>,
+    #[max_len(10)]
+    pub recipient_token_accounts: Vec<Pubkey>, // Same order as 'recipients'
+}
+
+impl BeneficiarySplit {
+    pub const MAX_RECIPIENTS: usize = 10;
+
+    // Each recipient's cut of `amount`; the last one also takes the rounding dust
+    pub fn shares(&self, amount: u64) -> Result<Vec<u64>> {
+        let mut shares = Vec::with_capacity(self.recipients.len());
+        let mut remaining = amount;
+        for (i, recipient) in self.recipients.iter().enumerate() {
+            let share = if i + 1 == self.recipients.len() {
+                remaining
+            } else {
+                let share = (amount as u128)
+                    .checked_mul(recipient.bps as u128)
+                    .and_then(|v| v.checked_div(BPS_DENOMINATOR as u128))
+                    .ok_or(VaultError::ArithmeticOverflow)?;
+                u64::try_from(share).map_err(|_| error!(VaultError::ArithmeticOverflow))?
+            };
+            remaining = remaining
+                .checked_sub(share)
+                .ok_or(VaultError::ArithmeticOverflow)?;
+            shares.push(share);
+        }
+        Ok(shares)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct SplitRecipient {
+    pub recipient: Pubkey, // Owner of the matching token account
+    pub bps: u16,
+}
+
+// The original Vault layout. Those accounts were written under the name 'Vault',
+// so the discriminator is pinned to sha256("account:Vault")[..8].
+#[account(zero_copy(unsafe), discriminator = [211, 8, 232, 43, 2, 152, 117, 119])]
+#[repr(C, packed)]
+pub struct VaultV1 {
+    pub authority: Pubkey,
+    pub token_account: Pubkey,
+    pub bump: u8,
+    pub authority_bump: u8,
+    pub is_locked: u8, // bool, kept as u8 so every byte pattern is valid
+    pub unlock_timestamp: i64,
+}
+
+// Migrated vault, Seeds: ["vault_v2", legacy_vault_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct VaultV2 {
+    pub schema_version: u8, // First field, so it always sits at offset 8
+    pub authority: Pubkey,
+    pub token_account: Pubkey,
     pub bump: u8,
     pub authority_bump: u8,
     pub is_locked: bool,
     pub unlock_timestamp: i64,
+    pub legacy_vault: Pubkey, // Seed of the vault authority PDA that still owns the tokens
+}
+
+impl VaultV2 {
+    pub const SCHEMA_VERSION: u8 = 2;
+}
+
+// Every vault an authority has created, Seeds: ["registry", authority_pubkey]
+// Sized exactly for its entries and grown with 'realloc' as vaults are added.
+#[account]
+pub struct VaultRegistry {
+    pub authority: Pubkey,
+    pub vault_count: u32,
+    pub bump: u8,
+    pub vaults: Vec<RegistryEntry>,
+}
+
+impl VaultRegistry {
+    pub const MAX_VAULTS: usize = 64;
+
+    // Discriminator + fixed fields + Vec length prefix + `vaults` entries
+    pub const fn space(vaults: usize) -> usize {
+        8 + 32 + 4 + 1 + 4 + RegistryEntry::INIT_SPACE * vaults
+    }
+}
+
+// One 'VaultRegistry' entry: the vault and the index in its seeds
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RegistryEntry {
+    pub vault: Pubkey,
+    pub vault_index: u8,
+}
+
+// Referral attribution across all vaults, Seeds: ["referral", referrer_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralRecord {
+    pub referrer: Pubkey,
+    pub referred_deposit_volume: u128,
+    pub earned_fees: u64, // Unclaimed, in the fee token
+}
+
+impl ReferralRecord {
+    // Credits a referred deposit of `amount` that paid `fee`
+    pub fn record_referral(&mut self, referrer: Pubkey, amount: u64, fee: u64) -> Result<()> {
+        self.referrer = referrer;
+        self.referred_deposit_volume = self
+            .referred_deposit_volume
+            .checked_add(amount as u128)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let share = (fee as u128)
+            .checked_mul(REFERRAL_FEE_SHARE_BPS as u128)
+            .and_then(|v| v.checked_div(BPS_DENOMINATOR as u128))
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let share = u64::try_from(share).map_err(|_| VaultError::ArithmeticOverflow)?;
+        self.earned_fees = self
+            .earned_fees
+            .checked_add(share)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+// N-of-M early unlock approvals, Seeds: ["unlock_vote", vault_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct UnlockVote {
+    pub vault: Pubkey,
+    #[max_len(5)]
+    pub required_signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub approvals_bitmask: u8, // Bit i set = required_signers[i] voted
+}
+
+impl UnlockVote {
+    pub const MAX_SIGNERS: usize = 5;
+
+    pub fn has_passed(&self) -> bool {
+        self.approvals_bitmask.count_ones() >= self.threshold as u32
+    }
+}
+
+// LP-weighted fee change vote, Seeds: ["fee_proposal", vault_pubkey, proposal_id]
+#[account]
+#[derive(InitSpace)]
+pub struct FeeProposal {
+    pub vault: Pubkey,
+    pub proposal_id: u64,
+    pub proposed_deposit_fee_bps: u16,
+    pub proposed_withdraw_fee_bps: u16,
+    pub votes_for: u32, // Whole LP shares, escrowed by each voter until voting ends
+    pub votes_against: u32,
+    pub voting_ends_at: i64,
+    pub executed: bool,
+}
+
+// One wallet's vote on a proposal, Seeds: ["fee_vote", proposal_pubkey, voter_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct FeeVoteRecord {
+    pub voter: Pubkey,
+    pub approve: bool,
+    pub weight: u32,
+}
+
+// Depositor and withdrawer roles granted by the authority, Seeds: ["roles", vault_pubkey]
+#[account]
+#[derive(InitSpace)]
+pub struct RoleConfig {
+    pub vault: Pubkey,
+    #[max_len(20)]
+    pub depositors: Vec<Pubkey>,
+    #[max_len(20)]
+    pub withdrawers: Vec<Pubkey>,
+}
+
+impl RoleConfig {
+    pub const MAX_MEMBERS: usize = 20;
+}
+
+// Point-in-time balance snapshot for auditors, Seeds: ["proof", vault_pubkey, proof_nonce]
+#[account]
+#[derive(InitSpace)]
+pub struct ProofOfReserve {
+    pub vault: Pubkey,
+    pub token_balance: u64,
+    pub is_locked: bool,
+    pub unlock_timestamp: i64,
+    pub snapshot_timestamp: i64,
+    pub snapshot_slot: u64,
+}
+
+// Replay guard for sensitive instructions, Seeds: ["nonce", vault_pubkey, nonce]
+// The record existing means the nonce has been spent.
+#[account]
+#[derive(InitSpace)]
+pub struct InstructionNonce {
+    pub vault: Pubkey,
+    pub nonce: u64,
+    pub used_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MultisigConfig {
+    pub vault: Pubkey, // Default until bound by 'initialize_vault'
+    #[max_len(10)]
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub pending_action: Option<PendingAction>,
+    pub bump: u8,
+}
+
+impl MultisigConfig {
+    pub const MAX_OWNERS: usize = 10;
+
+    // Position of `key` in the owner list, which is also its bit in the approvals bitfield
+    pub fn owner_index(&self, key: &Pubkey) -> Result<usize> {
+        self.owners
+            .iter()
+            .position(|owner| owner == key)
+            .ok_or_else(|| error!(VaultError::NotMultisigOwner))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PendingAction {
+    pub action: MultisigAction,
+    pub amount: u64,          // Used by Withdraw
+    pub recipient: Pubkey,    // Token account receiving a Withdraw
+    pub unlock_timestamp: i64, // Used by Lock
+    pub approvals: u16,       // Bit i set = owners[i] approved
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum MultisigAction {
+    Withdraw,
+    Lock,
+    Unlock,
+}
+
+
+
+// --- Events ---
+// Note: #[event] already derives AnchorSerialize and AnchorDeserialize
+
+#[event]
+#[derive(Clone)]
+pub struct VaultInitialized {
+    pub vault: Pubkey,
+    pub authority: Pubkey,
+    pub token_account: Pubkey,
+    pub mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct TokensDeposited {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64, // Net of fees
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct TokensWithdrawn {
+    pub vault: Pubkey,
+    pub destination: Pubkey, // Token account that received the funds
+    pub amount: u64,         // Net of fees
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct VaultLocked {
+    pub vault: Pubkey,
+    pub unlock_timestamp: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct VaultUnlocked {
+    pub vault: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct AuthorityTransferred {
+    pub vault: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct BeneficiarySet {
+    pub vault: Pubkey,
+    pub beneficiary: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct VaultMigrated {
+    pub legacy_vault: Pubkey,
+    pub vault_v2: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+
+#[event]
+#[derive(Clone)]
+pub struct HighWaterMarkReached {
+    pub vault: Pubkey,
+    pub high_water_mark: u64,
+    pub timestamp: i64,
+}
+
+
+#[event]
+#[derive(Clone)]
+pub struct BatchDepositCompleted {
+    pub authority: Pubkey,
+    pub deposit_count: u8,
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+
+#[event]
+#[derive(Clone)]
+pub struct BatchWithdrawCompleted {
+    pub vault: Pubkey,
+    pub total_withdrawn: u64,
+    pub recipient_count: u8,
+    pub timestamp: i64,
+}
+
+
+#[event]
+#[derive(Clone)]
+pub struct VestingPositionTransferred {
+    pub vault: Pubkey,
+    pub old_beneficiary: Pubkey,
+    pub new_beneficiary: Pubkey,
+    pub remaining_claimable: u64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct VaultTipped {
+    pub tipper: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct ComplianceAction {
+    pub officer: Pubkey,
+    pub amount: u64,
+    pub reason_hash: [u8; 32],
+}
+
+#[event]
+#[derive(Clone)]
+pub struct FeeTreasuryTransferred {
+    pub vault: Pubkey,
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct HeartbeatReceived {
+    pub vault: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct ShutdownModeEnabled {
+    pub vault: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone)]
+pub struct GuardianOverrideUsed {
+    pub vault: Pubkey,
+    pub guardians_who_approved: Vec<Pubkey>,
+}
+
+
+// --- Helpers ---
+
+// Transfers tokens out of the vault token account, signed by the vault authority PDA.
+// Uses 'transfer_checked' so the same path works for both Token and Token-2022 mints.
+fn transfer_from_vault<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault_authority: AccountInfo<'info>,
+    vault: &Account<'info, Vault>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = TransferChecked {
+        from,
+        mint: mint.to_account_info(),
+        to,
+        authority: vault_authority, // PDA is the authority
+    };
+
+    let vault_key = vault.key();
+    let authority_seed = &[
+        b"authority",
+        vault_key.as_ref(),
+        &[vault.authority_bump],
+    ];
+    let signer = &[&authority_seed[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer);
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)
+}
+
+
+// Mints LP shares, signed by the vault authority PDA (the LP mint authority)
+fn mint_lp_from_vault<'info>(
+    token_program: AccountInfo<'info>,
+    lp_mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    vault: &Account<'info, Vault>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = MintTo {
+        mint: lp_mint,
+        to,
+        authority: vault_authority,
+    };
+
+    let vault_key = vault.key();
+    let authority_seed = &[
+        b"authority",
+        vault_key.as_ref(),
+        &[vault.authority_bump],
+    ];
+    let signer = &[&authority_seed[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer);
+    token_interface::mint_to(cpi_ctx, amount)
+}
+
+// Transfers tokens from a user-owned token account, signed by the user
+fn transfer_from_user<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = TransferChecked {
+        from,
+        mint: mint.to_account_info(),
+        to,
+        authority,
+    };
+    let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)
+}
+
+// amount * lp_supply / vault_balance, 1:1 while the vault has no shares or no balance
+fn lp_shares(amount: u64, lp_supply: u64, vault_balance: u64, round_up: bool) -> Result<u64> {
+    if lp_supply == 0 || vault_balance == 0 {
+        return Ok(amount);
+    }
+    let numerator = (amount as u128)
+        .checked_mul(lp_supply as u128)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    let mut shares = numerator
+        .checked_div(vault_balance as u128)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    if round_up && numerator.checked_rem(vault_balance as u128) != Some(0) {
+        shares = shares.checked_add(1).ok_or(VaultError::ArithmeticOverflow)?;
+    }
+    u64::try_from(shares).map_err(|_| error!(VaultError::ArithmeticOverflow))
+}
+
+// amount * bps / 10_000, rounded down
+fn fee_amount(amount: u64, bps: u16) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(bps as u128)
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR as u128))
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    u64::try_from(fee).map_err(|_| error!(VaultError::ArithmeticOverflow))
+}
+
+
+// Reads the aggregate price from a legacy Pyth price account owned by `oracle_program`
+fn read_pyth_price(price_account: &AccountInfo, oracle_program: &Pubkey) -> Result<i64> {
+    require_keys_eq!(*price_account.owner, *oracle_program, VaultError::InvalidOracleAccount);
+
+    let data = price_account.try_borrow_data()?;
+    let read_u32 = |offset: usize| -> Result<u32> {
+        let bytes = data
+            .get(offset..offset + 4)
+            .ok_or(VaultError::InvalidOracleAccount)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    require!(read_u32(0)? == PYTH_MAGIC, VaultError::InvalidOracleAccount);
+    require!(read_u32(8)? == PYTH_PRICE_ACCOUNT_TYPE, VaultError::InvalidOracleAccount);
+
+    let price = data
+        .get(PYTH_AGG_PRICE_OFFSET..PYTH_AGG_PRICE_OFFSET + 8)
+        .ok_or(VaultError::InvalidOracleAccount)?;
+    Ok(i64::from_le_bytes(price.try_into().unwrap()))
+}
+
+// Reads the latest confirmed result and its round's open timestamp from a Switchboard v2 aggregator
+fn read_switchboard_price(aggregator: &AccountInfo) -> Result<(i128, i64)> {
+    require_keys_eq!(*aggregator.owner, SWITCHBOARD_V2_PROGRAM_ID, VaultError::InvalidOracleAccount);
+
+    let data = aggregator.try_borrow_data()?;
+    require!(
+        data.starts_with(&SWITCHBOARD_AGGREGATOR_DISCRIMINATOR),
+        VaultError::InvalidOracleAccount
+    );
+
+    let timestamp = data
+        .get(SWITCHBOARD_ROUND_OPEN_TIMESTAMP_OFFSET..SWITCHBOARD_ROUND_OPEN_TIMESTAMP_OFFSET + 8)
+        .ok_or(VaultError::InvalidOracleAccount)?;
+    let mantissa = data
+        .get(SWITCHBOARD_RESULT_MANTISSA_OFFSET..SWITCHBOARD_RESULT_MANTISSA_OFFSET + 16)
+        .ok_or(VaultError::InvalidOracleAccount)?;
+    Ok((
+        i128::from_le_bytes(mantissa.try_into().unwrap()),
+        i64::from_le_bytes(timestamp.try_into().unwrap()),
+    ))
+}
+
+// Simple (non-compounding) interest: balance * rate * days / 10_000
+fn interest_for_days(balance: u64, rate_bps_per_day: u16, days: i64) -> Result<u64> {
+    let days = u128::try_from(days.max(0)).map_err(|_| VaultError::ArithmeticOverflow)?;
+    let interest = (balance as u128)
+        .checked_mul(rate_bps_per_day as u128)
+        .and_then(|x| x.checked_mul(days))
+        .and_then(|x| x.checked_div(BPS_DENOMINATOR as u128))
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    u64::try_from(interest).map_err(|_| error!(VaultError::ArithmeticOverflow))
+}
+
+// A scheduled withdrawal can be swept once cancelled, or when left unclaimed long after it came due
+fn is_sweepable(sw: &ScheduledWithdrawal, clock: &Clock) -> bool {
+    sw.cancelled
+        || sw
+            .execute_after
+            .checked_add(SCHEDULED_WITHDRAWAL_EXPIRY_SECS)
+            .is_some_and(|expires_at| clock.unix_timestamp > expires_at)
+}
+
+// Close a program-owned account held as a raw 'AccountInfo': the rent moves to 'destination'
+// and the emptied account is handed back to the system program.
+fn close_program_account<'info>(account: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    let lamports = account.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&system_program::ID);
+    account.resize(0)?;
+    Ok(())
+}
+
+// Transfer fee a Token-2022 'TransferFeeConfig' mint withholds when sending enough to deliver
+// `amount` net, i.e. the inverse fee for the current epoch. 0 for mints without the extension.
+fn get_transfer_fee_for_amount(mint_account: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint_account.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+
+    let data = mint_account.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+
+    let epoch_fee = transfer_fee_config.get_epoch_fee(Clock::get()?.epoch);
+    epoch_fee
+        .calculate_inverse_fee(amount)
+        .ok_or_else(|| error!(VaultError::ArithmeticOverflow))
+}
+
+
+// What `amount` is worth in base units with the interest a Token-2022 'InterestBearingConfig' mint
+// has accrued so far, as reported by 'amount_to_ui_amount'. None for mints without the extension.
+fn interest_bearing_value<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    amount: u64,
+) -> Result<Option<u64>> {
+    let mint_account = mint.to_account_info();
+    if *mint_account.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+    {
+        let data = mint_account.try_borrow_data()?;
+        let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+        if state.get_extension::<InterestBearingConfig>().is_err() {
+            return Ok(None);
+        }
+    }
+
+    amount_to_ui_amount(
+        CpiContext::new(token_program.to_account_info(), AmountToUiAmount { account: mint_account }),
+        amount,
+    )?;
+    let (program_id, data) = get_return_data().ok_or(VaultError::InterestBearingQueryFailed)?;
+    require_keys_eq!(program_id, spl_token_2022::ID, VaultError::InterestBearingQueryFailed);
+    let ui_amount = core::str::from_utf8(&data).map_err(|_| VaultError::InterestBearingQueryFailed)?;
+    parse_ui_amount(ui_amount, mint.decimals).map(Some)
+}
+
+// Converts a UI amount such as "105.12" back into base units, dropping digits beyond `decimals`
+fn parse_ui_amount(ui_amount: &str, decimals: u8) -> Result<u64> {
+    let (whole, fraction) = ui_amount.split_once('.').unwrap_or((ui_amount, ""));
+    let fraction: String = fraction
+        .chars()
+        .chain(core::iter::repeat('0'))
+        .take(decimals as usize)
+        .collect();
+    format!("{}{}", whole, fraction)
+        .parse::<u64>()
+        .map_err(|_| error!(VaultError::InterestBearingQueryFailed))
+}
+
+// Whether a mint is a Token-2022 mint with the 'ConfidentialTransferMint' extension
+fn mint_supports_confidential_transfers(mint_account: &AccountInfo) -> Result<bool> {
+    if *mint_account.owner != spl_token_2022::ID {
+        return Ok(false);
+    }
+
+    let data = mint_account.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    Ok(mint.get_extension::<ConfidentialTransferMint>().is_ok())
+}
+
+
+// Moves `amount` between the vault token account's public and confidential balances, signed by
+// the vault authority PDA. Without a proof this is a confidential 'Deposit' (public -> pending);
+// with one it is a confidential 'Withdraw' (available -> public), which needs the vault's new
+// decryptable balance and the equality and range proof context state accounts.
+// The vault token account must already be configured for confidential transfers.
+fn confidential_transfer_from_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    vault_token_account: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault_authority: &UncheckedAccount<'info>,
+    vault: &Account<'info, Vault>,
+    amount: u64,
+    withdraw_proof: Option<([u8; 36], &AccountInfo<'info>, &AccountInfo<'info>)>,
+) -> Result<()> {
+    let mut accounts = vec![
+        AccountMeta::new(vault_token_account.key(), false),
+        AccountMeta::new_readonly(mint.key(), false),
+    ];
+    let mut account_infos = vec![
+        vault_token_account.to_account_info(),
+        mint.to_account_info(),
+    ];
+    let mut data = vec![
+        CONFIDENTIAL_TRANSFER_EXTENSION,
+        if withdraw_proof.is_some() { CONFIDENTIAL_WITHDRAW } else { CONFIDENTIAL_DEPOSIT },
+    ];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(mint.decimals);
+
+    if let Some((new_decryptable_available_balance, equality_proof_context, range_proof_context)) =
+        withdraw_proof
+    {
+        for proof_context in [equality_proof_context, range_proof_context] {
+            require_keys_eq!(
+                *proof_context.owner,
+                ZK_ELGAMAL_PROOF_PROGRAM_ID,
+                VaultError::InvalidConfidentialProofAccount
+            );
+            accounts.push(AccountMeta::new_readonly(proof_context.key(), false));
+            account_infos.push(proof_context.clone());
+        }
+        data.extend_from_slice(&new_decryptable_available_balance);
+        data.extend_from_slice(&[0, 0]); // Proof instruction offsets, 0 = read from context state accounts
+    }
+
+    accounts.push(AccountMeta::new_readonly(vault_authority.key(), true));
+    account_infos.push(vault_authority.to_account_info());
+
+    let vault_key = vault.key();
+    let authority_seed = &[
+        b"authority",
+        vault_key.as_ref(),
+        &[vault.authority_bump],
+    ];
+    invoke_signed(
+        &Instruction {
+            program_id: token_program.key(),
+            accounts,
+            data,
+        },
+        &account_infos,
+        &[&authority_seed[..]],
+    )?;
+    Ok(())
+}
+
+// Spends 'nonce' for 'vault', a record already filled in means the transaction is a replay
+fn mark_nonce_used(record: &mut Account<InstructionNonce>, vault: Pubkey, nonce: u64) -> Result<()> {
+    require!(record.vault == Pubkey::default(), VaultError::NonceAlreadyUsed);
+    record.vault = vault;
+    record.nonce = nonce;
+    record.used_at = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+// What a deposit path tells 'check_deposit_gates' about the tokens it is about to move in
+struct DepositGate<'a> {
+    program_config: &'a ProgramConfig,
+    depositor: Pubkey,
+    amount: u64,
+    vault_balance: u64,
+    role_config: Option<&'a RoleConfig>,
+    whitelist_entry: Option<&'a WhitelistEntry>,
+    merkle_proof: &'a [[u8; 32]],
+    memo_attached: bool,
+    depositor_record: Option<&'a DepositorRecord>,
+    depositor_rate_limit: Option<&'a mut DepositorRateLimit>,
+}
+
+// The checks every deposit into the vault passes, shared by 'deposit' and 'batch_deposit' so
+// batching never skips one. Counts the deposit against the hourly rate limit and returns the fee.
+fn check_deposit_gates(vault: &Vault, gate: DepositGate, now: i64) -> Result<u64> {
+    // --- Security Check 1: The program is not paused ---
+    require!(!gate.program_config.is_paused, VaultError::ProgramPaused);
+
+    // --- Security Check 2: No flash loan is outstanding, deposits would price LP shares off the short balance ---
+    check_no_flash_loan(vault)?;
+
+    // --- Security Check 3: The vault token account is not frozen ---
+    require!(!vault.token_account_frozen, VaultError::TokenAccountFrozen);
+
+    // --- Security Check 4: A vault being drained takes no new funds ---
+    require!(!vault.shutdown_mode, VaultError::VaultInShutdown);
+
+    // --- Security Check 5: Only the authority, depositor role holders, or whitelisted depositors, may deposit ---
+    let depositor = gate.depositor;
+    if gate.role_config.is_some_and(|roles| roles.depositors.contains(&depositor)) {
+        // Depositor roles are granted by the authority and stand in for the whitelists
+    } else if vault.whitelist_merkle_root != [0u8; 32] {
+        // Large depositor sets are committed to as a merkle root, a proof stands in for the entry PDA
+        let leaf = keccak::hashv(&[depositor.as_ref()]).to_bytes();
+        require!(
+            merkle::verify_merkle_proof(vault.whitelist_merkle_root, leaf, gate.merkle_proof),
+            VaultError::MerkleProofInvalid
+        );
+    } else if vault.whitelist_enabled {
+        let whitelist_entry = gate.whitelist_entry.ok_or(VaultError::NotWhitelisted)?;
+        require!(whitelist_entry.is_active, VaultError::NotWhitelisted);
+    } else if gate.role_config.is_some_and(|roles| !roles.depositors.is_empty()) {
+        require_keys_eq!(depositor, vault.authority, VaultError::MissingDepositorRole);
+    } else {
+        require_keys_eq!(depositor, vault.authority, VaultError::UnauthorizedAccess);
+    }
+
+    // --- Security Check 6: SOL vaults are funded through 'deposit_sol' ---
+    require!(!vault.is_sol_vault, VaultError::NotSplVault);
+
+    // --- Security Check 7: Reject dust deposits ---
+    require!(gate.amount >= vault.min_deposit_amount, VaultError::DepositTooSmall);
+
+    // --- Security Check 8: Large deposits carry a compliance memo ---
+    if vault.memo_required_above > 0 && gate.amount > vault.memo_required_above {
+        require!(gate.memo_attached, VaultError::MemoRequired);
+    }
+
+    // Protocol fee is skimmed off the deposit and sent to the treasury
+    let fee = fee_amount(gate.amount, vault.deposit_fee_bps)?;
+    let net_amount = gate
+        .amount
+        .checked_sub(fee)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    // --- Security Check 9: The vault cannot grow past its capacity ---
+    // Tips are not counted, so donations can never block deposits
+    if vault.max_capacity > 0 {
+        let new_balance = gate
+            .vault_balance
+            .saturating_sub(vault.total_received_tips)
+            .checked_add(net_amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(new_balance <= vault.max_capacity, VaultError::VaultCapacityExceeded);
+    }
+
+    // --- Security Check 10: No address deposits more than its cap over the vault's lifetime ---
+    if vault.max_per_depositor > 0 {
+        let depositor_record = gate.depositor_record.ok_or(VaultError::DepositorRecordRequired)?;
+        let total_deposited = depositor_record
+            .total_deposited
+            .checked_add(net_amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            total_deposited <= vault.max_per_depositor,
+            VaultError::PerDepositorCapExceeded
+        );
+    }
+
+    // --- Security Check 11: No address deposits more than 'max_deposits_per_hour' times per window ---
+    if vault.max_deposits_per_hour > 0 {
+        let depositor_rate_limit = gate
+            .depositor_rate_limit
+            .ok_or(VaultError::RateLimitRecordRequired)?;
+        depositor_rate_limit.record_deposit(now, vault.max_deposits_per_hour)?;
+    }
+    Ok(fee)
+}
+
+// The protocol fee and, while the recommended hold is running, the early-unlock penalty on a
+// withdrawal of `amount`. Both are paid to the fee treasury out of the withdrawn amount.
+fn withdrawal_fees(vault: &Vault, amount: u64, now: i64) -> Result<(u64, u64)> {
+    let fee = fee_amount(amount, vault.withdraw_fee_bps)?;
+    let penalty = if now < vault.recommended_hold_until && vault.early_unlock_penalty_bps > 0 {
+        require!(vault.fee_treasury != Pubkey::default(), VaultError::NoTreasuryForPenalty);
+        fee_amount(amount, vault.early_unlock_penalty_bps)?
+    } else {
+        0
+    };
+    Ok((fee, penalty))
+}
+
+// The token accounts a fee-charging payout moves tokens between
+struct Payout<'a, 'info> {
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    fee_treasury_token_account: Option<AccountInfo<'info>>,
+    mint: &'a InterfaceAccount<'info, Mint>,
+    vault_authority: AccountInfo<'info>,
+}
+
+// Pays `amount` out of the vault, the fees of 'withdrawal_fees' to the treasury and the rest to
+// the destination. Returns (net amount, treasury amount).
+fn pay_out_with_fees<'info>(
+    payout: Payout<'_, 'info>,
+    vault: &Account<'info, Vault>,
+    amount: u64,
+    now: i64,
+) -> Result<(u64, u64)> {
+    let (fee, penalty) = withdrawal_fees(vault, amount, now)?;
+    let treasury_amount = fee
+        .checked_add(penalty)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    let net_amount = amount
+        .checked_sub(treasury_amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    transfer_from_vault(
+        payout.token_program.clone(),
+        payout.from.clone(),
+        payout.to,
+        payout.mint,
+        payout.vault_authority.clone(),
+        vault,
+        net_amount,
+    )?;
+    if treasury_amount > 0 {
+        let fee_treasury_token_account = payout
+            .fee_treasury_token_account
+            .ok_or(VaultError::FeeTreasuryRequired)?;
+        transfer_from_vault(
+            payout.token_program,
+            payout.from,
+            fee_treasury_token_account,
+            payout.mint,
+            payout.vault_authority,
+            vault,
+            treasury_amount,
+        )?;
+    }
+    Ok((net_amount, treasury_amount))
+}
+
+// Adds `program` to, or removes it from, one of the protocol admin's allowlists
+fn set_allowlisted(allowlist: &mut Vec<Pubkey>, program: Pubkey, approved: bool) -> Result<()> {
+    if !approved {
+        allowlist.retain(|approved_program| *approved_program != program);
+    } else if !allowlist.contains(&program) {
+        require!(allowlist.len() < MAX_ALLOWLISTED_PROGRAMS, VaultError::AllowlistFull);
+        allowlist.push(program);
+    }
+    Ok(())
+}
+
+// A flash loan's tokens are out of the vault until 'flash_loan_repay' runs later in the transaction,
+// so the balance cannot be read or paid out of in between
+fn check_no_flash_loan(vault: &Vault) -> Result<()> {
+    require!(!vault.flash_loan_active, VaultError::FlashLoanActive);
+    Ok(())
+}
+
+// Until the lock expires, a full lock blocks every payout and a partial lock keeps `locked_amount`
+// in the vault
+fn check_time_lock(vault: &Vault, vault_balance: u64, amount: u64, now: i64) -> Result<()> {
+    if vault.is_locked && now < vault.unlock_timestamp {
+        if vault.locked_amount == 0 {
+            vault.log_locked_message();
+            return err!(VaultError::VaultStillLocked);
+        }
+        require!(
+            vault_balance.saturating_sub(amount) >= vault.locked_amount,
+            VaultError::WithdrawWouldViolateLock
+        );
+    }
+    Ok(())
+}
+
+// A claim pays out to whoever the authority picked, so it is paused with the program and cannot get
+// around the authority's own lock. A vesting claim is let through the lock 'setup_vesting' set, which
+// only holds back what has not vested yet, but not through a lock that outlasts the schedule.
+fn check_claim_gate(
+    vault: &Vault,
+    program_config: &ProgramConfig,
+    vault_balance: u64,
+    amount: u64,
+    now: i64,
+    vesting_claim: bool,
+) -> Result<()> {
+    require!(!program_config.is_paused, VaultError::ProgramPaused);
+    if vesting_claim && vault.unlock_timestamp == vault.vesting_end && vault.locked_amount == 0 {
+        return Ok(());
+    }
+    check_time_lock(vault, vault_balance, amount, now)
+}
+
+// What a lock path tells 'check_lock_gates' about the lock it is about to set
+struct LockGate<'a> {
+    program_config: &'a ProgramConfig,
+    vault_balance: u64,
+    unlock_timestamp: i64,
+    locked_amount: u64, // 0 = the whole balance
+}
+
+// The checks every new lock passes, in the order 'lock_vault' applies them, so a lock set through
+// another path is held to the same bounds and cannot replace a running lock with a weaker one
+fn check_lock_gates(vault: &Vault, gate: LockGate, now: i64) -> Result<()> {
+    // --- Security Check 1: No flash loan is outstanding ---
+    check_no_flash_loan(vault)?;
+
+    // --- Security Check 2: The program is not paused ---
+    require!(!gate.program_config.is_paused, VaultError::ProgramPaused);
+
+    // --- Security Check 3: Ensure time is in the future ---
+    require!(gate.unlock_timestamp > now, VaultError::InvalidUnlockTime);
+
+    // --- Security Check 4: The lock is long enough to mean something ---
+    let lock_duration = gate
+        .unlock_timestamp
+        .checked_sub(now)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    require!(
+        lock_duration >= vault.min_lock_duration_secs,
+        VaultError::LockDurationTooShort
+    );
+
+    // --- Security Check 5: The lock is not longer than the protocol allows ---
+    require!(
+        vault.max_lock_duration_secs == 0 || lock_duration <= vault.max_lock_duration_secs,
+        VaultError::LockDurationTooLong
+    );
+
+    // --- Security Check 6: There is enough in the vault to be worth locking ---
+    require!(
+        gate.vault_balance >= vault.minimum_lock_balance,
+        VaultError::BalanceTooLowToLock
+    );
+
+    // --- Security Check 7: A running lock is never shortened or shrunk ---
+    check_lock_not_weakened(vault, gate.unlock_timestamp, gate.locked_amount, now)
+}
+
+// Re-locking a vault whose lock is still running may only strengthen it: the unlock time cannot move
+// earlier and the locked amount cannot shrink (0 locks the whole balance). Otherwise the lock delegate
+// could re-lock the vault with a lock that is already over.
+fn check_lock_not_weakened(vault: &Vault, unlock_timestamp: i64, locked_amount: u64, now: i64) -> Result<()> {
+    if vault.is_locked && now < vault.unlock_timestamp {
+        require!(unlock_timestamp >= vault.unlock_timestamp, VaultError::NewTimestampNotLater);
+        let shrinks = match (vault.locked_amount, locked_amount) {
+            (_, 0) => false,
+            (0, _) => true,
+            (current, new) => new < current,
+        };
+        require!(!shrinks, VaultError::LockedAmountCannotShrink);
+    }
+    Ok(())
+}
+
+// What a payout path tells 'check_withdraw_gates' about the tokens it is about to move out
+struct WithdrawGate<'a> {
+    program_config: &'a ProgramConfig,
+    vault_balance: u64,
+    amount: u64,
+    destination_entry: Option<&'a WithdrawalDestinationEntry>, // The destination's whitelist entry, if passed
+    queued: bool, // Paid through the large-withdrawal queue, which enforced its own cool-off
+}
+
+// The checks every payout of the vault's tokens passes, in the order 'withdraw' applies them, so no
+// alternative path can sidestep a control 'withdraw' enforces. Records the amount against the daily,
+// cooldown and epoch limits. 'remaining_accounts' leads with the oracle feed (if configured), then the
+// Switchboard price floor aggregator (if active); the accounts after them are returned.
+fn check_withdraw_gates<'a, 'info>(
+    vault: &mut Vault,
+    gate: WithdrawGate,
+    clock: &Clock,
+    mut remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<&'a [AccountInfo<'info>]> {
+    // --- Security Check 1: The program is not paused ---
+    require!(!gate.program_config.is_paused, VaultError::ProgramPaused);
+
+    // --- Security Check 2: No flash loan is outstanding ---
+    check_no_flash_loan(vault)?;
+
+    // --- Security Check 3: The vault token account is not frozen ---
+    require!(!vault.token_account_frozen, VaultError::TokenAccountFrozen);
+
+    // --- Security Check 4: Large withdrawals must wait out the review window ---
+    require!(
+        gate.queued || vault.large_withdrawal_threshold == 0 || gate.amount <= vault.large_withdrawal_threshold,
+        VaultError::UseWithdrawalQueue
+    );
+
+    // --- Security Check 5: Time Lock, see 'check_time_lock' ---
+    check_time_lock(vault, gate.vault_balance, gate.amount, clock.unix_timestamp)?;
+
+    // --- Security Check 6: Insufficient Funds ---
+    require!(gate.vault_balance >= gate.amount, VaultError::InsufficientFunds);
+
+    // --- Security Check 7: The latest deposit stays put while its depositor can still claw it back ---
+    if clock.unix_timestamp <= vault.deposit_clawback_until {
+        require!(
+            gate.vault_balance - gate.amount >= vault.clawback_amount,
+            VaultError::DepositInClawbackWindow
+        );
+    }
+
+    // --- Security Check 8: Tokens delegated to another program stay put until revoked ---
+    require!(vault.current_delegate.is_none(), VaultError::DelegationActiveCannotWithdraw);
+
+    // --- Security Check 9: Daily limit, cooldown (doubled while the risk score is high) and epoch limit ---
+    vault.record_daily_withdrawal(clock.unix_timestamp, gate.amount)?;
+    vault.record_withdrawal_time(clock.unix_timestamp)?;
+    vault.record_epoch_withdrawal(clock.epoch, gate.amount)?;
+
+    // --- Security Check 10: Destination must be pre-approved when the whitelist is on ---
+    if vault.withdrawal_destination_whitelist_enabled {
+        let destination_entry = gate.destination_entry.ok_or(VaultError::DestinationNotApproved)?;
+        require!(destination_entry.is_approved, VaultError::DestinationNotApproved);
+    }
+
+    // --- Security Check 11: Oracle price condition, the feed leads the remaining accounts ---
+    if vault.oracle_feed != Pubkey::default() {
+        let (oracle_feed, rest) = remaining_accounts
+            .split_first()
+            .ok_or(VaultError::InvalidOracleAccount)?;
+        remaining_accounts = rest;
+
+        require_keys_eq!(oracle_feed.key(), vault.oracle_feed, VaultError::InvalidOracleAccount);
+        let price = read_pyth_price(oracle_feed, &vault.oracle_program)?;
+        let condition_met = match vault.oracle_unlock_direction {
+            ORACLE_UNLOCK_ABOVE => price >= vault.oracle_unlock_price,
+            _ => price <= vault.oracle_unlock_price,
+        };
+        require!(condition_met, VaultError::OracleConditionNotMet);
+    }
+
+    // --- Security Check 12: Switchboard price floor, the aggregator follows any oracle feed ---
+    if vault.price_floor_active {
+        let (aggregator, rest) = remaining_accounts
+            .split_first()
+            .ok_or(VaultError::InvalidOracleAccount)?;
+        remaining_accounts = rest;
+
+        require_keys_eq!(aggregator.key(), vault.price_floor_oracle, VaultError::InvalidOracleAccount);
+        let (price, round_open_timestamp) = read_switchboard_price(aggregator)?;
+        require!(
+            clock.unix_timestamp.saturating_sub(round_open_timestamp) <= vault.price_floor_staleness_secs,
+            VaultError::StaleOraclePrice
+        );
+        require!(price >= vault.price_floor_value as i128, VaultError::PriceBelowFloor);
+    }
+    Ok(remaining_accounts)
+}
+
+// The Global Stats PDA is opt-in: when it is passed, it is the LAST remaining account.
+// Returns it (if present) and the remaining accounts in front of it.
+fn split_global_stats<'a, 'info>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+) -> (Option<&'a AccountInfo<'info>>, &'a [AccountInfo<'info>]) {
+    match accounts.split_last() {
+        Some((last, rest))
+            if last.owner == program_id
+                && last
+                    .try_borrow_data()
+                    .is_ok_and(|data| data.starts_with(GlobalStats::DISCRIMINATOR)) =>
+        {
+            (Some(last), rest)
+        }
+        _ => (None, accounts),
+    }
+}
+
+// Applies `update` to the Global Stats PDA after checking it against its canonical seeds
+fn update_global_stats(
+    program_id: &Pubkey,
+    global_stats_info: &AccountInfo,
+    update: impl FnOnce(&mut GlobalStats) -> Result<()>,
+) -> Result<()> {
+    require!(global_stats_info.is_writable, VaultError::InvalidGlobalStatsAccount);
+    let mut data = global_stats_info.try_borrow_mut_data()?;
+    let mut global_stats = GlobalStats::try_deserialize(&mut &data[..])?;
+    let expected = Pubkey::create_program_address(
+        &[b"global_stats", program_id.as_ref(), &[global_stats.bump]],
+        program_id,
+    )
+    .map_err(|_| VaultError::InvalidGlobalStatsAccount)?;
+    require_keys_eq!(global_stats_info.key(), expected, VaultError::InvalidGlobalStatsAccount);
+
+    update(&mut global_stats)?;
+    global_stats.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+// Merkle whitelist verification. Leaves are keccak(depositor pubkey) and each level hashes
+// the sorted pair, so proofs carry no left/right flags.
+pub mod merkle {
+    use solana_keccak_hasher as keccak;
+
+    pub fn verify_merkle_proof(root: [u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+        let computed = proof.iter().fold(leaf, |node, sibling| {
+            if node <= *sibling {
+                keccak::hashv(&[&node, sibling]).to_bytes()
+            } else {
+                keccak::hashv(&[sibling, &node]).to_bytes()
+            }
+        });
+        computed == root
+    }
+}
+
+// PDA derivation with the canonical seeds, for CPI callers and off-chain clients.
+// Each helper mirrors the 'seeds = [...]' of the matching accounts struct.
+pub mod utils {
+    use anchor_lang::prelude::Pubkey;
+    use solana_sha256_hasher::hash;
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+
+    // The CPI a yield-bearing protocol must accept from 'harvest_yield'. The vault authority PDA
+    // signs as the stake owner, and the protocol pays the yield into 'destination'. Any further
+    // accounts the protocol needs are forwarded from 'remaining_accounts' as 'extra'.
+    pub trait YieldProtocol {
+        fn harvest_instruction(
+            program_id: Pubkey,
+            stake_account: Pubkey,
+            owner: Pubkey,
+            destination: Pubkey,
+            token_program: Pubkey,
+            extra: Vec<AccountMeta>,
+        ) -> Instruction;
+    }
+
+    // An Anchor program exposing 'harvest(ctx)' with accounts [stake_account, owner, destination, token_program]
+    pub struct GenericYieldProtocol;
+
+    impl YieldProtocol for GenericYieldProtocol {
+        fn harvest_instruction(
+            program_id: Pubkey,
+            stake_account: Pubkey,
+            owner: Pubkey,
+            destination: Pubkey,
+            token_program: Pubkey,
+            extra: Vec<AccountMeta>,
+        ) -> Instruction {
+            let mut accounts = vec![
+                AccountMeta::new(stake_account, false),
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(token_program, false),
+            ];
+            accounts.extend(extra);
+            Instruction {
+                program_id,
+                accounts,
+                data: hash(b"global:harvest").to_bytes()[..8].to_vec(),
+            }
+        }
+    }
+
+    // Seeds: ["vault", payer_pubkey], the payer is the vault's 'original_authority'
+    pub fn derive_vault_pda(payer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        derive_indexed_vault_pda(payer, 0, program_id)
+    }
+
+    // Seeds: ["vault", payer_pubkey, index], index 0 drops the index seed
+    pub fn derive_indexed_vault_pda(payer: &Pubkey, vault_index: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"vault", payer.as_ref(), super::vault_index_seed(&vault_index)], program_id)
+    }
+
+    // Seeds: ["authority", vault_pubkey]
+    pub fn derive_vault_authority_pda(vault: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"authority", vault.as_ref()], program_id)
+    }
+
+    // Seeds: ["depositor", vault_pubkey, depositor_pubkey]
+    pub fn derive_depositor_record_pda(vault: &Pubkey, depositor: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"depositor", vault.as_ref(), depositor.as_ref()], program_id)
+    }
+
+    // Seeds: ["whitelist", vault_pubkey, depositor_pubkey]
+    pub fn derive_whitelist_entry_pda(vault: &Pubkey, depositor: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"whitelist", vault.as_ref(), depositor.as_ref()], program_id)
+    }
+
+    // Seeds: ["lp_mint", vault_pubkey]
+    pub fn derive_lp_mint_pda(vault: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"lp_mint", vault.as_ref()], program_id)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn vault_pdas_match_initialize_vault_seeds() {
+            let payer = Pubkey::new_unique();
+            let (vault, vault_bump) = derive_vault_pda(&payer, &crate::ID);
+            let (authority, authority_bump) = derive_vault_authority_pda(&vault, &crate::ID);
+
+            // The same seeds 'InitializeVault' checks, under the program id from Anchor.toml
+            assert_eq!(
+                Pubkey::create_program_address(&[b"vault", payer.as_ref(), &[vault_bump]], &crate::ID),
+                Ok(vault)
+            );
+            assert_eq!(
+                Pubkey::create_program_address(&[b"authority", vault.as_ref(), &[authority_bump]], &crate::ID),
+                Ok(authority)
+            );
+            assert_ne!(derive_vault_pda(&Pubkey::new_unique(), &crate::ID).0, vault);
+
+            let (indexed, indexed_bump) = derive_indexed_vault_pda(&payer, 1, &crate::ID);
+            assert_eq!(
+                Pubkey::create_program_address(&[b"vault", payer.as_ref(), &[1], &[indexed_bump]], &crate::ID),
+                Ok(indexed)
+            );
+            assert_ne!(indexed, vault);
+        }
+
+        #[test]
+        fn per_depositor_pdas_use_distinct_seeds() {
+            let vault = Pubkey::new_unique();
+            let depositor = Pubkey::new_unique();
+            let record = derive_depositor_record_pda(&vault, &depositor, &crate::ID).0;
+            let entry = derive_whitelist_entry_pda(&vault, &depositor, &crate::ID).0;
+
+            assert_ne!(record, entry);
+            assert_ne!(record, derive_depositor_record_pda(&vault, &Pubkey::new_unique(), &crate::ID).0);
+            assert_eq!(
+                derive_lp_mint_pda(&vault, &crate::ID).0,
+                Pubkey::find_program_address(&[b"lp_mint", vault.as_ref()], &crate::ID).0
+            );
+        }
+    }
+}
+
+
+// Index 0 adds no seed bytes, so vaults created before 'vault_index' keep their address
+fn vault_index_seed(vault_index: &u8) -> &[u8] {
+    if *vault_index == 0 {
+        &[]
+    } else {
+        core::slice::from_ref(vault_index)
+    }
 }
 
 
@@ -295,4 +11492,324 @@ pub enum VaultError {
     UnauthorizedAccess,
     #[msg("The requested unlock time is not in the future")]
     InvalidUnlockTime,
+    #[msg("Vault token account must be empty before closing")]
+    VaultNotEmpty,
+    #[msg("New authority is the same as the current authority")]
+    SameAuthority,
+    #[msg("Not enough multisig approvals to execute")]
+    NotEnoughApprovals,
+    #[msg("This owner has already approved the pending action")]
+    AlreadyApproved,
+    #[msg("Signer is not a multisig owner")]
+    NotMultisigOwner,
+    #[msg("Multisig owners must be 1 to 10 unique keys")]
+    InvalidMultisigOwners,
+    #[msg("Multisig threshold must be between 1 and the number of owners")]
+    InvalidMultisigThreshold,
+    #[msg("A multisig action is already pending")]
+    ActionAlreadyPending,
+    #[msg("No multisig action is pending")]
+    NoPendingAction,
+    #[msg("Multisig config is already bound to a vault")]
+    MultisigAlreadyBound,
+    #[msg("Recipient does not match the pending action")]
+    InvalidRecipient,
+    #[msg("No guardian is configured for this vault")]
+    NoGuardianConfigured,
+    #[msg("Signer is not the vault guardian")]
+    UnauthorizedGuardian,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Vesting is not configured for this vault")]
+    VestingNotConfigured,
+    #[msg("Vesting end must be after start and the amount must be positive")]
+    InvalidVestingSchedule,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("The vesting cliff has not been reached")]
+    CliffNotReached,
+    #[msg("Signer is not the vault beneficiary")]
+    NotBeneficiary,
+    #[msg("Fee exceeds the 10% maximum")]
+    FeeTooHigh,
+    #[msg("A fee treasury token account is required when fees are charged")]
+    FeeTreasuryRequired,
+    #[msg("Daily withdrawal limit exceeded")]
+    DailyLimitExceeded,
+    #[msg("Withdrawal cooldown is still active")]
+    WithdrawalCooldownActive,
+    #[msg("Cooldown cannot be negative")]
+    InvalidCooldown,
+    #[msg("Token program does not match the one the vault was created with")]
+    TokenProgramMismatch,
+    #[msg("Deposit would exceed the vault capacity")]
+    VaultCapacityExceeded,
+    #[msg("Deposit is below the vault minimum")]
+    DepositTooSmall,
+    #[msg("Lock duration is too long")]
+    LockDurationTooLong,
+    #[msg("Vault is not locked")]
+    VaultNotLocked,
+    #[msg("New unlock timestamp must be later than the current one")]
+    NewTimestampNotLater,
+    #[msg("Depositor is not whitelisted")]
+    NotWhitelisted,
+    #[msg("Withdrawal destination is not approved")]
+    DestinationNotApproved,
+    #[msg("Delegate allowance exceeded")]
+    DelegateAllowanceExceeded,
+    #[msg("Delegate allowance has expired")]
+    DelegateExpired,
+    #[msg("Preimage does not match the hashlock")]
+    InvalidPreimage,
+    #[msg("No hashlock is configured for this vault")]
+    NoHashlockConfigured,
+    #[msg("The program is paused")]
+    ProgramPaused,
+    #[msg("A token slot already exists for this mint")]
+    SlotAlreadyExists,
+    #[msg("Token slot not found for this vault and mint")]
+    SlotNotFound,
+    #[msg("This instruction requires a native SOL (wSOL) vault")]
+    NotSolVault,
+    #[msg("SOL vaults must use 'deposit_sol'")]
+    NotSplVault,
+    #[msg("Both vaults must hold the same mint")]
+    VaultMintMismatch,
+    #[msg("Split amount exceeds the vault balance")]
+    SplitAmountExceedsBalance,
+    #[msg("LP mint accounts are missing or do not match the vault")]
+    LpMintNotConfigured,
+    #[msg("Not enough LP tokens to cover the withdrawal")]
+    InsufficientLpTokens,
+    #[msg("No flash loan is active")]
+    FlashLoanNotActive,
+    #[msg("Flash loan repayment does not cover principal and fee")]
+    FlashLoanRepaymentInsufficient,
+    #[msg("A flash loan is already active")]
+    FlashLoanActive,
+    #[msg("Flash loan must be repaid later in the same transaction")]
+    FlashLoanRepaymentMissing,
+    #[msg("The stream has not started yet")]
+    StreamNotStarted,
+    #[msg("The stream has ended and has been fully claimed")]
+    StreamEnded,
+    #[msg("Scheduled withdrawal is not due yet")]
+    WithdrawalNotReady,
+    #[msg("Scheduled withdrawal was cancelled")]
+    WithdrawalCancelled,
+    #[msg("Recovery guardians must be 1-5 unique keys with a reachable threshold")]
+    InvalidRecoveryConfig,
+    #[msg("Signer is not a recovery guardian")]
+    NotRecoveryGuardian,
+    #[msg("A recovery is already in progress")]
+    RecoveryAlreadyInitiated,
+    #[msg("No recovery is in progress")]
+    RecoveryNotInitiated,
+    #[msg("Not enough guardians approved the recovery")]
+    RecoveryThresholdNotMet,
+    #[msg("The recovery delay has not passed")]
+    RecoveryTimelockActive,
+    #[msg("The owner has not been inactive for long enough")]
+    InactivityPeriodNotElapsed,
+    #[msg("No dead-man's switch beneficiary is configured")]
+    NoBeneficiaryConfigured,
+    #[msg("Split recipient shares must sum to 10000 bps")]
+    SplitBpsDoNotSum,
+    #[msg("A split supports at most 10 recipients")]
+    TooManyRecipients,
+    #[msg("Split recipient token accounts do not match the configuration")]
+    SplitRecipientMismatch,
+    #[msg("This vault splits withdrawals, the BeneficiarySplit account is required")]
+    BeneficiarySplitRequired,
+    #[msg("The oracle price condition for unlocking is not met")]
+    OracleConditionNotMet,
+    #[msg("Oracle account is missing, mismatched, or not a price account")]
+    InvalidOracleAccount,
+    #[msg("Oracle unlock direction must be 0 (above) or 1 (below)")]
+    InvalidOracleDirection,
+    #[msg("Epoch withdrawal limit exceeded")]
+    EpochLimitExceeded,
+    #[msg("The vault account already has the current layout size")]
+    AlreadyCorrectSize,
+    #[msg("This vault is not in the v1 layout, it has already been migrated")]
+    MigrationAlreadyDone,
+    #[msg("The vault registry is full")]
+    RegistryFull,
+    #[msg("An early-unlock penalty requires a fee treasury")]
+    NoTreasuryForPenalty,
+    #[msg("A depositor cannot refer themselves")]
+    ReferrerIsDepositor,
+    #[msg("A referral record account is required when a referrer is given")]
+    ReferralRecordRequired,
+    #[msg("Vault tokens are delegated, revoke the delegation before withdrawing")]
+    DelegationActiveCannotWithdraw,
+    #[msg("This signer has already voted")]
+    AlreadyVoted,
+    #[msg("Signer is not one of the required unlock signers")]
+    NotARequiredSigner,
+    #[msg("Unlock signers must be 1-5 unique keys with a reachable threshold")]
+    InvalidUnlockVoteConfig,
+    #[msg("Merkle proof is missing or does not match the whitelist root")]
+    MerkleProofInvalid,
+    #[msg("The vault has no crank reward configured")]
+    CrankRewardNotConfigured,
+    #[msg("The voting window has closed")]
+    VotingClosed,
+    #[msg("The voting window is still open")]
+    VotingStillActive,
+    #[msg("This proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Confidential transfers are not enabled on this vault")]
+    ConfidentialTransfersNotEnabled,
+    #[msg("The mint does not have the Token-2022 confidential transfer extension")]
+    MintDoesNotSupportConfidentialTransfers,
+    #[msg("Confidential withdrawals need equality and range proof context state accounts")]
+    InvalidConfidentialProofAccount,
+    #[msg("Scheduled withdrawal is neither cancelled nor expired")]
+    WithdrawalNotSweepable,
+    #[msg("Batch needs one (user, vault) token account pair per amount")]
+    BatchSizeMismatch,
+    #[msg("Batch exceeds the maximum number of entries")]
+    BatchTooLarge,
+    #[msg("Batch total exceeds the vault balance")]
+    BatchWithdrawTotalExceedsBalance,
+    #[msg("Vaults with withdrawal fees, LP shares or withdrawal gates must use 'withdraw'")]
+    BatchWithdrawUnsupported,
+    #[msg("The interest reserve cannot cover the accrued interest")]
+    InsufficientInterestReserves,
+    #[msg("Signer does not hold the depositor role")]
+    MissingDepositorRole,
+    #[msg("Signer does not hold the withdrawer role")]
+    MissingWithdrawerRole,
+    #[msg("Role list is full")]
+    TooManyRoleMembers,
+    #[msg("Role is already granted to this key")]
+    RoleAlreadyGranted,
+    #[msg("Clawback is not enabled for this vault")]
+    ClawbackNotEnabled,
+    #[msg("Clawback is already enabled for this vault")]
+    ClawbackAlreadyEnabled,
+    #[msg("Nothing is left unvested to claw back")]
+    NothingToClawback,
+    #[msg("Lock duration is below the vault's minimum")]
+    LockDurationTooShort,
+    #[msg("The new beneficiary is the current beneficiary")]
+    SameBeneficiary,
+    #[msg("Withdrawal would take the balance below the locked amount")]
+    WithdrawWouldViolateLock,
+    #[msg("Proof nonce must equal the vault's proof count")]
+    ProofNonceMismatch,
+    #[msg("Tip amount must be greater than zero")]
+    TipAmountZero,
+    #[msg("This nonce has already been used")]
+    NonceAlreadyUsed,
+    #[msg("Migration target is not another deployed vault program")]
+    MigrationTargetInvalid,
+    #[msg("Vault invariant violated, see the health report in the logs")]
+    InvariantViolation,
+    #[msg("Amount is above the large-withdrawal threshold, use 'request_large_withdrawal'")]
+    UseWithdrawalQueue,
+    #[msg("Amount is not above the large-withdrawal threshold, use 'withdraw'")]
+    BelowLargeWithdrawalThreshold,
+    #[msg("Withdrawal request has already been fulfilled")]
+    WithdrawalRequestFulfilled,
+    #[msg("No compliance officer is configured for this vault")]
+    ComplianceOfficerNotConfigured,
+    #[msg("Vault balance is below the minimum required to lock")]
+    BalanceTooLowToLock,
+    #[msg("No yield strategy is configured for this vault")]
+    YieldStrategyNotConfigured,
+    #[msg("Yield can only be harvested once per day")]
+    HarvestCooldownActive,
+    #[msg("The vault token account is frozen")]
+    TokenAccountFrozen,
+    #[msg("The vault mint is not the native SOL mint")]
+    MintIsNotNative,
+    #[msg("The deposit clawback window has expired")]
+    ClawbackWindowExpired,
+    #[msg("The message does not fit in 64 bytes")]
+    MessageTooLong,
+    #[msg("This airdrop allocation has already been claimed")]
+    AlreadyClaimed,
+    #[msg("The global stats account is not the canonical writable PDA")]
+    InvalidGlobalStatsAccount,
+    #[msg("The fee treasury transfer is still timelocked")]
+    TreasuryTransferTimelockActive,
+    #[msg("Deposits above the threshold must carry a memo")]
+    MemoRequired,
+    #[msg("The memo does not fit in 64 bytes")]
+    MemoTooLong,
+    #[msg("A heartbeat was sent too recently")]
+    HeartbeatTooSoon,
+    #[msg("This deposit would exceed the per-depositor cap")]
+    PerDepositorCapExceeded,
+    #[msg("A depositor record is required while a per-depositor cap is set")]
+    DepositorRecordRequired,
+    #[msg("The minimum deposit exceeds the vault capacity")]
+    InvalidVaultConfig,
+    #[msg("Too many accounts in one bulk update")]
+    BulkUpdateTooLarge,
+    #[msg("The vault is in shutdown mode and refuses deposits")]
+    VaultInShutdown,
+    #[msg("The interest-bearing balance could not be queried from the mint")]
+    InterestBearingQueryFailed,
+    #[msg("The vault's own token cannot be rescued")]
+    CannotRescuePrimaryToken,
+    #[msg("The vault index is outside the range the registry can record")]
+    VaultIndexOutOfRange,
+    #[msg("Only the vault authority or its lock delegate can lock the vault")]
+    SignerCannotLock,
+    #[msg("Batch lock needs exactly one vault and one authority per unlock timestamp")]
+    BatchLockMismatch,
+    #[msg("A guardian override needs a fully approved recovery naming the current authority")]
+    GuardianOverrideInsufficientApprovals,
+    #[msg("Vault ownership changes are timelocked, use 'request_authority_change' and wait for the delay")]
+    AuthorityChangeTimelockActive,
+    #[msg("No authority change is pending")]
+    NoPendingAuthorityChange,
+    #[msg("The vault does not match the expected authority or mint")]
+    VaultIntegrityMismatch,
+    #[msg("Unusual withdrawal activity: the cooldown is doubled until the risk score drops")]
+    HighRiskCooldownActive,
+    #[msg("The oracle price is below the vault's withdrawal floor")]
+    PriceBelowFloor,
+    #[msg("The oracle's latest confirmed round is too old")]
+    StaleOraclePrice,
+    #[msg("This address has reached the vault's deposits-per-hour limit")]
+    DepositRateLimitExceeded,
+    #[msg("A depositor rate limit account is required while a deposit rate limit is set")]
+    RateLimitRecordRequired,
+    #[msg("The revealed amount and nonce do not match the commitment")]
+    CommitmentMismatch,
+    #[msg("The reveal window for this commitment has expired")]
+    RevealWindowExpired,
+    #[msg("No deposit commitment is pending")]
+    NoPendingCommitment,
+    #[msg("A batch deposit account does not belong to its vault")]
+    BatchDepositAccountMismatch,
+    #[msg("Vaults with deposit fees, LP shares or confidential deposits must use 'deposit'")]
+    BatchDepositUnsupported,
+    #[msg("A running lock cannot be replaced by one that locks a smaller amount")]
+    LockedAmountCannotShrink,
+    #[msg("The protocol admin's allowlist is full")]
+    AllowlistFull,
+    #[msg("The yield protocol has not been approved by the protocol admin")]
+    StrategyProgramNotApproved,
+    #[msg("The harvest moved tokens out of the vault's own token account")]
+    VaultBalanceChangedByStrategy,
+    #[msg("The migration target has not been approved by the protocol admin")]
+    MigrationTargetNotApproved,
+    #[msg("The vault owner cannot be its own clawback authority")]
+    ClawbackAuthorityIsOwner,
+    #[msg("The voter holds less than one whole LP share")]
+    NoVotingPower,
+    #[msg("The latest deposit can only be clawed back until its grace period ends")]
+    DepositInClawbackWindow,
+    #[msg("The Vault Stats and Depositor Record the deposit updated must be passed")]
+    ClawbackAccountsRequired,
+    #[msg("A whitelist entry is not this vault's entry for the depositor passed with it")]
+    WhitelistEntryMismatch,
+    #[msg("Migrated vaults are only accepted by CPI from a source program the protocol admin approved")]
+    MigrationSourceNotApproved,
 }
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+use token_vault::cpi::accounts::VerifyVaultIntegrity;
+use token_vault::program::TokenVault;
+
+declare_id!("3dM9GAwTMSRu4Qa6pyqA7Gz17DXz6yHp9bPZwSxWZiic");
+
+// A stand-in for a program that depends on a vault: it asks 'token_vault' for an integrity
+// report over CPI and refuses to continue unless the vault is in the state it expects.
+#[program]
+pub mod vault_integrity_probe {
+    use super::*;
+
+    // Instruction 1: Require the Vault to be in the expected State
+    pub fn check_vault(
+        ctx: Context<CheckVault>,
+        expected_authority: Pubkey,
+        expected_mint: Pubkey,
+        expected_locked: bool,
+        expected_balance: u64,
+    ) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_vault_program.to_account_info(),
+            VerifyVaultIntegrity {
+                vault: ctx.accounts.vault.to_account_info(),
+                vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+            },
+        );
+        let report = token_vault::cpi::verify_vault_integrity(cpi_ctx, expected_authority, expected_mint)?.get();
+
+        // --- Security Check 1: The vault is in the state this program relies on ---
+        require!(report.is_locked == expected_locked, ProbeError::UnexpectedLockState);
+        require!(report.balance == expected_balance, ProbeError::UnexpectedBalance);
+
+        msg!(
+            "Vault verified: locked {}, balance {}, unlocks at {}",
+            report.is_locked,
+            report.balance,
+            report.unlock_timestamp
+        );
+        Ok(())
+    }
+}
+
+// Accounts for 'check_vault'
+#[derive(Accounts)]
+pub struct CheckVault<'info> {
+    /// CHECK: Validated by 'token_vault' in 'verify_vault_integrity'
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Validated by 'token_vault' against 'vault.token_account'
+    pub vault_token_account: UncheckedAccount<'info>,
+    pub token_vault_program: Program<'info, TokenVault>,
+}
+
+#[error_code]
+pub enum ProbeError {
+    #[msg("The vault's lock state is not the expected one")]
+    UnexpectedLockState,
+    #[msg("The vault's balance is not the expected one")]
+    UnexpectedBalance,
+}
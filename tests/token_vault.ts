@@ -1,16 +1,30 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { TokenVault } from "../target/types/token_vault";
+import { VaultIntegrityProbe } from "../target/types/vault_integrity_probe";
 import {
   MINT_SIZE,
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
   createMint,
   createAccount,
+  closeAccount,
   mintTo,
+  transfer,
   getAccount,
   getAssociatedTokenAddress,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  NATIVE_MINT,
+  ExtensionType,
+  getMintLen,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeInterestBearingMintInstruction,
+  createInitializeMintInstruction,
 } from "@solana/spl-token";
+import { keccak_256 } from "@noble/hashes/sha3";
 import { expect } from "chai";
+import { createHash } from "crypto";
+import { readFileSync } from "fs";
 import { PublicKey, SystemProgram, Transaction, Keypair } from "@solana/web3.js";
 
 // --- Configuration ---
@@ -21,6 +35,269 @@ const LAMPORTS_PER_TOKEN = 10 ** DECIMAL_PLACES;
 // Utility function to pause execution
 const delay = (ms: number) => new Promise(resolve => setTimeout(resolve, ms));
 
+// Everything a test needs to drive a freshly initialized vault
+interface VaultFixture {
+  owner: Keypair;
+  mint: PublicKey;
+  tokenProgram: PublicKey;
+  ownerTokenAccount: PublicKey;
+  vault: PublicKey;
+  vaultAuthority: PublicKey;
+  vaultTokenAccount: PublicKey;
+  vaultBump: number;
+  authorityBump: number;
+}
+
+// Utility function to create a keypair with enough SOL for rent and fees
+const createFundedKeypair = async (provider: anchor.AnchorProvider): Promise<Keypair> => {
+  const keypair = Keypair.generate();
+  await provider.connection.confirmTransaction(
+    await provider.connection.requestAirdrop(keypair.publicKey, 10_000_000_000),
+    "confirmed"
+  );
+  return keypair;
+};
+
+// Utility function to create a token account for `owner` holding `amount` tokens of `mint`
+const createFundedTokenAccount = async (
+  provider: anchor.AnchorProvider,
+  mint: PublicKey,
+  owner: PublicKey,
+  amount: number,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+): Promise<PublicKey> => {
+  const mintAuthority = provider.wallet.payer as Keypair;
+  const tokenAccount = await createAccount(
+    provider.connection,
+    mintAuthority,
+    mint,
+    owner,
+    Keypair.generate(),
+    undefined,
+    tokenProgram
+  );
+  if (amount > 0) {
+    await mintTo(provider.connection, mintAuthority, mint, tokenAccount, mintAuthority, amount, [], undefined, tokenProgram);
+  }
+  return tokenAccount;
+};
+
+// The global Program Config PDA, required by deposit/withdraw/lock/unlock
+const programConfigPda = (program: Program<TokenVault>): PublicKey =>
+  PublicKey.findProgramAddressSync([Buffer.from("program_config")], program.programId)[0];
+
+const instructionNoncePda = (program: Program<TokenVault>, vault: PublicKey, nonce: number): PublicKey =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("nonce"), vault.toBuffer(), new anchor.BN(nonce).toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+
+// Index 0 adds no seed, matching vaults created before 'vault_index'
+const vaultIndexSeeds = (vaultIndex: number): Buffer[] => (vaultIndex === 0 ? [] : [Buffer.from([vaultIndex])]);
+
+// The Program Config is a singleton, so it is created once before any suite runs
+before(async () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+  const programConfig = programConfigPda(program);
+  if ((await provider.connection.getAccountInfo(programConfig)) === null) {
+    await program.methods
+      .initializeProgramConfig()
+      .accounts({ programConfig, admin: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+  }
+  // Most suites hand vaults over with the immediate 'transferVaultAuthority', the timelock has its own suite
+  await program.methods
+    .setAuthorityChangeDelay(new anchor.BN(0))
+    .accounts({ programConfig, admin: provider.wallet.publicKey })
+    .rpc();
+});
+
+// Utility function that sets up a brand new owner, mint and initialized vault.
+// Each vault PDA is seeded by its owner, so every suite needs its own keypair.
+interface VaultFixtureOptions {
+  mintAmount?: number;
+  owner?: Keypair;
+  mint?: PublicKey;
+  multisigConfig?: PublicKey | null;
+  vaultRegistry?: PublicKey | null;
+  guardian?: PublicKey | null;
+  emergencyRecipient?: PublicKey | null;
+  beneficiary?: PublicKey | null;
+  depositFeeBps?: number;
+  withdrawFeeBps?: number;
+  feeTreasury?: PublicKey | null;
+  tokenProgram?: PublicKey;
+  maxCapacity?: number;
+  minDepositAmount?: number;
+  isSolVault?: boolean;
+  minLockDurationSecs?: number;
+  minimumLockBalance?: number;
+  vaultBump?: number; // Stored as given, defaults to the canonical bump
+  vaultIndex?: number;
+}
+
+const createVaultFixture = async (
+  program: Program<TokenVault>,
+  provider: anchor.AnchorProvider,
+  opts: VaultFixtureOptions = {}
+): Promise<VaultFixture> => {
+  const mintAmount = opts.mintAmount ?? 1000 * LAMPORTS_PER_TOKEN;
+  const tokenProgram = opts.tokenProgram ?? TOKEN_PROGRAM_ID;
+  const owner = opts.owner ?? (await createFundedKeypair(provider));
+  const mintAuthority = provider.wallet.payer as Keypair;
+  const mint =
+    opts.mint ??
+    (await createMint(
+      provider.connection,
+      mintAuthority,
+      mintAuthority.publicKey,
+      mintAuthority.publicKey,
+      DECIMAL_PLACES,
+      undefined,
+      undefined,
+      tokenProgram
+    ));
+  const ownerTokenAccount = await createFundedTokenAccount(provider, mint, owner.publicKey, mintAmount, tokenProgram);
+
+  const vaultIndex = opts.vaultIndex ?? 0;
+  const [vault, vaultBump] = PublicKey.findProgramAddressSync(
+    [Buffer.from("vault"), owner.publicKey.toBuffer(), ...vaultIndexSeeds(vaultIndex)],
+    program.programId
+  );
+  const [vaultAuthority, authorityBump] = PublicKey.findProgramAddressSync(
+    [Buffer.from("authority"), vault.toBuffer()],
+    program.programId
+  );
+
+  const tokenAccount = Keypair.generate();
+  await program.methods
+    .initializeVault(
+      opts.vaultBump ?? vaultBump,
+      authorityBump,
+      vaultIndex,
+      opts.guardian ?? null,
+      opts.emergencyRecipient ?? null,
+      opts.beneficiary ?? null,
+      opts.depositFeeBps ?? 0,
+      opts.withdrawFeeBps ?? 0,
+      opts.feeTreasury ?? null,
+      new anchor.BN(opts.maxCapacity ?? 0),
+      new anchor.BN(opts.minDepositAmount ?? 0),
+      opts.isSolVault ?? false,
+      new anchor.BN(opts.minLockDurationSecs ?? 0),
+      new anchor.BN(opts.minimumLockBalance ?? 0)
+    )
+    .accounts({
+      vault,
+      vaultAuthority,
+      tokenAccount: tokenAccount.publicKey,
+      mint,
+      payer: owner.publicKey,
+      multisigConfig: opts.multisigConfig ?? null,
+      vaultRegistry: opts.vaultRegistry ?? null,
+      tokenProgram,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([owner, tokenAccount])
+    .rpc();
+
+  return {
+    owner,
+    mint,
+    tokenProgram,
+    ownerTokenAccount,
+    vault,
+    vaultAuthority,
+    vaultTokenAccount: tokenAccount.publicKey,
+    vaultBump,
+    authorityBump,
+  };
+};
+
+// Utility function that deposits `amount` tokens from the fixture owner's token account
+const depositToVault = async (
+  program: Program<TokenVault>,
+  f: VaultFixture,
+  amount: number | anchor.BN,
+  feeTreasuryTokenAccount: PublicKey | null = null,
+  vaultStats: PublicKey | null = null,
+  depositorRecord: PublicKey | null = null,
+  whitelistEntry: PublicKey | null = null,
+  depositorRateLimit: PublicKey | null = null
+) => {
+  return program.methods
+    .deposit(new anchor.BN(amount.toString()), null, null, null)
+    .accounts({
+      vault: f.vault,
+      userTokenAccount: f.ownerTokenAccount,
+      vaultTokenAccount: f.vaultTokenAccount,
+      authority: f.owner.publicKey,
+      tokenProgram: f.tokenProgram,
+      mint: f.mint,
+      feeTreasuryTokenAccount,
+      vaultStats,
+      depositorRecord,
+      whitelistEntry,
+      programConfig: programConfigPda(program),
+      vaultAuthority: null,
+      lpMint: null,
+      userLpTokenAccount: null,
+      referralRecord: null,
+      roleConfig: null,
+      memoProgram: null,
+      depositorRateLimit,
+    })
+    .signers([f.owner])
+    .rpc();
+};
+
+// Utility function that withdraws `amount` tokens back to the fixture owner's token account
+const withdrawFromVault = async (
+  program: Program<TokenVault>,
+  f: VaultFixture,
+  amount: number | anchor.BN,
+  feeTreasuryTokenAccount: PublicKey | null = null,
+  vaultStats: PublicKey | null = null,
+  depositorRecord: PublicKey | null = null,
+  destinationEntry: PublicKey | null = null
+) => {
+  return program.methods
+    .withdraw(new anchor.BN(amount.toString()), null)
+    .accounts({
+      vault: f.vault,
+      vaultAuthority: f.vaultAuthority,
+      userTokenAccount: f.ownerTokenAccount,
+      vaultTokenAccount: f.vaultTokenAccount,
+      authority: f.owner.publicKey,
+      tokenProgram: f.tokenProgram,
+      mint: f.mint,
+      feeTreasuryTokenAccount,
+      vaultStats,
+      depositorRecord,
+      destinationEntry,
+      programConfig: programConfigPda(program),
+      lpMint: null,
+      userLpTokenAccount: null,
+      beneficiarySplit: null,
+      roleConfig: null,
+    })
+    .signers([f.owner])
+    .rpc();
+};
+
+// Utility function that decodes the Anchor events emitted by a confirmed transaction
+const eventsFromTx = async (program: Program<TokenVault>, provider: anchor.AnchorProvider, signature: string) => {
+  await provider.connection.confirmTransaction(signature, "confirmed");
+  const tx = await provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const parser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+  return [...parser.parseLogs(tx.meta.logMessages)];
+};
+
 describe("token_vault (Local Validator)", () => {
   // Configure the client to use the local cluster (Anchor.toml default)
   const provider = anchor.AnchorProvider.env();
@@ -92,13 +369,30 @@ describe("token_vault (Local Validator)", () => {
 
   it("1. Initialize vault and token account", async () => {
     await program.methods
-      .initializeVault(vaultBump, authorityBump)
+      .initializeVault(
+        vaultBump,
+        authorityBump,
+        0,
+        null,
+        null,
+        null,
+        0,
+        0,
+        null,
+        new anchor.BN(0),
+        new anchor.BN(0),
+        false,
+        new anchor.BN(0),
+        new anchor.BN(0)
+      )
       .accounts({
         vault,
         vaultAuthority,
         tokenAccount: PublicKey.default, // Placeholder, actual account is initialized on-chain
         mint,
         payer,
+        multisigConfig: null, // Single-owner vault
+        vaultRegistry: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
@@ -117,13 +411,26 @@ describe("token_vault (Local Validator)", () => {
     const userPreBalance = (await getAccount(provider.connection, userTokenAccount)).amount;
 
     await program.methods
-      .deposit(new anchor.BN(depositAmount))
+      .deposit(new anchor.BN(depositAmount), null, null, null)
       .accounts({
         vault,
         userTokenAccount,
         vaultTokenAccount,
         authority: payer,
         tokenProgram: TOKEN_PROGRAM_ID,
+        mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
       })
       .rpc();
 
@@ -140,7 +447,7 @@ describe("token_vault (Local Validator)", () => {
     const userPreBalance = (await getAccount(provider.connection, userTokenAccount)).amount;
 
     await program.methods
-      .withdraw(new anchor.BN(withdrawAmount))
+      .withdraw(new anchor.BN(withdrawAmount), null)
       .accounts({
         vault,
         vaultAuthority,
@@ -148,6 +455,16 @@ describe("token_vault (Local Validator)", () => {
         vaultTokenAccount,
         authority: payer,
         tokenProgram: TOKEN_PROGRAM_ID,
+        mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
       })
       .rpc();
 
@@ -166,10 +483,12 @@ describe("token_vault (Local Validator)", () => {
     const unlockTime = currentUnixTime + 5; 
     
     await program.methods
-      .lockVault(new anchor.BN(unlockTime))
+      .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
       .accounts({
         vault,
+        vaultTokenAccount,
         authority: payer,
+        programConfig: programConfigPda(program),
       })
       .rpc();
 
@@ -182,7 +501,7 @@ describe("token_vault (Local Validator)", () => {
   it("5. FAIL to withdraw when locked (as expected)", async () => {
     try {
       await program.methods
-        .withdraw(new anchor.BN(10 * LAMPORTS_PER_TOKEN))
+        .withdraw(new anchor.BN(10 * LAMPORTS_PER_TOKEN), null)
         .accounts({
           vault,
           vaultAuthority,
@@ -190,6 +509,16 @@ describe("token_vault (Local Validator)", () => {
           vaultTokenAccount,
           authority: payer,
           tokenProgram: TOKEN_PROGRAM_ID,
+          mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          destinationEntry: null,
+          programConfig: programConfigPda(program),
+          lpMint: null,
+          userLpTokenAccount: null,
+          beneficiarySplit: null,
+          roleConfig: null,
         })
         .rpc();
       // If the transaction succeeds, force a failure
@@ -207,10 +536,12 @@ describe("token_vault (Local Validator)", () => {
     await delay(6000); 
 
     await program.methods
-      .unlockVault()
+      .authorityUnlockVault()
       .accounts({
         vault,
         authority: payer,
+        programConfig: programConfigPda(program),
+        unlockVote: null,
       })
       .rpc();
 
@@ -225,7 +556,7 @@ describe("token_vault (Local Validator)", () => {
     const userPreBalance = (await getAccount(provider.connection, userTokenAccount)).amount;
 
     await program.methods
-      .withdraw(new anchor.BN(finalWithdrawAmount))
+      .withdraw(new anchor.BN(finalWithdrawAmount), null)
       .accounts({
         vault,
         vaultAuthority,
@@ -233,6 +564,16 @@ describe("token_vault (Local Validator)", () => {
         vaultTokenAccount,
         authority: payer,
         tokenProgram: TOKEN_PROGRAM_ID,
+        mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
       })
       .rpc();
 
@@ -247,4 +588,7888 @@ describe("token_vault (Local Validator)", () => {
     console.log(`\t✅ Final withdrawal successful. Vault balance: ${expectedFinalBalance / LAMPORTS_PER_TOKEN}`);
   });
 
+  it("8. FAIL to close vault while it still holds tokens", async () => {
+    try {
+      await program.methods
+        .closeVault()
+        .accounts({
+          vault,
+          vaultAuthority,
+          vaultTokenAccount,
+          authority: payer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Close should have failed because the vault is not empty.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Vault token account must be empty before closing");
+      console.log("\t✅ Close correctly blocked while vault holds tokens.");
+    }
+  });
+
+  it("9. Close vault after draining it and reclaim rent", async () => {
+    // Drain the remaining balance first
+    const remaining = (await getAccount(provider.connection, vaultTokenAccount)).amount;
+    await program.methods
+      .withdraw(new anchor.BN(remaining.toString()), null)
+      .accounts({
+        vault,
+        vaultAuthority,
+        userTokenAccount,
+        vaultTokenAccount,
+        authority: payer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
+      })
+      .rpc();
+
+    const vaultRent = await provider.connection.getBalance(vault);
+    const tokenAccountRent = await provider.connection.getBalance(vaultTokenAccount);
+    const payerPreBalance = await provider.connection.getBalance(payer);
+
+    await program.methods
+      .closeVault()
+      .accounts({
+        vault,
+        vaultAuthority,
+        vaultTokenAccount,
+        authority: payer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const payerPostBalance = await provider.connection.getBalance(payer);
+    // Allow a generous margin for the transaction fee
+    expect(payerPostBalance).to.be.greaterThan(payerPreBalance + vaultRent + tokenAccountRent - 100_000);
+
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vaultTokenAccount)).to.be.null;
+    console.log(`\t✅ Vault closed. Reclaimed ${vaultRent + tokenAccountRent} lamports of rent.`);
+  });
+
+});
+
+describe("token_vault: authority transfer", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let newAuthority: Keypair;
+  let newAuthorityTokenAccount: PublicKey;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    newAuthority = await createFundedKeypair(provider);
+    newAuthorityTokenAccount = await createFundedTokenAccount(provider, f.mint, newAuthority.publicKey, 0);
+
+    await depositToVault(program, f, 200 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to transfer authority to the current authority", async () => {
+    try {
+      await program.methods
+        .transferVaultAuthority(f.owner.publicKey)
+        .accounts({ vault: f.vault, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Transfer to the same authority should have failed.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("New authority is the same as the current authority");
+    }
+  });
+
+  it("Transfers authority to a new key", async () => {
+    await program.methods
+      .transferVaultAuthority(newAuthority.publicKey)
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.authority.toString()).to.equal(newAuthority.publicKey.toString());
+    // The PDA is still derived from the original payer
+    expect(vaultAccount.originalAuthority.toString()).to.equal(f.owner.publicKey.toString());
+  });
+
+  it("New authority can withdraw, old authority cannot", async () => {
+    const amount = new anchor.BN(50 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .withdraw(amount, null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: newAuthorityTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: newAuthority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
+      })
+      .signers([newAuthority])
+      .rpc();
+    expect((await getAccount(provider.connection, newAuthorityTokenAccount)).amount.toString()).to.equal(amount.toString());
+
+    try {
+      await program.methods
+        .withdraw(amount, null)
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          userTokenAccount: f.ownerTokenAccount,
+          vaultTokenAccount: f.vaultTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          mint: f.mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          destinationEntry: null,
+          programConfig: programConfigPda(program),
+          lpMint: null,
+          userLpTokenAccount: null,
+          beneficiarySplit: null,
+          roleConfig: null,
+        })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Old authority should no longer be able to withdraw.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MissingWithdrawerRole");
+    }
+  });
+
+  it("New authority can lock, old authority cannot", async () => {
+    const unlockTime = Math.floor(Date.now() / 1000) + 60;
+
+    try {
+      await program.methods
+        .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
+        .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Old authority should no longer be able to lock.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SignerCannotLock");
+    }
+
+    await program.methods
+      .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: newAuthority.publicKey, programConfig: programConfigPda(program) })
+      .signers([newAuthority])
+      .rpc();
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.true;
+  });
+});
+
+describe("token_vault: multisig authority", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let owners: Keypair[];
+  let multisigConfig: PublicKey;
+  let recipientTokenAccount: PublicKey;
+  const amount = new anchor.BN(25 * LAMPORTS_PER_TOKEN);
+
+  const executeAccounts = (owner: Keypair) => ({
+    multisigConfig,
+    vault: f.vault,
+    vaultAuthority: f.vaultAuthority,
+    vaultTokenAccount: f.vaultTokenAccount,
+    mint: f.mint,
+    recipientTokenAccount,
+    programConfig: programConfigPda(program),
+    feeTreasuryTokenAccount: null,
+    destinationEntry: null,
+    owner: owner.publicKey,
+    tokenProgram: TOKEN_PROGRAM_ID,
+  });
+
+  before(async () => {
+    owners = [await createFundedKeypair(provider), await createFundedKeypair(provider), await createFundedKeypair(provider)];
+    const creator = owners[0];
+
+    [multisigConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("multisig"), creator.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createMultisigConfig(owners.map((o) => o.publicKey), 2)
+      .accounts({ multisigConfig, creator: creator.publicKey, systemProgram: SystemProgram.programId })
+      .signers([creator])
+      .rpc();
+
+    f = await createVaultFixture(program, provider, { mintAmount: 100 * LAMPORTS_PER_TOKEN, owner: creator, multisigConfig });
+    recipientTokenAccount = await createFundedTokenAccount(provider, f.mint, owners[2].publicKey, 0);
+
+    // A multisig vault cannot sign 'deposit', so fund it with a plain SPL transfer
+    await transfer(provider.connection, creator, f.ownerTokenAccount, f.vaultTokenAccount, creator, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Binds the multisig PDA as the vault authority", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.authority.toString()).to.equal(multisigConfig.toString());
+    const config = await program.account.multisigConfig.fetch(multisigConfig);
+    expect(config.vault.toString()).to.equal(f.vault.toString());
+  });
+
+  it("Requires the threshold of approvals before executing a withdrawal", async () => {
+    await program.methods
+      .proposeAction({ withdraw: {} }, amount, recipientTokenAccount, new anchor.BN(0))
+      .accounts({ multisigConfig, owner: owners[0].publicKey })
+      .signers([owners[0]])
+      .rpc();
+
+    try {
+      await program.methods
+        .approveAction()
+        .accounts({ multisigConfig, owner: owners[0].publicKey })
+        .signers([owners[0]])
+        .rpc();
+      expect.fail("The proposer has already approved.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("This owner has already approved the pending action");
+    }
+
+    try {
+      await program.methods.executeAction().accounts(executeAccounts(owners[0])).signers([owners[0]]).rpc();
+      expect.fail("One approval is below the 2-of-3 threshold.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Not enough multisig approvals to execute");
+    }
+
+    await program.methods
+      .approveAction()
+      .accounts({ multisigConfig, owner: owners[1].publicKey })
+      .signers([owners[1]])
+      .rpc();
+    await program.methods.executeAction().accounts(executeAccounts(owners[1])).signers([owners[1]]).rpc();
+
+    const recipientBalance = await getAccount(provider.connection, recipientTokenAccount);
+    expect(recipientBalance.amount.toString()).to.equal(amount.toString());
+    const config = await program.account.multisigConfig.fetch(multisigConfig);
+    expect(config.pendingAction).to.be.null;
+  });
+
+  it("Rejects proposals from non-owners", async () => {
+    const outsider = await createFundedKeypair(provider);
+    try {
+      await program.methods
+        .proposeAction({ unlock: {} }, new anchor.BN(0), PublicKey.default, new anchor.BN(0))
+        .accounts({ multisigConfig, owner: outsider.publicKey })
+        .signers([outsider])
+        .rpc();
+      expect.fail("Outsiders cannot propose.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Signer is not a multisig owner");
+    }
+  });
+
+  it("Charges the withdrawal fee on a multisig withdrawal", async () => {
+    const feeOwners = [await createFundedKeypair(provider), await createFundedKeypair(provider)];
+    const [feeMultisigConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("multisig"), feeOwners[0].publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createMultisigConfig(feeOwners.map((o) => o.publicKey), 1)
+      .accounts({ multisigConfig: feeMultisigConfig, creator: feeOwners[0].publicKey, systemProgram: SystemProgram.programId })
+      .signers([feeOwners[0]])
+      .rpc();
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, null, DECIMAL_PLACES);
+    const treasury = await createFundedTokenAccount(provider, mint, provider.wallet.publicKey, 0);
+    const g = await createVaultFixture(program, provider, {
+      mint,
+      owner: feeOwners[0],
+      multisigConfig: feeMultisigConfig,
+      withdrawFeeBps: 100,
+      feeTreasury: treasury,
+    });
+    await transfer(provider.connection, feeOwners[0], g.ownerTokenAccount, g.vaultTokenAccount, feeOwners[0], 100 * LAMPORTS_PER_TOKEN);
+    const recipient = await createFundedTokenAccount(provider, mint, feeOwners[1].publicKey, 0);
+
+    await program.methods
+      .proposeAction({ withdraw: {} }, new anchor.BN(100 * LAMPORTS_PER_TOKEN), recipient, new anchor.BN(0))
+      .accounts({ multisigConfig: feeMultisigConfig, owner: feeOwners[0].publicKey })
+      .signers([feeOwners[0]])
+      .rpc();
+    await program.methods
+      .executeAction()
+      .accounts({
+        multisigConfig: feeMultisigConfig,
+        vault: g.vault,
+        vaultAuthority: g.vaultAuthority,
+        vaultTokenAccount: g.vaultTokenAccount,
+        mint,
+        recipientTokenAccount: recipient,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: treasury,
+        destinationEntry: null,
+        owner: feeOwners[0].publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([feeOwners[0]])
+      .rpc();
+
+    expect((await getAccount(provider.connection, recipient)).amount.toString()).to.equal((99 * LAMPORTS_PER_TOKEN).toString());
+    expect((await getAccount(provider.connection, treasury)).amount.toString()).to.equal(LAMPORTS_PER_TOKEN.toString());
+  });
+
+  it("FAIL to shorten a running lock through a multisig action", async () => {
+    const lockUntil = async (unlockTimestamp: number) => {
+      await program.methods
+        .proposeAction({ lock: {} }, new anchor.BN(0), PublicKey.default, new anchor.BN(unlockTimestamp))
+        .accounts({ multisigConfig, owner: owners[0].publicKey })
+        .signers([owners[0]])
+        .rpc();
+      await program.methods
+        .approveAction()
+        .accounts({ multisigConfig, owner: owners[1].publicKey })
+        .signers([owners[1]])
+        .rpc();
+      await program.methods.executeAction().accounts(executeAccounts(owners[1])).signers([owners[1]]).rpc();
+    };
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await lockUntil(now + 3600);
+
+    try {
+      await lockUntil(now + 60);
+      expect.fail("A running lock cannot be shortened.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NewTimestampNotLater");
+    }
+  });
+});
+
+describe("token_vault: guardian emergency withdraw", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let guardian: Keypair;
+  let mint: PublicKey;
+  let emergencyRecipient: PublicKey;
+
+  const emergencyAccounts = (signer: PublicKey, nonce = 0) => ({
+    vault: f.vault,
+    vaultAuthority: f.vaultAuthority,
+    vaultTokenAccount: f.vaultTokenAccount,
+    mint: f.mint,
+    emergencyRecipientTokenAccount: emergencyRecipient,
+    guardian: signer,
+    instructionNonce: instructionNoncePda(program, f.vault, nonce),
+    tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: SystemProgram.programId,
+  });
+
+  before(async () => {
+    guardian = await createFundedKeypair(provider);
+    const mintAuthority = provider.wallet.payer as Keypair;
+    mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    emergencyRecipient = await createFundedTokenAccount(provider, mint, guardian.publicKey, 0);
+
+    f = await createVaultFixture(program, provider, { mint, guardian: guardian.publicKey, emergencyRecipient });
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+
+    // Lock far into the future so only the emergency path can move funds
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL: the vault owner cannot use the emergency path while locked", async () => {
+    try {
+      await program.methods
+        .emergencyWithdraw(new anchor.BN(10 * LAMPORTS_PER_TOKEN), new anchor.BN(0))
+        .accounts(emergencyAccounts(f.owner.publicKey))
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Only the guardian can emergency withdraw.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Signer is not the vault guardian");
+    }
+  });
+
+  it("Guardian withdraws despite the lock and the vault is left unlocked", async () => {
+    const amount = new anchor.BN(100 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .emergencyWithdraw(amount, new anchor.BN(0))
+      .accounts(emergencyAccounts(guardian.publicKey))
+      .signers([guardian])
+      .rpc();
+
+    expect((await getAccount(provider.connection, emergencyRecipient)).amount.toString()).to.equal(amount.toString());
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.isLocked).to.be.false;
+  });
+
+  it("FAIL to replay the emergency withdrawal with a spent nonce", async () => {
+    try {
+      await program.methods
+        .emergencyWithdraw(new anchor.BN(1), new anchor.BN(0))
+        .accounts(emergencyAccounts(guardian.publicKey))
+        .signers([guardian])
+        .rpc();
+      expect.fail("Nonce 0 was already used.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NonceAlreadyUsed");
+    }
+  });
+
+  it("FAIL: vaults without a guardian reject the emergency path", async () => {
+    const plain = await createVaultFixture(program, provider);
+    try {
+      await program.methods
+        .emergencyWithdraw(new anchor.BN(1), new anchor.BN(0))
+        .accounts({
+          vault: plain.vault,
+          vaultAuthority: plain.vaultAuthority,
+          vaultTokenAccount: plain.vaultTokenAccount,
+          mint: plain.mint,
+          emergencyRecipientTokenAccount: plain.ownerTokenAccount,
+          guardian: plain.owner.publicKey,
+          instructionNonce: instructionNoncePda(program, plain.vault, 0),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          mint: f.mint,
+        })
+        .signers([plain.owner])
+        .rpc();
+      expect.fail("No guardian was configured.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("No guardian is configured for this vault");
+    }
+  });
+});
+
+describe("token_vault: linear vesting", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const total = 120 * LAMPORTS_PER_TOKEN;
+  const duration = 12;
+
+  const claimAccounts = () => ({
+    vault: f.vault,
+    vaultAuthority: f.vaultAuthority,
+    vaultTokenAccount: f.vaultTokenAccount,
+    mint: f.mint,
+    beneficiaryTokenAccount: f.ownerTokenAccount,
+    authority: f.owner.publicKey,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    programConfig: programConfigPda(program),
+  });
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, total);
+  });
+
+  it("FAIL to claim before vesting is configured", async () => {
+    try {
+      await program.methods.claimVested().accounts(claimAccounts()).signers([f.owner]).rpc();
+      expect.fail("Vesting is not configured yet.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Vesting is not configured for this vault");
+    }
+  });
+
+  it("Releases a pro-rata amount partway through the schedule", async () => {
+    const start = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(start), new anchor.BN(start + duration), new anchor.BN(total), new anchor.BN(0), new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.true;
+
+    await delay(5000);
+    await program.methods.claimVested().accounts(claimAccounts()).signers([f.owner]).rpc();
+
+    // The claim must match total * elapsed / duration for some elapsed strictly inside the schedule
+    const claimed = (await program.account.vault.fetch(f.vault)).alreadyClaimed.toNumber();
+    expect(claimed % (total / duration)).to.equal(0);
+    expect(claimed).to.be.greaterThan(0);
+    expect(claimed).to.be.lessThan(total);
+  });
+
+  it("Releases the remainder once fully vested", async () => {
+    await delay((duration + 1) * 1000);
+    await program.methods.claimVested().accounts(claimAccounts()).signers([f.owner]).rpc();
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.alreadyClaimed.toString()).to.equal(total.toString());
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal("0");
+
+    try {
+      await program.methods.claimVested().accounts(claimAccounts()).signers([f.owner]).rpc();
+      expect.fail("Everything has been claimed.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Nothing has vested since the last claim");
+    }
+  });
+
+  it("FAIL to replace a running lock with a shorter vesting schedule", async () => {
+    const g = await createVaultFixture(program, provider);
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .lockVault(new anchor.BN(now + 3600), new anchor.BN(0))
+      .accounts({ vault: g.vault, vaultTokenAccount: g.vaultTokenAccount, authority: g.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([g.owner])
+      .rpc();
+    try {
+      await program.methods
+        .setupVesting(new anchor.BN(now), new anchor.BN(now + 60), new anchor.BN(total), new anchor.BN(0), new anchor.BN(0))
+        .accounts({ vault: g.vault, authority: g.owner.publicKey })
+        .signers([g.owner])
+        .rpc();
+      expect.fail("Vesting cannot end a running lock early.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NewTimestampNotLater");
+    }
+  });
+
+  it("FAIL to claim vested tokens under a lock that outlasts the schedule", async () => {
+    const g = await createVaultFixture(program, provider);
+    await depositToVault(program, g, total);
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(now), new anchor.BN(now + 60), new anchor.BN(total), new anchor.BN(0), new anchor.BN(0))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await program.methods
+      .lockVault(new anchor.BN(now + 3600), new anchor.BN(0))
+      .accounts({ vault: g.vault, vaultTokenAccount: g.vaultTokenAccount, authority: g.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([g.owner])
+      .rpc();
+    await delay(2000);
+
+    try {
+      await program.methods
+        .claimVested()
+        .accounts({
+          vault: g.vault,
+          vaultAuthority: g.vaultAuthority,
+          vaultTokenAccount: g.vaultTokenAccount,
+          mint: g.mint,
+          beneficiaryTokenAccount: g.ownerTokenAccount,
+          authority: g.owner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          programConfig: programConfigPda(program),
+        })
+        .signers([g.owner])
+        .rpc();
+      expect.fail("The longer lock holds the vested tokens too.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+    }
+  });
+});
+
+describe("token_vault: cliff vesting", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const total = 100 * LAMPORTS_PER_TOKEN;
+  const cliffAmount = 40 * LAMPORTS_PER_TOKEN;
+  let start: number;
+  const cliffOffset = 4;
+  const endOffset = 14;
+
+  const claim = () =>
+    program.methods
+      .claimVested()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        beneficiaryTokenAccount: f.ownerTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mint: f.mint,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, total);
+  });
+
+  it("FAIL to configure a cliff amount larger than the total", async () => {
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    try {
+      await program.methods
+        .setupVesting(new anchor.BN(now), new anchor.BN(now + endOffset), new anchor.BN(total), new anchor.BN(now + cliffOffset), new anchor.BN(total + 1))
+        .accounts({ vault: f.vault, authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Cliff amount exceeds the total.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Vesting end must be after start");
+    }
+  });
+
+  it("Releases nothing before the cliff", async () => {
+    start = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(start), new anchor.BN(start + endOffset), new anchor.BN(total), new anchor.BN(start + cliffOffset), new anchor.BN(cliffAmount))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    try {
+      await claim();
+      expect.fail("The cliff has not been reached.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("The vesting cliff has not been reached");
+    }
+  });
+
+  it("Releases the cliff amount plus a linear share after the cliff", async () => {
+    await delay((cliffOffset + 1) * 1000);
+    await claim();
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    const claimed = vaultAccount.alreadyClaimed.toNumber();
+    expect(vaultAccount.cliffClaimed).to.be.true;
+    expect(claimed).to.be.at.least(cliffAmount);
+    expect(claimed).to.be.lessThan(total);
+    // Linear part: (total - cliff) * elapsed / (end - cliff)
+    expect((claimed - cliffAmount) % ((total - cliffAmount) / (endOffset - cliffOffset))).to.equal(0);
+  });
+
+  it("Releases the full amount at the end", async () => {
+    await delay(endOffset * 1000);
+    await claim();
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.alreadyClaimed.toString()).to.equal(total.toString());
+  });
+});
+
+describe("token_vault: beneficiary withdrawals", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let mint: PublicKey;
+  let beneficiary: Keypair;
+  let beneficiaryTokenAccount: PublicKey;
+
+  const beneficiaryWithdraw = (signer: Keypair, destination: PublicKey, amount: number) =>
+    program.methods
+      .beneficiaryWithdraw(new anchor.BN(amount))
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        beneficiaryTokenAccount: destination,
+        beneficiary: signer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mint: f.mint,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([signer])
+      .rpc();
+
+  before(async () => {
+    beneficiary = await createFundedKeypair(provider);
+    const mintAuthority = provider.wallet.payer as Keypair;
+    mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    beneficiaryTokenAccount = await createFundedTokenAccount(provider, mint, beneficiary.publicKey, 0);
+    f = await createVaultFixture(program, provider, { mint });
+  });
+
+  it("Defaults the beneficiary to the authority", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.beneficiary.toString()).to.equal(f.owner.publicKey.toString());
+  });
+
+  it("Authority deposits, sets a beneficiary, and the beneficiary withdraws", async () => {
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .setBeneficiary(beneficiary.publicKey)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    await beneficiaryWithdraw(beneficiary, beneficiaryTokenAccount, 60 * LAMPORTS_PER_TOKEN);
+    expect((await getAccount(provider.connection, beneficiaryTokenAccount)).amount.toString()).to.equal(
+      (60 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL: the authority cannot use the beneficiary path", async () => {
+    try {
+      await beneficiaryWithdraw(f.owner, f.ownerTokenAccount, 1);
+      expect.fail("Only the beneficiary may withdraw here.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Signer is not the vault beneficiary");
+    }
+  });
+
+  it("FAIL: the beneficiary counts against the daily withdrawal limit", async () => {
+    await program.methods
+      .updateLimits(new anchor.BN(60 * LAMPORTS_PER_TOKEN))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await beneficiaryWithdraw(beneficiary, beneficiaryTokenAccount, 1);
+      expect.fail("Today's limit is used up by the earlier withdrawal.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Daily withdrawal limit exceeded");
+    }
+  });
+
+  it("FAIL: the beneficiary is subject to the time lock", async () => {
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await beneficiaryWithdraw(beneficiary, beneficiaryTokenAccount, 1);
+      expect.fail("The vault is locked.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Vault is still locked");
+    }
+  });
+});
+
+describe("token_vault: protocol fees", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let mint: PublicKey;
+  let treasury: PublicKey;
+  const balanceOf = async (account: PublicKey) => (await getAccount(provider.connection, account)).amount.toString();
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    treasury = await createFundedTokenAccount(provider, mint, Keypair.generate().publicKey, 0);
+    // 2% in, 5% out
+    f = await createVaultFixture(program, provider, { mint, depositFeeBps: 200, withdrawFeeBps: 500, feeTreasury: treasury });
+  });
+
+  it("FAIL to initialize with a fee above 10%", async () => {
+    try {
+      await createVaultFixture(program, provider, { depositFeeBps: 1001 });
+      expect.fail("Fee above the cap should be rejected.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Fee exceeds the 10% maximum");
+    }
+  });
+
+  it("Splits a deposit between the vault and the treasury", async () => {
+    const ownerBefore = BigInt(await balanceOf(f.ownerTokenAccount));
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN, treasury);
+
+    expect(await balanceOf(f.vaultTokenAccount)).to.equal((98 * LAMPORTS_PER_TOKEN).toString());
+    expect(await balanceOf(treasury)).to.equal((2 * LAMPORTS_PER_TOKEN).toString());
+    expect((ownerBefore - BigInt(await balanceOf(f.ownerTokenAccount))).toString()).to.equal((100 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("Splits a withdrawal between the user and the treasury", async () => {
+    const ownerBefore = BigInt(await balanceOf(f.ownerTokenAccount));
+    await withdrawFromVault(program, f, 40 * LAMPORTS_PER_TOKEN, treasury);
+
+    expect(await balanceOf(f.vaultTokenAccount)).to.equal((58 * LAMPORTS_PER_TOKEN).toString());
+    expect(await balanceOf(treasury)).to.equal((4 * LAMPORTS_PER_TOKEN).toString());
+    expect((BigInt(await balanceOf(f.ownerTokenAccount)) - ownerBefore).toString()).to.equal((38 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to deposit with a fee but no treasury account", async () => {
+    try {
+      await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+      expect.fail("The treasury account is required.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("A fee treasury token account is required");
+    }
+  });
+
+  it("Authority can update the fee config", async () => {
+    await program.methods
+      .updateFeeConfig(0, 0, treasury)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.depositFeeBps).to.equal(0);
+    expect(vaultAccount.withdrawFeeBps).to.equal(0);
+  });
+});
+
+describe("token_vault: daily withdrawal limit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const limit = 50 * LAMPORTS_PER_TOKEN;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 200 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .updateLimits(new anchor.BN(limit))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Allows withdrawals up to the limit within a day", async () => {
+    await withdrawFromVault(program, f, 30 * LAMPORTS_PER_TOKEN);
+    await withdrawFromVault(program, f, 20 * LAMPORTS_PER_TOKEN);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.currentDayWithdrawn.toString()).to.equal(limit.toString());
+    const today = Math.floor((await provider.connection.getBlockTime(await provider.connection.getSlot())) / 86400);
+    expect(vaultAccount.lastWithdrawalDay.toNumber()).to.equal(today);
+  });
+
+  it("FAIL to withdraw past the daily limit", async () => {
+    try {
+      await withdrawFromVault(program, f, 1);
+      expect.fail("Daily limit should be exhausted.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Daily withdrawal limit exceeded");
+    }
+  });
+
+  it("A limit of 0 disables the check", async () => {
+    await program.methods
+      .updateLimits(new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await withdrawFromVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+});
+
+describe("token_vault: withdrawal cooldown", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const cooldownSecs = 5;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .setWithdrawalCooldown(new anchor.BN(cooldownSecs))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to withdraw twice within the cooldown", async () => {
+    await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    try {
+      await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+      expect.fail("Second withdrawal should hit the cooldown.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Withdrawal cooldown is still active");
+    }
+  });
+
+  it("Withdraws again once the cooldown has passed", async () => {
+    await delay((cooldownSecs + 1) * 1000);
+    await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (80 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+});
+
+describe("token_vault: Token-2022 mints", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { tokenProgram: TOKEN_2022_PROGRAM_ID });
+  });
+
+  it("Stores the token program the vault was created with", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.tokenProgramId.toString()).to.equal(TOKEN_2022_PROGRAM_ID.toString());
+  });
+
+  it("Deposits and withdraws Token-2022 tokens", async () => {
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    await withdrawFromVault(program, f, 30 * LAMPORTS_PER_TOKEN);
+
+    const vaultBalance = await getAccount(provider.connection, f.vaultTokenAccount, undefined, TOKEN_2022_PROGRAM_ID);
+    expect(vaultBalance.amount.toString()).to.equal((70 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL when a different token program is supplied", async () => {
+    try {
+      await program.methods
+        .withdraw(new anchor.BN(1), null)
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          userTokenAccount: f.ownerTokenAccount,
+          vaultTokenAccount: f.vaultTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          mint: f.mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          destinationEntry: null,
+          programConfig: programConfigPda(program),
+          lpMint: null,
+          userLpTokenAccount: null,
+          beneficiarySplit: null,
+          roleConfig: null,
+        })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Token program must match the vault.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Token program does not match");
+    }
+  });
+
+  it("Classic SPL Token vaults keep working", async () => {
+    const classic = await createVaultFixture(program, provider);
+    await depositToVault(program, classic, 10 * LAMPORTS_PER_TOKEN);
+    const vaultAccount = await program.account.vault.fetch(classic.vault);
+    expect(vaultAccount.tokenProgramId.toString()).to.equal(TOKEN_PROGRAM_ID.toString());
+  });
+});
+
+describe("token_vault: events", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+  });
+
+  it("Emits TokensDeposited and TokensWithdrawn", async () => {
+    const depositSig = await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    const [deposited] = (await eventsFromTx(program, provider, depositSig)).filter((e) => e.name === "tokensDeposited");
+    expect(deposited.data.vault.toString()).to.equal(f.vault.toString());
+    expect(deposited.data.depositor.toString()).to.equal(f.owner.publicKey.toString());
+    expect(deposited.data.amount.toString()).to.equal((100 * LAMPORTS_PER_TOKEN).toString());
+
+    const withdrawSig = await withdrawFromVault(program, f, 40 * LAMPORTS_PER_TOKEN);
+    const [withdrawn] = (await eventsFromTx(program, provider, withdrawSig)).filter((e) => e.name === "tokensWithdrawn");
+    expect(withdrawn.data.destination.toString()).to.equal(f.ownerTokenAccount.toString());
+    expect(withdrawn.data.amount.toString()).to.equal((40 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("Emits VaultLocked and VaultUnlocked", async () => {
+    const unlockTime = Math.floor(Date.now() / 1000) + 3;
+    const lockSig = await program.methods
+      .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+    const [locked] = (await eventsFromTx(program, provider, lockSig)).filter((e) => e.name === "vaultLocked");
+    expect(locked.data.unlockTimestamp.toNumber()).to.equal(unlockTime);
+
+    await delay(5000);
+    const unlockSig = await program.methods
+      .authorityUnlockVault()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey, programConfig: programConfigPda(program), unlockVote: null })
+      .signers([f.owner])
+      .rpc();
+    const events = await eventsFromTx(program, provider, unlockSig);
+    expect(events.map((e) => e.name)).to.include("vaultUnlocked");
+  });
+
+  it("Emits BeneficiarySet and AuthorityTransferred", async () => {
+    const beneficiary = Keypair.generate().publicKey;
+    const beneficiarySig = await program.methods
+      .setBeneficiary(beneficiary)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    const [set] = (await eventsFromTx(program, provider, beneficiarySig)).filter((e) => e.name === "beneficiarySet");
+    expect(set.data.beneficiary.toString()).to.equal(beneficiary.toString());
+
+    const newAuthority = Keypair.generate().publicKey;
+    const transferSig = await program.methods
+      .transferVaultAuthority(newAuthority)
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    const [transferred] = (await eventsFromTx(program, provider, transferSig)).filter(
+      (e) => e.name === "authorityTransferred"
+    );
+    expect(transferred.data.oldAuthority.toString()).to.equal(f.owner.publicKey.toString());
+    expect(transferred.data.newAuthority.toString()).to.equal(newAuthority.toString());
+  });
+});
+
+describe("token_vault: vault statistics", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let vaultStats: PublicKey;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [vaultStats] = PublicKey.findProgramAddressSync([Buffer.from("stats"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .initializeVaultStats()
+      .accounts({ vault: f.vault, vaultStats, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Tracks two deposits and one withdrawal", async () => {
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN, null, vaultStats);
+    await depositToVault(program, f, 50 * LAMPORTS_PER_TOKEN, null, vaultStats);
+    await withdrawFromVault(program, f, 30 * LAMPORTS_PER_TOKEN, null, vaultStats);
+
+    const stats = await program.account.vaultStats.fetch(vaultStats);
+    expect(stats.totalDeposits.toNumber()).to.equal(2);
+    expect(stats.totalWithdrawals.toNumber()).to.equal(1);
+    expect(stats.cumulativeDepositVolume.toString()).to.equal((150 * LAMPORTS_PER_TOKEN).toString());
+    expect(stats.cumulativeWithdrawalVolume.toString()).to.equal((30 * LAMPORTS_PER_TOKEN).toString());
+    expect(stats.uniqueDepositorCount).to.equal(1);
+    expect(stats.lastUpdated.toNumber()).to.be.greaterThan(0);
+  });
+
+  it("Logs the stats through get_stats", async () => {
+    const sig = await program.methods.getStats().accounts({ vaultStats }).rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    expect(tx.meta.logMessages.join("\n")).to.include("Total deposits: 2");
+  });
+});
+
+describe("token_vault: depositor records", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let depositorRecord: PublicKey;
+  let vaultStats: PublicKey;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [depositorRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("depositor"), f.vault.toBuffer(), f.owner.publicKey.toBuffer()],
+      program.programId
+    );
+    [vaultStats] = PublicKey.findProgramAddressSync([Buffer.from("stats"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .initializeVaultStats()
+      .accounts({ vault: f.vault, vaultStats, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Creates the record on first deposit and tracks mixed activity", async () => {
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN, null, vaultStats, depositorRecord);
+    await withdrawFromVault(program, f, 25 * LAMPORTS_PER_TOKEN, null, vaultStats, depositorRecord);
+    await depositToVault(program, f, 40 * LAMPORTS_PER_TOKEN, null, vaultStats, depositorRecord);
+    // A deposit without the record is still accepted, it just is not attributed
+    await depositToVault(program, f, 5 * LAMPORTS_PER_TOKEN);
+
+    const record = await program.account.depositorRecord.fetch(depositorRecord);
+    expect(record.depositor.toString()).to.equal(f.owner.publicKey.toString());
+    expect(record.totalDeposited.toString()).to.equal((140 * LAMPORTS_PER_TOKEN).toString());
+    expect(record.totalWithdrawn.toString()).to.equal((25 * LAMPORTS_PER_TOKEN).toString());
+    expect(record.lastDepositTimestamp.toNumber()).to.be.greaterThan(0);
+
+    const stats = await program.account.vaultStats.fetch(vaultStats);
+    expect(stats.uniqueDepositorCount).to.equal(1);
+  });
+
+  it("Logs the record through get_depositor_record", async () => {
+    const sig = await program.methods.getDepositorRecord().accounts({ depositorRecord }).rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    expect(tx.meta.logMessages.join("\n")).to.include(`Total deposited: ${140 * LAMPORTS_PER_TOKEN}`);
+  });
+});
+
+describe("token_vault: max capacity", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const capacity = 100 * LAMPORTS_PER_TOKEN;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { maxCapacity: capacity });
+  });
+
+  it("Stores the capacity set at initialization", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.maxCapacity.toString()).to.equal(capacity.toString());
+  });
+
+  it("FAIL to deposit past the capacity", async () => {
+    await depositToVault(program, f, 60 * LAMPORTS_PER_TOKEN);
+    try {
+      await depositToVault(program, f, 41 * LAMPORTS_PER_TOKEN);
+      expect.fail("Deposit should exceed the capacity.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Deposit would exceed the vault capacity");
+    }
+  });
+
+  it("Allows a deposit that fills the vault exactly to capacity", async () => {
+    await depositToVault(program, f, 40 * LAMPORTS_PER_TOKEN);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal(capacity.toString());
+  });
+
+  it("Raising the capacity with set_max_capacity allows further deposits", async () => {
+    await program.methods
+      .setMaxCapacity(new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+  });
+});
+
+describe("token_vault: minimum deposit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const minimum = 5 * LAMPORTS_PER_TOKEN;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { minDepositAmount: minimum });
+  });
+
+  it("FAIL to deposit below the minimum", async () => {
+    try {
+      await depositToVault(program, f, minimum - 1);
+      expect.fail("Dust deposit should be rejected.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Deposit is below the vault minimum");
+    }
+  });
+
+  it("Allows a deposit of exactly the minimum", async () => {
+    await depositToVault(program, f, minimum);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal(minimum.toString());
+  });
+
+  it("A minimum of 0 disables the check", async () => {
+    await program.methods
+      .updateMinDeposit(new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await depositToVault(program, f, 1);
+  });
+});
+
+describe("token_vault: auto-lock on deposit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const duration = 3600;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await program.methods
+      .setAutoLockDuration(new anchor.BN(duration))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Locks the vault when tokens are deposited", async () => {
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    expect(vaultAccount.isLocked).to.be.true;
+    expect(vaultAccount.unlockTimestamp.toNumber()).to.be.closeTo(now + duration, 5);
+  });
+
+  it("FAIL to withdraw immediately after an auto-locking deposit", async () => {
+    try {
+      await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+      expect.fail("Vault should be auto-locked.");
+    } catch (error) {
+      expect(error.error.errorMessage).to.include("Vault is still locked");
+    }
+  });
+
+  it("Does not shorten a longer existing lock", async () => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    const farFuture = now + 10 * duration;
+    await program.methods
+      .lockVault(new anchor.BN(farFuture), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.unlockTimestamp.toNumber()).to.equal(farFuture);
+  });
+});
+
+describe("token_vault: lock for duration", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+  });
+
+  it("Locks the vault for 3600 seconds from the current clock", async () => {
+    const before = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await program.methods
+      .lockVaultForDuration(new anchor.BN(3600))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.isLocked).to.be.true;
+    expect(vaultAccount.unlockTimestamp.toNumber()).to.be.closeTo(before + 3600, 5);
+  });
+
+  it("FAIL to lock for a zero duration", async () => {
+    try {
+      await program.methods
+        .lockVaultForDuration(new anchor.BN(0))
+        .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Zero duration should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidUnlockTime");
+    }
+  });
+
+  it("FAIL to lock for longer than u32::MAX seconds", async () => {
+    try {
+      await program.methods
+        .lockVaultForDuration(new anchor.BN(2).pow(new anchor.BN(32)))
+        .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Overlong duration should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("LockDurationTooLong");
+    }
+  });
+
+  it("FAIL to shorten the running lock with a shorter duration", async () => {
+    try {
+      await program.methods
+        .lockVaultForDuration(new anchor.BN(60))
+        .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("A running lock cannot be shortened.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NewTimestampNotLater");
+    }
+  });
+});
+
+describe("token_vault: extend lock", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let unlockTime: number;
+
+  const extendLock = (newUnlockTimestamp: number) =>
+    program.methods
+      .extendLock(new anchor.BN(newUnlockTimestamp))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+  });
+
+  it("FAIL to extend an unlocked vault", async () => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    try {
+      await extendLock(now + 3600);
+      expect.fail("Unlocked vault cannot be extended.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultNotLocked");
+    }
+  });
+
+  it("Extends an active lock", async () => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    unlockTime = now + 3600;
+    await program.methods
+      .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+
+    await extendLock(unlockTime + 3600);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.isLocked).to.be.true;
+    expect(vaultAccount.unlockTimestamp.toNumber()).to.equal(unlockTime + 3600);
+  });
+
+  it("FAIL to shorten the lock", async () => {
+    try {
+      await extendLock(unlockTime);
+      expect.fail("Lock cannot be shortened.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NewTimestampNotLater");
+    }
+  });
+});
+
+describe("token_vault: deposit whitelist", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let depositor: Keypair;
+  let depositorTokenAccount: PublicKey;
+  let whitelistEntry: PublicKey;
+
+  const depositAs = (entry: PublicKey | null) =>
+    program.methods
+      .deposit(new anchor.BN(10 * LAMPORTS_PER_TOKEN), null, null, null)
+      .accounts({
+        vault: f.vault,
+        userTokenAccount: depositorTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: depositor.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: entry,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
+      })
+      .signers([depositor])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    depositor = await createFundedKeypair(provider);
+    depositorTokenAccount = await createFundedTokenAccount(provider, f.mint, depositor.publicKey, 100 * LAMPORTS_PER_TOKEN);
+    [whitelistEntry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), f.vault.toBuffer(), depositor.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .enableWhitelist()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to deposit without a whitelist entry", async () => {
+    try {
+      await depositAs(null);
+      expect.fail("Non-whitelisted depositor should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotWhitelisted");
+    }
+  });
+
+  it("Allows a whitelisted depositor to deposit", async () => {
+    await program.methods
+      .addToWhitelist()
+      .accounts({
+        vault: f.vault,
+        candidate: depositor.publicKey,
+        whitelistEntry,
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    const entry = await program.account.whitelistEntry.fetch(whitelistEntry);
+    expect(entry.isActive).to.be.true;
+    expect(entry.addedBy.toString()).to.equal(f.owner.publicKey.toString());
+
+    await depositAs(whitelistEntry);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((10 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to deposit once removed from the whitelist", async () => {
+    await program.methods
+      .removeFromWhitelist()
+      .accounts({ vault: f.vault, candidate: depositor.publicKey, whitelistEntry, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await depositAs(whitelistEntry);
+      expect.fail("Inactive entry should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotWhitelisted");
+    }
+  });
+
+  it("FAIL for non-authority deposits once the whitelist is disabled", async () => {
+    await program.methods
+      .disableWhitelist()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await depositAs(null);
+      expect.fail("Only the authority may deposit without a whitelist.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAccess");
+    }
+  });
+});
+
+describe("token_vault: withdrawal destination whitelist", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let destinationEntry: PublicKey;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    [destinationEntry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("wd"), f.vault.toBuffer(), f.ownerTokenAccount.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .enableDestinationWhitelist()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to withdraw to an unapproved destination", async () => {
+    try {
+      await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+      expect.fail("Destination should not be approved.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DestinationNotApproved");
+    }
+  });
+
+  it("Allows withdrawals to an approved destination", async () => {
+    await program.methods
+      .approveDestination()
+      .accounts({
+        vault: f.vault,
+        destinationTokenAccount: f.ownerTokenAccount,
+        destinationEntry,
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN, null, null, null, destinationEntry);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((90 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to withdraw once the destination is revoked", async () => {
+    await program.methods
+      .revokeDestination()
+      .accounts({
+        vault: f.vault,
+        destinationTokenAccount: f.ownerTokenAccount,
+        destinationEntry,
+        authority: f.owner.publicKey,
+      })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN, null, null, null, destinationEntry);
+      expect.fail("Revoked destination should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DestinationNotApproved");
+    }
+  });
+});
+
+describe("token_vault: withdrawal delegates", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let delegate: Keypair;
+  let delegateTokenAccount: PublicKey;
+  let delegateConfig: PublicKey;
+  const allowance = 30 * LAMPORTS_PER_TOKEN;
+
+  const delegateWithdraw = (amount: number) =>
+    program.methods
+      .delegateWithdraw(new anchor.BN(amount))
+      .accounts({
+        vault: f.vault,
+        delegateConfig,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        delegateTokenAccount,
+        delegate: delegate.publicKey,
+        tokenProgram: f.tokenProgram,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([delegate])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    delegate = await createFundedKeypair(provider);
+    delegateTokenAccount = await createFundedTokenAccount(provider, f.mint, delegate.publicKey, 0);
+    [delegateConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("delegate"), f.vault.toBuffer(), delegate.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await program.methods
+      .grantDelegateAllowance(new anchor.BN(allowance), new anchor.BN(now + 3600))
+      .accounts({
+        vault: f.vault,
+        delegate: delegate.publicKey,
+        delegateConfig,
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Tracks usage against the allowance", async () => {
+    await delegateWithdraw(20 * LAMPORTS_PER_TOKEN);
+
+    const config = await program.account.delegateConfig.fetch(delegateConfig);
+    expect(config.used.toString()).to.equal((20 * LAMPORTS_PER_TOKEN).toString());
+    const delegateBalance = await getAccount(provider.connection, delegateTokenAccount);
+    expect(delegateBalance.amount.toString()).to.equal((20 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to withdraw past the allowance", async () => {
+    try {
+      await delegateWithdraw(11 * LAMPORTS_PER_TOKEN);
+      expect.fail("Allowance should be exceeded.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DelegateAllowanceExceeded");
+    }
+  });
+
+  it("Exhausts the allowance exactly", async () => {
+    await delegateWithdraw(10 * LAMPORTS_PER_TOKEN);
+    const config = await program.account.delegateConfig.fetch(delegateConfig);
+    expect(config.used.toString()).to.equal(allowance.toString());
+
+    try {
+      await delegateWithdraw(1);
+      expect.fail("Allowance should be exhausted.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DelegateAllowanceExceeded");
+    }
+  });
+
+  it("Revoking closes the delegate config", async () => {
+    await program.methods
+      .revokeDelegate()
+      .accounts({ vault: f.vault, delegate: delegate.publicKey, delegateConfig, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(delegateConfig)).to.be.null;
+  });
+});
+
+describe("token_vault: hashlock withdrawals", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const preimage = Buffer.alloc(32, 7);
+  const hashlock = createHash("sha256").update(preimage).digest();
+
+  const hashlockWithdraw = (secret: Buffer, nonce = 0) =>
+    program.methods
+      .hashlockWithdraw(new anchor.BN(10 * LAMPORTS_PER_TOKEN), Array.from(secret), new anchor.BN(nonce))
+      .accounts({
+        withdraw: {
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          userTokenAccount: f.ownerTokenAccount,
+          vaultTokenAccount: f.vaultTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: f.tokenProgram,
+          mint: f.mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          destinationEntry: null,
+          programConfig: programConfigPda(program),
+          lpMint: null,
+          userLpTokenAccount: null,
+          beneficiarySplit: null,
+          roleConfig: null,
+        },
+        instructionNonce: instructionNoncePda(program, f.vault, nonce),
+        payer: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL without a configured hashlock", async () => {
+    try {
+      await hashlockWithdraw(preimage);
+      expect.fail("No hashlock should be configured.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoHashlockConfigured");
+    }
+  });
+
+  it("FAIL with the wrong preimage", async () => {
+    await program.methods
+      .setHashlock(Array.from(hashlock))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await hashlockWithdraw(Buffer.alloc(32, 8));
+      expect.fail("Wrong preimage should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidPreimage");
+    }
+  });
+
+  it("Withdraws with the correct preimage and clears the hashlock", async () => {
+    await hashlockWithdraw(preimage);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.hashlock).to.be.null;
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((90 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to replay the withdrawal, even after the same hashlock is set again", async () => {
+    await program.methods
+      .setHashlock(Array.from(hashlock))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await hashlockWithdraw(preimage);
+      expect.fail("Nonce 0 was already used.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NonceAlreadyUsed");
+    }
+
+    // A fresh nonce goes through
+    await hashlockWithdraw(preimage, 1);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((80 * LAMPORTS_PER_TOKEN).toString());
+  });
+});
+
+describe("token_vault: program pause", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let sol: VaultFixture;
+  const programConfig = programConfigPda(program);
+
+  const setPaused = (paused: boolean) =>
+    (paused ? program.methods.pauseProgram() : program.methods.unpauseProgram())
+      .accounts({ programConfig, admin: provider.wallet.publicKey })
+      .rpc();
+
+  const expectPaused = async (action: () => Promise<string>) => {
+    try {
+      await action();
+      expect.fail("Instruction should fail while paused.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ProgramPaused");
+    }
+  };
+
+  const lock = () =>
+    program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 2), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  const unlock = () =>
+    program.methods
+      .authorityUnlockVault()
+      .accounts({ vault: f.vault, programConfig, unlockVote: null, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  const depositSol = () =>
+    program.methods
+      .depositSol(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10))
+      .accounts({
+        vault: sol.vault,
+        programConfig,
+        vaultTokenAccount: sol.vaultTokenAccount,
+        nativeMint: NATIVE_MINT,
+        authority: sol.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([sol.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    sol = await createVaultFixture(program, provider, { mint: NATIVE_MINT, mintAmount: 0, isSolVault: true });
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  // Never leave the singleton paused for the suites that follow
+  after(async () => {
+    await setPaused(false);
+  });
+
+  it("FAIL all state-changing instructions while paused", async () => {
+    await setPaused(true);
+    expect((await program.account.programConfig.fetch(programConfig)).isPaused).to.be.true;
+
+    await expectPaused(() => depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN));
+    await expectPaused(() => withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN));
+    await expectPaused(lock);
+    await expectPaused(unlock);
+    await expectPaused(depositSol);
+  });
+
+  it("FAIL to pause as a non-admin", async () => {
+    try {
+      await program.methods
+        .pauseProgram()
+        .accounts({ programConfig, admin: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Only the admin can pause.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+  });
+
+  it("Succeeds again after unpause", async () => {
+    await setPaused(false);
+
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    await depositSol();
+    await lock();
+    await delay(3000);
+    await unlock();
+  });
+});
+
+describe("token_vault: multi-mint token slots", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const slots: { mint: PublicKey; slot: PublicKey; slotTokenAccount: PublicKey; userTokenAccount: PublicKey }[] = [];
+
+  const addSlot = (mint: PublicKey, slot: PublicKey, slotTokenAccount: PublicKey) =>
+    program.methods
+      .addTokenSlot()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        slot,
+        slotTokenAccount,
+        mint,
+        authority: f.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  const slotAccounts = (s: (typeof slots)[number]) => ({
+    vault: f.vault,
+    slot: s.slot,
+    userTokenAccount: s.userTokenAccount,
+    slotTokenAccount: s.slotTokenAccount,
+    mint: s.mint,
+    authority: f.owner.publicKey,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    programConfig: programConfigPda(program),
+  });
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    const mintAuthority = provider.wallet.payer as Keypair;
+    for (let i = 0; i < 2; i++) {
+      const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, null, DECIMAL_PLACES);
+      const [slot] = PublicKey.findProgramAddressSync(
+        [Buffer.from("slot"), f.vault.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+      const slotTokenAccount = await getAssociatedTokenAddress(mint, f.vaultAuthority, true);
+      const userTokenAccount = await createFundedTokenAccount(provider, mint, f.owner.publicKey, 100 * LAMPORTS_PER_TOKEN);
+      slots.push({ mint, slot, slotTokenAccount, userTokenAccount });
+    }
+  });
+
+  it("Adds a slot per mint", async () => {
+    for (const s of slots) {
+      await addSlot(s.mint, s.slot, s.slotTokenAccount);
+      const slotAccount = await program.account.vaultTokenSlot.fetch(s.slot);
+      expect(slotAccount.mint.toString()).to.equal(s.mint.toString());
+      expect(slotAccount.tokenAccount.toString()).to.equal(s.slotTokenAccount.toString());
+    }
+  });
+
+  it("FAIL to add the same slot twice", async () => {
+    try {
+      await addSlot(slots[0].mint, slots[0].slot, slots[0].slotTokenAccount);
+      expect.fail("Duplicate slot should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SlotAlreadyExists");
+    }
+  });
+
+  it("Keeps independent balances per slot", async () => {
+    await program.methods.depositToSlot(new anchor.BN(10 * LAMPORTS_PER_TOKEN)).accounts(slotAccounts(slots[0])).signers([f.owner]).rpc();
+    await program.methods.depositToSlot(new anchor.BN(20 * LAMPORTS_PER_TOKEN)).accounts(slotAccounts(slots[1])).signers([f.owner]).rpc();
+    await program.methods
+      .withdrawFromSlot(new anchor.BN(5 * LAMPORTS_PER_TOKEN))
+      .accounts({
+        ...slotAccounts(slots[0]),
+        vaultAuthority: f.vaultAuthority,
+        destinationEntry: null,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    const first = await getAccount(provider.connection, slots[0].slotTokenAccount);
+    const second = await getAccount(provider.connection, slots[1].slotTokenAccount);
+    expect(first.amount.toString()).to.equal((5 * LAMPORTS_PER_TOKEN).toString());
+    expect(second.amount.toString()).to.equal((20 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to use a slot with the wrong mint", async () => {
+    try {
+      await program.methods
+        .depositToSlot(new anchor.BN(LAMPORTS_PER_TOKEN))
+        .accounts({ ...slotAccounts(slots[1]), slot: slots[0].slot })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Mismatched slot should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SlotNotFound");
+    }
+  });
+});
+
+describe("token_vault: native SOL vaults", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const depositLamports = anchor.web3.LAMPORTS_PER_SOL;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { mint: NATIVE_MINT, mintAmount: 0, isSolVault: true });
+  });
+
+  it("Wraps deposited SOL into the vault's wSOL account", async () => {
+    await program.methods
+      .depositSol(new anchor.BN(depositLamports))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        nativeMint: NATIVE_MINT,
+        authority: f.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal(depositLamports.toString());
+  });
+
+  it("FAIL to use the SPL deposit path on a SOL vault", async () => {
+    try {
+      await depositToVault(program, f, 1);
+      expect.fail("SOL vault should reject SPL deposits.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotSplVault");
+    }
+  });
+
+  it("Unwraps withdrawn wSOL back to native SOL", async () => {
+    const before = await provider.connection.getBalance(f.owner.publicKey);
+    await program.methods
+      .withdrawSol(new anchor.BN(depositLamports / 2))
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        userWsolAccount: f.ownerTokenAccount,
+        nativeMint: NATIVE_MINT,
+        authority: f.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    // The temporary wSOL account is closed, its rent and the withdrawn SOL go to the owner
+    expect(await provider.connection.getAccountInfo(f.ownerTokenAccount)).to.be.null;
+    const after = await provider.connection.getBalance(f.owner.publicKey);
+    expect(after - before).to.be.greaterThan(depositLamports / 2 - 10_000);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((depositLamports / 2).toString());
+  });
+
+  it("FAIL to deposit SOL into an SPL vault", async () => {
+    const spl = await createVaultFixture(program, provider);
+    try {
+      await program.methods
+        .depositSol(new anchor.BN(depositLamports))
+        .accounts({
+          vault: spl.vault,
+          vaultTokenAccount: spl.vaultTokenAccount,
+          nativeMint: NATIVE_MINT,
+          authority: spl.owner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          programConfig: programConfigPda(program),
+        })
+        .signers([spl.owner])
+        .rpc();
+      expect.fail("SPL vault should reject SOL deposits.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotSolVault");
+    }
+  });
+});
+
+describe("token_vault: vault-to-vault transfers", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let source: VaultFixture;
+  let destination: VaultFixture;
+  let otherMint: VaultFixture;
+
+  // Vault PDAs are seeded by their creator, so the second vault is handed over to the first owner
+  const handOver = async (f: VaultFixture, newAuthority: PublicKey) =>
+    program.methods
+      .transferVaultAuthority(newAuthority)
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  const moveTokens = (to: VaultFixture, amount: number) =>
+    program.methods
+      .vaultToVaultTransfer(new anchor.BN(amount))
+      .accounts({
+        sourceVault: source.vault,
+        sourceVaultAuthority: source.vaultAuthority,
+        sourceVaultTokenAccount: source.vaultTokenAccount,
+        destinationVault: to.vault,
+        destinationVaultTokenAccount: to.vaultTokenAccount,
+        mint: source.mint,
+        authority: source.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([source.owner])
+      .rpc();
+
+  before(async () => {
+    source = await createVaultFixture(program, provider);
+    destination = await createVaultFixture(program, provider, { mint: source.mint });
+    otherMint = await createVaultFixture(program, provider);
+    await handOver(destination, source.owner.publicKey);
+    await handOver(otherMint, source.owner.publicKey);
+    await depositToVault(program, source, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Moves tokens from the source vault to the destination vault", async () => {
+    await moveTokens(destination, 40 * LAMPORTS_PER_TOKEN);
+
+    const sourceBalance = await getAccount(provider.connection, source.vaultTokenAccount);
+    const destinationBalance = await getAccount(provider.connection, destination.vaultTokenAccount);
+    expect(sourceBalance.amount.toString()).to.equal((60 * LAMPORTS_PER_TOKEN).toString());
+    expect(destinationBalance.amount.toString()).to.equal((40 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to move tokens into a vault with a different mint", async () => {
+    try {
+      await moveTokens(otherMint, LAMPORTS_PER_TOKEN);
+      expect.fail("Mint mismatch should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultMintMismatch");
+    }
+  });
+
+  it("Books the transfer as a deposit into the destination", async () => {
+    const before = await program.account.vault.fetch(destination.vault);
+    await moveTokens(destination, 10 * LAMPORTS_PER_TOKEN);
+
+    const after = await program.account.vault.fetch(destination.vault);
+    expect(after.allTimeDepositCount.toNumber()).to.equal(before.allTimeDepositCount.toNumber() + 1);
+    expect(after.depositAmountOriginal.sub(before.depositAmountOriginal).toString()).to.equal(
+      (10 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL to move tokens into a vault in shutdown", async () => {
+    const drained = await createVaultFixture(program, provider, { mint: source.mint });
+    await handOver(drained, source.owner.publicKey);
+    await program.methods
+      .enableShutdownMode()
+      .accounts({ vault: drained.vault, authority: source.owner.publicKey })
+      .signers([source.owner])
+      .rpc();
+
+    try {
+      await moveTokens(drained, LAMPORTS_PER_TOKEN);
+      expect.fail("A vault in shutdown takes no new funds.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultInShutdown");
+    }
+  });
+});
+
+describe("token_vault: split vault", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let newOwner: Keypair;
+
+  const splitAccounts = (splitIndex: number) => {
+    const [splitSeed] = PublicKey.findProgramAddressSync(
+      [Buffer.from("split"), f.vault.toBuffer(), Buffer.from([splitIndex])],
+      program.programId
+    );
+    const [newVault, newVaultBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), splitSeed.toBuffer()],
+      program.programId
+    );
+    const [newVaultAuthority, newAuthorityBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), newVault.toBuffer()],
+      program.programId
+    );
+    return { splitSeed, newVault, newVaultBump, newVaultAuthority, newAuthorityBump };
+  };
+
+  const split = async (amount: number, splitIndex: number, newTokenAccount: Keypair) => {
+    const a = splitAccounts(splitIndex);
+    await program.methods
+      .splitVault(new anchor.BN(amount), a.newAuthorityBump, splitIndex, newOwner.publicKey)
+      .accounts({
+        sourceVault: f.vault,
+        sourceVaultAuthority: f.vaultAuthority,
+        sourceVaultTokenAccount: f.vaultTokenAccount,
+        splitSeed: a.splitSeed,
+        newVault: a.newVault,
+        newVaultAuthority: a.newVaultAuthority,
+        newTokenAccount: newTokenAccount.publicKey,
+        mint: f.mint,
+        authority: f.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([f.owner, newTokenAccount])
+      .rpc();
+    return a;
+  };
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    newOwner = await createFundedKeypair(provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to split more than the vault holds", async () => {
+    try {
+      await split(101 * LAMPORTS_PER_TOKEN, 0, Keypair.generate());
+      expect.fail("Split should exceed the balance.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SplitAmountExceedsBalance");
+    }
+  });
+
+  it("Creates an independent vault holding the split amount", async () => {
+    const newTokenAccount = Keypair.generate();
+    const a = await split(30 * LAMPORTS_PER_TOKEN, 0, newTokenAccount);
+
+    const newVaultAccount = await program.account.vault.fetch(a.newVault);
+    expect(newVaultAccount.authority.toString()).to.equal(newOwner.publicKey.toString());
+    expect(newVaultAccount.originalAuthority.toString()).to.equal(a.splitSeed.toString());
+
+    const sourceBalance = await getAccount(provider.connection, f.vaultTokenAccount);
+    const newBalance = await getAccount(provider.connection, newTokenAccount.publicKey);
+    expect(sourceBalance.amount.toString()).to.equal((70 * LAMPORTS_PER_TOKEN).toString());
+    expect(newBalance.amount.toString()).to.equal((30 * LAMPORTS_PER_TOKEN).toString());
+
+    // The new owner drives the split vault through the normal instructions
+    const split: VaultFixture = {
+      owner: newOwner,
+      mint: f.mint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      ownerTokenAccount: await createFundedTokenAccount(provider, f.mint, newOwner.publicKey, 0),
+      vault: a.newVault,
+      vaultAuthority: a.newVaultAuthority,
+      vaultTokenAccount: newTokenAccount.publicKey,
+      vaultBump: a.newVaultBump,
+      authorityBump: a.newAuthorityBump,
+    };
+    await withdrawFromVault(program, split, 10 * LAMPORTS_PER_TOKEN);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (70 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+});
+
+describe("token_vault: LP share mint", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let lpMint: PublicKey;
+  let userLpTokenAccount: PublicKey;
+
+  const lpAccounts = () => ({
+    vault: f.vault,
+    vaultAuthority: f.vaultAuthority,
+    userTokenAccount: f.ownerTokenAccount,
+    vaultTokenAccount: f.vaultTokenAccount,
+    authority: f.owner.publicKey,
+    tokenProgram: f.tokenProgram,
+    mint: f.mint,
+    feeTreasuryTokenAccount: null,
+    vaultStats: null,
+    depositorRecord: null,
+    programConfig: programConfigPda(program),
+    lpMint,
+    userLpTokenAccount,
+  });
+
+  const deposit = (amount: number) =>
+    program.methods
+      .deposit(new anchor.BN(amount), null, null, null)
+      .accounts({
+        ...lpAccounts(),
+        whitelistEntry: null,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  const withdraw = (amount: number) =>
+    program.methods
+      .withdraw(new anchor.BN(amount), null)
+      .accounts({ ...lpAccounts(), destinationEntry: null, beneficiarySplit: null, roleConfig: null })
+      .signers([f.owner])
+      .rpc();
+
+  const lpBalance = async () => (await getAccount(provider.connection, userLpTokenAccount)).amount.toString();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [lpMint] = PublicKey.findProgramAddressSync([Buffer.from("lp_mint"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .initializeLpMint()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        lpMint,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+    userLpTokenAccount = await createFundedTokenAccount(provider, lpMint, f.owner.publicKey, 0);
+  });
+
+  it("Mints shares 1:1 on the first deposit", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.lpMint.toString()).to.equal(lpMint.toString());
+
+    await deposit(100 * LAMPORTS_PER_TOKEN);
+    expect(await lpBalance()).to.equal((100 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("Prices shares higher after a direct donation to the vault", async () => {
+    // Doubling the underlying without minting doubles the share price
+    await transfer(
+      provider.connection,
+      f.owner,
+      f.ownerTokenAccount,
+      f.vaultTokenAccount,
+      f.owner,
+      100 * LAMPORTS_PER_TOKEN
+    );
+    await deposit(100 * LAMPORTS_PER_TOKEN);
+    expect(await lpBalance()).to.equal((150 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("Burns the proportional shares on withdraw", async () => {
+    await withdraw(100 * LAMPORTS_PER_TOKEN);
+    expect(await lpBalance()).to.equal((100 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to withdraw more than the LP shares cover", async () => {
+    // Shares are transferable, so move most of them to another holder
+    const holder = await createFundedTokenAccount(provider, lpMint, provider.wallet.publicKey, 0);
+    await transfer(provider.connection, f.owner, userLpTokenAccount, holder, f.owner, 60 * LAMPORTS_PER_TOKEN);
+    try {
+      await withdraw(100 * LAMPORTS_PER_TOKEN);
+      expect.fail("LP shares should not cover the withdrawal.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InsufficientLpTokens");
+    }
+  });
+
+  it("FAIL to deposit without the LP accounts", async () => {
+    try {
+      await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("LP accounts should be required.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("LpMintNotConfigured");
+    }
+  });
+});
+
+describe("token_vault: flash loans", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let borrower: Keypair;
+  let borrowerTokenAccount: PublicKey;
+  const loan = 50 * LAMPORTS_PER_TOKEN;
+  const feeBps = 30; // 0.3%
+
+  const flashLoanIx = () =>
+    program.methods
+      .flashLoan(new anchor.BN(loan))
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        borrowerTokenAccount,
+        borrower: borrower.publicKey,
+        tokenProgram: f.tokenProgram,
+        instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .instruction();
+
+  const repayIx = () =>
+    program.methods
+      .flashLoanRepay()
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        borrowerTokenAccount,
+        borrower: borrower.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .instruction();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .setFlashLoanFee(feeBps)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    borrower = await createFundedKeypair(provider);
+    // Enough to pay the fee, the principal comes from the vault
+    borrowerTokenAccount = await createFundedTokenAccount(provider, f.mint, borrower.publicKey, LAMPORTS_PER_TOKEN);
+  });
+
+  it("Borrows and repays within one transaction", async () => {
+    const tx = new Transaction().add(await flashLoanIx(), await repayIx());
+    await provider.sendAndConfirm(tx, [borrower]);
+
+    const fee = (loan * feeBps) / 10_000;
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((100 * LAMPORTS_PER_TOKEN + fee).toString());
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.flashLoanActive).to.be.false;
+    expect(vaultAccount.flashLoanAmount.toNumber()).to.equal(0);
+  });
+
+  it("FAIL to borrow without a repay in the same transaction", async () => {
+    try {
+      await provider.sendAndConfirm(new Transaction().add(await flashLoanIx()), [borrower]);
+      expect.fail("Unrepaid flash loan should be rejected.");
+    } catch (error) {
+      expect(error.logs.join("\n")).to.include("FlashLoanRepaymentMissing");
+    }
+  });
+
+  it("FAIL to withdraw while a flash loan is outstanding", async () => {
+    const withdrawIx = await program.methods
+      .withdraw(new anchor.BN(LAMPORTS_PER_TOKEN), null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
+      })
+      .instruction();
+    try {
+      await provider.sendAndConfirm(new Transaction().add(await flashLoanIx(), withdrawIx, await repayIx()), [
+        borrower,
+        f.owner,
+      ]);
+      expect.fail("The vault balance is short the borrowed tokens.");
+    } catch (error) {
+      expect(error.logs.join("\n")).to.include("FlashLoanActive");
+    }
+  });
+
+  it("FAIL to repay when no loan is active", async () => {
+    try {
+      await provider.sendAndConfirm(new Transaction().add(await repayIx()), [borrower]);
+      expect.fail("Repay without a loan should be rejected.");
+    } catch (error) {
+      expect(error.logs.join("\n")).to.include("FlashLoanNotActive");
+    }
+  });
+});
+
+describe("token_vault: payment streams", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let recipient: Keypair;
+  let recipientTokenAccount: PublicKey;
+  let stream: PublicKey;
+  const rate = LAMPORTS_PER_TOKEN; // 1 token per second
+
+  const streamFor = (who: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("stream"), f.vault.toBuffer(), who.toBuffer()], program.programId)[0];
+
+  const createStream = (who: PublicKey, start: number, end: number) =>
+    program.methods
+      .createStream(new anchor.BN(rate), new anchor.BN(start), new anchor.BN(end))
+      .accounts({
+        vault: f.vault,
+        recipient: who,
+        stream: streamFor(who),
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  const claim = (who: Keypair, tokenAccount: PublicKey) =>
+    program.methods
+      .claimStream()
+      .accounts({
+        vault: f.vault,
+        stream: streamFor(who.publicKey),
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        recipientTokenAccount: tokenAccount,
+        recipient: who.publicKey,
+        tokenProgram: f.tokenProgram,
+        programConfig: programConfigPda(program),
+      })
+      .signers([who])
+      .rpc();
+
+  const claimed = async () => (await program.account.streamConfig.fetch(stream)).totalClaimed.toNumber();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 500 * LAMPORTS_PER_TOKEN);
+    recipient = await createFundedKeypair(provider);
+    recipientTokenAccount = await createFundedTokenAccount(provider, f.mint, recipient.publicKey, 0);
+    stream = streamFor(recipient.publicKey);
+
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await createStream(recipient.publicKey, now, now + 300);
+  });
+
+  it("Accrues claimable tokens incrementally", async () => {
+    await delay(2000);
+    await claim(recipient, recipientTokenAccount);
+    const first = await claimed();
+    expect(first).to.be.greaterThan(0);
+
+    await delay(2000);
+    await claim(recipient, recipientTokenAccount);
+    const second = await claimed();
+    expect(second).to.be.greaterThan(first);
+
+    const balance = await getAccount(provider.connection, recipientTokenAccount);
+    expect(balance.amount.toString()).to.equal(second.toString());
+  });
+
+  it("FAIL to claim a stream that has not started", async () => {
+    const later = await createFundedKeypair(provider);
+    const laterTokenAccount = await createFundedTokenAccount(provider, f.mint, later.publicKey, 0);
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await createStream(later.publicKey, now + 3600, now + 7200);
+    try {
+      await claim(later, laterTokenAccount);
+      expect.fail("Stream should not have started.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("StreamNotStarted");
+    }
+  });
+
+  it("Cancelling stops accrual", async () => {
+    await program.methods
+      .cancelStream()
+      .accounts({ vault: f.vault, stream, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    // Whatever accrued up to the cancel may still be claimed once, then the stream is done
+    await claim(recipient, recipientTokenAccount).catch(() => {});
+    try {
+      await claim(recipient, recipientTokenAccount);
+      expect.fail("Cancelled stream should be exhausted.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("StreamEnded");
+    }
+  });
+
+  it("FAIL to claim a stream while the vault is locked", async () => {
+    const other = await createFundedKeypair(provider);
+    const otherTokenAccount = await createFundedTokenAccount(provider, f.mint, other.publicKey, 0);
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await createStream(other.publicKey, now, now + 300);
+    await program.methods
+      .lockVault(new anchor.BN(now + 3600), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+
+    await delay(2000);
+    try {
+      await claim(other, otherTokenAccount);
+      expect.fail("The time lock holds the streamed tokens too.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+    }
+  });
+});
+
+describe("token_vault: scheduled withdrawals", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let cranker: Keypair;
+
+  const scheduledFor = (nonce: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("scheduled"), f.vault.toBuffer(), new anchor.BN(nonce).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const schedule = (nonce: number, amount: number, executeAfter: number) =>
+    program.methods
+      .createScheduledWithdrawal(new anchor.BN(nonce), new anchor.BN(amount), new anchor.BN(executeAfter), f.ownerTokenAccount)
+      .accounts({
+        vault: f.vault,
+        scheduledWithdrawal: scheduledFor(nonce),
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  const execute = (nonce: number) =>
+    program.methods
+      .executeScheduledWithdrawal(new anchor.BN(nonce))
+      .accounts({
+        vault: f.vault,
+        scheduledWithdrawal: scheduledFor(nonce),
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        recipientTokenAccount: f.ownerTokenAccount,
+        rentRecipient: f.owner.publicKey,
+        cranker: cranker.publicKey,
+        tokenProgram: f.tokenProgram,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([cranker])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    cranker = await createFundedKeypair(provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to execute early, then a crank executes it once due", async () => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await schedule(0, 25 * LAMPORTS_PER_TOKEN, now + 3);
+
+    try {
+      await execute(0);
+      expect.fail("Withdrawal should not be due yet.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WithdrawalNotReady");
+    }
+
+    await delay(5000);
+    await execute(0);
+
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((75 * LAMPORTS_PER_TOKEN).toString());
+    expect(await provider.connection.getAccountInfo(scheduledFor(0))).to.be.null;
+  });
+
+  it("FAIL to execute a cancelled withdrawal", async () => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await schedule(1, 25 * LAMPORTS_PER_TOKEN, now);
+    await program.methods
+      .cancelScheduledWithdrawal(new anchor.BN(1))
+      .accounts({ vault: f.vault, scheduledWithdrawal: scheduledFor(1), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    try {
+      await execute(1);
+      expect.fail("Cancelled withdrawal should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WithdrawalCancelled");
+    }
+  });
+});
+
+describe("token_vault: social recovery", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let recoveryConfig: PublicKey;
+  const guardians = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+  const outsider = Keypair.generate();
+  const newAuthority = Keypair.generate();
+
+  const guardianCall = (method: "approveRecovery", guardian: Keypair) =>
+    program.methods[method]()
+      .accounts({ recoveryConfig, guardian: guardian.publicKey })
+      .signers([guardian])
+      .rpc();
+
+  const executeRecovery = () =>
+    program.methods
+      .executeRecovery()
+      .accounts({ vault: f.vault, recoveryConfig, caller: provider.wallet.publicKey })
+      .rpc();
+
+  const expectCode = async (action: Promise<string>, code: string) => {
+    try {
+      await action;
+      expect.fail(`Expected ${code}.`);
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal(code);
+    }
+  };
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [recoveryConfig] = PublicKey.findProgramAddressSync([Buffer.from("recovery"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .setupRecoveryConfig(guardians.map((g) => g.publicKey), 2)
+      .accounts({ vault: f.vault, recoveryConfig, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to initiate a recovery as a non-guardian", async () => {
+    await expectCode(
+      program.methods
+        .initiateRecovery(newAuthority.publicKey)
+        .accounts({ recoveryConfig, guardian: outsider.publicKey })
+        .signers([outsider])
+        .rpc(),
+      "NotRecoveryGuardian"
+    );
+  });
+
+  it("Initiates a recovery and rejects execution below threshold", async () => {
+    await program.methods
+      .initiateRecovery(newAuthority.publicKey)
+      .accounts({ recoveryConfig, guardian: guardians[0].publicKey })
+      .signers([guardians[0]])
+      .rpc();
+
+    const config = await program.account.recoveryConfig.fetch(recoveryConfig);
+    expect(config.newAuthorityProposed.toString()).to.equal(newAuthority.publicKey.toString());
+    expect(config.approvals.length).to.equal(1);
+
+    await expectCode(executeRecovery(), "RecoveryThresholdNotMet");
+    await expectCode(guardianCall("approveRecovery", guardians[0]), "AlreadyApproved");
+  });
+
+  it("FAIL to execute before the 24h delay even with enough approvals", async () => {
+    await guardianCall("approveRecovery", guardians[1]);
+    await expectCode(executeRecovery(), "RecoveryTimelockActive");
+
+    // The 24h delay cannot be fast-forwarded on a local validator, so the authority is unchanged
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.authority.toString()).to.equal(f.owner.publicKey.toString());
+  });
+
+  it("The owner can cancel a pending recovery", async () => {
+    await program.methods
+      .cancelRecovery()
+      .accounts({ vault: f.vault, recoveryConfig, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    const config = await program.account.recoveryConfig.fetch(recoveryConfig);
+    expect(config.recoveryInitiatedAt.toNumber()).to.equal(0);
+    expect(config.approvals.length).to.equal(0);
+    await expectCode(executeRecovery(), "RecoveryNotInitiated");
+  });
+});
+
+describe("token_vault: dead-man's switch", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const inactivitySecs = 3;
+  let f: VaultFixture;
+  let heir: Keypair;
+  let heirTokenAccount: PublicKey;
+
+  const deadManClaim = (signer: Keypair, destination: PublicKey) =>
+    program.methods
+      .deadManClaim()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        beneficiaryTokenAccount: destination,
+        claimant: signer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        programConfig: programConfigPda(program),
+      })
+      .signers([signer])
+      .rpc();
+
+  const configure = (period: number) =>
+    program.methods
+      .configureDeadManSwitch(new anchor.BN(period), heir.publicKey)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    heir = await createFundedKeypair(provider);
+    heirTokenAccount = await createFundedTokenAccount(provider, f.mint, heir.publicKey, 0);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to claim when no switch is configured", async () => {
+    try {
+      await deadManClaim(heir, heirTokenAccount);
+      expect.fail("No switch is armed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoBeneficiaryConfigured");
+    }
+  });
+
+  it("FAIL to claim while the owner is still active", async () => {
+    await configure(inactivitySecs);
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN); // Activity resets the timer
+    try {
+      await deadManClaim(heir, heirTokenAccount);
+      expect.fail("The owner is still active.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InactivityPeriodNotElapsed");
+    }
+  });
+
+  it("FAIL: only the designated beneficiary can claim", async () => {
+    await delay((inactivitySecs + 1) * 1000);
+    try {
+      await deadManClaim(f.owner, f.ownerTokenAccount);
+      expect.fail("The owner is not the beneficiary.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotBeneficiary");
+    }
+  });
+
+  it("The beneficiary claims every token after the inactivity period", async () => {
+    await deadManClaim(heir, heirTokenAccount);
+
+    expect((await getAccount(provider.connection, heirTokenAccount)).amount.toString()).to.equal(
+      (110 * LAMPORTS_PER_TOKEN).toString()
+    );
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal("0");
+  });
+});
+
+describe("token_vault: beneficiary split", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let beneficiarySplit: PublicKey;
+  const recipients = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+  const bps = [5_000, 3_000, 2_000];
+  let recipientTokenAccounts: PublicKey[];
+
+  const configureSplit = (shares: number[], owners: Keypair[], tokenAccounts: PublicKey[]) =>
+    program.methods
+      .configureSplit(
+        owners.map((owner, i) => ({ recipient: owner.publicKey, bps: shares[i] })),
+        tokenAccounts
+      )
+      .accounts({ vault: f.vault, beneficiarySplit, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+
+  const withdraw = (amount: number, split: PublicKey | null) =>
+    program.methods
+      .withdraw(new anchor.BN(amount), null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: split,
+        roleConfig: null,
+      })
+      .remainingAccounts(recipientTokenAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [beneficiarySplit] = PublicKey.findProgramAddressSync([Buffer.from("split"), f.vault.toBuffer()], program.programId);
+    recipientTokenAccounts = await Promise.all(
+      recipients.map((recipient) => createFundedTokenAccount(provider, f.mint, recipient.publicKey, 0))
+    );
+    await depositToVault(program, f, 200 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to configure shares that do not sum to 10000 bps", async () => {
+    try {
+      await configureSplit([5_000, 3_000, 1_000], recipients, recipientTokenAccounts);
+      expect.fail("Shares must sum to 100%.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SplitBpsDoNotSum");
+    }
+  });
+
+  it("FAIL to configure more than 10 recipients", async () => {
+    const many = Array.from({ length: 11 }, () => Keypair.generate());
+    try {
+      await configureSplit(many.map(() => 909), many, many.map((k) => k.publicKey));
+      expect.fail("At most 10 recipients.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("TooManyRecipients");
+    }
+  });
+
+  it("Splits a withdrawal across three recipients by bps", async () => {
+    await configureSplit(bps, recipients, recipientTokenAccounts);
+    await withdraw(100 * LAMPORTS_PER_TOKEN, beneficiarySplit);
+
+    for (let i = 0; i < recipients.length; i++) {
+      const balance = (await getAccount(provider.connection, recipientTokenAccounts[i])).amount.toString();
+      expect(balance).to.equal(((100 * LAMPORTS_PER_TOKEN * bps[i]) / 10_000).toString());
+    }
+  });
+
+  it("FAIL to bypass the split by omitting the split account", async () => {
+    try {
+      await withdraw(10 * LAMPORTS_PER_TOKEN, null);
+      expect.fail("The split account is required.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BeneficiarySplitRequired");
+    }
+  });
+});
+
+describe("token_vault: oracle-gated unlock", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  // Loaded into the local validator from tests/fixtures, see Anchor.toml
+  const PYTH_PROGRAM_ID = new PublicKey("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+  const highPriceFeed = new PublicKey("A1F2j7JaaenADbehhMLHyBCrGPpb716E7q1zVeC8LyQ"); // price = 2000
+  const lowPriceFeed = new PublicKey("9VHgh9oZ6cP1zkfP4F4GLFphYDpdUxUsy4evRUgsY1Qi"); // price = 1000
+
+  let f: VaultFixture;
+
+  const setOracleUnlock = (feed: PublicKey, price: number, direction: number) =>
+    program.methods
+      .setOracleUnlock(feed, PYTH_PROGRAM_ID, new anchor.BN(price), direction)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  const withdrawWithFeed = (feed: PublicKey) =>
+    program.methods
+      .withdraw(new anchor.BN(LAMPORTS_PER_TOKEN), null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
+      })
+      .remainingAccounts([{ pubkey: feed, isSigner: false, isWritable: false }])
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Withdraws when the price is above the threshold", async () => {
+    await setOracleUnlock(highPriceFeed, 1500, 0);
+    await withdrawWithFeed(highPriceFeed);
+  });
+
+  it("FAIL to withdraw when the price is below an 'above' threshold", async () => {
+    await setOracleUnlock(lowPriceFeed, 1500, 0);
+    try {
+      await withdrawWithFeed(lowPriceFeed);
+      expect.fail("The price condition is not met.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("OracleConditionNotMet");
+    }
+  });
+
+  it("FAIL to pass a different feed than the configured one", async () => {
+    try {
+      await withdrawWithFeed(highPriceFeed);
+      expect.fail("The feed must match the vault config.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidOracleAccount");
+    }
+  });
+
+  it("Withdraws once a 'below' condition is satisfied", async () => {
+    await setOracleUnlock(lowPriceFeed, 1500, 1);
+    await withdrawWithFeed(lowPriceFeed);
+  });
+});
+
+describe("token_vault: epoch withdrawal limit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const limit = 50 * LAMPORTS_PER_TOKEN;
+
+  // Epochs are short on the local validator (see 'slots_per_epoch' in Anchor.toml)
+  const waitForNextEpoch = async (epoch: number) => {
+    while ((await provider.connection.getEpochInfo()).epoch <= epoch) {
+      await delay(1000);
+    }
+  };
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 200 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .setEpochWithdrawalLimit(new anchor.BN(limit))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Enforces the limit within an epoch and resets it in the next one", async () => {
+    await withdrawFromVault(program, f, limit);
+    const { lastEpoch } = await program.account.vault.fetch(f.vault);
+
+    try {
+      await withdrawFromVault(program, f, 1);
+      expect.fail("Epoch limit should be exhausted.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("EpochLimitExceeded");
+    }
+
+    await waitForNextEpoch(lastEpoch.toNumber());
+    await withdrawFromVault(program, f, limit);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.lastEpoch.toNumber()).to.be.greaterThan(lastEpoch.toNumber());
+    expect(vaultAccount.currentEpochWithdrawn.toString()).to.equal(limit.toString());
+  });
+});
+
+describe("token_vault: realloc vault", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+  });
+
+  it("New vaults start at the current layout version", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.version).to.equal(0);
+  });
+
+  // A local validator cannot shrink a program-owned account from the client, so the
+  // old-layout upgrade path is not exercised here; only the size guard is.
+  it("FAIL to realloc a vault that already has the current size", async () => {
+    try {
+      await program.methods
+        .reallocVault(f.owner.publicKey)
+        .accounts({ vault: f.vault, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("The vault is already the right size.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("AlreadyCorrectSize");
+    }
+  });
+});
+
+describe("token_vault: v1 to v2 migration", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  // A v1-layout vault is loaded into the local validator from tests/fixtures, see Anchor.toml
+  const legacyOwner = Keypair.fromSecretKey(
+    Uint8Array.from(JSON.parse(readFileSync("tests/fixtures/legacy_vault_owner.json", "utf8")))
+  );
+  const [legacyVault] = PublicKey.findProgramAddressSync(
+    [Buffer.from("vault"), legacyOwner.publicKey.toBuffer()],
+    program.programId
+  );
+  const vaultV2Pda = (legacy: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("vault_v2"), legacy.toBuffer()], program.programId)[0];
+
+  const migrate = (owner: Keypair, legacy: PublicKey) =>
+    program.methods
+      .migrateVault()
+      .accounts({
+        legacyVault: legacy,
+        vaultV2: vaultV2Pda(legacy),
+        authority: owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([owner])
+      .rpc();
+
+  before(async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(legacyOwner.publicKey, 1_000_000_000),
+      "confirmed"
+    );
+  });
+
+  it("Migrates a v1 vault and preserves every field", async () => {
+    await migrate(legacyOwner, legacyVault);
+
+    const vaultV2 = await program.account.vaultV2.fetch(vaultV2Pda(legacyVault));
+    expect(vaultV2.schemaVersion).to.equal(2);
+    expect(vaultV2.authority.toString()).to.equal(legacyOwner.publicKey.toString());
+    expect(vaultV2.tokenAccount.toString()).to.equal("Cp26FTSjR6StQyCnz6RVLiMANJFYmoGCpfsQhNkrDZnz");
+    expect(vaultV2.bump).to.equal(250);
+    expect(vaultV2.authorityBump).to.equal(255);
+    expect(vaultV2.isLocked).to.equal(true);
+    expect(vaultV2.unlockTimestamp.toNumber()).to.equal(1_700_000_000);
+    expect(vaultV2.legacyVault.toString()).to.equal(legacyVault.toString());
+
+    // The v1 account is closed and its rent returned
+    expect(await provider.connection.getAccountInfo(legacyVault)).to.be.null;
+  });
+
+  it("FAIL to migrate a vault that is already on a newer layout", async () => {
+    const f = await createVaultFixture(program, provider);
+    try {
+      await migrate(f.owner, f.vault);
+      expect.fail("Current vaults are not v1.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MigrationAlreadyDone");
+    }
+  });
+});
+
+describe("token_vault: vault registry", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let owner: Keypair;
+  let vaultRegistry: PublicKey;
+
+  before(async () => {
+    owner = await createFundedKeypair(provider);
+    [vaultRegistry] = PublicKey.findProgramAddressSync([Buffer.from("registry"), owner.publicKey.toBuffer()], program.programId);
+    await program.methods
+      .createVaultRegistry()
+      .accounts({ vaultRegistry, authority: owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([owner])
+      .rpc();
+  });
+
+  it("Starts empty", async () => {
+    const registry = await program.account.vaultRegistry.fetch(vaultRegistry);
+    expect(registry.vaultCount).to.equal(0);
+    expect(registry.vaults).to.be.empty;
+  });
+
+  // Vault PDAs are seeded by the payer, so each authority can only create one vault for now
+  it("Records the vault created by the registry owner and grows the account", async () => {
+    const sizeBefore = (await provider.connection.getAccountInfo(vaultRegistry)).data.length;
+    const f = await createVaultFixture(program, provider, { owner, vaultRegistry });
+
+    const registry = await program.account.vaultRegistry.fetch(vaultRegistry);
+    expect(registry.vaultCount).to.equal(1);
+    expect(registry.vaults.map((v) => v.vault.toString())).to.deep.equal([f.vault.toString()]);
+    expect(registry.vaults[0].vaultIndex).to.equal(0);
+    expect((await provider.connection.getAccountInfo(vaultRegistry)).data.length).to.equal(sizeBefore + 33);
+
+    await program.methods.getRegistry().accounts({ vaultRegistry }).rpc();
+  });
+
+  it("FAIL to record a vault in another authority's registry", async () => {
+    try {
+      await createVaultFixture(program, provider, { vaultRegistry });
+      expect.fail("The registry belongs to a different payer.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintSeeds");
+    }
+  });
+});
+
+describe("token_vault: early-unlock penalty", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const holdSecs = 4;
+  let f: VaultFixture;
+  let treasury: PublicKey;
+  const balanceOf = async (account: PublicKey) => BigInt((await getAccount(provider.connection, account)).amount.toString());
+
+  const setPenalty = (vault: VaultFixture, holdUntil: number, bps: number) =>
+    program.methods
+      .setEarlyUnlockPenalty(new anchor.BN(holdUntil), bps)
+      .accounts({ vault: vault.vault, authority: vault.owner.publicKey })
+      .signers([vault.owner])
+      .rpc();
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    treasury = await createFundedTokenAccount(provider, mint, Keypair.generate().publicKey, 0);
+    f = await createVaultFixture(program, provider, { mint, feeTreasury: treasury });
+    await depositToVault(program, f, 200 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Charges the exact penalty before the hold ends", async () => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await setPenalty(f, now + holdSecs, 250); // 2.5%
+
+    const ownerBefore = await balanceOf(f.ownerTokenAccount);
+    await withdrawFromVault(program, f, 100 * LAMPORTS_PER_TOKEN, treasury);
+
+    expect((await balanceOf(treasury)).toString()).to.equal((2.5 * LAMPORTS_PER_TOKEN).toString());
+    expect((await balanceOf(f.ownerTokenAccount)) - ownerBefore).to.equal(BigInt(97.5 * LAMPORTS_PER_TOKEN));
+  });
+
+  it("Charges nothing once the hold has passed", async () => {
+    await delay((holdSecs + 1) * 1000);
+
+    const ownerBefore = await balanceOf(f.ownerTokenAccount);
+    await withdrawFromVault(program, f, 50 * LAMPORTS_PER_TOKEN);
+
+    expect((await balanceOf(treasury)).toString()).to.equal((2.5 * LAMPORTS_PER_TOKEN).toString());
+    expect((await balanceOf(f.ownerTokenAccount)) - ownerBefore).to.equal(BigInt(50 * LAMPORTS_PER_TOKEN));
+  });
+
+  it("FAIL to charge a penalty without a fee treasury", async () => {
+    const g = await createVaultFixture(program, provider);
+    await depositToVault(program, g, 10 * LAMPORTS_PER_TOKEN);
+    await setPenalty(g, Math.floor(Date.now() / 1000) + 3600, 100);
+    try {
+      await withdrawFromVault(program, g, LAMPORTS_PER_TOKEN);
+      expect.fail("The penalty needs a treasury.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoTreasuryForPenalty");
+    }
+  });
+});
+
+describe("token_vault: high-water mark", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const highWaterMark = async () => (await program.account.vault.fetch(f.vault)).highWaterMark.toString();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+  });
+
+  it("Tracks the running maximum balance across tranches", async () => {
+    await depositToVault(program, f, 30 * LAMPORTS_PER_TOKEN);
+    await depositToVault(program, f, 20 * LAMPORTS_PER_TOKEN);
+    expect(await highWaterMark()).to.equal((50 * LAMPORTS_PER_TOKEN).toString());
+
+    // Withdrawing and re-depositing below the peak leaves the mark unchanged
+    await withdrawFromVault(program, f, 40 * LAMPORTS_PER_TOKEN);
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    expect(await highWaterMark()).to.equal((50 * LAMPORTS_PER_TOKEN).toString());
+
+    const signature = await depositToVault(program, f, 45 * LAMPORTS_PER_TOKEN);
+    expect(await highWaterMark()).to.equal((65 * LAMPORTS_PER_TOKEN).toString());
+    const events = await eventsFromTx(program, provider, signature);
+    expect(events.map((e) => e.name)).to.include("highWaterMarkReached");
+  });
+
+  it("Counts every deposit and withdrawal", async () => {
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.allTimeDepositCount.toNumber()).to.equal(4);
+    expect(vaultAccount.allTimeWithdrawalCount.toNumber()).to.equal(1);
+  });
+});
+
+describe("token_vault: referrals", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let referrer: Keypair;
+  let treasuryOwner: Keypair;
+  let treasury: PublicKey;
+  let referrerTokenAccount: PublicKey;
+  let referralRecord: PublicKey;
+
+  const referredDeposit = (amount: number, referrerKey: PublicKey, record: PublicKey | null) =>
+    program.methods
+      .deposit(new anchor.BN(amount), referrerKey, null, null)
+      .accounts({
+        vault: f.vault,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: treasury,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: record,
+        systemProgram: SystemProgram.programId,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    referrer = await createFundedKeypair(provider);
+    treasuryOwner = Keypair.generate();
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    treasury = await createFundedTokenAccount(provider, mint, treasuryOwner.publicKey, 0);
+    referrerTokenAccount = await createFundedTokenAccount(provider, mint, referrer.publicKey, 0);
+    f = await createVaultFixture(program, provider, { mint, depositFeeBps: 100, feeTreasury: treasury }); // 1%
+    [referralRecord] = PublicKey.findProgramAddressSync([Buffer.from("referral"), referrer.publicKey.toBuffer()], program.programId);
+  });
+
+  it("Attributes volume and 20% of the fee over multiple referred deposits", async () => {
+    await referredDeposit(100 * LAMPORTS_PER_TOKEN, referrer.publicKey, referralRecord);
+    await referredDeposit(50 * LAMPORTS_PER_TOKEN, referrer.publicKey, referralRecord);
+
+    const record = await program.account.referralRecord.fetch(referralRecord);
+    expect(record.referrer.toString()).to.equal(referrer.publicKey.toString());
+    expect(record.referredDepositVolume.toString()).to.equal((150 * LAMPORTS_PER_TOKEN).toString());
+    // 1% fee on 150 tokens = 1.5, of which 20% = 0.3
+    expect(record.earnedFees.toString()).to.equal((0.3 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL to refer yourself", async () => {
+    const [selfRecord] = PublicKey.findProgramAddressSync([Buffer.from("referral"), f.owner.publicKey.toBuffer()], program.programId);
+    try {
+      await referredDeposit(10 * LAMPORTS_PER_TOKEN, f.owner.publicKey, selfRecord);
+      expect.fail("Self-referral is not allowed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ReferrerIsDepositor");
+    }
+  });
+
+  it("The referrer claims earned fees from the protocol fee account", async () => {
+    await program.methods
+      .claimReferralFees()
+      .accounts({
+        referralRecord,
+        feeTreasuryTokenAccount: treasury,
+        referrerTokenAccount,
+        mint: f.mint,
+        referrer: referrer.publicKey,
+        treasuryAuthority: treasuryOwner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([referrer, treasuryOwner])
+      .rpc();
+
+    expect((await getAccount(provider.connection, referrerTokenAccount)).amount.toString()).to.equal(
+      (0.3 * LAMPORTS_PER_TOKEN).toString()
+    );
+    expect((await program.account.referralRecord.fetch(referralRecord)).earnedFees.toNumber()).to.equal(0);
+  });
+});
+
+describe("token_vault: SPL delegation of vault tokens", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const delegate = Keypair.generate();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Grants an SPL delegation on the vault token account", async () => {
+    await program.methods
+      .grantVaultDelegation(delegate.publicKey, new anchor.BN(40 * LAMPORTS_PER_TOKEN))
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        delegate: delegate.publicKey,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    const tokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(tokenAccount.delegate.toString()).to.equal(delegate.publicKey.toString());
+    expect(tokenAccount.delegatedAmount.toString()).to.equal((40 * LAMPORTS_PER_TOKEN).toString());
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.currentDelegate.toString()).to.equal(delegate.publicKey.toString());
+  });
+
+  it("FAIL to withdraw while a delegation is active", async () => {
+    try {
+      await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("The delegation must be revoked first.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DelegationActiveCannotWithdraw");
+    }
+  });
+
+  it("Revokes the delegation and allows withdrawals again", async () => {
+    await program.methods
+      .revokeVaultDelegation()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    const tokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(tokenAccount.delegate).to.be.null;
+    expect((await program.account.vault.fetch(f.vault)).currentDelegate).to.be.null;
+    await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to delegate tokens held by a time lock", async () => {
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await program.methods
+        .grantVaultDelegation(delegate.publicKey, new anchor.BN(LAMPORTS_PER_TOKEN))
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          vaultTokenAccount: f.vaultTokenAccount,
+          delegate: delegate.publicKey,
+          authority: f.owner.publicKey,
+          tokenProgram: f.tokenProgram,
+        })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("A locked vault cannot be delegated.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+    }
+  });
+});
+
+describe("token_vault: unlock vote", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let unlockVote: PublicKey;
+  const signers = [Keypair.generate(), Keypair.generate()];
+
+  const castVote = (signer: Keypair) =>
+    program.methods
+      .castUnlockVote()
+      .accounts({ unlockVote, signer: signer.publicKey })
+      .signers([signer])
+      .rpc();
+
+  const unlock = () =>
+    program.methods
+      .authorityUnlockVault()
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), unlockVote, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [unlockVote] = PublicKey.findProgramAddressSync([Buffer.from("unlock_vote"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+    await program.methods
+      .configureUnlockVote(signers.map((s) => s.publicKey), 2)
+      .accounts({ vault: f.vault, unlockVote, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to vote as a non-signer or twice", async () => {
+    try {
+      await castVote(Keypair.generate());
+      expect.fail("Only required signers may vote.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotARequiredSigner");
+    }
+
+    await castVote(signers[0]);
+    try {
+      await castVote(signers[0]);
+      expect.fail("A signer votes once.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("AlreadyVoted");
+    }
+  });
+
+  it("FAIL to unlock early with one of two votes", async () => {
+    try {
+      await unlock();
+      expect.fail("One vote is not enough.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+    }
+  });
+
+  it("Unlocks early once both signers vote, and resets the votes", async () => {
+    await castVote(signers[1]);
+    await unlock();
+
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.equal(false);
+    expect((await program.account.unlockVote.fetch(unlockVote)).approvalsBitmask).to.equal(0);
+  });
+});
+
+describe("token_vault: Token-2022 transfer fees", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const transferFeeBps = 100; // 1%
+  let f: VaultFixture;
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mintKeypair = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: mintAuthority.publicKey,
+          newAccountPubkey: mintKeypair.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(
+          mintKeypair.publicKey,
+          mintAuthority.publicKey,
+          mintAuthority.publicKey,
+          transferFeeBps,
+          BigInt(1_000_000 * LAMPORTS_PER_TOKEN),
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(mintKeypair.publicKey, DECIMAL_PLACES, mintAuthority.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [mintKeypair]
+    );
+
+    f = await createVaultFixture(program, provider, { mint: mintKeypair.publicKey, tokenProgram: TOKEN_2022_PROGRAM_ID });
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN); // The vault receives 99 after the 1% fee
+  });
+
+  it("The user nets exactly the requested amount, the vault pays the withheld fee", async () => {
+    const balanceOf = async (account: PublicKey) =>
+      BigInt((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount.toString());
+    const ownerBefore = await balanceOf(f.ownerTokenAccount);
+    const vaultBefore = await balanceOf(f.vaultTokenAccount);
+
+    await withdrawFromVault(program, f, 50 * LAMPORTS_PER_TOKEN);
+
+    expect((await balanceOf(f.ownerTokenAccount)) - ownerBefore).to.equal(BigInt(50 * LAMPORTS_PER_TOKEN));
+    // Inverse fee: 50 / 0.99 rounds up to 50.505050506 sent, of which 0.505050506 is withheld
+    expect(vaultBefore - (await balanceOf(f.vaultTokenAccount))).to.equal(BigInt(50_505_050_506));
+  });
+
+  it("Predicts a withdrawal of the whole balance fails, it leaves nothing for the transfer fee", async () => {
+    const balance = (await getAccount(provider.connection, f.vaultTokenAccount, undefined, TOKEN_2022_PROGRAM_ID)).amount;
+    const simulation = await program.methods
+      .simulateWithdraw(new anchor.BN(balance.toString()))
+      .accounts({
+        vault: f.vault,
+        programConfig: programConfigPda(program),
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        userTokenAccount: f.ownerTokenAccount,
+        destinationEntry: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+      })
+      .view();
+    expect(simulation.willSucceed).to.be.false;
+    const insufficientFunds = program.idl.errors.find((e) => e.name.toLowerCase() === "insufficientfunds");
+    expect(simulation.failureReason).to.equal(insufficientFunds.code - 6000);
+  });
+});
+
+describe("token_vault: merkle whitelist", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let depositors: Keypair[];
+  let tokenAccounts: PublicKey[];
+  let leaves: Buffer[];
+
+  // Sorted-pair keccak, matching 'merkle::verify_merkle_proof'
+  const hashPair = (a: Buffer, b: Buffer) =>
+    Buffer.from(keccak_256(Buffer.compare(a, b) <= 0 ? Buffer.concat([a, b]) : Buffer.concat([b, a])));
+
+  const depositWithProof = (i: number, proof: Buffer[] | null) =>
+    program.methods
+      .deposit(new anchor.BN(LAMPORTS_PER_TOKEN), null, proof ? proof.map((node) => [...node]) : null, null)
+      .accounts({
+        vault: f.vault,
+        userTokenAccount: tokenAccounts[i],
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: depositors[i].publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
+      })
+      .signers([depositors[i]])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    depositors = await Promise.all([0, 1, 2, 3].map(() => createFundedKeypair(provider)));
+    tokenAccounts = await Promise.all(
+      depositors.map((d) => createFundedTokenAccount(provider, f.mint, d.publicKey, 10 * LAMPORTS_PER_TOKEN))
+    );
+
+    // Four-leaf tree: root = H(H(l0, l1), H(l2, l3))
+    leaves = depositors.map((d) => Buffer.from(keccak_256(d.publicKey.toBuffer())));
+    const root = hashPair(hashPair(leaves[0], leaves[1]), hashPair(leaves[2], leaves[3]));
+    await program.methods
+      .setWhitelistRoot([...root])
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Accepts a deposit with a valid proof", async () => {
+    await depositWithProof(2, [leaves[3], hashPair(leaves[0], leaves[1])]);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      LAMPORTS_PER_TOKEN.toString()
+    );
+  });
+
+  it("FAIL with a tampered proof", async () => {
+    const tampered = Buffer.from(leaves[1]);
+    tampered[0] ^= 0xff;
+    try {
+      await depositWithProof(0, [tampered, hashPair(leaves[2], leaves[3])]);
+      expect.fail("The tampered proof should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MerkleProofInvalid");
+    }
+  });
+
+  it("FAIL without a proof", async () => {
+    try {
+      await depositWithProof(1, null);
+      expect.fail("A proof is required.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MerkleProofInvalid");
+    }
+  });
+});
+
+describe("token_vault: crank unlock", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const REWARD = 10_000;
+  let f: VaultFixture;
+  let crank: Keypair;
+
+  const crankUnlock = () =>
+    program.methods
+      .crankUnlockVault()
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), crank: crank.publicKey })
+      .signers([crank])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    crank = await createFundedKeypair(provider);
+
+    // Top the vault up so the reward comes out of lamports above rent
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: provider.wallet.publicKey, toPubkey: f.vault, lamports: 10 * REWARD })
+      )
+    );
+    await program.methods
+      .lockVaultForDuration(new anchor.BN(2))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL before a crank reward is configured", async () => {
+    await delay(3000);
+    try {
+      await crankUnlock();
+      expect.fail("Cranking requires a reward.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("CrankRewardNotConfigured");
+    }
+  });
+
+  it("Pays the crank exactly the reward once the lock expires", async () => {
+    await program.methods
+      .setCrankReward(new anchor.BN(REWARD))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    // The provider wallet pays the transaction fee, so the crank's balance moves by the reward alone
+    const before = await provider.connection.getBalance(crank.publicKey);
+    await crankUnlock();
+    expect(await provider.connection.getBalance(crank.publicKey)).to.equal(before + REWARD);
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.false;
+  });
+
+  it("FAIL cranking a vault that is already unlocked", async () => {
+    try {
+      await crankUnlock();
+      expect.fail("An unlocked vault pays no reward.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultNotLocked");
+    }
+  });
+});
+
+describe("token_vault: fee governance", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let lpMint: PublicKey;
+  let ownerLpTokenAccount: PublicKey;
+  let voter: Keypair;
+  let voterLpTokenAccount: PublicKey;
+
+  const proposalPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_proposal"), f.vault.toBuffer(), new anchor.BN(id).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const propose = (id: number, votingPeriodSecs: number) =>
+    program.methods
+      .createFeeProposal(new anchor.BN(id), 50, 75, new anchor.BN(votingPeriodSecs))
+      .accounts({
+        vault: f.vault,
+        feeProposal: proposalPda(id),
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  const escrowPda = (id: number, signer: Keypair) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_vote_escrow"), proposalPda(id).toBuffer(), signer.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+  const vote = (id: number, signer: Keypair, lpTokenAccount: PublicKey, approve: boolean) => {
+    const [feeVoteRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_vote"), proposalPda(id).toBuffer(), signer.publicKey.toBuffer()],
+      program.programId
+    );
+    return program.methods
+      .voteOnFeeProposal(new anchor.BN(id), approve)
+      .accounts({
+        vault: f.vault,
+        feeProposal: proposalPda(id),
+        feeVoteRecord,
+        vaultAuthority: f.vaultAuthority,
+        lpMint,
+        voterLpTokenAccount: lpTokenAccount,
+        voteEscrow: escrowPda(id, signer),
+        voter: signer.publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([signer])
+      .rpc();
+  };
+
+  const release = (id: number, signer: Keypair, lpTokenAccount: PublicKey) =>
+    program.methods
+      .releaseFeeVoteEscrow(new anchor.BN(id))
+      .accounts({
+        vault: f.vault,
+        feeProposal: proposalPda(id),
+        vaultAuthority: f.vaultAuthority,
+        lpMint,
+        voteEscrow: escrowPda(id, signer),
+        voterLpTokenAccount: lpTokenAccount,
+        voter: signer.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .signers([signer])
+      .rpc();
+
+  const execute = (id: number) =>
+    program.methods
+      .executeFeeProposal(new anchor.BN(id))
+      .accounts({ vault: f.vault, feeProposal: proposalPda(id) })
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [lpMint] = PublicKey.findProgramAddressSync([Buffer.from("lp_mint"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .initializeLpMint()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        lpMint,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+    ownerLpTokenAccount = await createFundedTokenAccount(provider, lpMint, f.owner.publicKey, 0);
+    await program.methods
+      .deposit(new anchor.BN(100 * LAMPORTS_PER_TOKEN), null, null, null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint,
+        userLpTokenAccount: ownerLpTokenAccount,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    // A smaller holder who votes against
+    voter = await createFundedKeypair(provider);
+    voterLpTokenAccount = await createFundedTokenAccount(provider, lpMint, voter.publicKey, 0);
+    await transfer(
+      provider.connection,
+      f.owner,
+      ownerLpTokenAccount,
+      voterLpTokenAccount,
+      f.owner,
+      30 * LAMPORTS_PER_TOKEN
+    );
+  });
+
+  it("Tallies LP-weighted votes", async () => {
+    await propose(1, 3);
+    await vote(1, f.owner, ownerLpTokenAccount, true);
+    await vote(1, voter, voterLpTokenAccount, false);
+
+    // One vote per whole LP share
+    const proposal = await program.account.feeProposal.fetch(proposalPda(1));
+    expect(proposal.votesFor).to.equal(70);
+    expect(proposal.votesAgainst).to.equal(30);
+  });
+
+  it("Escrows the voted LP so it cannot vote again from another wallet", async () => {
+    expect((await getAccount(provider.connection, voterLpTokenAccount)).amount.toString()).to.equal("0");
+    expect((await getAccount(provider.connection, escrowPda(1, voter))).amount.toString()).to.equal(
+      (30 * LAMPORTS_PER_TOKEN).toString()
+    );
+    try {
+      await release(1, voter, voterLpTokenAccount);
+      expect.fail("The shares back the vote until voting ends.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VotingStillActive");
+    }
+  });
+
+  it("FAIL to vote twice from the same wallet", async () => {
+    try {
+      await vote(1, voter, voterLpTokenAccount, false);
+      expect.fail("The vote record already exists.");
+    } catch (error) {
+      expect(error.toString()).to.include("already in use");
+    }
+  });
+
+  it("FAIL to execute while voting is open", async () => {
+    try {
+      await execute(1);
+      expect.fail("Voting has not ended.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VotingStillActive");
+    }
+  });
+
+  it("Applies the proposed fees after voting ends", async () => {
+    await delay(4000);
+    await execute(1);
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.depositFeeBps).to.equal(50);
+    expect(vaultAccount.withdrawFeeBps).to.equal(75);
+  });
+
+  it("Returns the escrowed LP once voting ends", async () => {
+    await release(1, f.owner, ownerLpTokenAccount);
+    await release(1, voter, voterLpTokenAccount);
+    expect((await getAccount(provider.connection, ownerLpTokenAccount)).amount.toString()).to.equal(
+      (70 * LAMPORTS_PER_TOKEN).toString()
+    );
+    expect((await getAccount(provider.connection, voterLpTokenAccount)).amount.toString()).to.equal(
+      (30 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL to execute a proposal twice", async () => {
+    try {
+      await execute(1);
+      expect.fail("The proposal was already applied.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ProposalAlreadyExecuted");
+    }
+  });
+
+  it("FAIL to vote after the window closes", async () => {
+    await propose(2, 1);
+    await delay(2000);
+    try {
+      await vote(2, voter, voterLpTokenAccount, true);
+      expect.fail("Voting has closed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VotingClosed");
+    }
+  });
+});
+
+describe("token_vault: confidential transfers", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let confidentialVault: VaultFixture;
+
+  const setConfidentialTransfers = (fixture: VaultFixture, enabled: boolean) =>
+    program.methods
+      .setConfidentialTransfers(enabled)
+      .accounts({
+        vault: fixture.vault,
+        vaultTokenAccount: fixture.vaultTokenAccount,
+        mint: fixture.mint,
+        authority: fixture.owner.publicKey,
+      })
+      .signers([fixture.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+
+    // A mock confidential mint: the extension is initialized with no authority and no auditor.
+    // spl-token has no builder for it, so the instruction data is laid out by hand.
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mintKeypair = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.ConfidentialTransferMint]);
+    const initializeConfidentialMint = new anchor.web3.TransactionInstruction({
+      programId: TOKEN_2022_PROGRAM_ID,
+      keys: [{ pubkey: mintKeypair.publicKey, isSigner: false, isWritable: true }],
+      // [ConfidentialTransferExtension, InitializeMint, authority, auto_approve, auditor ElGamal key]
+      data: Buffer.concat([Buffer.from([27, 0]), Buffer.alloc(32), Buffer.from([1]), Buffer.alloc(32)]),
+    });
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: mintAuthority.publicKey,
+          newAccountPubkey: mintKeypair.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        initializeConfidentialMint,
+        createInitializeMintInstruction(mintKeypair.publicKey, DECIMAL_PLACES, mintAuthority.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [mintKeypair]
+    );
+    confidentialVault = await createVaultFixture(program, provider, {
+      mint: mintKeypair.publicKey,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+    });
+  });
+
+  it("FAIL to enable confidential transfers for a mint without the extension", async () => {
+    try {
+      await setConfidentialTransfers(f, true);
+      expect.fail("The mint is not confidential.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MintDoesNotSupportConfidentialTransfers");
+    }
+  });
+
+  it("Enables confidential transfers for a confidential mint", async () => {
+    await setConfidentialTransfers(confidentialVault, true);
+    expect((await program.account.vault.fetch(confidentialVault.vault)).confidentialTransfersEnabled).to.be.true;
+  });
+
+  it("FAIL to unshield from a vault without confidential transfers", async () => {
+    await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+    try {
+      await program.methods
+        .withdraw(new anchor.BN(LAMPORTS_PER_TOKEN), new Array(36).fill(0))
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          userTokenAccount: f.ownerTokenAccount,
+          vaultTokenAccount: f.vaultTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: f.tokenProgram,
+          mint: f.mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          destinationEntry: null,
+          programConfig: programConfigPda(program),
+          lpMint: null,
+          userLpTokenAccount: null,
+          beneficiarySplit: null,
+          roleConfig: null,
+        })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("The vault has no confidential balance.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConfidentialTransfersNotEnabled");
+    }
+  });
+});
+
+describe("token_vault: sweeping scheduled withdrawals", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  const scheduledFor = (nonce: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("scheduled"), f.vault.toBuffer(), new anchor.BN(nonce).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const schedule = async (nonce: number, cancel: boolean) => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    await program.methods
+      .createScheduledWithdrawal(new anchor.BN(nonce), new anchor.BN(LAMPORTS_PER_TOKEN), new anchor.BN(now + 3600), f.ownerTokenAccount)
+      .accounts({
+        vault: f.vault,
+        scheduledWithdrawal: scheduledFor(nonce),
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+    if (cancel) {
+      await program.methods
+        .cancelScheduledWithdrawal(new anchor.BN(nonce))
+        .accounts({ vault: f.vault, scheduledWithdrawal: scheduledFor(nonce), authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+    }
+  };
+
+  const sweep = (nonces: number[]) =>
+    program.methods
+      .sweepExpiredScheduledWithdrawals(nonces.map((nonce) => new anchor.BN(nonce)))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .remainingAccounts(nonces.map((nonce) => ({ pubkey: scheduledFor(nonce), isSigner: false, isWritable: true })))
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    for (const nonce of [0, 1, 2]) {
+      await schedule(nonce, true);
+    }
+    await schedule(3, false); // Pending and not yet due
+  });
+
+  it("Closes three cancelled withdrawals in one sweep", async () => {
+    expect((await program.account.vault.fetch(f.vault)).pendingScheduledWithdrawalCount).to.equal(4);
+    const before = await provider.connection.getBalance(f.owner.publicKey);
+
+    await sweep([0, 1, 2]);
+
+    for (const nonce of [0, 1, 2]) {
+      expect(await provider.connection.getAccountInfo(scheduledFor(nonce))).to.be.null;
+    }
+    expect((await program.account.vault.fetch(f.vault)).pendingScheduledWithdrawalCount).to.equal(1);
+    expect(await provider.connection.getBalance(f.owner.publicKey)).to.be.greaterThan(before);
+  });
+
+  it("FAIL to sweep a pending withdrawal", async () => {
+    try {
+      await sweep([3]);
+      expect.fail("A pending withdrawal is not sweepable.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WithdrawalNotSweepable");
+    }
+  });
+});
+
+describe("token_vault: batch deposit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let vaults: VaultFixture[];
+  let payer: Keypair;
+  let payerTokenAccount: PublicKey;
+
+  const batchDeposit = (amounts: number[], remainingAccounts: PublicKey[]) =>
+    program.methods
+      .batchDeposit(amounts.map((amount) => new anchor.BN(amount)))
+      .accounts({
+        programConfig: programConfigPda(program),
+        mint: vaults[0].mint,
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      // The depositor access account closing each group is only read
+      .remainingAccounts(remainingAccounts.map((pubkey, i) => ({ pubkey, isSigner: false, isWritable: i % 4 !== 3 })))
+      .signers([payer])
+      .rpc();
+
+  const whitelistEntryFor = (v: VaultFixture) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), v.vault.toBuffer(), payer.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+  // One (vault, source, vault token account, depositor access) group per deposit
+  const groupFor = (v: VaultFixture, access: PublicKey = whitelistEntryFor(v)) => [
+    v.vault,
+    payerTokenAccount,
+    v.vaultTokenAccount,
+    access,
+  ];
+
+  before(async () => {
+    // Three employee vaults sharing one payroll mint, each whitelisting the payroll payer
+    const first = await createVaultFixture(program, provider);
+    vaults = [
+      first,
+      await createVaultFixture(program, provider, { mint: first.mint }),
+      await createVaultFixture(program, provider, { mint: first.mint }),
+    ];
+    payer = await createFundedKeypair(provider);
+    payerTokenAccount = await createFundedTokenAccount(provider, first.mint, payer.publicKey, 100 * LAMPORTS_PER_TOKEN);
+    for (const v of vaults) {
+      await program.methods
+        .enableWhitelist()
+        .accounts({ vault: v.vault, authority: v.owner.publicKey })
+        .signers([v.owner])
+        .rpc();
+      await program.methods
+        .addToWhitelist()
+        .accounts({
+          vault: v.vault,
+          candidate: payer.publicKey,
+          whitelistEntry: whitelistEntryFor(v),
+          authority: v.owner.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([v.owner])
+        .rpc();
+    }
+  });
+
+  it("FAIL: the payer is not whitelisted without its entry", async () => {
+    try {
+      await batchDeposit([LAMPORTS_PER_TOKEN], groupFor(vaults[0], program.programId));
+      expect.fail("The vault only takes whitelisted deposits.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotWhitelisted");
+    }
+  });
+
+  it("FAIL when a vault token account does not belong to its vault", async () => {
+    try {
+      await batchDeposit([LAMPORTS_PER_TOKEN], [vaults[0].vault, payerTokenAccount, vaults[1].vaultTokenAccount, whitelistEntryFor(vaults[0])]);
+      expect.fail("The token account belongs to another vault.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BatchDepositAccountMismatch");
+    }
+  });
+
+  it("Funds three vaults in one transaction", async () => {
+    const amounts = [10, 20, 30].map((tokens) => tokens * LAMPORTS_PER_TOKEN);
+    const tx = await batchDeposit(amounts, vaults.flatMap((v) => groupFor(v)));
+
+    for (const [i, v] of vaults.entries()) {
+      const vaultTokenAccount = await getAccount(provider.connection, v.vaultTokenAccount);
+      expect(vaultTokenAccount.amount.toString()).to.equal(amounts[i].toString());
+    }
+    const [completed] = (await eventsFromTx(program, provider, tx)).filter((e) => e.name === "batchDepositCompleted");
+    expect(completed.data.totalAmount.toString()).to.equal((60 * LAMPORTS_PER_TOKEN).toString());
+  });
+
+  it("FAIL when the accounts do not pair up with the amounts", async () => {
+    try {
+      await batchDeposit([LAMPORTS_PER_TOKEN], [vaults[0].vault, payerTokenAccount]);
+      expect.fail("Each amount needs a group of four accounts.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BatchSizeMismatch");
+    }
+  });
+
+  it("FAIL with more than five deposits", async () => {
+    try {
+      await batchDeposit(
+        new Array(6).fill(1),
+        new Array(6).fill(null).flatMap(() => groupFor(vaults[0]))
+      );
+      expect.fail("The batch is capped at five.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BatchTooLarge");
+    }
+  });
+});
+
+describe("token_vault: batch withdraw", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let recipients: PublicKey[];
+
+  const batchWithdraw = (amounts: number[], recipientTokenAccounts: PublicKey[]) =>
+    program.methods
+      .batchWithdraw(amounts.map((amount) => new anchor.BN(amount)))
+      .accounts({
+        vault: f.vault,
+        programConfig: programConfigPda(program),
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .remainingAccounts(recipientTokenAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    recipients = await Promise.all(
+      [0, 1, 2, 3].map(() => createFundedTokenAccount(provider, f.mint, Keypair.generate().publicKey, 0))
+    );
+  });
+
+  it("FAIL when the batch total exceeds the vault balance", async () => {
+    try {
+      await batchWithdraw([60, 50].map((tokens) => tokens * LAMPORTS_PER_TOKEN), recipients.slice(0, 2));
+      expect.fail("The vault holds only 100 tokens.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BatchWithdrawTotalExceedsBalance");
+    }
+  });
+
+  it("Pays four recipients exactly their amounts", async () => {
+    const amounts = [5, 10, 15, 20].map((tokens) => tokens * LAMPORTS_PER_TOKEN);
+    const tx = await batchWithdraw(amounts, recipients);
+
+    for (const [i, recipient] of recipients.entries()) {
+      expect((await getAccount(provider.connection, recipient)).amount.toString()).to.equal(amounts[i].toString());
+    }
+    const [completed] = (await eventsFromTx(program, provider, tx)).filter((e) => e.name === "batchWithdrawCompleted");
+    expect(completed.data.totalWithdrawn.toString()).to.equal((50 * LAMPORTS_PER_TOKEN).toString());
+    expect(completed.data.recipientCount).to.equal(4);
+  });
+
+  it("FAIL with more than eight recipients", async () => {
+    try {
+      await batchWithdraw(new Array(9).fill(1), new Array(9).fill(recipients[0]));
+      expect.fail("The batch is capped at eight.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BatchTooLarge");
+    }
+  });
+
+  it("FAIL: a batch above the large-withdrawal threshold must use the queue", async () => {
+    await program.methods
+      .setLargeWithdrawalThreshold(new anchor.BN(10 * LAMPORTS_PER_TOKEN), new anchor.BN(60))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await batchWithdraw([6, 6].map((tokens) => tokens * LAMPORTS_PER_TOKEN), recipients.slice(0, 2));
+      expect.fail("The batch total is above the threshold.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UseWithdrawalQueue");
+    }
+  });
+});
+
+describe("token_vault: interest accrual", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let interestReserve: PublicKey;
+
+  const accrue = () =>
+    program.methods
+      .accrueInterest()
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, cranker: provider.wallet.publicKey })
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    [interestReserve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("interest_reserve"), f.vault.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .setInterestRate(10) // 0.1% per day
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        interestReserve,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  // The local validator cannot warp a week ahead, so this covers the same-day path:
+  // 7 days at 10 bps on 100 tokens would be 100 * 10 * 7 / 10_000 = 0.7 tokens.
+  it("Starts the accrual clock and accrues nothing within the first day", async () => {
+    await accrue();
+    const started = await program.account.vault.fetch(f.vault);
+    expect(started.lastInterestUpdate.toNumber()).to.be.greaterThan(0);
+
+    await accrue();
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.interestAccrued.toNumber()).to.equal(0);
+    expect(vaultAccount.lastInterestUpdate.toNumber()).to.equal(started.lastInterestUpdate.toNumber());
+  });
+
+  it("FAIL to claim before any interest accrues", async () => {
+    // The treasury funds the reserve directly
+    await transfer(provider.connection, f.owner, f.ownerTokenAccount, interestReserve, f.owner, LAMPORTS_PER_TOKEN);
+    try {
+      await program.methods
+        .claimInterest()
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          interestReserve,
+          mint: f.mint,
+          authorityTokenAccount: f.ownerTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: f.tokenProgram,
+        })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Nothing has accrued yet.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NothingToClaim");
+    }
+  });
+});
+
+describe("token_vault: depositor and withdrawer roles", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let roleConfig: PublicKey;
+  let member: Keypair;
+  let memberTokenAccount: PublicKey;
+  let outsider: Keypair;
+  let outsiderTokenAccount: PublicKey;
+
+  const grant = (method: "grantDepositorRole" | "grantWithdrawerRole", key: PublicKey) =>
+    program.methods[method](key)
+      .accounts({ vault: f.vault, roleConfig, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+
+  const depositAs = (signer: Keypair, userTokenAccount: PublicKey) =>
+    program.methods
+      .deposit(new anchor.BN(LAMPORTS_PER_TOKEN), null, null, null)
+      .accounts({
+        vault: f.vault,
+        userTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: signer.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: null,
+        roleConfig,
+        memoProgram: null,
+        depositorRateLimit: null,
+      })
+      .signers([signer])
+      .rpc();
+
+  const withdrawAs = (signer: Keypair, userTokenAccount: PublicKey) =>
+    program.methods
+      .withdraw(new anchor.BN(LAMPORTS_PER_TOKEN), null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: signer.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig,
+      })
+      .signers([signer])
+      .rpc();
+
+  const expectError = async (action: () => Promise<string>, code: string) => {
+    try {
+      await action();
+      expect.fail(`Expected ${code}.`);
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal(code);
+    }
+  };
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [roleConfig] = PublicKey.findProgramAddressSync([Buffer.from("roles"), f.vault.toBuffer()], program.programId);
+    member = await createFundedKeypair(provider);
+    memberTokenAccount = await createFundedTokenAccount(provider, f.mint, member.publicKey, 10 * LAMPORTS_PER_TOKEN);
+    outsider = await createFundedKeypair(provider);
+    outsiderTokenAccount = await createFundedTokenAccount(provider, f.mint, outsider.publicKey, 10 * LAMPORTS_PER_TOKEN);
+
+    await grant("grantDepositorRole", member.publicKey);
+    await grant("grantWithdrawerRole", member.publicKey);
+  });
+
+  it("Lets role holders deposit and withdraw", async () => {
+    await depositAs(member, memberTokenAccount);
+    await withdrawAs(member, memberTokenAccount);
+    expect((await getAccount(provider.connection, memberTokenAccount)).amount.toString()).to.equal(
+      (10 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL for addresses without a role", async () => {
+    await expectError(() => depositAs(outsider, outsiderTokenAccount), "MissingDepositorRole");
+    await expectError(() => withdrawAs(outsider, outsiderTokenAccount), "MissingWithdrawerRole");
+  });
+
+  it("The authority bypasses role checks", async () => {
+    await depositAs(f.owner, f.ownerTokenAccount);
+    await withdrawAs(f.owner, f.ownerTokenAccount);
+  });
+
+  it("FAIL once a role is revoked", async () => {
+    await program.methods
+      .revokeWithdrawerRole(member.publicKey)
+      .accounts({ vault: f.vault, roleConfig, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await expectError(() => withdrawAs(member, memberTokenAccount), "MissingWithdrawerRole");
+  });
+});
+
+describe("token_vault: vesting clawback", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let employer: Keypair;
+  let employerTokenAccount: PublicKey;
+  const total = 120 * LAMPORTS_PER_TOKEN;
+
+  const clawbackAs = (signer: Keypair, clawbackTokenAccount: PublicKey, v: VaultFixture = f) =>
+    program.methods
+      .clawback()
+      .accounts({
+        vault: v.vault,
+        vaultAuthority: v.vaultAuthority,
+        vaultTokenAccount: v.vaultTokenAccount,
+        mint: v.mint,
+        clawbackTokenAccount,
+        clawbackAuthority: signer.publicKey,
+        tokenProgram: v.tokenProgram,
+      })
+      .signers([signer])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, total);
+    employer = await createFundedKeypair(provider);
+    employerTokenAccount = await createFundedTokenAccount(provider, f.mint, employer.publicKey, 0);
+
+    // Vesting starts in an hour, so the whole grant is still unvested
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(now + 3600), new anchor.BN(now + 7200), new anchor.BN(total), new anchor.BN(0), new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to claw back before clawback is enabled", async () => {
+    try {
+      await clawbackAs(employer, employerTokenAccount);
+      expect.fail("Clawback is not enabled.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClawbackNotEnabled");
+    }
+  });
+
+  it("FAIL to appoint the vault owner as the clawback authority", async () => {
+    try {
+      await program.methods
+        .enableClawback(f.owner.publicKey)
+        .accounts({ vault: f.vault, authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("The owner cannot claw back its own grant.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClawbackAuthorityIsOwner");
+    }
+  });
+
+  it("FAIL to claw back as anyone but the clawback authority", async () => {
+    await program.methods
+      .enableClawback(employer.publicKey)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await clawbackAs(f.owner, f.ownerTokenAccount);
+      expect.fail("Only the clawback authority may claw back.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAccess");
+    }
+  });
+
+  it("Returns the exact unvested balance and ends the schedule", async () => {
+    await clawbackAs(employer, employerTokenAccount);
+    expect((await getAccount(provider.connection, employerTokenAccount)).amount.toString()).to.equal(total.toString());
+
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.isLocked).to.be.false;
+    expect(vaultAccount.totalVestedAmount.toNumber()).to.equal(0);
+
+    try {
+      await program.methods
+        .claimVested()
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          vaultTokenAccount: f.vaultTokenAccount,
+          mint: f.mint,
+          beneficiaryTokenAccount: f.ownerTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: f.tokenProgram,
+          programConfig: programConfigPda(program),
+        })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("The schedule was clawed back.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VestingNotConfigured");
+    }
+  });
+
+  it("Leaves vested but unclaimed tokens claimable by the beneficiary", async () => {
+    const g = await createVaultFixture(program, provider);
+    await depositToVault(program, g, total);
+    const gEmployerTokenAccount = await createFundedTokenAccount(provider, g.mint, employer.publicKey, 0);
+
+    // Half-way through the schedule, nothing claimed yet
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(now - 3600), new anchor.BN(now + 3600), new anchor.BN(total), new anchor.BN(0), new anchor.BN(0))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await program.methods
+      .enableClawback(employer.publicKey)
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await clawbackAs(employer, gEmployerTokenAccount, g);
+
+    const ownerBefore = (await getAccount(provider.connection, g.ownerTokenAccount)).amount;
+    await program.methods
+      .claimVested()
+      .accounts({
+        vault: g.vault,
+        vaultAuthority: g.vaultAuthority,
+        vaultTokenAccount: g.vaultTokenAccount,
+        mint: g.mint,
+        beneficiaryTokenAccount: g.ownerTokenAccount,
+        authority: g.owner.publicKey,
+        tokenProgram: g.tokenProgram,
+        programConfig: programConfigPda(program),
+      })
+      .signers([g.owner])
+      .rpc();
+    const claimed = (await getAccount(provider.connection, g.ownerTokenAccount)).amount - ownerBefore;
+    const clawedBack = (await getAccount(provider.connection, gEmployerTokenAccount)).amount;
+    expect(claimed > BigInt(0)).to.be.true;
+    expect((claimed + clawedBack).toString()).to.equal(total.toString());
+  });
+
+  it("Keeps a time lock the owner set on its own", async () => {
+    const g = await createVaultFixture(program, provider);
+    await depositToVault(program, g, total);
+    const gEmployerTokenAccount = await createFundedTokenAccount(provider, g.mint, employer.publicKey, 0);
+
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(now + 3600), new anchor.BN(now + 7200), new anchor.BN(total / 2), new anchor.BN(0), new anchor.BN(0))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await program.methods
+      .lockVault(new anchor.BN(now + 10000), new anchor.BN(0))
+      .accounts({ vault: g.vault, vaultTokenAccount: g.vaultTokenAccount, authority: g.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([g.owner])
+      .rpc();
+    await program.methods
+      .enableClawback(employer.publicKey)
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await clawbackAs(employer, gEmployerTokenAccount, g);
+
+    const vaultAccount = await program.account.vault.fetch(g.vault);
+    expect(vaultAccount.isLocked).to.be.true;
+    expect(vaultAccount.unlockTimestamp.toNumber()).to.equal(now + 10000);
+  });
+});
+
+describe("token_vault: minimum lock duration", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const minLockDurationSecs = 60;
+  let f: VaultFixture;
+
+  const lockFor = (durationSecs: number) =>
+    program.methods
+      .lockVaultForDuration(new anchor.BN(durationSecs))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { minLockDurationSecs });
+  });
+
+  it("FAIL to lock for one second less than the minimum", async () => {
+    try {
+      await lockFor(minLockDurationSecs - 1);
+      expect.fail("The lock is too short.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("LockDurationTooShort");
+    }
+  });
+
+  it("FAIL to lock_vault one second from now", async () => {
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    try {
+      await program.methods
+        .lockVault(new anchor.BN(now + 1), new anchor.BN(0))
+        .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("The lock is too short.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("LockDurationTooShort");
+    }
+  });
+
+  it("Locks for exactly the minimum duration", async () => {
+    await lockFor(minLockDurationSecs);
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.true;
+  });
+
+  it("Lets the authority lower the minimum", async () => {
+    await program.methods
+      .updateMinLockDuration(new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    expect((await program.account.vault.fetch(f.vault)).minLockDurationSecs.toNumber()).to.equal(0);
+  });
+});
+
+describe("token_vault: vesting position transfer", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let newWallet: Keypair;
+  let newWalletTokenAccount: PublicKey;
+  const total = 120 * LAMPORTS_PER_TOKEN;
+  const duration = 6;
+
+  const claim = (signer: Keypair, beneficiaryTokenAccount: PublicKey) =>
+    program.methods
+      .claimVested()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        beneficiaryTokenAccount,
+        authority: signer.publicKey,
+        tokenProgram: f.tokenProgram,
+        programConfig: programConfigPda(program),
+      })
+      .signers([signer])
+      .rpc();
+
+  const transferPosition = (newBeneficiary: PublicKey) =>
+    program.methods
+      .transferVestingPosition(newBeneficiary)
+      .accounts({ vault: f.vault, beneficiary: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, total);
+    newWallet = await createFundedKeypair(provider);
+    newWalletTokenAccount = await createFundedTokenAccount(provider, f.mint, newWallet.publicKey, 0);
+
+    const start = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .setupVesting(new anchor.BN(start), new anchor.BN(start + duration), new anchor.BN(total), new anchor.BN(0), new anchor.BN(0))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to transfer the position to the current beneficiary", async () => {
+    try {
+      await transferPosition(f.owner.publicKey);
+      expect.fail("The beneficiary is unchanged.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SameBeneficiary");
+    }
+  });
+
+  it("The new wallet receives exactly what was left unclaimed", async () => {
+    await delay(2000);
+    await claim(f.owner, f.ownerTokenAccount);
+    const claimedBefore = (await program.account.vault.fetch(f.vault)).alreadyClaimed.toNumber();
+    expect(claimedBefore).to.be.greaterThan(0);
+
+    const tx = await transferPosition(newWallet.publicKey);
+    const [transferred] = (await eventsFromTx(program, provider, tx)).filter(
+      (e) => e.name === "vestingPositionTransferred"
+    );
+    expect(transferred.data.remainingClaimable.toNumber()).to.equal(total - claimedBefore);
+
+    await delay((duration + 1) * 1000);
+    await claim(newWallet, newWalletTokenAccount);
+    expect((await getAccount(provider.connection, newWalletTokenAccount)).amount.toString()).to.equal(
+      (total - claimedBefore).toString()
+    );
+  });
+
+  it("FAIL to claim into the old beneficiary's token account", async () => {
+    try {
+      await claim(f.owner, f.ownerTokenAccount);
+      expect.fail("The old token account is no longer authorized.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintTokenOwner");
+    }
+  });
+});
+
+describe("token_vault: partial lock", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    await program.methods
+      .lockVault(new anchor.BN(now + 4), new anchor.BN(60 * LAMPORTS_PER_TOKEN))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Withdraws the unlocked 40 tokens", async () => {
+    await withdrawFromVault(program, f, 40 * LAMPORTS_PER_TOKEN);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (60 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL to dip into the locked 60 tokens", async () => {
+    try {
+      await withdrawFromVault(program, f, 1);
+      expect.fail("The remaining balance is locked.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WithdrawWouldViolateLock");
+    }
+  });
+
+  it("Withdraws the locked 60 tokens once the lock expires", async () => {
+    await delay(5000);
+    await withdrawFromVault(program, f, 60 * LAMPORTS_PER_TOKEN);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal("0");
+  });
+});
+
+describe("token_vault: proof of reserve", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let auditor: Keypair;
+
+  const proofPda = (nonce: number) => {
+    const nonceBytes = Buffer.alloc(4);
+    nonceBytes.writeUInt32LE(nonce);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("proof"), f.vault.toBuffer(), nonceBytes],
+      program.programId
+    )[0];
+  };
+
+  const generateProof = (nonce: number) =>
+    program.methods
+      .generateProofOfReserve(nonce)
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        proofOfReserve: proofPda(nonce),
+        payer: auditor.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([auditor])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    auditor = await createFundedKeypair(provider);
+  });
+
+  it("Keeps two sequential proofs as distinct snapshots", async () => {
+    await depositToVault(program, f, 30 * LAMPORTS_PER_TOKEN);
+    await generateProof(0);
+    await depositToVault(program, f, 20 * LAMPORTS_PER_TOKEN);
+    await generateProof(1);
+
+    const first = await program.account.proofOfReserve.fetch(proofPda(0));
+    const second = await program.account.proofOfReserve.fetch(proofPda(1));
+    expect(first.vault.toBase58()).to.equal(f.vault.toBase58());
+    expect(first.tokenBalance.toString()).to.equal((30 * LAMPORTS_PER_TOKEN).toString());
+    expect(second.tokenBalance.toString()).to.equal((50 * LAMPORTS_PER_TOKEN).toString());
+    expect(second.snapshotSlot.toNumber()).to.be.greaterThan(first.snapshotSlot.toNumber());
+    expect((await program.account.vault.fetch(f.vault)).proofCount).to.equal(2);
+  });
+
+  it("FAIL to skip ahead in the nonce sequence", async () => {
+    try {
+      await generateProof(5);
+      expect.fail("Nonce 5 is not next.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ProofNonceMismatch");
+    }
+  });
+});
+
+describe("token_vault: tips", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let tipper: Keypair;
+  let tipperTokenAccount: PublicKey;
+
+  const tip = (amount: number) =>
+    program.methods
+      .tip(new anchor.BN(amount))
+      .accounts({
+        vault: f.vault,
+        tipperTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        tipper: tipper.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .signers([tipper])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    tipper = await createFundedKeypair(provider);
+    tipperTokenAccount = await createFundedTokenAccount(provider, f.mint, tipper.publicKey, 50 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Lets a third party tip the vault", async () => {
+    const sig = await tip(15 * LAMPORTS_PER_TOKEN);
+
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (15 * LAMPORTS_PER_TOKEN).toString()
+    );
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.totalReceivedTips.toString()).to.equal((15 * LAMPORTS_PER_TOKEN).toString());
+
+    const events = (await eventsFromTx(program, provider, sig)).filter((e) => e.name === "vaultTipped");
+    expect(events).to.have.length(1);
+    expect(events[0].data.tipper.toBase58()).to.equal(tipper.publicKey.toBase58());
+  });
+
+  it("FAIL to tip zero tokens", async () => {
+    try {
+      await tip(0);
+      expect.fail("Zero tips are rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("TipAmountZero");
+    }
+  });
+});
+
+describe("token_vault: maximum lock duration", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const maxLockDurationSecs = 3600;
+
+  const lockFor = (durationSecs: number) =>
+    program.methods
+      .lockVaultForDuration(new anchor.BN(durationSecs))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await program.methods
+      .setMaxLockDuration(new anchor.BN(maxLockDurationSecs))
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), protocolAdmin: provider.wallet.publicKey })
+      .rpc();
+  });
+
+  it("FAIL: the vault owner cannot change the bound", async () => {
+    try {
+      await program.methods
+        .setMaxLockDuration(new anchor.BN(0))
+        .accounts({ vault: f.vault, programConfig: programConfigPda(program), protocolAdmin: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Only the protocol admin sets the bound.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+  });
+
+  it("FAIL to lock for one second longer than allowed", async () => {
+    try {
+      await lockFor(maxLockDurationSecs + 1);
+      expect.fail("Lock exceeds the maximum duration.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("LockDurationTooLong");
+    }
+  });
+
+  it("Locks for exactly the maximum duration", async () => {
+    await lockFor(maxLockDurationSecs);
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.true;
+  });
+});
+
+describe("token_vault: arithmetic overflow", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const U64_MAX = new anchor.BN("18446744073709551615");
+  let f: VaultFixture;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { maxCapacity: 1000 * LAMPORTS_PER_TOKEN });
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL: a u64::MAX deposit overflows the capacity check instead of wrapping", async () => {
+    try {
+      await depositToVault(program, f, U64_MAX);
+      expect.fail("The balance would overflow.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ArithmeticOverflow");
+    }
+  });
+});
+
+describe("token_vault: migration to a new program", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  // The round trip needs a second deployment under another program id, which this
+  // single-program workspace does not provide. These tests cover the target validation.
+  let f: VaultFixture;
+
+  const migrate = (newProgram: PublicKey, newProgramId: PublicKey = newProgram) => {
+    const newVaultTokenAccount = Keypair.generate();
+    const [newVault] = PublicKey.findProgramAddressSync([Buffer.from("vault"), f.owner.publicKey.toBuffer()], newProgramId);
+    const [newVaultAuthority] = PublicKey.findProgramAddressSync([Buffer.from("authority"), newVault.toBuffer()], newProgramId);
+    const [newProgramConfig] = PublicKey.findProgramAddressSync([Buffer.from("program_config")], newProgramId);
+    return program.methods
+      .closeAndMigrateToNewVault(newProgramId)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        newProgram,
+        newVault,
+        newVaultAuthority,
+        newVaultTokenAccount: newVaultTokenAccount.publicKey,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+        programConfig: programConfigPda(program),
+        newProgramConfig,
+        instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .signers([f.owner, newVaultTokenAccount])
+      .rpc();
+  };
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to migrate into the same program", async () => {
+    try {
+      await migrate(program.programId);
+      expect.fail("The target must be a different program.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MigrationTargetInvalid");
+    }
+  });
+
+  it("FAIL to migrate into an account that is not a program", async () => {
+    try {
+      await migrate(Keypair.generate().publicKey);
+      expect.fail("The target must be executable.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MigrationTargetInvalid");
+    }
+  });
+
+  it("FAIL when the passed program does not match the requested id", async () => {
+    try {
+      await migrate(TOKEN_PROGRAM_ID, Keypair.generate().publicKey);
+      expect.fail("Program account and id must agree.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MigrationTargetInvalid");
+    }
+    // The vault is untouched
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (10 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL to migrate into a program the protocol admin has not approved", async () => {
+    try {
+      await migrate(TOKEN_PROGRAM_ID);
+      expect.fail("Only approved targets may receive vaults.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MigrationTargetNotApproved");
+    }
+  });
+
+  it("FAIL to migrate a locked vault into an approved program", async () => {
+    const approve = (approved: boolean) =>
+      program.methods
+        .setMigrationTargetApproval(TOKEN_PROGRAM_ID, approved)
+        .accounts({ programConfig: programConfigPda(program), protocolAdmin: provider.wallet.publicKey })
+        .rpc();
+    await approve(true);
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey, programConfig: programConfigPda(program) })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await migrate(TOKEN_PROGRAM_ID);
+      expect.fail("The time lock holds the tokens.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+    } finally {
+      await approve(false);
+    }
+  });
+
+  it("FAIL to hand this program a migrated vault outside a CPI from an approved source", async () => {
+    // The state is read back from a live vault, a direct call could otherwise forge any of it
+    const owner = await createFundedKeypair(provider);
+    const state = { ...(await program.account.vault.fetch(f.vault)), authority: owner.publicKey };
+    const [vault] = PublicKey.findProgramAddressSync([Buffer.from("vault"), owner.publicKey.toBuffer()], program.programId);
+    const [vaultAuthority] = PublicKey.findProgramAddressSync([Buffer.from("authority"), vault.toBuffer()], program.programId);
+    const tokenAccount = Keypair.generate();
+    const approve = (approved: boolean) =>
+      program.methods
+        .setMigrationSourceApproval(program.programId, approved)
+        .accounts({ programConfig: programConfigPda(program), protocolAdmin: provider.wallet.publicKey })
+        .rpc();
+
+    // Even with this program approved as a source, a top-level call is not a migration
+    await approve(true);
+    try {
+      await program.methods
+        .initializeVaultFromMigration(state)
+        .accounts({
+          vault,
+          vaultAuthority,
+          tokenAccount: tokenAccount.publicKey,
+          mint: f.mint,
+          authority: owner.publicKey,
+          tokenProgram: f.tokenProgram,
+          systemProgram: SystemProgram.programId,
+          programConfig: programConfigPda(program),
+          instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .signers([owner, tokenAccount])
+        .rpc();
+      expect.fail("The vault must come from an approved program's CPI.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MigrationSourceNotApproved");
+    } finally {
+      await approve(false);
+    }
+  });
+});
+
+describe("token_vault: health check", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  const healthCheck = (vaultTokenAccount: PublicKey = f.vaultTokenAccount) =>
+    program.methods.vaultHealthCheck().accounts({ vault: f.vault, vaultTokenAccount }).rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Reports a healthy vault", async () => {
+    await healthCheck();
+  });
+
+  it("Reports a healthy indexed vault", async () => {
+    const owner = await createFundedKeypair(provider);
+    const indexed = await createVaultFixture(program, provider, { owner, vaultIndex: 1 });
+    await program.methods
+      .vaultHealthCheck()
+      .accounts({ vault: indexed.vault, vaultTokenAccount: indexed.vaultTokenAccount })
+      .rpc();
+  });
+
+  it("FAIL when checked against a token account the vault does not own", async () => {
+    try {
+      await healthCheck(f.ownerTokenAccount);
+      expect.fail("The token account is not the vault's.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvariantViolation");
+    }
+  });
+
+  it("FAIL once today's withdrawals exceed a newly lowered daily limit", async () => {
+    await withdrawFromVault(program, f, 50 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .updateLimits(new anchor.BN(10 * LAMPORTS_PER_TOKEN))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await healthCheck();
+      expect.fail("current_day_withdrawn is above the limit.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvariantViolation");
+    }
+  });
+});
+
+describe("token_vault: program version", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const VAULT_VERSION = 1;
+
+  it("Returns the program version", async () => {
+    expect(await program.methods.vaultVersion().accounts({ programConfig: programConfigPda(program) }).view()).to.equal(VAULT_VERSION);
+  });
+
+  it("New vaults start at schema version 1", async () => {
+    const f = await createVaultFixture(program, provider);
+    expect((await program.account.vault.fetch(f.vault)).schemaVersion).to.equal(1);
+  });
+});
+
+describe("token_vault: large withdrawal queue", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const threshold = 50 * LAMPORTS_PER_TOKEN;
+  const cooloffSecs = 3;
+
+  const requestPda = (seq: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("wr"), f.vault.toBuffer(), new anchor.BN(seq).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const fulfill = (seq: number) =>
+    program.methods
+      .fulfillWithdrawalRequest(new anchor.BN(seq))
+      .accounts({
+        vault: f.vault,
+        withdrawalRequest: requestPda(seq),
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        requesterTokenAccount: f.ownerTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        programConfig: programConfigPda(program),
+        feeTreasuryTokenAccount: null,
+        destinationEntry: null,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 200 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .setLargeWithdrawalThreshold(new anchor.BN(threshold), new anchor.BN(cooloffSecs))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Small withdrawals still use the normal path", async () => {
+    await withdrawFromVault(program, f, threshold);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (150 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+
+  it("FAIL to withdraw above the threshold directly", async () => {
+    try {
+      await withdrawFromVault(program, f, threshold + 1);
+      expect.fail("Large withdrawals must be queued.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UseWithdrawalQueue");
+    }
+  });
+
+  it("Queues a large withdrawal and pays it after the cool-off", async () => {
+    const amount = 100 * LAMPORTS_PER_TOKEN;
+    await program.methods
+      .requestLargeWithdrawal(new anchor.BN(0), new anchor.BN(amount))
+      .accounts({
+        vault: f.vault,
+        withdrawalRequest: requestPda(0),
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+
+    try {
+      await fulfill(0);
+      expect.fail("The cool-off has not elapsed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WithdrawalNotReady");
+    }
+
+    await delay((cooloffSecs + 1) * 1000);
+    await fulfill(0);
+
+    const request = await program.account.withdrawalRequest.fetch(requestPda(0));
+    expect(request.fulfilled).to.be.true;
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (50 * LAMPORTS_PER_TOKEN).toString()
+    );
+
+    try {
+      await fulfill(0);
+      expect.fail("Requests pay out once.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WithdrawalRequestFulfilled");
+    }
+  });
+});
+
+describe("token_vault: compliance redistribution", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let officer: Keypair;
+  let seizureAccount: PublicKey;
+  const reasonHash = Array.from(createHash("sha256").update("court order #42").digest());
+
+  const redistribute = (signer: Keypair, amount: number) =>
+    program.methods
+      .revokeAndRedistribute(seizureAccount, new anchor.BN(amount), reasonHash)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        beneficiaryTokenAccount: seizureAccount,
+        complianceOfficer: signer.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .signers([signer])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    officer = await createFundedKeypair(provider);
+    seizureAccount = await createFundedTokenAccount(provider, f.mint, officer.publicKey, 0);
+  });
+
+  it("FAIL before a compliance officer is appointed", async () => {
+    try {
+      await redistribute(officer, LAMPORTS_PER_TOKEN);
+      expect.fail("No officer is configured.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ComplianceOfficerNotConfigured");
+    }
+  });
+
+  it("FAIL: the vault owner cannot appoint the compliance officer", async () => {
+    try {
+      await program.methods
+        .setComplianceOfficer(f.owner.publicKey)
+        .accounts({ vault: f.vault, programConfig: programConfigPda(program), admin: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Only the protocol admin may appoint an officer.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+  });
+
+  it("FAIL: the vault owner is not the compliance officer", async () => {
+    await program.methods
+      .setComplianceOfficer(officer.publicKey)
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), admin: provider.wallet.publicKey })
+      .rpc();
+    try {
+      await redistribute(f.owner, LAMPORTS_PER_TOKEN);
+      expect.fail("Only the officer may redistribute.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAccess");
+    }
+  });
+
+  it("Officer moves funds, freezes the vault and records the reason", async () => {
+    const sig = await redistribute(officer, 40 * LAMPORTS_PER_TOKEN);
+
+    expect((await getAccount(provider.connection, seizureAccount)).amount.toString()).to.equal(
+      (40 * LAMPORTS_PER_TOKEN).toString()
+    );
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.true;
+    const events = (await eventsFromTx(program, provider, sig)).filter((e) => e.name === "complianceAction");
+    expect(events).to.have.length(1);
+    expect(Array.from(events[0].data.reasonHash)).to.deep.equal(reasonHash);
+
+    try {
+      await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("The vault is frozen.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+    }
+  });
+});
+
+describe("token_vault: minimum lock balance", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  const lock = async () => {
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) as number;
+    return program.methods
+      .lockVault(new anchor.BN(now + 60), new anchor.BN(0))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+  };
+
+  before(async () => {
+    f = await createVaultFixture(program, provider, { minimumLockBalance: 10 * LAMPORTS_PER_TOKEN });
+  });
+
+  it("FAIL to lock an empty vault", async () => {
+    try {
+      await lock();
+      expect.fail("The vault holds nothing to lock.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BalanceTooLowToLock");
+    }
+  });
+
+  it("Locks once the balance reaches the minimum", async () => {
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    await lock();
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.be.true;
+  });
+});
+
+describe("token_vault: yield strategy", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  // No yield protocol is deployed on the local validator, so the harvest CPI itself is not exercised
+  let f: VaultFixture;
+  let yieldStrategy: PublicKey;
+  let yieldTokenAccount: PublicKey;
+  const strategyProgram = Keypair.generate().publicKey;
+  const stakeAccount = Keypair.generate().publicKey;
+
+  const harvest = (strategy: PublicKey | null) =>
+    program.methods
+      .harvestYield()
+      .accounts({
+        vault: f.vault,
+        yieldStrategy: strategy,
+        vaultAuthority: f.vaultAuthority,
+        yieldTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        programConfig: programConfigPda(program),
+        strategyProgram,
+        stakeAccount,
+        cranker: provider.wallet.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .rpc();
+
+  const reinvest = () =>
+    program.methods
+      .reinvestYield()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        yieldTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        cranker: provider.wallet.publicKey,
+        tokenProgram: f.tokenProgram,
+      })
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [yieldStrategy] = PublicKey.findProgramAddressSync([Buffer.from("strategy"), f.vault.toBuffer()], program.programId);
+    [yieldTokenAccount] = PublicKey.findProgramAddressSync([Buffer.from("yield"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .setStrategyProgramApproval(strategyProgram, true)
+      .accounts({ programConfig: programConfigPda(program), protocolAdmin: provider.wallet.publicKey })
+      .rpc();
+    await program.methods
+      .initializeYieldStrategy(strategyProgram, stakeAccount)
+      .accounts({
+        programConfig: programConfigPda(program),
+        vault: f.vault,
+        yieldStrategy,
+        vaultAuthority: f.vaultAuthority,
+        yieldTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to attach a yield protocol the protocol admin has not approved", async () => {
+    const other = await createVaultFixture(program, provider);
+    const [otherStrategy] = PublicKey.findProgramAddressSync([Buffer.from("strategy"), other.vault.toBuffer()], program.programId);
+    const [otherYieldTokenAccount] = PublicKey.findProgramAddressSync([Buffer.from("yield"), other.vault.toBuffer()], program.programId);
+    try {
+      await program.methods
+        .initializeYieldStrategy(Keypair.generate().publicKey, stakeAccount)
+        .accounts({
+          programConfig: programConfigPda(program),
+          vault: other.vault,
+          yieldStrategy: otherStrategy,
+          vaultAuthority: other.vaultAuthority,
+          yieldTokenAccount: otherYieldTokenAccount,
+          vaultTokenAccount: other.vaultTokenAccount,
+          mint: other.mint,
+          authority: other.owner.publicKey,
+          tokenProgram: other.tokenProgram,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([other.owner])
+        .rpc();
+      expect.fail("Only approved yield protocols may be attached.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("StrategyProgramNotApproved");
+    }
+  });
+
+  it("Records the strategy", async () => {
+    const strategy = await program.account.yieldStrategy.fetch(yieldStrategy);
+    expect(strategy.strategyProgram.toBase58()).to.equal(strategyProgram.toBase58());
+    expect(strategy.stakeAccount.toBase58()).to.equal(stakeAccount.toBase58());
+    expect(strategy.lastHarvest.toNumber()).to.equal(0);
+  });
+
+  it("FAIL to harvest without the strategy account", async () => {
+    try {
+      await harvest(null);
+      expect.fail("The strategy is required.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("YieldStrategyNotConfigured");
+    }
+  });
+
+  it("FAIL to reinvest before anything was harvested", async () => {
+    try {
+      await reinvest();
+      expect.fail("The yield account is empty.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NothingToClaim");
+    }
+  });
+
+  it("Reinvests yield paid into the yield account", async () => {
+    // Stand in for a harvest by paying yield straight into the yield account
+    await transfer(provider.connection, f.owner, f.ownerTokenAccount, yieldTokenAccount, f.owner, 5 * LAMPORTS_PER_TOKEN);
+    await reinvest();
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (5 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+});
+
+describe("token_vault: withdrawal simulation", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let treasury: PublicKey;
+
+  const simulate = (amount: number) =>
+    program.methods
+      .simulateWithdraw(new anchor.BN(amount))
+      .accounts({
+        vault: f.vault,
+        programConfig: programConfigPda(program),
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        userTokenAccount: f.ownerTokenAccount,
+        destinationEntry: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+      })
+      .view();
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, null, DECIMAL_PLACES);
+    treasury = await createFundedTokenAccount(provider, mint, Keypair.generate().publicKey, 0);
+    // 5% out
+    f = await createVaultFixture(program, provider, { mint, withdrawFeeBps: 500, feeTreasury: treasury });
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Predicts the net amount the real withdrawal pays", async () => {
+    const amount = 40 * LAMPORTS_PER_TOKEN;
+    const simulation = await simulate(amount);
+    expect(simulation.willSucceed).to.be.true;
+    expect(simulation.failureReason).to.be.null;
+    expect(simulation.feeDeducted.toString()).to.equal((2 * LAMPORTS_PER_TOKEN).toString());
+
+    const before = (await getAccount(provider.connection, f.ownerTokenAccount)).amount;
+    await withdrawFromVault(program, f, amount, treasury);
+    const after = (await getAccount(provider.connection, f.ownerTokenAccount)).amount;
+    expect((after - before).toString()).to.equal(simulation.netAmount.toString());
+  });
+
+  it("Reports why an oversized withdrawal would fail", async () => {
+    const simulation = await simulate(1000 * LAMPORTS_PER_TOKEN);
+    expect(simulation.willSucceed).to.be.false;
+    const insufficientFunds = program.idl.errors.find((e) => e.name.toLowerCase() === "insufficientfunds");
+    expect(simulation.failureReason).to.equal(insufficientFunds.code - 6000);
+  });
+
+  it("Reports a destination the whitelist has not approved", async () => {
+    await program.methods
+      .enableDestinationWhitelist()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    const simulation = await simulate(LAMPORTS_PER_TOKEN);
+    expect(simulation.willSucceed).to.be.false;
+    const notApproved = program.idl.errors.find((e) => e.name.toLowerCase() === "destinationnotapproved");
+    expect(simulation.failureReason).to.equal(notApproved.code - 6000);
+  });
+});
+
+describe("token_vault: frozen vault token account", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  const freezeAccounts = () => ({
+    vault: f.vault,
+    vaultAuthority: f.vaultAuthority,
+    vaultTokenAccount: f.vaultTokenAccount,
+    mint: f.mint,
+    authority: f.owner.publicKey,
+    tokenProgram: f.tokenProgram,
+  });
+
+  before(async () => {
+    // The mint's freeze authority has to be the vault authority PDA, so derive it up front
+    const owner = await createFundedKeypair(provider);
+    const [vault] = PublicKey.findProgramAddressSync([Buffer.from("vault"), owner.publicKey.toBuffer()], program.programId);
+    const [vaultAuthority] = PublicKey.findProgramAddressSync([Buffer.from("authority"), vault.toBuffer()], program.programId);
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, vaultAuthority, DECIMAL_PLACES);
+
+    f = await createVaultFixture(program, provider, { owner, mint });
+    await depositToVault(program, f, 50 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Blocks deposits and withdrawals while frozen", async () => {
+    await program.methods.freezeVaultTokenAccount().accounts(freezeAccounts()).signers([f.owner]).rpc();
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).isFrozen).to.be.true;
+
+    for (const attempt of [() => depositToVault(program, f, LAMPORTS_PER_TOKEN), () => withdrawFromVault(program, f, LAMPORTS_PER_TOKEN)]) {
+      try {
+        await attempt();
+        expect.fail("The token account is frozen.");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("TokenAccountFrozen");
+      }
+    }
+  });
+
+  it("Allows transfers again after thawing", async () => {
+    await program.methods.unfreezeVaultTokenAccount().accounts(freezeAccounts()).signers([f.owner]).rpc();
+    await withdrawFromVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(
+      (40 * LAMPORTS_PER_TOKEN).toString()
+    );
+  });
+});
+
+describe("token_vault: deposit with SOL wrap", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const depositLamports = anchor.web3.LAMPORTS_PER_SOL;
+
+  const wrapAccounts = (fixture: VaultFixture) => ({
+    vault: fixture.vault,
+    userTokenAccount: fixture.ownerTokenAccount,
+    vaultTokenAccount: fixture.vaultTokenAccount,
+    authority: fixture.owner.publicKey,
+    tokenProgram: fixture.tokenProgram,
+    mint: fixture.mint,
+    feeTreasuryTokenAccount: null,
+    vaultStats: null,
+    depositorRecord: null,
+    whitelistEntry: null,
+    programConfig: programConfigPda(program),
+    vaultAuthority: null,
+    lpMint: null,
+    userLpTokenAccount: null,
+    referralRecord: null,
+    roleConfig: null,
+    memoProgram: null,
+    depositorRateLimit: null,
+  });
+
+  before(async () => {
+    // A wSOL vault on the SPL path, the owner's temporary wSOL account starts empty
+    f = await createVaultFixture(program, provider, { mint: NATIVE_MINT, mintAmount: 0 });
+  });
+
+  it("Wraps the owner's SOL and deposits it as wSOL", async () => {
+    const before = await provider.connection.getBalance(f.owner.publicKey);
+    await program.methods
+      .depositWithSolWrap(new anchor.BN(depositLamports), null, null, null)
+      .accounts(wrapAccounts(f))
+      .signers([f.owner])
+      .rpc();
+
+    const after = await provider.connection.getBalance(f.owner.publicKey);
+    expect(before - after).to.be.at.least(depositLamports);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal(depositLamports.toString());
+    const ownerTokenAccount = await getAccount(provider.connection, f.ownerTokenAccount);
+    expect(ownerTokenAccount.amount.toString()).to.equal("0");
+  });
+
+  it("Returns the SOL once the withdrawn wSOL is unwrapped", async () => {
+    await withdrawFromVault(program, f, depositLamports / 2);
+    const ownerTokenAccount = await getAccount(provider.connection, f.ownerTokenAccount);
+    expect(ownerTokenAccount.amount.toString()).to.equal((depositLamports / 2).toString());
+
+    // Closing the wSOL account unwraps it, the owner receives the lamports and the rent
+    const before = await provider.connection.getBalance(f.owner.publicKey);
+    await closeAccount(provider.connection, f.owner, f.ownerTokenAccount, f.owner.publicKey, f.owner);
+    const after = await provider.connection.getBalance(f.owner.publicKey);
+    expect(after - before).to.be.greaterThan(depositLamports / 2 - 10_000);
+  });
+
+  it("FAIL to wrap SOL into a vault with a non-native mint", async () => {
+    const spl = await createVaultFixture(program, provider);
+    try {
+      await program.methods
+        .depositWithSolWrap(new anchor.BN(depositLamports), null, null, null)
+        .accounts(wrapAccounts(spl))
+        .signers([spl.owner])
+        .rpc();
+      expect.fail("A non-native vault should reject wrapped SOL.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MintIsNotNative");
+    }
+  });
+});
+
+describe("token_vault: deposit clawback window", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const windowSecs = 3;
+  const amount = 10 * LAMPORTS_PER_TOKEN;
+
+  const clawback = (
+    fixture: VaultFixture,
+    value: number,
+    depositor: Keypair = fixture.owner,
+    depositorTokenAccount: PublicKey = fixture.ownerTokenAccount
+  ) =>
+    program.methods
+      .clawbackDeposit(new anchor.BN(value))
+      .accounts({
+        vault: fixture.vault,
+        vaultAuthority: fixture.vaultAuthority,
+        vaultTokenAccount: fixture.vaultTokenAccount,
+        mint: fixture.mint,
+        depositorTokenAccount,
+        depositor: depositor.publicKey,
+        tokenProgram: fixture.tokenProgram,
+        vaultStats: null,
+        depositorRecord: null,
+        lpMint: null,
+        depositorLpTokenAccount: null,
+      })
+      .signers([depositor])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await program.methods
+      .setDepositClawbackWindow(new anchor.BN(windowSecs))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Claws back a deposit inside the window, even while the vault is locked", async () => {
+    await depositToVault(program, f, amount);
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .lockVault(new anchor.BN(now + 60), new anchor.BN(0))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+
+    await clawback(f, amount);
+
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal("0");
+    // The deposit no longer counts towards the raw deposited amount
+    const vaultAccount = await program.account.vault.fetch(f.vault);
+    expect(vaultAccount.depositAmountOriginal.toNumber()).to.equal(0);
+  });
+
+  it("FAIL to claw back more than the latest deposit", async () => {
+    try {
+      await clawback(f, 1);
+      expect.fail("The deposit was already clawed back.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InsufficientFunds");
+    }
+  });
+
+  it("FAIL to claw back once the window has expired", async () => {
+    const g = await createVaultFixture(program, provider);
+    await program.methods
+      .setDepositClawbackWindow(new anchor.BN(windowSecs))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await depositToVault(program, g, amount);
+    await delay((windowSecs + 2) * 1000);
+
+    try {
+      await clawback(g, amount);
+      expect.fail("The clawback window should be closed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClawbackWindowExpired");
+    }
+  });
+
+  it("FAIL to claw back someone else's deposit", async () => {
+    const g = await createVaultFixture(program, provider);
+    await program.methods
+      .setDepositClawbackWindow(new anchor.BN(windowSecs))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await depositToVault(program, g, amount);
+    const stranger = await createFundedKeypair(provider);
+    const strangerTokenAccount = await createFundedTokenAccount(provider, g.mint, stranger.publicKey, 0);
+
+    try {
+      await clawback(g, amount, stranger, strangerTokenAccount);
+      expect.fail("Only the depositor can claw back.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAccess");
+    }
+  });
+
+  it("FAIL to withdraw the latest deposit while it can still be clawed back", async () => {
+    const g = await createVaultFixture(program, provider);
+    await program.methods
+      .setDepositClawbackWindow(new anchor.BN(60))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await depositToVault(program, g, amount);
+
+    try {
+      await withdrawFromVault(program, g, amount);
+      expect.fail("The deposit is still inside its clawback window.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DepositInClawbackWindow");
+    }
+  });
+
+  it("FAIL to claw back without the Vault Stats the deposit was counted in", async () => {
+    const g = await createVaultFixture(program, provider);
+    const [vaultStats] = PublicKey.findProgramAddressSync(
+      [Buffer.from("stats"), g.vault.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeVaultStats()
+      .accounts({ vault: g.vault, vaultStats, authority: g.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([g.owner])
+      .rpc();
+    await program.methods
+      .setDepositClawbackWindow(new anchor.BN(60))
+      .accounts({ vault: g.vault, authority: g.owner.publicKey })
+      .signers([g.owner])
+      .rpc();
+    await depositToVault(program, g, amount, null, vaultStats);
+
+    try {
+      await clawback(g, amount);
+      expect.fail("The Vault Stats PDA must be passed to roll the deposit back.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClawbackAccountsRequired");
+    }
+  });
+});
+
+describe("token_vault: custom locked message", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const message = "Your 1-year lockup expires on 2026-01-01";
+
+  const setMessage = (value: string) =>
+    program.methods
+      .setCustomLockedMessage(value)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Stores the message zero-padded in the vault", async () => {
+    await setMessage(message);
+
+    const vault = await program.account.vault.fetch(f.vault);
+    const stored = Buffer.from(vault.customLockedMessage);
+    expect(stored.length).to.equal(64);
+    expect(stored.subarray(0, message.length).toString("utf8")).to.equal(message);
+    expect(stored.subarray(message.length).every(b => b === 0)).to.be.true;
+  });
+
+  it("Logs the message when a withdrawal hits the time lock", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .lockVault(new anchor.BN(now + 60), new anchor.BN(0))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+
+    try {
+      await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("Locked vault should reject withdrawals.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultStillLocked");
+      expect(error.logs.join("\n")).to.include(message);
+    }
+  });
+
+  it("FAIL to set a message longer than 64 bytes", async () => {
+    try {
+      await setMessage("x".repeat(65));
+      expect.fail("Oversized message should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MessageTooLong");
+    }
+  });
+});
+
+describe("token_vault: merkle airdrop", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let recipients: Keypair[];
+  let tokenAccounts: PublicKey[];
+  let leaves: Buffer[];
+  const amounts = [1, 2, 3, 4].map((n) => n * LAMPORTS_PER_TOKEN);
+
+  // Sorted-pair keccak, matching 'merkle::verify_merkle_proof'
+  const hashPair = (a: Buffer, b: Buffer) =>
+    Buffer.from(keccak_256(Buffer.compare(a, b) <= 0 ? Buffer.concat([a, b]) : Buffer.concat([b, a])));
+
+  // Sibling path for each leaf of the four-leaf tree
+  const proofFor = (i: number) => {
+    const sibling = leaves[i ^ 1];
+    const otherPair = i < 2 ? hashPair(leaves[2], leaves[3]) : hashPair(leaves[0], leaves[1]);
+    return [sibling, otherPair].map((node) => [...node]);
+  };
+
+  const claim = (i: number, amount: number = amounts[i]) =>
+    program.methods
+      .claimAirdrop(new anchor.BN(amount), proofFor(i))
+      .accounts({
+        vault: f.vault,
+        airdropClaim: PublicKey.findProgramAddressSync(
+          [Buffer.from("airdrop_claim"), f.vault.toBuffer(), recipients[i].publicKey.toBuffer()],
+          program.programId
+        )[0],
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        mint: f.mint,
+        recipientTokenAccount: tokenAccounts[i],
+        recipient: recipients[i].publicKey,
+        tokenProgram: f.tokenProgram,
+        systemProgram: SystemProgram.programId,
+        programConfig: programConfigPda(program),
+      })
+      .signers([recipients[i]])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    const total = amounts.reduce((a, b) => a + b, 0);
+    await depositToVault(program, f, total);
+
+    recipients = await Promise.all([0, 1, 2, 3].map(() => createFundedKeypair(provider)));
+    tokenAccounts = await Promise.all(
+      recipients.map((r) => createFundedTokenAccount(provider, f.mint, r.publicKey, 0))
+    );
+
+    // Four-leaf tree over keccak(recipient || amount_le): root = H(H(l0, l1), H(l2, l3))
+    leaves = recipients.map((r, i) =>
+      Buffer.from(
+        keccak_256(Buffer.concat([r.publicKey.toBuffer(), new anchor.BN(amounts[i]).toArrayLike(Buffer, "le", 8)]))
+      )
+    );
+    const root = hashPair(hashPair(leaves[0], leaves[1]), hashPair(leaves[2], leaves[3]));
+    await program.methods
+      .initializeAirdrop([...root], new anchor.BN(total))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to claim more than the allocation", async () => {
+    try {
+      await claim(0, amounts[0] + 1);
+      expect.fail("The inflated amount is not in the tree.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MerkleProofInvalid");
+    }
+  });
+
+  it("Pays every recipient its allocation exactly once", async () => {
+    for (let i = 0; i < recipients.length; i++) {
+      await claim(i);
+      const account = await getAccount(provider.connection, tokenAccounts[i]);
+      expect(account.amount.toString()).to.equal(amounts[i].toString());
+    }
+
+    for (let i = 0; i < recipients.length; i++) {
+      try {
+        await claim(i);
+        expect.fail("A second claim should be rejected.");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("AlreadyClaimed");
+      }
+    }
+
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal("0");
+  });
+});
+
+describe("token_vault: global stats", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const globalStats = PublicKey.findProgramAddressSync(
+    [Buffer.from("global_stats"), program.programId.toBuffer()],
+    program.programId
+  )[0];
+  const globalStatsMeta = { pubkey: globalStats, isWritable: true, isSigner: false };
+  const amount = 10 * LAMPORTS_PER_TOKEN;
+  let vaults: VaultFixture[];
+
+  const depositWithStats = (f: VaultFixture, value: number) =>
+    program.methods
+      .deposit(new anchor.BN(value), null, null, null)
+      .accounts({
+        vault: f.vault,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: null,
+        depositorRateLimit: null,
+      })
+      .remainingAccounts([globalStatsMeta])
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    if ((await provider.connection.getAccountInfo(globalStats)) === null) {
+      await program.methods
+        .initializeGlobalStats()
+        .accounts({
+          programConfig: programConfigPda(program),
+          globalStats,
+          admin: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+    vaults = [await createVaultFixture(program, provider), await createVaultFixture(program, provider)];
+  });
+
+  it("Aggregates deposits across two vaults", async () => {
+    const before = await program.account.globalStats.fetch(globalStats);
+    for (const f of vaults) {
+      await depositWithStats(f, amount);
+    }
+
+    const after = await program.account.globalStats.fetch(globalStats);
+    expect(after.totalDepositEvents.sub(before.totalDepositEvents).toNumber()).to.equal(2);
+    expect(after.totalValueLockedApprox.sub(before.totalValueLockedApprox).toString()).to.equal(
+      (2 * amount).toString()
+    );
+  });
+
+  it("Subtracts withdrawals from the TVL", async () => {
+    const before = await program.account.globalStats.fetch(globalStats);
+    const f = vaults[0];
+    await program.methods
+      .withdraw(new anchor.BN(amount / 2), null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
+      })
+      .remainingAccounts([globalStatsMeta])
+      .signers([f.owner])
+      .rpc();
+
+    const after = await program.account.globalStats.fetch(globalStats);
+    expect(before.totalValueLockedApprox.sub(after.totalValueLockedApprox).toString()).to.equal((amount / 2).toString());
+    expect(after.totalDepositEvents.toString()).to.equal(before.totalDepositEvents.toString());
+  });
+
+  it("Leaves the stats alone when the account is not passed", async () => {
+    const before = await program.account.globalStats.fetch(globalStats);
+    await depositToVault(program, vaults[1], amount);
+
+    const after = await program.account.globalStats.fetch(globalStats);
+    expect(after.totalDepositEvents.toString()).to.equal(before.totalDepositEvents.toString());
+  });
+});
+
+describe("token_vault: canonical bump migration", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  const migrate = () =>
+    program.methods
+      .migrateToCanonicalBumps()
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    const owner = await createFundedKeypair(provider);
+    const [, canonicalBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    f = await createVaultFixture(program, provider, { owner, vaultBump: canonicalBump - 1 });
+  });
+
+  it("FAIL to use a vault stored with an off-canonical bump", async () => {
+    try {
+      await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("The stored bump does not derive the vault address.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintSeeds");
+    }
+  });
+
+  it("Migrates the stored bump to the canonical one", async () => {
+    await migrate();
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.bump).to.equal(f.vaultBump);
+    expect(vault.authorityBump).to.equal(f.authorityBump);
+    expect(vault.bumpsMigrated).to.be.true;
+
+    await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal(LAMPORTS_PER_TOKEN.toString());
+  });
+
+  it("Is a no-op once the bumps are canonical", async () => {
+    await migrate();
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.bump).to.equal(f.vaultBump);
+  });
+});
+
+describe("token_vault: fee treasury transfer", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let oldTreasury: PublicKey;
+  let newTreasury: PublicKey;
+  const balanceOf = async (account: PublicKey) => (await getAccount(provider.connection, account)).amount.toString();
+
+  const transferTreasury = (treasury: PublicKey) =>
+    program.methods
+      .transferFeeTreasury(treasury)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    oldTreasury = await createFundedTokenAccount(provider, mint, Keypair.generate().publicKey, 0);
+    newTreasury = await createFundedTokenAccount(provider, mint, Keypair.generate().publicKey, 0);
+    // 2% in
+    f = await createVaultFixture(program, provider, { mint, depositFeeBps: 200, feeTreasury: oldTreasury });
+  });
+
+  it("Proposes the new treasury behind a 48-hour timelock", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await transferTreasury(newTreasury);
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.feeTreasury.toBase58()).to.equal(oldTreasury.toBase58());
+    expect(vault.pendingTreasury.toBase58()).to.equal(newTreasury.toBase58());
+    expect(vault.treasuryTransferUnlocksAt.toNumber()).to.be.at.least(now + 48 * 3600 - 60);
+  });
+
+  it("FAIL to finalize the transfer during the timelock", async () => {
+    try {
+      await transferTreasury(newTreasury);
+      expect.fail("The timelock has not passed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("TreasuryTransferTimelockActive");
+    }
+  });
+
+  it("Keeps paying fees to the old treasury during the timelock", async () => {
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN, oldTreasury);
+
+    expect(await balanceOf(oldTreasury)).to.equal((2 * LAMPORTS_PER_TOKEN).toString());
+    expect(await balanceOf(newTreasury)).to.equal("0");
+  });
+
+  it("FAIL to skip the timelock through the fee config", async () => {
+    try {
+      await program.methods
+        .updateFeeConfig(200, 0, newTreasury)
+        .accounts({ vault: f.vault, authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Treasury rotation must wait out the timelock.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("TreasuryTransferTimelockActive");
+    }
+  });
+});
+
+describe("token_vault: deposit memos", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const MEMO_PROGRAM_ID = new PublicKey("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+  const threshold = 50 * LAMPORTS_PER_TOKEN;
+  let f: VaultFixture;
+
+  const depositWithMemo = (amount: number, memo: Buffer | null) =>
+    program.methods
+      .deposit(new anchor.BN(amount), null, null, memo)
+      .accounts({
+        vault: f.vault,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        whitelistEntry: null,
+        programConfig: programConfigPda(program),
+        vaultAuthority: null,
+        lpMint: null,
+        userLpTokenAccount: null,
+        referralRecord: null,
+        roleConfig: null,
+        memoProgram: memo ? MEMO_PROGRAM_ID : null,
+        depositorRateLimit: null,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await program.methods
+      .setMemoRequiredAbove(new anchor.BN(threshold))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Accepts small deposits without a memo", async () => {
+    await depositWithMemo(threshold, null);
+  });
+
+  it("FAIL to make a large deposit without a memo", async () => {
+    try {
+      await depositWithMemo(threshold + 1, null);
+      expect.fail("A memo is required above the threshold.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MemoRequired");
+    }
+  });
+
+  it("FAIL to make a large deposit with an empty memo", async () => {
+    try {
+      await depositWithMemo(threshold + 1, Buffer.alloc(0));
+      expect.fail("An empty memo does not count.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MemoRequired");
+    }
+  });
+
+  it("FAIL to attach a memo longer than 64 bytes", async () => {
+    try {
+      await depositWithMemo(threshold + 1, Buffer.from("x".repeat(65)));
+      expect.fail("Oversized memo should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MemoTooLong");
+    }
+  });
+
+  it("Records the memo of a large deposit", async () => {
+    const memo = Buffer.from("AML-REF-2026-0042");
+    const signature = await depositWithMemo(threshold + 1, memo);
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(Buffer.from(vault.lastMemoHash).toString("hex")).to.equal(Buffer.from(keccak_256(memo)).toString("hex"));
+
+    await provider.connection.confirmTransaction(signature, "confirmed");
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    expect(tx.meta.logMessages.join("\n")).to.include("AML-REF-2026-0042");
+  });
+});
+
+describe("token_vault: heartbeat", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+
+  const heartbeat = () =>
+    program.methods
+      .heartbeat()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+  });
+
+  it("Resets the inactivity timer and emits an event", async () => {
+    const before = (await program.account.vault.fetch(f.vault)).lastActivityTimestamp.toNumber();
+    await delay(1500);
+    const signature = await heartbeat();
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.lastActivityTimestamp.toNumber()).to.be.greaterThan(before);
+    expect(vault.lastHeartbeatTimestamp.toNumber()).to.equal(vault.lastActivityTimestamp.toNumber());
+
+    const events = (await eventsFromTx(program, provider, signature)).filter((e) => e.name === "heartbeatReceived");
+    expect(events).to.have.length(1);
+    expect(events[0].data.authority.toBase58()).to.equal(f.owner.publicKey.toBase58());
+  });
+
+  it("FAIL to send a heartbeat inside the cooldown", async () => {
+    await program.methods
+      .setHeartbeatMinInterval(new anchor.BN(3600))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    try {
+      await heartbeat();
+      expect.fail("The heartbeat cooldown is active.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("HeartbeatTooSoon");
+    }
+  });
+
+  it("FAIL to send a heartbeat as someone else", async () => {
+    const stranger = await createFundedKeypair(provider);
+    try {
+      await program.methods
+        .heartbeat()
+        .accounts({ vault: f.vault, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("Only the authority can send heartbeats.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+  });
+});
+
+describe("token_vault: per-depositor cap", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let depositorRecord: PublicKey;
+  const cap = 10 * LAMPORTS_PER_TOKEN;
+
+  const depositWithRecord = (amount: number) => depositToVault(program, f, amount, null, null, depositorRecord);
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    depositorRecord = PublicKey.findProgramAddressSync(
+      [Buffer.from("depositor"), f.vault.toBuffer(), f.owner.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    await program.methods
+      .setPerDepositorCap(new anchor.BN(cap))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to deposit without a depositor record while capped", async () => {
+    try {
+      await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("The cap is tracked by the depositor record.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DepositorRecordRequired");
+    }
+  });
+
+  it("Accepts deposits that approach the cap", async () => {
+    await depositWithRecord(4 * LAMPORTS_PER_TOKEN);
+    await depositWithRecord(4 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("FAIL to deposit past the cap", async () => {
+    try {
+      await depositWithRecord(3 * LAMPORTS_PER_TOKEN);
+      expect.fail("The deposit would exceed the cap.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("PerDepositorCapExceeded");
+    }
+  });
+
+  it("Accepts a deposit that lands exactly on the cap, and nothing after", async () => {
+    await depositWithRecord(2 * LAMPORTS_PER_TOKEN);
+    const record = await program.account.depositorRecord.fetch(depositorRecord);
+    expect(record.totalDeposited.toString()).to.equal(cap.toString());
+
+    try {
+      await depositWithRecord(1);
+      expect.fail("The cap has been reached.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("PerDepositorCapExceeded");
+    }
+  });
+});
+
+describe("token_vault: initialize with config", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let treasury: PublicKey;
+  let mint: PublicKey;
+  const config = {
+    minDepositAmount: new anchor.BN(LAMPORTS_PER_TOKEN),
+    maxCapacity: new anchor.BN(1000 * LAMPORTS_PER_TOKEN),
+    depositFeeBps: 100,
+    withdrawFeeBps: 200,
+    feeTreasury: PublicKey.default,
+    autoLockDurationSecs: new anchor.BN(60),
+    dailyWithdrawalLimit: new anchor.BN(50 * LAMPORTS_PER_TOKEN),
+    withdrawalCooldownSecs: new anchor.BN(30),
+    minLockDurationSecs: new anchor.BN(10),
+    maxLockDurationSecs: new anchor.BN(3600),
+  };
+
+  // Everything but the per-vault keys and timestamps
+  const configuredState = (vault: any) => ({
+    minDepositAmount: vault.minDepositAmount.toString(),
+    maxCapacity: vault.maxCapacity.toString(),
+    depositFeeBps: vault.depositFeeBps,
+    withdrawFeeBps: vault.withdrawFeeBps,
+    feeTreasury: vault.feeTreasury.toBase58(),
+    autoLockDurationSecs: vault.autoLockDurationSecs.toString(),
+    dailyWithdrawalLimit: vault.dailyWithdrawalLimit.toString(),
+    withdrawalCooldownSecs: vault.withdrawalCooldownSecs.toString(),
+    minLockDurationSecs: vault.minLockDurationSecs.toString(),
+    maxLockDurationSecs: vault.maxLockDurationSecs.toString(),
+    schemaVersion: vault.schemaVersion,
+    isLocked: vault.isLocked,
+  });
+
+  const initializeWithConfig = async (vaultConfig: typeof config) => {
+    const owner = await createFundedKeypair(provider);
+    const [vault, vaultBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    const [vaultAuthority, authorityBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), vault.toBuffer()],
+      program.programId
+    );
+    const tokenAccount = Keypair.generate();
+    await program.methods
+      .initializeVaultWithConfig(vaultBump, authorityBump, 0, vaultConfig)
+      .accounts({
+        vault,
+        vaultAuthority,
+        tokenAccount: tokenAccount.publicKey,
+        mint,
+        payer: owner.publicKey,
+        multisigConfig: null,
+        vaultRegistry: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([owner, tokenAccount])
+      .rpc();
+    return vault;
+  };
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    mint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, mintAuthority.publicKey, DECIMAL_PLACES);
+    treasury = await createFundedTokenAccount(provider, mint, Keypair.generate().publicKey, 0);
+    config.feeTreasury = treasury;
+  });
+
+  it("Produces the same vault as the individual instructions", async () => {
+    const f = await createVaultFixture(program, provider, {
+      mint,
+      minDepositAmount: config.minDepositAmount.toNumber(),
+      maxCapacity: config.maxCapacity.toNumber(),
+      depositFeeBps: config.depositFeeBps,
+      withdrawFeeBps: config.withdrawFeeBps,
+      feeTreasury: treasury,
+      minLockDurationSecs: config.minLockDurationSecs.toNumber(),
+    });
+    const asOwner = { vault: f.vault, authority: f.owner.publicKey };
+    await program.methods.setAutoLockDuration(config.autoLockDurationSecs).accounts(asOwner).signers([f.owner]).rpc();
+    await program.methods.updateLimits(config.dailyWithdrawalLimit).accounts(asOwner).signers([f.owner]).rpc();
+    await program.methods.setWithdrawalCooldown(config.withdrawalCooldownSecs).accounts(asOwner).signers([f.owner]).rpc();
+    await program.methods
+      .setMaxLockDuration(config.maxLockDurationSecs)
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), protocolAdmin: provider.wallet.publicKey })
+      .rpc();
+
+    const combined = await initializeWithConfig(config);
+
+    expect(configuredState(await program.account.vault.fetch(combined))).to.deep.equal(
+      configuredState(await program.account.vault.fetch(f.vault))
+    );
+  });
+
+  it("FAIL when the minimum deposit exceeds the capacity", async () => {
+    try {
+      await initializeWithConfig({ ...config, minDepositAmount: config.maxCapacity.addn(1) });
+      expect.fail("Inconsistent deposit bounds should be rejected.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidVaultConfig");
+    }
+  });
+});
+
+describe("token_vault: whitelist authority transfer", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let newAuthority: Keypair;
+  let ownerEntry: PublicKey;
+  const whitelistEntryPda = (depositor: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), f.vault.toBuffer(), depositor.toBuffer()],
+      program.programId
+    )[0];
+
+  const transferEntries = (
+    signer: Keypair,
+    depositors: PublicKey[],
+    entries: PublicKey[] = depositors.map(whitelistEntryPda)
+  ) =>
+    program.methods
+      .whitelistTransferAuthority(f.owner.publicKey, newAuthority.publicKey, depositors)
+      .accounts({ vault: f.vault, authority: signer.publicKey })
+      .remainingAccounts(entries.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false })))
+      .signers([signer])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    newAuthority = await createFundedKeypair(provider);
+    ownerEntry = whitelistEntryPda(f.owner.publicKey);
+
+    await program.methods
+      .enableWhitelist()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await program.methods
+      .addToWhitelist()
+      .accounts({
+        vault: f.vault,
+        candidate: f.owner.publicKey,
+        whitelistEntry: ownerEntry,
+        authority: f.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([f.owner])
+      .rpc();
+    await program.methods
+      .transferVaultAuthority(newAuthority.publicKey)
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to re-attribute the entries as the old authority", async () => {
+    try {
+      await transferEntries(f.owner, [f.owner.publicKey]);
+      expect.fail("Only the current authority can update the whitelist.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+  });
+
+  it("FAIL to update more than 20 entries at once", async () => {
+    const depositors = Array.from({ length: 21 }, () => Keypair.generate().publicKey);
+    try {
+      await transferEntries(newAuthority, depositors);
+      expect.fail("The batch is too large.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BulkUpdateTooLarge");
+    }
+  });
+
+  it("FAIL to re-attribute another vault's entry", async () => {
+    const other = await createVaultFixture(program, provider, { owner: newAuthority });
+    const [otherEntry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), other.vault.toBuffer(), f.owner.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .addToWhitelist()
+      .accounts({
+        vault: other.vault,
+        candidate: f.owner.publicKey,
+        whitelistEntry: otherEntry,
+        authority: newAuthority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([newAuthority])
+      .rpc();
+
+    try {
+      await transferEntries(newAuthority, [f.owner.publicKey], [otherEntry]);
+      expect.fail("The entry belongs to another vault.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("WhitelistEntryMismatch");
+    }
+  });
+
+  it("Re-attributes the entries to the new authority", async () => {
+    await transferEntries(newAuthority, [f.owner.publicKey]);
+
+    const entry = await program.account.whitelistEntry.fetch(ownerEntry);
+    expect(entry.addedBy.toBase58()).to.equal(newAuthority.publicKey.toBase58());
+    expect(entry.isActive).to.be.true;
+  });
+
+  it("Keeps depositor-seeded entries valid across the rotation", async () => {
+    // The old authority's own entry is keyed by its depositor key, so it still deposits
+    await depositToVault(program, f, LAMPORTS_PER_TOKEN, null, null, null, ownerEntry);
+  });
+
+  it("Enforces the whitelist with the new signer", async () => {
+    await program.methods
+      .removeFromWhitelist()
+      .accounts({
+        vault: f.vault,
+        candidate: f.owner.publicKey,
+        whitelistEntry: ownerEntry,
+        authority: newAuthority.publicKey,
+      })
+      .signers([newAuthority])
+      .rpc();
+
+    try {
+      await depositToVault(program, f, LAMPORTS_PER_TOKEN, null, null, null, ownerEntry);
+      expect.fail("The entry was removed by the new authority.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NotWhitelisted");
+    }
+  });
+});
+
+describe("token_vault: graceful shutdown", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const amount = 10 * LAMPORTS_PER_TOKEN;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, amount);
+  });
+
+  it("Enters shutdown mode and emits an event", async () => {
+    const signature = await program.methods
+      .enableShutdownMode()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    const events = (await eventsFromTx(program, provider, signature)).filter((e) => e.name === "shutdownModeEnabled");
+    expect(events).to.have.length(1);
+    expect((await program.account.vault.fetch(f.vault)).shutdownMode).to.be.true;
+  });
+
+  it("FAIL to deposit during shutdown", async () => {
+    try {
+      await depositToVault(program, f, amount);
+      expect.fail("A vault in shutdown should refuse deposits.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultInShutdown");
+    }
+  });
+
+  it("Keeps withdrawals working during shutdown", async () => {
+    await withdrawFromVault(program, f, amount / 2);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((amount / 2).toString());
+  });
+
+  it("Accepts deposits and withdrawals again once shutdown is disabled", async () => {
+    await program.methods
+      .disableShutdownMode()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    await depositToVault(program, f, amount);
+    await withdrawFromVault(program, f, amount);
+    const vaultTokenAccount = await getAccount(provider.connection, f.vaultTokenAccount);
+    expect(vaultTokenAccount.amount.toString()).to.equal((amount / 2).toString());
+  });
+});
+
+describe("token_vault: Token-2022 interest-bearing mints", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  const rateBps = 500; // 5% a year
+  const amount = 100 * LAMPORTS_PER_TOKEN;
+  let f: VaultFixture;
+
+  const interestBearingBalance = (fixture: VaultFixture) =>
+    program.methods
+      .getInterestBearingBalance()
+      .accounts({
+        vault: fixture.vault,
+        vaultTokenAccount: fixture.vaultTokenAccount,
+        mint: fixture.mint,
+        tokenProgram: fixture.tokenProgram,
+      })
+      .view();
+
+  before(async () => {
+    const mintAuthority = provider.wallet.payer as Keypair;
+    const mintKeypair = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.InterestBearingConfig]);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: mintAuthority.publicKey,
+          newAccountPubkey: mintKeypair.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeInterestBearingMintInstruction(
+          mintKeypair.publicKey,
+          mintAuthority.publicKey,
+          rateBps,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(mintKeypair.publicKey, DECIMAL_PLACES, mintAuthority.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [mintKeypair]
+    );
+
+    f = await createVaultFixture(program, provider, { mint: mintKeypair.publicKey, tokenProgram: TOKEN_2022_PROGRAM_ID });
+    await depositToVault(program, f, amount);
+  });
+
+  it("Tracks the deposited amount without interest", async () => {
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.depositAmountOriginal.toString()).to.equal(amount.toString());
+  });
+
+  it("Reports the balance including accrued interest", async () => {
+    await delay(2000);
+    const value = await interestBearingBalance(f);
+
+    // 5% a year accrues a few hundred base units per second on 100 tokens
+    expect(value.gt(new anchor.BN(amount))).to.be.true;
+    expect(value.lt(new anchor.BN(Math.floor(amount * 1.0001)))).to.be.true;
+  });
+
+  it("Reports the raw balance for mints without the extension", async () => {
+    const plain = await createVaultFixture(program, provider);
+    await depositToVault(program, plain, amount);
+    expect((await interestBearingBalance(plain)).toString()).to.equal(amount.toString());
+  });
+
+  it("Logs the interest earned on withdrawal and reduces the original amount", async () => {
+    const signature = await withdrawFromVault(program, f, amount / 2);
+    await provider.connection.confirmTransaction(signature, "confirmed");
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    expect(tx.meta.logMessages.join("\n")).to.include("Withdrawable incl. interest");
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.depositAmountOriginal.toString()).to.equal((amount / 2).toString());
+  });
+});
+
+describe("token_vault: rescue wrong token", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let wrongMint: PublicKey;
+  let wrongTokenAccount: PublicKey;
+  let rescueDestination: PublicKey;
+  const amount = 5 * LAMPORTS_PER_TOKEN;
+
+  const rescue = (from: PublicKey, mint: PublicKey, to: PublicKey, value: number) =>
+    program.methods
+      .rescueWrongToken(new anchor.BN(value))
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        vaultTokenAccount: f.vaultTokenAccount,
+        wrongTokenAccount: from,
+        wrongMint: mint,
+        rescueDestination: to,
+        authority: f.owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    // A wSOL vault, someone then sends a different token to an account of the vault authority
+    f = await createVaultFixture(program, provider, { mint: NATIVE_MINT, mintAmount: 0 });
+    const mintAuthority = provider.wallet.payer as Keypair;
+    wrongMint = await createMint(provider.connection, mintAuthority, mintAuthority.publicKey, null, DECIMAL_PLACES);
+    wrongTokenAccount = await createFundedTokenAccount(provider, wrongMint, f.vaultAuthority, amount);
+    rescueDestination = await createFundedTokenAccount(provider, wrongMint, f.owner.publicKey, 0);
+  });
+
+  it("FAIL to rescue the vault's own token", async () => {
+    try {
+      await rescue(f.vaultTokenAccount, NATIVE_MINT, f.ownerTokenAccount, 0);
+      expect.fail("The primary token cannot be rescued.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("CannotRescuePrimaryToken");
+    }
+  });
+
+  it("Recovers the wrong token to the destination", async () => {
+    await rescue(wrongTokenAccount, wrongMint, rescueDestination, amount);
+
+    expect((await getAccount(provider.connection, rescueDestination)).amount.toString()).to.equal(amount.toString());
+    expect((await getAccount(provider.connection, wrongTokenAccount)).amount.toString()).to.equal("0");
+  });
+});
+
+describe("token_vault: vault index", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let owner: Keypair;
+  let first: VaultFixture;
+  let second: VaultFixture;
+  const amount = 10 * LAMPORTS_PER_TOKEN;
+
+  before(async () => {
+    owner = await createFundedKeypair(provider);
+    first = await createVaultFixture(program, provider, { owner, vaultIndex: 0 });
+    second = await createVaultFixture(program, provider, { owner, vaultIndex: 1 });
+  });
+
+  it("Creates two vaults for the same authority", async () => {
+    expect(first.vault.toString()).to.not.equal(second.vault.toString());
+
+    const [legacyVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    expect(first.vault.toString()).to.equal(legacyVault.toString());
+
+    const vault = await program.account.vault.fetch(second.vault);
+    expect(vault.vaultIndex).to.equal(1);
+    expect(vault.originalAuthority.toString()).to.equal(owner.publicKey.toString());
+  });
+
+  it("Keeps deposits and locks independent", async () => {
+    await depositToVault(program, second, amount);
+    const unlockTime = Math.floor(Date.now() / 1000) + 3600;
+    await program.methods
+      .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
+      .accounts({
+        vault: second.vault,
+        vaultTokenAccount: second.vaultTokenAccount,
+        authority: owner.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([owner])
+      .rpc();
+
+    expect((await getAccount(provider.connection, first.vaultTokenAccount)).amount.toString()).to.equal("0");
+    expect((await getAccount(provider.connection, second.vaultTokenAccount)).amount.toString()).to.equal(amount.toString());
+    expect((await program.account.vault.fetch(first.vault)).isLocked).to.equal(false);
+    expect((await program.account.vault.fetch(second.vault)).isLocked).to.equal(true);
+
+    await depositToVault(program, first, amount);
+    await withdrawFromVault(program, first, amount);
+    expect((await getAccount(provider.connection, first.vaultTokenAccount)).amount.toString()).to.equal("0");
+  });
+});
+
+describe("token_vault: lock delegate", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let delegate: Keypair;
+  let delegateTokenAccount: PublicKey;
+  const amount = 10 * LAMPORTS_PER_TOKEN;
+
+  const lockAs = (signer: Keypair, unlockTime: number) =>
+    program.methods
+      .lockVault(new anchor.BN(unlockTime), new anchor.BN(0))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: signer.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([signer])
+      .rpc();
+
+  const unlockAs = (signer: Keypair) =>
+    program.methods
+      .authorityUnlockVault()
+      .accounts({ vault: f.vault, authority: signer.publicKey, programConfig: programConfigPda(program), unlockVote: null })
+      .signers([signer])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    delegate = await createFundedKeypair(provider);
+    delegateTokenAccount = await createFundedTokenAccount(provider, f.mint, delegate.publicKey, 0);
+    await depositToVault(program, f, amount);
+  });
+
+  it("FAIL to lock as a stranger", async () => {
+    try {
+      await lockAs(delegate, Math.floor(Date.now() / 1000) + 3);
+      expect.fail("No lock delegate has been set yet.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SignerCannotLock");
+    }
+  });
+
+  it("Lets the delegate lock once appointed", async () => {
+    await program.methods
+      .setLockDelegate(delegate.publicKey)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await lockAs(delegate, Math.floor(Date.now() / 1000) + 3);
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.isLocked).to.equal(true);
+    expect(vault.lockDelegate.toString()).to.equal(delegate.publicKey.toString());
+  });
+
+  it("FAIL to withdraw or unlock as the delegate", async () => {
+    try {
+      await program.methods
+        .withdraw(new anchor.BN(amount), null)
+        .accounts({
+          vault: f.vault,
+          vaultAuthority: f.vaultAuthority,
+          userTokenAccount: delegateTokenAccount,
+          vaultTokenAccount: f.vaultTokenAccount,
+          authority: delegate.publicKey,
+          tokenProgram: f.tokenProgram,
+          mint: f.mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          destinationEntry: null,
+          programConfig: programConfigPda(program),
+          lpMint: null,
+          userLpTokenAccount: null,
+          beneficiarySplit: null,
+          roleConfig: null,
+        })
+        .signers([delegate])
+        .rpc();
+      expect.fail("The lock delegate cannot touch the funds.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+
+    await delay(4000);
+    try {
+      await unlockAs(delegate);
+      expect.fail("The lock delegate cannot unlock.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+  });
+
+  it("Lets the authority override the lock and unlock", async () => {
+    await lockAs(f.owner, Math.floor(Date.now() / 1000) + 2);
+    await delay(3000);
+    await unlockAs(f.owner);
+
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.equal(false);
+    await withdrawFromVault(program, f, amount);
+  });
+
+  it("FAIL to shorten a running lock as the delegate", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await lockAs(delegate, now + 3600);
+    try {
+      await lockAs(delegate, now + 10);
+      expect.fail("A running lock can only be pushed out.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NewTimestampNotLater");
+    }
+    expect((await program.account.vault.fetch(f.vault)).unlockTimestamp.toNumber()).to.equal(now + 3600);
+  });
+});
+
+describe("token_vault: batch lock", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let fixtures: VaultFixture[];
+
+  const batchAccounts = (fs: VaultFixture[]) =>
+    fs.flatMap((f) => [
+      { pubkey: f.vault, isSigner: false, isWritable: true },
+      { pubkey: f.vaultTokenAccount, isSigner: false, isWritable: false },
+      { pubkey: f.owner.publicKey, isSigner: true, isWritable: false },
+    ]);
+
+  before(async () => {
+    fixtures = [];
+    for (let i = 0; i < 3; i++) {
+      fixtures.push(await createVaultFixture(program, provider));
+    }
+  });
+
+  it("FAIL when the timestamps do not match the vaults", async () => {
+    try {
+      await program.methods
+        .batchLockVaults([new anchor.BN(Math.floor(Date.now() / 1000) + 3600)])
+        .accounts({ programConfig: programConfigPda(program) })
+        .remainingAccounts(batchAccounts(fixtures))
+        .signers(fixtures.map((f) => f.owner))
+        .rpc();
+      expect.fail("Three vaults need three timestamps.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BatchLockMismatch");
+    }
+  });
+
+  it("FAIL the whole batch when one authority does not match", async () => {
+    const accounts = batchAccounts(fixtures);
+    accounts[8] = { pubkey: fixtures[0].owner.publicKey, isSigner: true, isWritable: false };
+    try {
+      await program.methods
+        .batchLockVaults(fixtures.map(() => new anchor.BN(Math.floor(Date.now() / 1000) + 3600)))
+        .accounts({ programConfig: programConfigPda(program) })
+        .remainingAccounts(accounts)
+        .signers(fixtures.slice(0, 2).map((f) => f.owner))
+        .rpc();
+      expect.fail("The third vault is not owned by the first owner.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SignerCannotLock");
+    }
+    expect((await program.account.vault.fetch(fixtures[0].vault)).isLocked).to.equal(false);
+  });
+
+  it("Locks three vaults in a single transaction", async () => {
+    const unlockTime = Math.floor(Date.now() / 1000) + 3600;
+    await program.methods
+      .batchLockVaults(fixtures.map((_, i) => new anchor.BN(unlockTime + i)))
+      .accounts({ programConfig: programConfigPda(program) })
+      .remainingAccounts(batchAccounts(fixtures))
+      .signers(fixtures.map((f) => f.owner))
+      .rpc();
+
+    for (const [i, f] of fixtures.entries()) {
+      const vault = await program.account.vault.fetch(f.vault);
+      expect(vault.isLocked).to.equal(true);
+      expect(vault.unlockTimestamp.toNumber()).to.equal(unlockTime + i);
+    }
+  });
+
+  it("FAIL to re-lock the batch with earlier unlock times", async () => {
+    try {
+      await program.methods
+        .batchLockVaults(fixtures.map(() => new anchor.BN(Math.floor(Date.now() / 1000) + 60)))
+        .accounts({ programConfig: programConfigPda(program) })
+        .remainingAccounts(batchAccounts(fixtures))
+        .signers(fixtures.map((f) => f.owner))
+        .rpc();
+      expect.fail("The running locks end later.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NewTimestampNotLater");
+    }
+  });
+
+  it("Checks each vault's balance against its minimum lock balance", async () => {
+    const g = await createVaultFixture(program, provider, { minimumLockBalance: 10 * LAMPORTS_PER_TOKEN });
+    const lockG = () =>
+      program.methods
+        .batchLockVaults([new anchor.BN(Math.floor(Date.now() / 1000) + 3600)])
+        .accounts({ programConfig: programConfigPda(program) })
+        .remainingAccounts(batchAccounts([g]))
+        .signers([g.owner])
+        .rpc();
+    try {
+      await lockG();
+      expect.fail("The vault holds less than its minimum lock balance.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BalanceTooLowToLock");
+    }
+
+    await depositToVault(program, g, 10 * LAMPORTS_PER_TOKEN);
+    await lockG();
+    expect((await program.account.vault.fetch(g.vault)).isLocked).to.equal(true);
+  });
+});
+
+describe("token_vault: guardian override unlock", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let guardians: Keypair[];
+  let recoveryConfig: PublicKey;
+
+  const unlockWithOverride = () =>
+    program.methods
+      .authorityUnlockVault()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey, programConfig: programConfigPda(program), unlockVote: null })
+      .remainingAccounts([{ pubkey: recoveryConfig, isSigner: false, isWritable: true }])
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    guardians = [await createFundedKeypair(provider), await createFundedKeypair(provider)];
+    [recoveryConfig] = PublicKey.findProgramAddressSync([Buffer.from("recovery"), f.vault.toBuffer()], program.programId);
+    await program.methods
+      .setupRecoveryConfig(guardians.map((g) => g.publicKey), 2)
+      .accounts({ vault: f.vault, recoveryConfig, authority: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+
+    // The guardians consent by proposing a recovery to the current authority
+    await program.methods
+      .initiateRecovery(f.owner.publicKey)
+      .accounts({ recoveryConfig, guardian: guardians[0].publicKey })
+      .signers([guardians[0]])
+      .rpc();
+  });
+
+  it("FAIL to override below the guardian threshold", async () => {
+    try {
+      await unlockWithOverride();
+      expect.fail("Only one of two guardians has approved.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("GuardianOverrideInsufficientApprovals");
+    }
+  });
+
+  it("Unlocks before the unlock timestamp once the guardians approve", async () => {
+    await program.methods
+      .approveRecovery()
+      .accounts({ recoveryConfig, guardian: guardians[1].publicKey })
+      .signers([guardians[1]])
+      .rpc();
+    const before = await program.account.vault.fetch(f.vault);
+    expect(before.unlockTimestamp.toNumber()).to.be.greaterThan(Math.floor(Date.now() / 1000));
+
+    const signature = await unlockWithOverride();
+
+    expect((await program.account.vault.fetch(f.vault)).isLocked).to.equal(false);
+    const event = (await eventsFromTx(program, provider, signature)).find((e) => e.name === "guardianOverrideUsed");
+    expect(event.data.guardiansWhoApproved.map((g) => g.toString())).to.deep.equal(
+      guardians.map((g) => g.publicKey.toString())
+    );
+
+    const config = await program.account.recoveryConfig.fetch(recoveryConfig);
+    expect(config.approvals).to.be.empty;
+  });
+});
+
+describe("token_vault: timelocked authority change", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+  const programConfig = programConfigPda(program);
+
+  let f: VaultFixture;
+  let newAuthority: Keypair;
+  const delaySecs = 2;
+
+  const setDelay = (secs: number) =>
+    program.methods
+      .setAuthorityChangeDelay(new anchor.BN(secs))
+      .accounts({ programConfig, admin: provider.wallet.publicKey })
+      .rpc();
+
+  const request = () =>
+    program.methods
+      .requestAuthorityChange(newAuthority.publicKey)
+      .accounts({ vault: f.vault, programConfig, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  const finalize = () =>
+    program.methods
+      .finalizeAuthorityChange()
+      .accounts({ vault: f.vault, pendingAuthority: newAuthority.publicKey })
+      .signers([newAuthority])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    newAuthority = await createFundedKeypair(provider);
+    await setDelay(delaySecs);
+  });
+
+  after(async () => {
+    await setDelay(0);
+  });
+
+  it("FAIL to transfer authority immediately while a delay is configured", async () => {
+    try {
+      await program.methods
+        .transferVaultAuthority(newAuthority.publicKey)
+        .accounts({ vault: f.vault, programConfig, authority: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Ownership changes are timelocked.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("AuthorityChangeTimelockActive");
+    }
+  });
+
+  it("Cancels a requested change", async () => {
+    await request();
+    await program.methods
+      .cancelAuthorityChange()
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.pendingAuthority.toString()).to.equal(PublicKey.default.toString());
+    try {
+      await finalize();
+      expect.fail("Nothing is pending after the cancel.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoPendingAuthorityChange");
+    }
+  });
+
+  it("FAIL to finalize before the delay", async () => {
+    await request();
+    try {
+      await finalize();
+      expect.fail("The delay has not passed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("AuthorityChangeTimelockActive");
+    }
+  });
+
+  it("Hands the vault to the new authority after the delay", async () => {
+    await delay((delaySecs + 1) * 1000);
+    await finalize();
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.authority.toString()).to.equal(newAuthority.publicKey.toString());
+    expect(vault.pendingAuthority.toString()).to.equal(PublicKey.default.toString());
+  });
+});
+
+describe("token_vault: verify vault integrity over CPI", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+  const probe = anchor.workspace.VaultIntegrityProbe as Program<VaultIntegrityProbe>;
+
+  let f: VaultFixture;
+  const amount = 25 * LAMPORTS_PER_TOKEN;
+
+  const check = (expectedAuthority: PublicKey, expectedLocked: boolean, expectedBalance: number) =>
+    probe.methods
+      .checkVault(expectedAuthority, f.mint, expectedLocked, new anchor.BN(expectedBalance))
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount, tokenVaultProgram: program.programId })
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, amount);
+    await program.methods
+      .lockVault(new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0))
+      .accounts({
+        vault: f.vault,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        programConfig: programConfigPda(program),
+      })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Returns the report to the calling program", async () => {
+    const view = await program.methods
+      .verifyVaultIntegrity(f.owner.publicKey, f.mint)
+      .accounts({ vault: f.vault, vaultTokenAccount: f.vaultTokenAccount })
+      .view();
+    expect(view.isLocked).to.equal(true);
+    expect(view.balance.toString()).to.equal(amount.toString());
+
+    await check(f.owner.publicKey, true, amount);
+  });
+
+  it("FAIL when the calling program expects a different state", async () => {
+    try {
+      await check(f.owner.publicKey, true, amount + 1);
+      expect.fail("The balance does not match.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnexpectedBalance");
+    }
+  });
+
+  it("FAIL when the vault has a different authority", async () => {
+    try {
+      await check(Keypair.generate().publicKey, true, amount);
+      expect.fail("The authority does not match.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("VaultIntegrityMismatch");
+    }
+  });
+});
+
+describe("token_vault: withdrawal risk score", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  const cooldownSecs = 3;
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 100 * LAMPORTS_PER_TOKEN);
+    await program.methods
+      .setWithdrawalCooldown(new anchor.BN(cooldownSecs))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("Raises the score on three rapid large withdrawals", async () => {
+    // Each takes more than half the balance: +20, then +20 +10 twice for the rapid repeats
+    for (const amount of [60, 30, 6]) {
+      await withdrawFromVault(program, f, amount * LAMPORTS_PER_TOKEN);
+      await delay((cooldownSecs + 1) * 1000);
+    }
+
+    const vault = await program.account.vault.fetch(f.vault);
+    expect(vault.riskScore).to.equal(80);
+  });
+
+  it("FAIL to withdraw after the normal cooldown while high risk", async () => {
+    try {
+      await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("The cooldown is doubled.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("HighRiskCooldownActive");
+    }
+  });
+
+  it("Withdraws again once the risk admin resets the score", async () => {
+    try {
+      await program.methods
+        .resetRiskScore()
+        .accounts({ vault: f.vault, programConfig: programConfigPda(program), riskAdmin: f.owner.publicKey })
+        .signers([f.owner])
+        .rpc();
+      expect.fail("Only the risk admin can reset.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ConstraintHasOne");
+    }
+
+    await program.methods
+      .resetRiskScore()
+      .accounts({ vault: f.vault, programConfig: programConfigPda(program), riskAdmin: provider.wallet.publicKey })
+      .rpc();
+    expect((await program.account.vault.fetch(f.vault)).riskScore).to.equal(0);
+
+    await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+  });
+});
+
+describe("token_vault: price floor withdraw guard", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  // Loaded into the local validator from tests/fixtures, see Anchor.toml. Both confirmed their
+  // latest round at unix time 1_700_000_000.
+  const highAggregator = new PublicKey("4cfyyjTu49urMYe1KtYcrcNo7EEpzQQTka5jEQ8jbDPq"); // mantissa = 2000
+  const lowAggregator = new PublicKey("3qzcwXNYL6PazpC1yQ3dpr1gTynSGxdjSUD122DqWAEL"); // mantissa = 1000
+  const roundAge = () => Math.floor(Date.now() / 1000) - 1_700_000_000;
+
+  let f: VaultFixture;
+
+  const setGuard = (aggregator: PublicKey, floor: number, stalenessSecs: number, active = true) =>
+    program.methods
+      .setPriceFloorGuard(aggregator, new anchor.BN(floor), new anchor.BN(stalenessSecs), active)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+
+  const withdrawWithAggregator = (aggregator: PublicKey) =>
+    program.methods
+      .withdraw(new anchor.BN(LAMPORTS_PER_TOKEN), null)
+      .accounts({
+        vault: f.vault,
+        vaultAuthority: f.vaultAuthority,
+        userTokenAccount: f.ownerTokenAccount,
+        vaultTokenAccount: f.vaultTokenAccount,
+        authority: f.owner.publicKey,
+        tokenProgram: f.tokenProgram,
+        mint: f.mint,
+        feeTreasuryTokenAccount: null,
+        vaultStats: null,
+        depositorRecord: null,
+        destinationEntry: null,
+        programConfig: programConfigPda(program),
+        lpMint: null,
+        userLpTokenAccount: null,
+        beneficiarySplit: null,
+        roleConfig: null,
+      })
+      .remainingAccounts([{ pubkey: aggregator, isSigner: false, isWritable: false }])
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    await depositToVault(program, f, 10 * LAMPORTS_PER_TOKEN);
+  });
+
+  it("Withdraws while the price is above the floor", async () => {
+    await setGuard(highAggregator, 1500, roundAge() + 3600);
+    await withdrawWithAggregator(highAggregator);
+  });
+
+  it("FAIL to withdraw once the price drops below the floor", async () => {
+    await setGuard(lowAggregator, 1500, roundAge() + 3600);
+    try {
+      await withdrawWithAggregator(lowAggregator);
+      expect.fail("The price is below the floor.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("PriceBelowFloor");
+    }
+  });
+
+  it("FAIL to withdraw on a stale round", async () => {
+    await setGuard(highAggregator, 1500, 60);
+    try {
+      await withdrawWithAggregator(highAggregator);
+      expect.fail("The round is older than a minute.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("StaleOraclePrice");
+    }
+  });
+
+  it("Withdraws without the aggregator once the guard is off", async () => {
+    await setGuard(lowAggregator, 1500, 60, false);
+    await withdrawFromVault(program, f, LAMPORTS_PER_TOKEN);
+  });
+});
+
+describe("token_vault: deposit rate limit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let rateLimit: PublicKey;
+  const maxPerHour = 2;
+
+  const depositWithRateLimit = () => depositToVault(program, f, LAMPORTS_PER_TOKEN, null, null, null, null, rateLimit);
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [rateLimit] = PublicKey.findProgramAddressSync(
+      [Buffer.from("rate_limit"), f.vault.toBuffer(), f.owner.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .setDepositRateLimit(maxPerHour)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to deposit without the rate limit account while limited", async () => {
+    try {
+      await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+      expect.fail("The limit is tracked by the rate limit account.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RateLimitRecordRequired");
+    }
+  });
+
+  it("Counts deposits within the hour", async () => {
+    await depositWithRateLimit();
+    const first = await program.account.depositorRateLimit.fetch(rateLimit);
+    expect(first.depositCountThisHour).to.equal(1);
+    expect(first.windowStart.toNumber()).to.be.greaterThan(0);
+
+    await depositWithRateLimit();
+    const second = await program.account.depositorRateLimit.fetch(rateLimit);
+    expect(second.depositCountThisHour).to.equal(2);
+    // Same window, the counter only resets once it is more than an hour old
+    expect(second.windowStart.toString()).to.equal(first.windowStart.toString());
+  });
+
+  it("FAIL to deposit past the hourly limit", async () => {
+    try {
+      await depositWithRateLimit();
+      expect.fail("Only two deposits per hour are allowed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DepositRateLimitExceeded");
+    }
+    // The one-hour window cannot be fast-forwarded on a local validator, so the reset is not exercised here
+  });
+
+  it("Accepts deposits again once the limit is lifted", async () => {
+    await program.methods
+      .setDepositRateLimit(0)
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+    await depositToVault(program, f, LAMPORTS_PER_TOKEN);
+  });
+});
+
+describe("token_vault: commit-reveal deposit", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.TokenVault as Program<TokenVault>;
+
+  let f: VaultFixture;
+  let depositCommitment: PublicKey;
+  const amount = 7 * LAMPORTS_PER_TOKEN;
+  const revealWindowSecs = 2;
+
+  const commitmentFor = (value: number, nonce: Buffer) =>
+    Array.from(keccak_256(Buffer.concat([new anchor.BN(value).toArrayLike(Buffer, "le", 8), nonce])));
+
+  const commit = (value: number, nonce: Buffer) =>
+    program.methods
+      .commitDeposit(commitmentFor(value, nonce))
+      .accounts({ vault: f.vault, depositCommitment, depositor: f.owner.publicKey, systemProgram: SystemProgram.programId })
+      .signers([f.owner])
+      .rpc();
+
+  const reveal = (value: number, nonce: Buffer) =>
+    program.methods
+      .revealDeposit(new anchor.BN(value), null, Array.from(nonce))
+      .accounts({
+        deposit: {
+          vault: f.vault,
+          userTokenAccount: f.ownerTokenAccount,
+          vaultTokenAccount: f.vaultTokenAccount,
+          authority: f.owner.publicKey,
+          tokenProgram: f.tokenProgram,
+          mint: f.mint,
+          feeTreasuryTokenAccount: null,
+          vaultStats: null,
+          depositorRecord: null,
+          whitelistEntry: null,
+          programConfig: programConfigPda(program),
+          vaultAuthority: null,
+          lpMint: null,
+          userLpTokenAccount: null,
+          referralRecord: null,
+          roleConfig: null,
+          memoProgram: null,
+          depositorRateLimit: null,
+          systemProgram: SystemProgram.programId,
+        },
+        depositCommitment,
+      })
+      .signers([f.owner])
+      .rpc();
+
+  before(async () => {
+    f = await createVaultFixture(program, provider);
+    [depositCommitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commit"), f.vault.toBuffer(), f.owner.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .setRevealWindow(new anchor.BN(revealWindowSecs))
+      .accounts({ vault: f.vault, authority: f.owner.publicKey })
+      .signers([f.owner])
+      .rpc();
+  });
+
+  it("FAIL to reveal without a commitment", async () => {
+    try {
+      await reveal(amount, Keypair.generate().publicKey.toBuffer());
+      expect.fail("Nothing was committed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoPendingCommitment");
+    }
+  });
+
+  it("Deposits the committed amount on reveal and closes the commitment", async () => {
+    const nonce = Keypair.generate().publicKey.toBuffer();
+    await commit(amount, nonce);
+    const stored = await program.account.depositCommitment.fetch(depositCommitment);
+    expect(stored.revealWindowSecs.toNumber()).to.equal(revealWindowSecs);
+
+    try {
+      await reveal(amount + 1, nonce);
+      expect.fail("The amount differs from the commitment.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("CommitmentMismatch");
+    }
+
+    await reveal(amount, nonce);
+    expect((await getAccount(provider.connection, f.vaultTokenAccount)).amount.toString()).to.equal(amount.toString());
+    expect(await provider.connection.getAccountInfo(depositCommitment)).to.be.null;
+  });
+
+  it("FAIL to reveal after the window", async () => {
+    const nonce = Keypair.generate().publicKey.toBuffer();
+    await commit(amount, nonce);
+    await delay((revealWindowSecs + 2) * 1000);
+    try {
+      await reveal(amount, nonce);
+      expect.fail("The reveal window has passed.");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RevealWindowExpired");
+    }
+  });
 });